@@ -2,7 +2,12 @@
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use scrubkit_core::scrubber_for_file;
+use scrubkit_core::dicom::{DicomScrubber, is_dicom};
+use scrubkit_core::ooxml::{OoxmlScrubber, is_ooxml};
+use scrubkit_core::{
+    MetadataCategory, RiskLevel, ScrubPolicy, Scrubber, Selector, scrubber_for_file,
+};
+use std::io::IsTerminal;
 use std::path::PathBuf;
 
 /// A tool to view and remove potentially sensitive metadata from files.
@@ -20,6 +25,10 @@ enum Commands {
         /// The path to the file
         #[arg(required = true)]
         file_path: PathBuf,
+
+        /// Also list authors, rsids, printer settings and thumbnails in Office documents
+        #[arg(long)]
+        deep: bool,
     },
     /// Remove metadata from a file
     Clean {
@@ -30,29 +39,113 @@ enum Commands {
         /// Overwrite the file in-place
         #[arg(short, long)]
         in_place: bool,
+
+        /// Keep entries with this key or tag ID (e.g. "Copyright" or "0x0112")
+        #[arg(long, value_name = "KEY_OR_TAG")]
+        keep: Vec<String>,
+
+        /// Keep every entry in this category (e.g. "IFD0")
+        #[arg(long, value_name = "CATEGORY")]
+        keep_category: Vec<MetadataCategory>,
+
+        /// Also anonymize authors, strip rsids and drop printer settings and
+        /// thumbnails in Office documents
+        #[arg(long)]
+        deep: bool,
+
+        /// Derive new DICOM UIDs from this secret, so files cleaned separately
+        /// still share them. By default a random one is used for each run
+        #[arg(long, value_name = "SECRET")]
+        uid_key: Option<String>,
     },
 }
 
+/// Builds a scrub policy that removes everything except what the user asked to keep.
+fn build_policy(keep: Vec<String>, keep_category: Vec<MetadataCategory>) -> ScrubPolicy {
+    let mut policy = ScrubPolicy::remove_all();
+    for item in keep {
+        let tag_id = item
+            .strip_prefix("0x")
+            .or_else(|| item.strip_prefix("0X"))
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok());
+        policy = match tag_id {
+            Some(id) => policy.keep(Selector::TagId(id)),
+            None => policy.keep(Selector::Key(item)),
+        };
+    }
+    for category in keep_category {
+        policy = policy.keep(Selector::Category(category));
+    }
+    policy
+}
+
+/// Picks the scrubber for a file, turning on deep scrubbing for Office
+/// documents and setting the DICOM UID key if asked to.
+fn open_scrubber(
+    file_bytes: Vec<u8>,
+    deep: bool,
+    uid_key: Option<&str>,
+) -> Result<Box<dyn Scrubber>> {
+    if deep && is_ooxml(&file_bytes) {
+        return Ok(Box::new(OoxmlScrubber::new(file_bytes)?.deep()));
+    }
+    if let Some(key) = uid_key
+        && is_dicom(&file_bytes)
+    {
+        return Ok(Box::new(
+            DicomScrubber::new(file_bytes)?.with_uid_key(key.as_bytes()),
+        ));
+    }
+    Ok(scrubber_for_file(file_bytes)?)
+}
+
+/// Formats a risk level as a fixed-width label, colored when writing to a terminal.
+fn risk_label(risk: RiskLevel, color: bool) -> String {
+    let label = format!("[{:<8}]", risk);
+    if !color {
+        return label;
+    }
+    let code = match risk {
+        RiskLevel::Critical => "1;31",
+        RiskLevel::High => "33",
+        RiskLevel::Medium => "36",
+        RiskLevel::Low => "2",
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, label)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::View { file_path } => {
+        Commands::View { file_path, deep } => {
             let file_bytes = tokio::fs::read(&file_path)
                 .await
                 .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
 
             // Use the factory function to get the correct scrubber
-            let scrubber = scrubber_for_file(file_bytes)?;
-            let metadata = scrubber.view_metadata()?;
+            let scrubber = open_scrubber(file_bytes, deep, None)?;
+            let summary = scrubber.summary()?;
 
-            if metadata.is_empty() {
+            if summary.is_clean() {
                 println!("No metadata found in {}.", file_path.display());
             } else {
-                println!("Metadata for {}:", file_path.display());
-                for entry in metadata {
-                    println!("  - {}: {} = {}", entry.category, entry.key, entry.value);
+                println!("Metadata for {} ({}):", file_path.display(), summary);
+                let color = std::io::stdout().is_terminal();
+                for entry in &summary.entries {
+                    let tag = match entry.tag_id {
+                        Some(tag_id) => format!(" (0x{:04X})", tag_id),
+                        None => String::new(),
+                    };
+                    println!(
+                        "  - {} {}: {}{} = {}",
+                        risk_label(entry.risk, color),
+                        entry.category,
+                        entry.key,
+                        tag,
+                        entry.display_value
+                    );
                 }
             }
         }
@@ -60,13 +153,17 @@ async fn main() -> Result<()> {
         Commands::Clean {
             file_path,
             in_place,
+            keep,
+            keep_category,
+            deep,
+            uid_key,
         } => {
             let file_bytes = tokio::fs::read(&file_path)
                 .await
                 .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
 
-            let scrubber = scrubber_for_file(file_bytes)?;
-            let result = scrubber.scrub()?;
+            let scrubber = open_scrubber(file_bytes, deep, uid_key.as_deref())?;
+            let result = scrubber.scrub_with(&build_policy(keep, keep_category))?;
 
             if result.metadata_removed.is_empty() {
                 println!("No metadata found to remove from {}.", file_path.display());
@@ -99,6 +196,9 @@ async fn main() -> Result<()> {
                 "Successfully removed {} metadata entries.",
                 result.metadata_removed.len()
             );
+            if !result.metadata_kept.is_empty() {
+                println!("Kept {} metadata entries.", result.metadata_kept.len());
+            }
             println!("Cleaned file saved to: {}", output_path.display());
         }
     }
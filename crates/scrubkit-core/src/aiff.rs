@@ -0,0 +1,263 @@
+// File: crates/scrubkit-core/src/aiff.rs
+
+//! AIFF support.
+//!
+//! An AIFF file is an IFF container with the `AIFF` or `AIFC` form type.
+//! Besides the audio chunks, it may carry text chunks (`NAME`, `AUTH`,
+//! `ANNO` and `(c) `), timestamped comments in a `COMT` chunk, application
+//! data in `APPL` chunks and an embedded ID3v2 tag. ID3 tags are filtered
+//! frame by frame; other chunks are kept or removed whole.
+
+use crate::id3::Id3v2;
+use crate::iff::{self, ByteOrder, Chunk};
+use crate::mp4::quicktime_time;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+
+const ORDER: ByteOrder = ByteOrder::Big;
+
+/// Chunks that hold or describe the audio.
+const AUDIO_CHUNKS: &[&[u8; 4]] = &[
+    b"COMM", b"SSND", b"FVER", b"MARK", b"INST", b"MIDI", b"AESD", b"CHAN",
+];
+
+/// The text chunks, with the names we show for them.
+const TEXT_CHUNKS: &[(&[u8; 4], &str)] = &[
+    (b"NAME", "Name"),
+    (b"AUTH", "Author"),
+    (b"(c) ", "Copyright"),
+    (b"ANNO", "Annotation"),
+];
+
+/// Returns true for IFF files with the `AIFF` or `AIFC` form type.
+pub fn is_aiff(bytes: &[u8]) -> bool {
+    iff::has_form(bytes, b"FORM", &[b"AIFF", b"AIFC"])
+}
+
+fn is_metadata(chunk: &Chunk) -> bool {
+    !AUDIO_CHUNKS.contains(&&chunk.fourcc)
+}
+
+fn is_id3(chunk: &Chunk) -> bool {
+    &chunk.fourcc == b"ID3 " || &chunk.fourcc == b"id3 "
+}
+
+fn text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+/// Describes the comments of a `COMT` chunk: each has a timestamp, a marker
+/// and its text.
+fn comment_entries(data: &[u8]) -> Option<Vec<MetadataEntry>> {
+    let count = u16::from_be_bytes([*data.first()?, *data.get(1)?]);
+    let mut entries = Vec::new();
+    let mut pos = 2;
+    for _ in 0..count {
+        let header = data.get(pos..pos + 8)?;
+        let seconds = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let len = u16::from_be_bytes([header[6], header[7]]) as usize;
+        let comment = data.get(pos + 8..pos + 8 + len)?;
+        if seconds != 0 {
+            entries.push(MetadataEntry::new(
+                MetadataCategory::AiffText,
+                "CommentDate",
+                MetadataValue::DateTime(quicktime_time(seconds.into())),
+            ));
+        }
+        entries.push(MetadataEntry::new(
+            MetadataCategory::AiffText,
+            "Comment",
+            MetadataValue::Text(text(comment)),
+        ));
+        pos += 8 + len.next_multiple_of(2);
+    }
+    Some(entries)
+}
+
+/// Describes the metadata in a chunk. Chunks we can't read are shown as
+/// opaque data, so they are still listed and removed.
+fn metadata_entries(chunk: &Chunk) -> Vec<MetadataEntry> {
+    if let Some((_, key)) = TEXT_CHUNKS.iter().find(|(f, _)| **f == chunk.fourcc) {
+        return vec![MetadataEntry::new(
+            MetadataCategory::AiffText,
+            *key,
+            MetadataValue::Text(text(chunk.data)),
+        )];
+    }
+    let entries = match &chunk.fourcc {
+        b"COMT" => comment_entries(chunk.data).unwrap_or_default(),
+        _ if is_id3(chunk) => Id3v2::parse(chunk.data)
+            .map(|id3| id3.metadata_entries())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    if !entries.is_empty() {
+        return entries;
+    }
+    let (category, key) = match &chunk.fourcc {
+        b"COMT" => (MetadataCategory::AiffText, "Comments".to_string()),
+        _ if is_id3(chunk) => (MetadataCategory::Id3, "ID3".to_string()),
+        // Application chunks start with the application's signature.
+        b"APPL" => (
+            MetadataCategory::Vendor,
+            format!(
+                "Application {}",
+                String::from_utf8_lossy(&chunk.data[..4.min(chunk.data.len())])
+            ),
+        ),
+        _ => (MetadataCategory::Vendor, chunk.name()),
+    };
+    vec![MetadataEntry::new(
+        category,
+        key,
+        MetadataValue::Binary {
+            len: chunk.data.len(),
+        },
+    )]
+}
+
+/// A Scrubber implementation for AIFF and AIFF-C files.
+#[derive(Debug, Clone)]
+pub struct AiffScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for AiffScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_aiff(&file_bytes) {
+            return Err(ScrubError::ParsingError("Not a valid AIFF file".into()));
+        }
+        iff::chunks(&file_bytes, ORDER, "AIFF")?;
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        Ok(iff::chunks(&self.file_bytes, ORDER, "AIFF")?
+            .iter()
+            .filter(|chunk| is_metadata(chunk))
+            .flat_map(metadata_entries)
+            .collect())
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let chunks = iff::chunks(&self.file_bytes, ORDER, "AIFF")?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut output = Vec::new();
+
+        for chunk in &chunks {
+            if !is_metadata(chunk) {
+                output.push(chunk.bytes.to_vec());
+                continue;
+            }
+            // ID3 tags are filtered frame by frame, as in MP3 files.
+            if is_id3(chunk)
+                && let Ok(mut id3) = Id3v2::parse(chunk.data)
+            {
+                let (kept, removed) = id3.apply_policy(policy);
+                if removed.is_empty() {
+                    output.push(chunk.bytes.to_vec());
+                } else if !id3.is_empty() {
+                    output.push(iff::write_chunk(&chunk.fourcc, &id3.to_bytes(), ORDER)?);
+                }
+                metadata_kept.extend(kept);
+                metadata_removed.extend(removed);
+                continue;
+            }
+            let entries = metadata_entries(chunk);
+            if entries.iter().all(|e| policy.keeps(e)) {
+                output.push(chunk.bytes.to_vec());
+                metadata_kept.extend(entries);
+            } else {
+                metadata_removed.extend(entries);
+            }
+        }
+
+        let cleaned_bytes = if metadata_removed.is_empty() {
+            self.file_bytes.clone()
+        } else {
+            iff::write_container(&self.file_bytes, &output, ORDER)?
+        };
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aiff(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut all = vec![
+            iff::write_chunk(b"COMM", &[0; 18], ORDER).unwrap(),
+            iff::write_chunk(b"SSND", &[0; 12], ORDER).unwrap(),
+        ];
+        all.extend_from_slice(chunks);
+        iff::write_container(b"FORM\0\0\0\0AIFF", &all, ORDER).unwrap()
+    }
+
+    fn comments() -> Vec<u8> {
+        // One comment, made at 2024-05-01T14:30:00Z.
+        let mut data = 1u16.to_be_bytes().to_vec();
+        data.extend(3_797_418_600u32.to_be_bytes());
+        data.extend([0, 0, 0, 5]);
+        data.extend(b"Hello\0");
+        iff::write_chunk(b"COMT", &data, ORDER).unwrap()
+    }
+
+    #[test]
+    fn view_metadata_lists_text_chunks_and_comments() {
+        let file = aiff(&[
+            iff::write_chunk(b"AUTH", b"Jane Doe", ORDER).unwrap(),
+            comments(),
+        ]);
+        let entries = AiffScrubber::new(file).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(keys, ["Author", "CommentDate", "Comment"]);
+        assert_eq!(
+            entries[1].value,
+            MetadataValue::DateTime("2024-05-01T14:30:00Z".into())
+        );
+        assert_eq!(entries[2].value, MetadataValue::Text("Hello".into()));
+    }
+
+    #[test]
+    fn scrub_drops_metadata_chunks_and_fixes_the_form_size() {
+        let file = aiff(&[
+            iff::write_chunk(b"NAME", b"Song", ORDER).unwrap(),
+            iff::write_chunk(b"ANNO", b"Recorded at home", ORDER).unwrap(),
+            comments(),
+        ]);
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("Name".into()));
+        let result = AiffScrubber::new(file)
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 3);
+        assert_eq!(
+            result.cleaned_file_bytes,
+            aiff(&[iff::write_chunk(b"NAME", b"Song", ORDER).unwrap()])
+        );
+    }
+
+    #[test]
+    fn id3_chunks_without_readable_frames_are_removed() {
+        // The frame size runs past the end of the tag.
+        let tag = b"ID3\x03\x00\x00\x00\x00\x00\x13TIT2\x00\x00\x01\x00\x00\x00\x00Jane Doe";
+        let file = aiff(&[iff::write_chunk(b"id3 ", tag, ORDER).unwrap()]);
+        let scrubber = AiffScrubber::new(file).unwrap();
+
+        let entries = scrubber.view_metadata().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, "ID3v2");
+        let result = scrubber.scrub().unwrap();
+        assert_eq!(result.metadata_removed, entries);
+        assert_eq!(result.cleaned_file_bytes, aiff(&[]));
+    }
+}
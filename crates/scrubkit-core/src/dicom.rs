@@ -0,0 +1,1214 @@
+// File: crates/scrubkit-core/src/dicom.rs
+
+//! DICOM support.
+//!
+//! A DICOM Part 10 file is a 128-byte preamble, the `DICM` magic, a file meta
+//! group and then the data set. The meta group is always explicit VR little
+//! endian; the data set uses the transfer syntax the meta group names. A data
+//! set is a list of elements sorted by tag, each with a value representation
+//! (VR), which implicit VR syntaxes leave out, a length and a value. Sequences
+//! hold items that are data sets of their own, and either may have an
+//! undefined length and end with a delimiter instead.
+//!
+//! Scrubbing applies the actions of the Basic Application Level
+//! Confidentiality Profile of DICOM PS3.15 Annex E to the attributes in
+//! `PROFILE`, at any depth: identifying attributes are removed, emptied or
+//! given dummy values, UIDs are replaced with keyed hashes of themselves and
+//! private attributes are removed. `PROFILE` covers the common attributes of
+//! the profile's table, not all of them, so the file is marked as
+//! de-identified without claiming conformance to the profile. Pixel data is
+//! copied unchanged, so text burned into the image is not touched.
+
+use crate::tiff::ByteOrder;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, RiskLevel, ScrubError, ScrubPolicy,
+    ScrubResult, Scrubber,
+};
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io::{Read, Write};
+use std::sync::OnceLock;
+
+const PREAMBLE_LEN: usize = 128;
+const MAGIC: &[u8] = b"DICM";
+
+/// A tag, as its group number followed by its element number.
+type Tag = u32;
+
+const META_GROUP_LENGTH: Tag = 0x0002_0000;
+const TRANSFER_SYNTAX_UID: Tag = 0x0002_0010;
+const PATIENT_IDENTITY_REMOVED: Tag = 0x0012_0062;
+const DEIDENTIFICATION_METHOD: Tag = 0x0012_0063;
+const PIXEL_DATA: Tag = 0x7FE0_0010;
+const ITEM: Tag = 0xFFFE_E000;
+const ITEM_DELIMITER: Tag = 0xFFFE_E00D;
+const SEQUENCE_DELIMITER: Tag = 0xFFFE_E0DD;
+
+const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
+
+const IMPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+const EXPLICIT_VR_BIG_ENDIAN: &str = "1.2.840.10008.1.2.2";
+const DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1.99";
+
+/// The most a deflated data set may inflate to, so a small file can't
+/// expand into gigabytes.
+const MAX_INFLATED_LEN: u64 = 512 << 20;
+
+/// How deeply sequences may nest, so a crafted file can't overflow the stack.
+/// Real files rarely go past a handful of levels.
+const MAX_DEPTH: usize = 64;
+
+/// VRs whose length takes four bytes, after two reserved ones, in explicit
+/// VR syntaxes. The rest have a two-byte length.
+const LONG_VRS: &[&[u8; 2]] = &[
+    b"OB", b"OD", b"OF", b"OL", b"OV", b"OW", b"SQ", b"SV", b"UC", b"UN", b"UR", b"UT", b"UV",
+];
+
+/// What the profile does to an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// X: remove the attribute.
+    Remove,
+    /// Z: keep the attribute with an empty value.
+    Empty,
+    /// D: replace the value with a dummy of the same VR.
+    Dummy,
+    /// U: replace each UID with one derived from it.
+    RemapUid,
+}
+
+use Action::{Dummy, Empty, RemapUid, Remove};
+
+/// The attributes of PS3.15 Table E.1-1 handled here, with their keyword,
+/// VR and action. Where the profile lets the action depend on whether the
+/// IOD requires the attribute (X/Z, X/D, Z/D or X/Z/D), the one that keeps
+/// it is used, so the file stays valid whatever kind of object it is.
+const PROFILE: &[(Tag, &str, &[u8; 2], Action)] = &[
+    (0x0002_0003, "MediaStorageSOPInstanceUID", b"UI", RemapUid),
+    (
+        0x0004_1511,
+        "ReferencedSOPInstanceUIDInFile",
+        b"UI",
+        RemapUid,
+    ),
+    (0x0008_0012, "InstanceCreationDate", b"DA", Dummy),
+    (0x0008_0013, "InstanceCreationTime", b"TM", Dummy),
+    (0x0008_0014, "InstanceCreatorUID", b"UI", RemapUid),
+    (0x0008_0018, "SOPInstanceUID", b"UI", RemapUid),
+    (0x0008_0020, "StudyDate", b"DA", Empty),
+    (0x0008_0021, "SeriesDate", b"DA", Dummy),
+    (0x0008_0022, "AcquisitionDate", b"DA", Empty),
+    (0x0008_0023, "ContentDate", b"DA", Dummy),
+    (0x0008_0024, "OverlayDate", b"DA", Remove),
+    (0x0008_0025, "CurveDate", b"DA", Remove),
+    (0x0008_002A, "AcquisitionDateTime", b"DT", Dummy),
+    (0x0008_0030, "StudyTime", b"TM", Empty),
+    (0x0008_0031, "SeriesTime", b"TM", Dummy),
+    (0x0008_0032, "AcquisitionTime", b"TM", Empty),
+    (0x0008_0033, "ContentTime", b"TM", Dummy),
+    (0x0008_0034, "OverlayTime", b"TM", Remove),
+    (0x0008_0035, "CurveTime", b"TM", Remove),
+    (0x0008_0050, "AccessionNumber", b"SH", Empty),
+    (0x0008_0058, "FailedSOPInstanceUIDList", b"UI", RemapUid),
+    (0x0008_0080, "InstitutionName", b"LO", Dummy),
+    (0x0008_0081, "InstitutionAddress", b"ST", Remove),
+    (0x0008_0082, "InstitutionCodeSequence", b"SQ", Empty),
+    (0x0008_0090, "ReferringPhysicianName", b"PN", Empty),
+    (0x0008_0092, "ReferringPhysicianAddress", b"ST", Remove),
+    (
+        0x0008_0094,
+        "ReferringPhysicianTelephoneNumbers",
+        b"SH",
+        Remove,
+    ),
+    (
+        0x0008_0096,
+        "ReferringPhysicianIdentificationSequence",
+        b"SQ",
+        Remove,
+    ),
+    (0x0008_009C, "ConsultingPhysicianName", b"PN", Remove),
+    (0x0008_0201, "TimezoneOffsetFromUTC", b"SH", Remove),
+    (0x0008_1010, "StationName", b"SH", Dummy),
+    (0x0008_1030, "StudyDescription", b"LO", Remove),
+    (0x0008_103E, "SeriesDescription", b"LO", Remove),
+    (0x0008_1040, "InstitutionalDepartmentName", b"LO", Remove),
+    (0x0008_1048, "PhysiciansOfRecord", b"PN", Remove),
+    (
+        0x0008_1049,
+        "PhysiciansOfRecordIdentificationSequence",
+        b"SQ",
+        Remove,
+    ),
+    (0x0008_1050, "PerformingPhysicianName", b"PN", Remove),
+    (
+        0x0008_1052,
+        "PerformingPhysicianIdentificationSequence",
+        b"SQ",
+        Remove,
+    ),
+    (0x0008_1060, "NameOfPhysiciansReadingStudy", b"PN", Remove),
+    (
+        0x0008_1062,
+        "PhysiciansReadingStudyIdentificationSequence",
+        b"SQ",
+        Remove,
+    ),
+    (0x0008_1070, "OperatorsName", b"PN", Dummy),
+    (0x0008_1080, "AdmittingDiagnosesDescription", b"LO", Remove),
+    (0x0008_1084, "AdmittingDiagnosesCodeSequence", b"SQ", Remove),
+    (
+        0x0008_1111,
+        "ReferencedPerformedProcedureStepSequence",
+        b"SQ",
+        Empty,
+    ),
+    (0x0008_1120, "ReferencedPatientSequence", b"SQ", Remove),
+    (0x0008_1155, "ReferencedSOPInstanceUID", b"UI", RemapUid),
+    (0x0008_1195, "TransactionUID", b"UI", RemapUid),
+    (0x0008_2111, "DerivationDescription", b"ST", Remove),
+    (0x0008_3010, "IrradiationEventUID", b"UI", RemapUid),
+    (0x0008_4000, "IdentifyingComments", b"LT", Remove),
+    (0x0008_9123, "CreatorVersionUID", b"UI", RemapUid),
+    (0x0010_0010, "PatientName", b"PN", Empty),
+    (0x0010_0020, "PatientID", b"LO", Empty),
+    (0x0010_0021, "IssuerOfPatientID", b"LO", Remove),
+    (0x0010_0030, "PatientBirthDate", b"DA", Empty),
+    (0x0010_0032, "PatientBirthTime", b"TM", Remove),
+    (0x0010_0040, "PatientSex", b"CS", Empty),
+    (
+        0x0010_0050,
+        "PatientInsurancePlanCodeSequence",
+        b"SQ",
+        Remove,
+    ),
+    (0x0010_1000, "OtherPatientIDs", b"LO", Remove),
+    (0x0010_1001, "OtherPatientNames", b"PN", Remove),
+    (0x0010_1002, "OtherPatientIDsSequence", b"SQ", Remove),
+    (0x0010_1005, "PatientBirthName", b"PN", Remove),
+    (0x0010_1010, "PatientAge", b"AS", Remove),
+    (0x0010_1020, "PatientSize", b"DS", Remove),
+    (0x0010_1030, "PatientWeight", b"DS", Remove),
+    (0x0010_1040, "PatientAddress", b"LO", Remove),
+    (0x0010_1060, "PatientMotherBirthName", b"PN", Remove),
+    (0x0010_1080, "MilitaryRank", b"LO", Remove),
+    (0x0010_1081, "BranchOfService", b"LO", Remove),
+    (0x0010_1090, "MedicalRecordLocator", b"LO", Remove),
+    (0x0010_2000, "MedicalAlerts", b"LO", Remove),
+    (0x0010_2110, "Allergies", b"LO", Remove),
+    (0x0010_2150, "CountryOfResidence", b"LO", Remove),
+    (0x0010_2152, "RegionOfResidence", b"LO", Remove),
+    (0x0010_2154, "PatientTelephoneNumbers", b"SH", Remove),
+    (0x0010_2160, "EthnicGroup", b"SH", Remove),
+    (0x0010_2180, "Occupation", b"SH", Remove),
+    (0x0010_21A0, "SmokingStatus", b"CS", Remove),
+    (0x0010_21B0, "AdditionalPatientHistory", b"LT", Remove),
+    (0x0010_21C0, "PregnancyStatus", b"US", Remove),
+    (0x0010_21F0, "PatientReligiousPreference", b"LO", Remove),
+    (0x0010_2297, "ResponsiblePerson", b"PN", Remove),
+    (0x0010_2299, "ResponsibleOrganization", b"LO", Remove),
+    (0x0010_4000, "PatientComments", b"LT", Remove),
+    (0x0018_1000, "DeviceSerialNumber", b"LO", Dummy),
+    (0x0018_1002, "DeviceUID", b"UI", RemapUid),
+    (0x0018_1004, "PlateID", b"LO", Remove),
+    (0x0018_1005, "GeneratorID", b"LO", Remove),
+    (0x0018_1007, "CassetteID", b"LO", Remove),
+    (0x0018_1008, "GantryID", b"LO", Remove),
+    (0x0018_1030, "ProtocolName", b"LO", Dummy),
+    (
+        0x0018_1400,
+        "AcquisitionDeviceProcessingDescription",
+        b"LO",
+        Dummy,
+    ),
+    (0x0018_2042, "TargetUID", b"UI", RemapUid),
+    (0x0018_9424, "AcquisitionProtocolDescription", b"LT", Remove),
+    (0x0020_000D, "StudyInstanceUID", b"UI", RemapUid),
+    (0x0020_000E, "SeriesInstanceUID", b"UI", RemapUid),
+    (0x0020_0010, "StudyID", b"SH", Empty),
+    (0x0020_0052, "FrameOfReferenceUID", b"UI", RemapUid),
+    (
+        0x0020_0200,
+        "SynchronizationFrameOfReferenceUID",
+        b"UI",
+        RemapUid,
+    ),
+    (0x0020_4000, "ImageComments", b"LT", Remove),
+    (0x0020_9161, "ConcatenationUID", b"UI", RemapUid),
+    (0x0020_9164, "DimensionOrganizationUID", b"UI", RemapUid),
+    (0x0028_1199, "PaletteColorLookupTableUID", b"UI", RemapUid),
+    (
+        0x0028_1214,
+        "LargePaletteColorLookupTableUID",
+        b"UI",
+        RemapUid,
+    ),
+    (0x0032_1030, "ReasonForStudy", b"LO", Remove),
+    (0x0032_1032, "RequestingPhysician", b"PN", Remove),
+    (0x0032_1033, "RequestingService", b"LO", Remove),
+    (0x0032_1060, "RequestedProcedureDescription", b"LO", Empty),
+    (0x0032_4000, "StudyComments", b"LT", Remove),
+    (0x0038_0010, "AdmissionID", b"LO", Remove),
+    (0x0038_0300, "CurrentPatientLocation", b"LO", Remove),
+    (0x0038_0400, "PatientInstitutionResidence", b"LO", Remove),
+    (0x0038_0500, "PatientState", b"LO", Remove),
+    (
+        0x0040_0006,
+        "ScheduledPerformingPhysicianName",
+        b"PN",
+        Remove,
+    ),
+    (0x0040_0009, "ScheduledProcedureStepID", b"SH", Remove),
+    (0x0040_0241, "PerformedStationAETitle", b"AE", Remove),
+    (0x0040_0242, "PerformedStationName", b"SH", Remove),
+    (0x0040_0243, "PerformedLocation", b"SH", Remove),
+    (
+        0x0040_0244,
+        "PerformedProcedureStepStartDate",
+        b"DA",
+        Remove,
+    ),
+    (
+        0x0040_0245,
+        "PerformedProcedureStepStartTime",
+        b"TM",
+        Remove,
+    ),
+    (0x0040_0250, "PerformedProcedureStepEndDate", b"DA", Remove),
+    (0x0040_0251, "PerformedProcedureStepEndTime", b"TM", Remove),
+    (0x0040_0253, "PerformedProcedureStepID", b"SH", Remove),
+    (
+        0x0040_0254,
+        "PerformedProcedureStepDescription",
+        b"LO",
+        Remove,
+    ),
+    (0x0040_0275, "RequestAttributesSequence", b"SQ", Remove),
+    (
+        0x0040_0280,
+        "CommentsOnThePerformedProcedureStep",
+        b"ST",
+        Remove,
+    ),
+    (0x0040_1001, "RequestedProcedureID", b"SH", Remove),
+    (0x0040_A027, "VerifyingOrganization", b"LO", Dummy),
+    (0x0040_A030, "VerificationDateTime", b"DT", Dummy),
+    (0x0040_A075, "VerifyingObserverName", b"PN", Dummy),
+    (0x0040_A123, "PersonName", b"PN", Dummy),
+    (0x0040_A124, "UID", b"UI", RemapUid),
+    (0x0040_A171, "ObservationUID", b"UI", RemapUid),
+    (0x0040_A730, "ContentSequence", b"SQ", Remove),
+    (
+        0x0040_DB0C,
+        "TemplateExtensionOrganizationUID",
+        b"UI",
+        RemapUid,
+    ),
+    (0x0040_DB0D, "TemplateExtensionCreatorUID", b"UI", RemapUid),
+    (0x0062_0021, "TrackingUID", b"UI", RemapUid),
+    (0x0064_0003, "SourceFrameOfReferenceUID", b"UI", RemapUid),
+    (0x0070_0084, "ContentCreatorName", b"PN", Empty),
+    (0x0070_031A, "FiducialUID", b"UI", RemapUid),
+    (0x0088_0140, "StorageMediaFileSetUID", b"UI", RemapUid),
+    (
+        0x3006_0024,
+        "ReferencedFrameOfReferenceUID",
+        b"UI",
+        RemapUid,
+    ),
+    (0x3006_00C2, "RelatedFrameOfReferenceUID", b"UI", RemapUid),
+    (0x300A_0013, "DoseReferenceUID", b"UI", RemapUid),
+    (0x4008_010C, "InterpretationAuthor", b"PN", Remove),
+    (
+        0x4008_0114,
+        "PhysicianApprovingInterpretation",
+        b"PN",
+        Remove,
+    ),
+];
+
+/// VRs whose values are text.
+const TEXT_VRS: &[&[u8; 2]] = &[
+    b"AE", b"AS", b"CS", b"DA", b"DS", b"DT", b"IS", b"LO", b"LT", b"PN", b"SH", b"ST", b"TM",
+    b"UC", b"UI", b"UR", b"UT",
+];
+
+/// Returns true for DICOM Part 10 files, which have `DICM` after the preamble.
+pub fn is_dicom(bytes: &[u8]) -> bool {
+    bytes.get(PREAMBLE_LEN..PREAMBLE_LEN + MAGIC.len()) == Some(MAGIC)
+}
+
+/// How a data set is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Syntax {
+    order: ByteOrder,
+    explicit_vr: bool,
+}
+
+const META_SYNTAX: Syntax = Syntax {
+    order: ByteOrder::Little,
+    explicit_vr: true,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    tag: Tag,
+    /// The VR written in the file, or the one from [`PROFILE`] in implicit
+    /// VR syntaxes (`UN` when the tag isn't there).
+    vr: [u8; 2],
+    value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bytes(Vec<u8>),
+    /// The items of a sequence, with the length the sequence had in the file.
+    Sequence {
+        items: Vec<Vec<Element>>,
+        len: usize,
+    },
+    /// Encapsulated pixel data: its fragment items and the sequence
+    /// delimiter, copied as they are.
+    Fragments(Vec<u8>),
+}
+
+fn truncated() -> ScrubError {
+    ScrubError::ParsingError("Truncated DICOM data set".into())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    syntax: Syntax,
+    /// How many sequences the reader is inside.
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ScrubError> {
+        let data = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or_else(truncated)?;
+        self.pos += len;
+        Ok(data)
+    }
+
+    fn u16(&mut self) -> Result<u16, ScrubError> {
+        let bytes = self.take(2)?;
+        self.syntax.order.read_u16(bytes, 0).ok_or_else(truncated)
+    }
+
+    fn u32(&mut self) -> Result<u32, ScrubError> {
+        let bytes = self.take(4)?;
+        self.syntax.order.read_u32(bytes, 0).ok_or_else(truncated)
+    }
+
+    fn tag(&mut self) -> Result<Tag, ScrubError> {
+        Ok(((self.u16()? as u32) << 16) | self.u16()? as u32)
+    }
+
+    fn peek_tag(&self) -> Option<Tag> {
+        let mut peek = *self;
+        peek.tag().ok()
+    }
+}
+
+fn profile_entry(tag: Tag) -> Option<&'static (Tag, &'static str, &'static [u8; 2], Action)> {
+    PROFILE.iter().find(|entry| entry.0 == tag)
+}
+
+/// Reads elements up to `end`, or up to an item delimiter if it is `None`.
+fn read_data_set(r: &mut Reader, end: Option<usize>) -> Result<Vec<Element>, ScrubError> {
+    let mut elements = Vec::new();
+    loop {
+        match end {
+            Some(end) if r.pos >= end => break,
+            None if r.peek_tag() == Some(ITEM_DELIMITER) => {
+                r.take(8)?;
+                break;
+            }
+            _ => elements.push(read_element(r)?),
+        }
+    }
+    Ok(elements)
+}
+
+fn read_element(r: &mut Reader) -> Result<Element, ScrubError> {
+    let tag = r.tag()?;
+    let (mut vr, len) = if r.syntax.explicit_vr {
+        let vr = r.take(2)?;
+        let vr = [vr[0], vr[1]];
+        let len = if LONG_VRS.contains(&&vr) {
+            r.take(2)?;
+            r.u32()?
+        } else {
+            r.u16()? as u32
+        };
+        (vr, len)
+    } else {
+        let vr = profile_entry(tag).map_or(*b"UN", |entry| *entry.2);
+        (vr, r.u32()?)
+    };
+
+    let value = if tag == PIXEL_DATA && len == UNDEFINED_LENGTH {
+        Value::Fragments(read_fragments(r)?)
+    } else if &vr == b"SQ" || len == UNDEFINED_LENGTH {
+        if r.syntax.explicit_vr && &vr != b"SQ" {
+            // Such values are sequences in implicit VR little endian,
+            // whatever the syntax of the data set.
+            return Err(ScrubError::ParsingError(format!(
+                "Unsupported undefined-length {} element",
+                String::from_utf8_lossy(&vr)
+            )));
+        }
+        vr = *b"SQ";
+        let start = r.pos;
+        let items = read_items(r, len)?;
+        Value::Sequence {
+            items,
+            len: r.pos - start,
+        }
+    } else {
+        let data = r.take(len as usize)?;
+        // Implicit VR syntaxes don't say which values are sequences, so
+        // look for an item tag where one would start.
+        let mut inner = Reader {
+            bytes: data,
+            pos: 0,
+            ..*r
+        };
+        let maybe_sequence =
+            !r.syntax.explicit_vr && &vr == b"UN" && inner.peek_tag() == Some(ITEM);
+        match maybe_sequence.then(|| read_items(&mut inner, len)) {
+            Some(Ok(items)) => {
+                vr = *b"SQ";
+                Value::Sequence {
+                    items,
+                    len: data.len(),
+                }
+            }
+            _ => Value::Bytes(data.to_vec()),
+        }
+    };
+    Ok(Element { tag, vr, value })
+}
+
+/// Reads the items of a sequence of the given length, which may be undefined.
+fn read_items(r: &mut Reader, len: u32) -> Result<Vec<Vec<Element>>, ScrubError> {
+    if r.depth >= MAX_DEPTH {
+        return Err(ScrubError::ParsingError(
+            "DICOM sequences nested too deeply".into(),
+        ));
+    }
+    r.depth += 1;
+    let end = match len {
+        UNDEFINED_LENGTH => None,
+        len => Some(r.pos.checked_add(len as usize).ok_or_else(truncated)?),
+    };
+    let mut items = Vec::new();
+    while end.is_none_or(|end| r.pos < end) {
+        let tag = r.tag()?;
+        let item_len = r.u32()?;
+        match tag {
+            SEQUENCE_DELIMITER => break,
+            ITEM if item_len == UNDEFINED_LENGTH => items.push(read_data_set(r, None)?),
+            ITEM => {
+                let item_end = r.pos.checked_add(item_len as usize).ok_or_else(truncated)?;
+                items.push(read_data_set(r, Some(item_end))?);
+            }
+            _ => {
+                return Err(ScrubError::ParsingError(format!(
+                    "Unexpected tag {:08X} in a sequence",
+                    tag
+                )));
+            }
+        }
+    }
+    r.depth -= 1;
+    Ok(items)
+}
+
+/// Reads encapsulated pixel data, up to and including its sequence delimiter.
+fn read_fragments(r: &mut Reader) -> Result<Vec<u8>, ScrubError> {
+    let start = r.pos;
+    loop {
+        let tag = r.tag()?;
+        let len = r.u32()?;
+        if tag == SEQUENCE_DELIMITER {
+            break;
+        }
+        r.take(len as usize)?;
+    }
+    Ok(r.bytes[start..r.pos].to_vec())
+}
+
+fn write_tag(out: &mut Vec<u8>, tag: Tag, order: ByteOrder) {
+    out.extend(order.u16_bytes((tag >> 16) as u16));
+    out.extend(order.u16_bytes(tag as u16));
+}
+
+/// Writes elements. Sequences and items are written with undefined lengths,
+/// so nothing has to be measured first.
+fn write_data_set(out: &mut Vec<u8>, elements: &[Element], syntax: Syntax) {
+    let order = syntax.order;
+    for element in elements {
+        let len = match &element.value {
+            Value::Bytes(data) => data.len() as u32,
+            _ => UNDEFINED_LENGTH,
+        };
+        write_tag(out, element.tag, order);
+        if !syntax.explicit_vr {
+            out.extend(order.u32_bytes(len));
+        } else if LONG_VRS.contains(&&element.vr) {
+            out.extend(element.vr);
+            out.extend([0, 0]);
+            out.extend(order.u32_bytes(len));
+        } else {
+            out.extend(element.vr);
+            out.extend(order.u16_bytes(len as u16));
+        }
+        match &element.value {
+            Value::Bytes(data) | Value::Fragments(data) => out.extend(data),
+            Value::Sequence { items, .. } => {
+                for item in items {
+                    write_tag(out, ITEM, order);
+                    out.extend(order.u32_bytes(UNDEFINED_LENGTH));
+                    write_data_set(out, item, syntax);
+                    write_tag(out, ITEM_DELIMITER, order);
+                    out.extend(order.u32_bytes(0));
+                }
+                write_tag(out, SEQUENCE_DELIMITER, order);
+                out.extend(order.u32_bytes(0));
+            }
+        }
+    }
+}
+
+/// Reads a text value, without the padding that makes its length even.
+fn text(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+/// Pads a value to an even length, with a null for UIDs and a space otherwise.
+fn padded(mut data: Vec<u8>, vr: &[u8; 2]) -> Vec<u8> {
+    if data.len() % 2 == 1 {
+        data.push(if vr == b"UI" { 0 } else { b' ' });
+    }
+    data
+}
+
+/// A random key made once per process, so the files scrubbed in one run
+/// share their new UIDs unless the caller picks a key of their own.
+fn run_key() -> &'static [u8] {
+    static KEY: OnceLock<[u8; 32]> = OnceLock::new();
+    KEY.get_or_init(|| {
+        let mut key = [0; 32];
+        getrandom::getrandom(&mut key).expect("system random number generator");
+        key
+    })
+}
+
+/// Replaces a UID with one under the `2.25` root derived from an HMAC of it.
+///
+/// With the same key, a UID shared by several files, such as a study's, is
+/// replaced with the same UID in each of them. Without the key, the new UIDs
+/// can't be recomputed from the originals, so whoever holds those (the
+/// source PACS, or another export) can't link the scrubbed files back to
+/// them. The flip side is that files scrubbed with different keys no longer
+/// share UIDs, so a study exported in parts must be scrubbed with one key.
+fn remap_uid(uid: &str, key: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(uid.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let mut first = [0; 16];
+    first.copy_from_slice(&digest[..16]);
+    format!("2.25.{}", u128::from_be_bytes(first))
+}
+
+fn dummy_value(vr: &[u8; 2]) -> &'static [u8] {
+    match vr {
+        b"DA" => b"19000101",
+        b"TM" => b"000000",
+        b"DT" => b"19000101000000",
+        b"AS" => b"000Y",
+        b"DS" | b"IS" => b"0",
+        _ => b"ANONYMIZED",
+    }
+}
+
+fn is_private(tag: Tag) -> bool {
+    let group = tag >> 16;
+    // Odd groups below 0008 and FFFF are reserved rather than private.
+    group % 2 == 1 && group > 0x0007 && group != 0xFFFF
+}
+
+/// Describes what the profile does to an element, if anything.
+fn profile_action(element: &Element) -> Option<(MetadataEntry, &'static [u8; 2], Action)> {
+    let value_len = match &element.value {
+        Value::Bytes(data) | Value::Fragments(data) => data.len(),
+        Value::Sequence { len, .. } => *len,
+    };
+    if let Some(&(_, keyword, vr, action)) = profile_entry(element.tag) {
+        let value = match &element.value {
+            Value::Bytes(data) if TEXT_VRS.contains(&vr) => {
+                let text = text(data);
+                // Dates are YYYYMMDD.
+                if vr == b"DA" && text.len() == 8 && text.bytes().all(|b| b.is_ascii_digit()) {
+                    MetadataValue::DateTime(format!(
+                        "{}-{}-{}",
+                        &text[..4],
+                        &text[4..6],
+                        &text[6..]
+                    ))
+                } else {
+                    MetadataValue::Text(text)
+                }
+            }
+            _ => MetadataValue::Binary { len: value_len },
+        };
+        let entry = MetadataEntry::new(MetadataCategory::Dicom, keyword, value);
+        // Everything the profile covers can help identify a patient.
+        let risk = if keyword.contains("Patient") {
+            RiskLevel::Critical
+        } else {
+            entry.risk.max(RiskLevel::High)
+        };
+        let entry = entry.with_risk(risk);
+        return Some((entry, vr, action));
+    }
+    if is_private(element.tag) {
+        let key = format!(
+            "Private Attribute ({:04X},{:04X})",
+            element.tag >> 16,
+            element.tag & 0xFFFF
+        );
+        let entry = MetadataEntry::new(
+            MetadataCategory::Vendor,
+            key,
+            MetadataValue::Binary { len: value_len },
+        );
+        return Some((entry, b"UN", Remove));
+    }
+    None
+}
+
+/// Applies the profile to a data set and to the items of its sequences,
+/// sorting the entries for the attributes it touches into kept and removed.
+fn apply_profile(
+    elements: &mut Vec<Element>,
+    policy: &ScrubPolicy,
+    uid_key: &[u8],
+    kept: &mut Vec<MetadataEntry>,
+    removed: &mut Vec<MetadataEntry>,
+) {
+    for mut element in std::mem::take(elements) {
+        let action = match profile_action(&element) {
+            Some((entry, _, _)) if policy.keeps(&entry) => {
+                kept.push(entry);
+                None
+            }
+            Some((entry, vr, action)) => {
+                removed.push(entry);
+                Some((vr, action))
+            }
+            None => None,
+        };
+        match action {
+            Some((_, Remove)) => continue,
+            Some((b"SQ", Empty)) => {
+                element.value = Value::Sequence {
+                    items: Vec::new(),
+                    len: 0,
+                };
+            }
+            Some((vr, Empty)) => {
+                element.vr = *vr;
+                element.value = Value::Bytes(Vec::new());
+            }
+            Some((vr, Dummy)) => {
+                element.vr = *vr;
+                element.value = Value::Bytes(padded(dummy_value(vr).to_vec(), vr));
+            }
+            Some((vr, RemapUid)) => {
+                let uids = match &element.value {
+                    Value::Bytes(data) => text(data),
+                    _ => String::new(),
+                };
+                let remapped: Vec<String> = uids
+                    .split('\\')
+                    .map(|uid| remap_uid(uid, uid_key))
+                    .collect();
+                element.vr = *vr;
+                element.value = Value::Bytes(padded(remapped.join("\\").into_bytes(), vr));
+            }
+            None => {
+                if let Value::Sequence { items, .. } = &mut element.value {
+                    for item in items {
+                        apply_profile(item, policy, uid_key, kept, removed);
+                    }
+                }
+            }
+        }
+        elements.push(element);
+    }
+}
+
+/// Replaces any de-identification attributes with ones saying the file was
+/// de-identified, and how.
+fn mark_deidentified(elements: &mut Vec<Element>) {
+    elements.retain(|e| e.tag != PATIENT_IDENTITY_REMOVED && e.tag != DEIDENTIFICATION_METHOD);
+    let at = elements.partition_point(|e| e.tag < PATIENT_IDENTITY_REMOVED);
+    let marks = [
+        (PATIENT_IDENTITY_REMOVED, b"CS", "YES"),
+        (
+            DEIDENTIFICATION_METHOD,
+            b"LO",
+            "scrubkit: identifying attributes removed or replaced",
+        ),
+    ]
+    .map(|(tag, vr, value)| Element {
+        tag,
+        vr: *vr,
+        value: Value::Bytes(padded(value.as_bytes().to_vec(), vr)),
+    });
+    elements.splice(at..at, marks);
+}
+
+/// A parsed Part 10 file.
+#[derive(Debug, Clone)]
+struct DicomFile {
+    meta: Vec<Element>,
+    syntax: Syntax,
+    deflated: bool,
+    data_set: Vec<Element>,
+}
+
+impl DicomFile {
+    fn parse(bytes: &[u8]) -> Result<Self, ScrubError> {
+        if !is_dicom(bytes) {
+            return Err(ScrubError::ParsingError("Not a valid DICOM file".into()));
+        }
+        let mut r = Reader {
+            bytes,
+            pos: PREAMBLE_LEN + MAGIC.len(),
+            syntax: META_SYNTAX,
+            depth: 0,
+        };
+        let mut meta = Vec::new();
+        while r.peek_tag().is_some_and(|tag| tag >> 16 == 0x0002) {
+            meta.push(read_element(&mut r)?);
+        }
+
+        let transfer_syntax = meta
+            .iter()
+            .find(|e| e.tag == TRANSFER_SYNTAX_UID)
+            .and_then(|e| match &e.value {
+                Value::Bytes(data) => Some(text(data)),
+                _ => None,
+            })
+            .ok_or_else(|| ScrubError::ParsingError("Missing DICOM transfer syntax".into()))?;
+        // Every other syntax is explicit VR little endian, compressed pixel
+        // data included.
+        let syntax = Syntax {
+            order: if transfer_syntax == EXPLICIT_VR_BIG_ENDIAN {
+                ByteOrder::Big
+            } else {
+                ByteOrder::Little
+            },
+            explicit_vr: transfer_syntax != IMPLICIT_VR_LITTLE_ENDIAN,
+        };
+        let deflated = transfer_syntax == DEFLATED_EXPLICIT_VR_LITTLE_ENDIAN;
+
+        let rest = &bytes[r.pos..];
+        let inflated;
+        let data = if deflated {
+            inflated = inflate(rest)?;
+            &inflated
+        } else {
+            rest
+        };
+        let mut r = Reader {
+            bytes: data,
+            pos: 0,
+            syntax,
+            depth: 0,
+        };
+        let data_set = read_data_set(&mut r, Some(data.len()))?;
+
+        Ok(Self {
+            meta,
+            syntax,
+            deflated,
+            data_set,
+        })
+    }
+
+    /// Writes the file back with an empty preamble. The preamble belongs to
+    /// applications, which may put a TIFF header with metadata there.
+    fn to_bytes(&self) -> Vec<u8> {
+        // The meta group starts with its own length, which is rewritten.
+        let mut meta = Vec::new();
+        let rest: Vec<Element> = self
+            .meta
+            .iter()
+            .filter(|e| e.tag != META_GROUP_LENGTH)
+            .cloned()
+            .collect();
+        write_data_set(&mut meta, &rest, META_SYNTAX);
+        let group_length = Element {
+            tag: META_GROUP_LENGTH,
+            vr: *b"UL",
+            value: Value::Bytes((meta.len() as u32).to_le_bytes().to_vec()),
+        };
+
+        let mut data_set = Vec::new();
+        write_data_set(&mut data_set, &self.data_set, self.syntax);
+
+        let mut out = vec![0; PREAMBLE_LEN];
+        out.extend(MAGIC);
+        write_data_set(&mut out, &[group_length], META_SYNTAX);
+        out.extend(meta);
+        if self.deflated {
+            out.extend(deflate(&data_set));
+        } else {
+            out.extend(data_set);
+        }
+        out
+    }
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>, ScrubError> {
+    let mut inflated = Vec::new();
+    DeflateDecoder::new(data)
+        .take(MAX_INFLATED_LEN + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|e| ScrubError::ParsingError(format!("Corrupt deflated data set: {}", e)))?;
+    if inflated.len() as u64 > MAX_INFLATED_LEN {
+        return Err(ScrubError::ParsingError(
+            "Deflated data set too large".into(),
+        ));
+    }
+    Ok(inflated)
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    // Writing to a Vec can't fail.
+    encoder.write_all(data).expect("in-memory write");
+    encoder.finish().expect("in-memory write")
+}
+
+/// A Scrubber implementation for DICOM Part 10 files.
+#[derive(Debug, Clone)]
+pub struct DicomScrubber {
+    file_bytes: Vec<u8>,
+    file: DicomFile,
+    uid_key: Vec<u8>,
+}
+
+impl DicomScrubber {
+    /// Sets the secret that new UIDs are derived from. Files scrubbed with
+    /// the same key get the same new UID for a shared original, across runs.
+    /// By default, a random key is made for each run.
+    pub fn with_uid_key(mut self, key: &[u8]) -> Self {
+        self.uid_key = key.to_vec();
+        self
+    }
+
+    /// Applies the profile to the meta group and the data set.
+    fn apply(&self, policy: &ScrubPolicy) -> (DicomFile, Vec<MetadataEntry>, Vec<MetadataEntry>) {
+        let mut file = self.file.clone();
+        let (mut kept, mut removed) = (Vec::new(), Vec::new());
+        let key = &self.uid_key;
+        apply_profile(&mut file.meta, policy, key, &mut kept, &mut removed);
+        apply_profile(&mut file.data_set, policy, key, &mut kept, &mut removed);
+        (file, kept, removed)
+    }
+}
+
+impl Scrubber for DicomScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        let file = DicomFile::parse(&file_bytes)?;
+        Ok(Self {
+            file_bytes,
+            file,
+            uid_key: run_key().to_vec(),
+        })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let (_, kept, _) = self.apply(&ScrubPolicy::keep_all());
+        Ok(kept)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let (mut file, metadata_kept, metadata_removed) = self.apply(policy);
+        if metadata_removed.is_empty() {
+            return Ok(ScrubResult {
+                cleaned_file_bytes: self.file_bytes.clone(),
+                metadata_removed,
+                metadata_kept,
+            });
+        }
+        // Only a file scrubbed of everything the profile covers claims to be
+        // de-identified.
+        if metadata_kept.is_empty() {
+            mark_deidentified(&mut file.data_set);
+        }
+        // Group lengths in the data set are retired, and would be wrong now.
+        file.data_set.retain(|e| e.tag & 0xFFFF != 0);
+
+        Ok(ScrubResult {
+            cleaned_file_bytes: file.to_bytes(),
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explicit(tag: Tag, vr: &[u8; 2], value: &[u8]) -> Vec<u8> {
+        let element = Element {
+            tag,
+            vr: *vr,
+            value: Value::Bytes(value.to_vec()),
+        };
+        let mut out = Vec::new();
+        write_data_set(&mut out, &[element], META_SYNTAX);
+        out
+    }
+
+    fn implicit(tag: Tag, value: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_tag(&mut out, tag, ByteOrder::Little);
+        out.extend((value.len() as u32).to_le_bytes());
+        out.extend(value);
+        out
+    }
+
+    fn file(transfer_syntax: &str, data_set: &[u8]) -> Vec<u8> {
+        let transfer_syntax = padded(transfer_syntax.as_bytes().to_vec(), b"UI");
+        let meta = [
+            explicit(0x0002_0003, b"UI", b"1.2.3.4\0"),
+            explicit(TRANSFER_SYNTAX_UID, b"UI", &transfer_syntax),
+        ]
+        .concat();
+        let group_length = explicit(META_GROUP_LENGTH, b"UL", &(meta.len() as u32).to_le_bytes());
+        [
+            &[0; PREAMBLE_LEN][..],
+            MAGIC,
+            &group_length,
+            &meta,
+            data_set,
+        ]
+        .concat()
+    }
+
+    fn value(elements: &[Element], tag: Tag) -> Option<&[u8]> {
+        elements
+            .iter()
+            .find(|e| e.tag == tag)
+            .map(|e| match &e.value {
+                Value::Bytes(data) => data.as_slice(),
+                _ => panic!("not a plain value"),
+            })
+    }
+
+    #[test]
+    fn scrub_applies_the_profile_and_marks_the_file() {
+        let data_set = [
+            explicit(0x0008_0018, b"UI", b"1.2.3.4\0"),
+            explicit(0x0008_0060, b"CS", b"MR"),
+            explicit(0x0008_0080, b"LO", b"General Hospital"),
+            explicit(0x0009_0010, b"LO", b"ACME 1.0"),
+            explicit(0x0010_0010, b"PN", b"Doe^Jane"),
+            explicit(0x0010_0030, b"DA", b"19800101"),
+            explicit(0x0010_1010, b"AS", b"044Y"),
+        ]
+        .concat();
+        let scrubber = DicomScrubber::new(file("1.2.840.10008.1.2.1", &data_set)).unwrap();
+        let result = scrubber.scrub().unwrap();
+
+        let keys: Vec<&str> = result
+            .metadata_removed
+            .iter()
+            .map(|e| e.key.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "MediaStorageSOPInstanceUID",
+                "SOPInstanceUID",
+                "InstitutionName",
+                "Private Attribute (0009,0010)",
+                "PatientName",
+                "PatientBirthDate",
+                "PatientAge",
+            ]
+        );
+        assert_eq!(
+            result.metadata_removed[5].value,
+            MetadataValue::DateTime("1980-01-01".into())
+        );
+
+        let cleaned = DicomFile::parse(&result.cleaned_file_bytes).unwrap();
+        let uid = padded(remap_uid("1.2.3.4", run_key()).into_bytes(), b"UI");
+        assert!(uid.starts_with(b"2.25."));
+        assert_eq!(value(&cleaned.meta, 0x0002_0003), Some(uid.as_slice()));
+        assert_eq!(value(&cleaned.data_set, 0x0008_0018), Some(uid.as_slice()));
+        let mut meta = Vec::new();
+        write_data_set(&mut meta, &cleaned.meta[1..], META_SYNTAX);
+        let group_length = value(&cleaned.meta, META_GROUP_LENGTH).unwrap();
+        assert_eq!(group_length, (meta.len() as u32).to_le_bytes());
+
+        let tags: Vec<Tag> = cleaned.data_set.iter().map(|e| e.tag).collect();
+        assert_eq!(
+            tags,
+            [
+                0x0008_0018,
+                0x0008_0060,
+                0x0008_0080,
+                0x0010_0010,
+                0x0010_0030,
+                PATIENT_IDENTITY_REMOVED,
+                DEIDENTIFICATION_METHOD,
+            ]
+        );
+        assert_eq!(
+            value(&cleaned.data_set, 0x0008_0080),
+            Some(&b"ANONYMIZED"[..])
+        );
+        assert_eq!(value(&cleaned.data_set, 0x0010_0010), Some(&[][..]));
+        assert_eq!(
+            value(&cleaned.data_set, PATIENT_IDENTITY_REMOVED),
+            Some(&b"YES "[..])
+        );
+    }
+
+    #[test]
+    fn scrub_remaps_device_uids_and_removes_procedure_step_times() {
+        let data_set = [
+            explicit(0x0018_1002, b"UI", b"1.2.3.6\0"),
+            explicit(0x0040_0250, b"DA", b"20240501"),
+            explicit(0x0040_0251, b"TM", b"143000"),
+        ]
+        .concat();
+        let result = DicomScrubber::new(file("1.2.840.10008.1.2.1", &data_set))
+            .unwrap()
+            .scrub()
+            .unwrap();
+
+        let cleaned = DicomFile::parse(&result.cleaned_file_bytes).unwrap();
+        let uid = padded(remap_uid("1.2.3.6", run_key()).into_bytes(), b"UI");
+        assert_eq!(value(&cleaned.data_set, 0x0018_1002), Some(uid.as_slice()));
+        assert_eq!(value(&cleaned.data_set, 0x0040_0250), None);
+        assert_eq!(value(&cleaned.data_set, 0x0040_0251), None);
+        let method = value(&cleaned.data_set, DEIDENTIFICATION_METHOD).unwrap();
+        assert!(!String::from_utf8_lossy(method).contains("Profile"));
+    }
+
+    #[test]
+    fn implicit_vr_sequences_are_scrubbed_too() {
+        let item = implicit(0x0008_1155, b"1.2.3.5\0");
+        let data_set = [
+            implicit(0x0008_1140, &implicit(ITEM, &item)),
+            implicit(0x0010_0020, b"MRN-0042"),
+        ]
+        .concat();
+        let scrubber = DicomScrubber::new(file(IMPLICIT_VR_LITTLE_ENDIAN, &data_set)).unwrap();
+
+        let entries = scrubber.view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            [
+                "MediaStorageSOPInstanceUID",
+                "ReferencedSOPInstanceUID",
+                "PatientID"
+            ]
+        );
+        assert_eq!(entries[2].risk, crate::RiskLevel::Critical);
+
+        let cleaned = DicomFile::parse(&scrubber.scrub().unwrap().cleaned_file_bytes).unwrap();
+        let Value::Sequence { items, .. } = &cleaned.data_set[0].value else {
+            panic!("not a sequence");
+        };
+        let uid = padded(remap_uid("1.2.3.5", run_key()).into_bytes(), b"UI");
+        assert_eq!(value(&items[0], 0x0008_1155), Some(uid.as_slice()));
+        assert_eq!(value(&cleaned.data_set, 0x0010_0020), Some(&[][..]));
+    }
+
+    #[test]
+    fn kept_attributes_are_left_alone() {
+        let data_set = [
+            explicit(0x0008_0060, b"CS", b"MR"),
+            explicit(0x0010_0010, b"PN", b"Doe^Jane"),
+        ]
+        .concat();
+        let bytes = file("1.2.840.10008.1.2.1", &data_set);
+        let scrubber = DicomScrubber::new(bytes.clone()).unwrap();
+
+        let result = scrubber.scrub_with(&ScrubPolicy::keep_all()).unwrap();
+        assert_eq!(result.cleaned_file_bytes, bytes);
+
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("PatientName".into()));
+        let result = scrubber.scrub_with(&policy).unwrap();
+        let cleaned = DicomFile::parse(&result.cleaned_file_bytes).unwrap();
+        assert_eq!(result.metadata_kept.len(), 1);
+        assert_eq!(
+            value(&cleaned.data_set, 0x0010_0010),
+            Some(&b"Doe^Jane"[..])
+        );
+        // Not everything the profile covers was removed, so the file isn't marked.
+        assert_eq!(value(&cleaned.data_set, PATIENT_IDENTITY_REMOVED), None);
+    }
+
+    #[test]
+    fn uids_are_remapped_with_the_key() {
+        let bytes = file(
+            "1.2.840.10008.1.2.1",
+            &explicit(0x0008_0018, b"UI", b"1.2.3.4\0"),
+        );
+        let uid = |scrubber: DicomScrubber| {
+            let cleaned = scrubber.scrub().unwrap().cleaned_file_bytes;
+            let data_set = DicomFile::parse(&cleaned).unwrap().data_set;
+            value(&data_set, 0x0008_0018).unwrap().to_vec()
+        };
+        let keyed = |key: &[u8]| DicomScrubber::new(bytes.clone()).unwrap().with_uid_key(key);
+
+        assert_eq!(uid(keyed(b"site secret")), uid(keyed(b"site secret")));
+        assert_ne!(uid(keyed(b"site secret")), uid(keyed(b"other secret")));
+        assert_ne!(
+            uid(keyed(b"site secret")),
+            uid(DicomScrubber::new(bytes.clone()).unwrap())
+        );
+    }
+
+    #[test]
+    fn deeply_nested_sequences_are_an_error() {
+        // An undefined-length sequence holding an undefined-length item, 1000 times over.
+        let level = [
+            &[0x08, 0x00, 0x40, 0x11][..],
+            b"SQ\0\0\xFF\xFF\xFF\xFF",
+            b"\xFE\xFF\x00\xE0\xFF\xFF\xFF\xFF",
+        ]
+        .concat();
+        let bytes = file("1.2.840.10008.1.2.1", &level.repeat(1000));
+
+        match DicomFile::parse(&bytes) {
+            Err(ScrubError::ParsingError(message)) => assert!(message.contains("nested")),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,700 @@
+// File: crates/scrubkit-core/src/exif.rs
+
+//! A small reader and writer for the TIFF structure that holds EXIF data.
+//!
+//! Unlike a read-only parser, this keeps every IFD entry as raw bytes so that
+//! a filtered copy can be written back out with freshly computed offsets. The
+//! IFD model itself lives in the `tiff` module, which shares it with
+//! `TiffScrubber`.
+
+use crate::exif_tags::tag_name;
+use crate::tiff::{
+    ByteOrder, IfdEntry, IfdValue, TYPE_LONG, ifd_size, read_header, read_ifd, write_ifd,
+};
+use crate::{MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy};
+
+pub(crate) const TAG_EXIF_IFD_POINTER: u16 = 0x8769;
+pub(crate) const TAG_GPS_IFD_POINTER: u16 = 0x8825;
+pub(crate) const TAG_INTEROP_IFD_POINTER: u16 = 0xA005;
+const TAG_THUMBNAIL_OFFSET: u16 = 0x0201;
+const TAG_THUMBNAIL_LENGTH: u16 = 0x0202;
+
+/// The IFDs that make up an EXIF block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IfdKind {
+    Ifd0,
+    Exif,
+    Gps,
+    Interop,
+    Ifd1,
+}
+
+impl IfdKind {
+    /// The category reported in `MetadataEntry::category`.
+    pub(crate) fn category(self) -> MetadataCategory {
+        match self {
+            IfdKind::Ifd0 => MetadataCategory::Ifd0,
+            IfdKind::Exif => MetadataCategory::Exif,
+            IfdKind::Gps => MetadataCategory::Gps,
+            IfdKind::Interop => MetadataCategory::Interop,
+            IfdKind::Ifd1 => MetadataCategory::Ifd1,
+        }
+    }
+}
+
+/// A parsed IFD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Ifd {
+    pub(crate) kind: IfdKind,
+    pub(crate) entries: Vec<IfdEntry>,
+}
+
+/// A parsed EXIF block: the IFDs it contains and the embedded thumbnail, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExifData {
+    pub(crate) order: ByteOrder,
+    pub(crate) ifds: Vec<Ifd>,
+    pub(crate) thumbnail: Option<Vec<u8>>,
+}
+
+impl ExifData {
+    /// Parses a TIFF structure starting with the `II*\0` or `MM\0*` header.
+    pub(crate) fn parse(tiff: &[u8]) -> Result<Self, ScrubError> {
+        let (order, ifd0_offset) = read_header(tiff)?;
+
+        let mut data = ExifData {
+            order,
+            ifds: Vec::new(),
+            thumbnail: None,
+        };
+
+        let (ifd0, next) = read_ifd(tiff, order, ifd0_offset as usize)?;
+        let exif_offset = pointer_value(&ifd0, TAG_EXIF_IFD_POINTER, order);
+        let gps_offset = pointer_value(&ifd0, TAG_GPS_IFD_POINTER, order);
+        data.push(IfdKind::Ifd0, ifd0);
+
+        if let Some(offset) = exif_offset {
+            let (exif, _) = read_ifd(tiff, order, offset)?;
+            let interop_offset = pointer_value(&exif, TAG_INTEROP_IFD_POINTER, order);
+            data.push(IfdKind::Exif, exif);
+            if let Some(offset) = interop_offset {
+                let (interop, _) = read_ifd(tiff, order, offset)?;
+                data.push(IfdKind::Interop, interop);
+            }
+        }
+        if let Some(offset) = gps_offset {
+            let (gps, _) = read_ifd(tiff, order, offset)?;
+            data.push(IfdKind::Gps, gps);
+        }
+        if next != 0 {
+            let (ifd1, _) = read_ifd(tiff, order, next as usize)?;
+            let thumb_offset = pointer_value(&ifd1, TAG_THUMBNAIL_OFFSET, order);
+            let thumb_len = pointer_value(&ifd1, TAG_THUMBNAIL_LENGTH, order);
+            if let (Some(start), Some(len)) = (thumb_offset, thumb_len) {
+                data.thumbnail = start
+                    .checked_add(len)
+                    .and_then(|end| tiff.get(start..end))
+                    .map(<[u8]>::to_vec);
+            }
+            data.push(IfdKind::Ifd1, ifd1);
+        }
+
+        Ok(data)
+    }
+
+    /// Stores an IFD, dropping the structural pointer entries that are regenerated on write.
+    fn push(&mut self, kind: IfdKind, mut entries: Vec<IfdEntry>) {
+        entries.retain(|e| !is_structural(e.tag));
+        self.ifds.push(Ifd { kind, entries });
+    }
+
+    /// Returns a `MetadataEntry` for every entry, in IFD order.
+    pub(crate) fn metadata_entries(&self) -> Vec<MetadataEntry> {
+        self.ifds
+            .iter()
+            .flat_map(|ifd| ifd.entries.iter().map(|e| self.metadata_entry(ifd.kind, e)))
+            .collect()
+    }
+
+    /// Describes a single IFD entry as a `MetadataEntry`.
+    fn metadata_entry(&self, kind: IfdKind, entry: &IfdEntry) -> MetadataEntry {
+        let key = match tag_name(kind, entry.tag) {
+            Some(name) => name.to_string(),
+            None => format!("Unknown(0x{:04X})", entry.tag),
+        };
+        let (value, display_value) = self.decode(kind, entry);
+        let metadata = MetadataEntry::new(kind.category(), key, value).with_tag_id(entry.tag);
+        match display_value {
+            Some(display_value) => metadata.with_display_value(display_value),
+            None => metadata,
+        }
+    }
+
+    /// Finds the entry with the given tag in the given IFD.
+    fn find(&self, kind: IfdKind, tag: u16) -> Option<&IfdEntry> {
+        self.ifds
+            .iter()
+            .filter(|ifd| ifd.kind == kind)
+            .flat_map(|ifd| ifd.entries.iter())
+            .find(|e| e.tag == tag)
+    }
+
+    /// Returns the text of an ASCII entry, if present.
+    fn find_ascii(&self, kind: IfdKind, tag: u16) -> Option<String> {
+        match self.find(kind, tag)?.value(self.order) {
+            IfdValue::Ascii(text) => Some(text.trim().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Decodes an entry's value, using neighbouring entries where they change
+    /// its meaning (GPS references, time zone offsets). Returns the value and,
+    /// for enumerated or unit-bearing values, a friendlier display string.
+    fn decode(&self, kind: IfdKind, entry: &IfdEntry) -> (MetadataValue, Option<String>) {
+        let value = entry.value(self.order);
+        let is_main = matches!(kind, IfdKind::Ifd0 | IfdKind::Ifd1 | IfdKind::Exif);
+        let decoded = match (kind, entry.tag, &value) {
+            // GPSLatitude, GPSLongitude, GPSDestLatitude, GPSDestLongitude
+            (IfdKind::Gps, 0x0002 | 0x0004 | 0x0014 | 0x0016, IfdValue::Rational(dms)) => {
+                dms_to_degrees(dms).map(|degrees| {
+                    // The matching *Ref entry always has the preceding tag ID.
+                    let negative = matches!(
+                        self.find_ascii(IfdKind::Gps, entry.tag - 1).as_deref(),
+                        Some("S" | "W")
+                    );
+                    let degrees = if negative { -degrees } else { degrees };
+                    (MetadataValue::GpsCoordinate(degrees), None)
+                })
+            }
+            (IfdKind::Gps, 0x0006, IfdValue::Rational(v)) if v.len() == 1 => {
+                let below_sea_level = matches!(
+                    self.find(IfdKind::Gps, 0x0005).map(|e| e.value(self.order)),
+                    Some(IfdValue::Byte(b)) if b.first() == Some(&1)
+                );
+                let sign = if below_sea_level { -1 } else { 1 };
+                let altitude = MetadataValue::Rational {
+                    numerator: sign * v[0].0 as i64,
+                    denominator: v[0].1 as i64,
+                };
+                let display = format!("{} m", altitude);
+                Some((altitude, Some(display)))
+            }
+            (IfdKind::Gps, 0x0007, IfdValue::Rational(v)) if v.len() == 3 => {
+                let part = |(n, d): (u32, u32)| if d == 0 { 0.0 } else { n as f64 / d as f64 };
+                let seconds = part(v[2]);
+                let seconds = if seconds.fract() == 0.0 {
+                    format!("{:02}", seconds as u32)
+                } else {
+                    format!("{:06.3}", seconds)
+                };
+                let time = format!(
+                    "{:02}:{:02}:{}Z",
+                    part(v[0]) as u32,
+                    part(v[1]) as u32,
+                    seconds
+                );
+                Some((MetadataValue::Text(time), None))
+            }
+            (IfdKind::Gps, 0x001D, IfdValue::Ascii(date)) => {
+                Some((MetadataValue::DateTime(date.trim().replace(':', "-")), None))
+            }
+            (IfdKind::Gps, 0x0000, IfdValue::Byte(version)) => {
+                let version: Vec<String> = version.iter().map(u8::to_string).collect();
+                Some((MetadataValue::Text(version.join(".")), None))
+            }
+            // DateTime, DateTimeOriginal, DateTimeDigitized
+            (_, 0x0132 | 0x9003 | 0x9004, IfdValue::Ascii(text)) if is_main => {
+                let offset_tag = match entry.tag {
+                    0x0132 => 0x9010,
+                    0x9003 => 0x9011,
+                    _ => 0x9012,
+                };
+                let offset = self.find_ascii(IfdKind::Exif, offset_tag);
+                iso_datetime(text, offset.as_deref())
+                    .map(|iso| (MetadataValue::DateTime(iso), None))
+            }
+            (_, 0x0112, IfdValue::Short(v)) if is_main && v.len() == 1 => {
+                let name = orientation_name(v[0]).map(str::to_string);
+                Some((MetadataValue::Integer(v[0].into()), name))
+            }
+            (_, 0x0128 | 0xA210, IfdValue::Short(v)) if is_main && v.len() == 1 => {
+                let name = match v[0] {
+                    1 => Some("None"),
+                    2 => Some("inches"),
+                    3 => Some("cm"),
+                    _ => None,
+                };
+                Some((
+                    MetadataValue::Integer(v[0].into()),
+                    name.map(str::to_string),
+                ))
+            }
+            (_, 0xA001, IfdValue::Short(v)) if is_main && v.len() == 1 => {
+                let name = match v[0] {
+                    1 => Some("sRGB"),
+                    0xFFFF => Some("Uncalibrated"),
+                    _ => None,
+                };
+                Some((
+                    MetadataValue::Integer(v[0].into()),
+                    name.map(str::to_string),
+                ))
+            }
+            (_, 0x9286, IfdValue::Undefined(bytes)) if is_main => {
+                Some((MetadataValue::Text(user_comment(bytes)), None))
+            }
+            (_, 0x9C9B..=0x9C9F, IfdValue::Byte(bytes)) if is_main => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .take_while(|&u| u != 0)
+                    .collect();
+                Some((MetadataValue::Text(String::from_utf16_lossy(&units)), None))
+            }
+            _ => None,
+        };
+        decoded.unwrap_or_else(|| (typed_value(&value), None))
+    }
+
+    /// Drops every entry the policy does not keep.
+    /// Returns the `(kept, removed)` entries.
+    pub(crate) fn apply_policy(
+        &mut self,
+        policy: &ScrubPolicy,
+    ) -> (Vec<MetadataEntry>, Vec<MetadataEntry>) {
+        let entries = self.metadata_entries();
+        let decisions: Vec<bool> = entries.iter().map(|e| policy.keeps(e)).collect();
+
+        let mut decision = decisions.iter();
+        for ifd in &mut self.ifds {
+            ifd.entries.retain(|_| *decision.next().unwrap_or(&false));
+        }
+
+        let (mut kept, mut removed) = (Vec::new(), Vec::new());
+        for (entry, keep) in entries.into_iter().zip(decisions) {
+            if keep {
+                kept.push(entry);
+            } else {
+                removed.push(entry);
+            }
+        }
+        self.ifds.retain(|ifd| !ifd.entries.is_empty());
+
+        // The thumbnail only survives if its offset entry does.
+        let has_thumbnail_offset = self.ifds.iter().any(|ifd| {
+            ifd.kind == IfdKind::Ifd1 && ifd.entries.iter().any(|e| e.tag == TAG_THUMBNAIL_OFFSET)
+        });
+        if !has_thumbnail_offset {
+            self.thumbnail = None;
+        }
+
+        (kept, removed)
+    }
+
+    /// Returns true if no IFD has any entries left.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ifds.iter().all(|ifd| ifd.entries.is_empty())
+    }
+
+    /// Serializes this EXIF block back into a TIFF structure.
+    ///
+    /// Sub-IFD pointers and the thumbnail offset are recomputed. Maker notes
+    /// that rely on absolute offsets may not survive being moved.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let order = self.order;
+        let find = |kind: IfdKind| {
+            self.ifds
+                .iter()
+                .find(|i| i.kind == kind && !i.entries.is_empty())
+        };
+
+        let has_interop = find(IfdKind::Interop).is_some();
+        let has_exif = find(IfdKind::Exif).is_some() || has_interop;
+        let has_gps = find(IfdKind::Gps).is_some();
+        let has_ifd1 = find(IfdKind::Ifd1).is_some();
+
+        // Assemble the IFDs in output order, with placeholder pointer entries.
+        let pointer = |tag| IfdEntry {
+            tag,
+            field_type: TYPE_LONG,
+            count: 1,
+            data: vec![0; 4],
+        };
+        let mut layout: Vec<(IfdKind, Vec<IfdEntry>)> = Vec::new();
+        let entries_of = |kind| find(kind).map(|i| i.entries.clone()).unwrap_or_default();
+
+        let mut ifd0 = entries_of(IfdKind::Ifd0);
+        if has_exif {
+            ifd0.push(pointer(TAG_EXIF_IFD_POINTER));
+        }
+        if has_gps {
+            ifd0.push(pointer(TAG_GPS_IFD_POINTER));
+        }
+        layout.push((IfdKind::Ifd0, ifd0));
+        if has_exif {
+            let mut exif = entries_of(IfdKind::Exif);
+            if has_interop {
+                exif.push(pointer(TAG_INTEROP_IFD_POINTER));
+            }
+            layout.push((IfdKind::Exif, exif));
+        }
+        if has_interop {
+            layout.push((IfdKind::Interop, entries_of(IfdKind::Interop)));
+        }
+        if has_gps {
+            layout.push((IfdKind::Gps, entries_of(IfdKind::Gps)));
+        }
+        if has_ifd1 {
+            layout.push((IfdKind::Ifd1, entries_of(IfdKind::Ifd1)));
+        }
+        for (_, entries) in &mut layout {
+            entries.sort_by_key(|e| e.tag);
+        }
+
+        // First pass: work out where each IFD and the thumbnail will land.
+        let mut offsets = Vec::with_capacity(layout.len());
+        let mut offset = 8usize;
+        for (_, entries) in &layout {
+            offsets.push(offset);
+            offset += ifd_size(entries);
+        }
+        let thumbnail_offset = offset;
+        let kinds: Vec<IfdKind> = layout.iter().map(|(kind, _)| *kind).collect();
+        let offset_of = |kind: IfdKind| {
+            kinds
+                .iter()
+                .position(|k| *k == kind)
+                .map(|i| offsets[i] as u32)
+                .unwrap_or(0)
+        };
+
+        // Second pass: fill in the pointers now that every offset is known.
+        let thumbnail = self.thumbnail.as_deref().filter(|_| has_ifd1);
+        for (_, entries) in &mut layout {
+            for entry in entries.iter_mut() {
+                let target = match entry.tag {
+                    TAG_EXIF_IFD_POINTER => offset_of(IfdKind::Exif),
+                    TAG_GPS_IFD_POINTER => offset_of(IfdKind::Gps),
+                    TAG_INTEROP_IFD_POINTER => offset_of(IfdKind::Interop),
+                    TAG_THUMBNAIL_OFFSET if thumbnail.is_some() => thumbnail_offset as u32,
+                    _ => continue,
+                };
+                entry.field_type = TYPE_LONG;
+                entry.count = 1;
+                entry.data = order.u32_bytes(target).to_vec();
+            }
+        }
+
+        let mut out = Vec::with_capacity(thumbnail_offset);
+        match order {
+            ByteOrder::Little => out.extend_from_slice(b"II\x2A\x00"),
+            ByteOrder::Big => out.extend_from_slice(b"MM\x00\x2A"),
+        }
+        out.extend_from_slice(&order.u32_bytes(8));
+
+        for (i, (kind, entries)) in layout.iter().enumerate() {
+            let next = if *kind == IfdKind::Ifd0 && has_ifd1 {
+                offset_of(IfdKind::Ifd1)
+            } else {
+                0
+            };
+            write_ifd(&mut out, order, offsets[i], entries, next);
+        }
+        if let Some(thumbnail) = thumbnail {
+            out.extend_from_slice(thumbnail);
+        }
+        out
+    }
+}
+
+/// Converts a value without any knowledge of which tag it belongs to.
+fn typed_value(value: &IfdValue) -> MetadataValue {
+    fn one_or_list<T>(values: &[T], convert: impl Fn(&T) -> MetadataValue) -> MetadataValue {
+        match values {
+            [single] => convert(single),
+            _ => MetadataValue::List(values.iter().map(convert).collect()),
+        }
+    }
+    fn integers<T: Copy + Into<i64>>(values: &[T]) -> MetadataValue {
+        one_or_list(values, |&v| MetadataValue::Integer(v.into()))
+    }
+    fn rationals<T: Copy + Into<i64>>(values: &[(T, T)]) -> MetadataValue {
+        one_or_list(values, |&(n, d)| MetadataValue::Rational {
+            numerator: n.into(),
+            denominator: d.into(),
+        })
+    }
+
+    match value {
+        IfdValue::Ascii(text) => MetadataValue::Text(text.trim_end().to_string()),
+        IfdValue::Undefined(bytes) if is_printable(bytes) => {
+            let text = String::from_utf8_lossy(bytes);
+            MetadataValue::Text(text.trim_end_matches('\0').to_string())
+        }
+        IfdValue::Byte(bytes) | IfdValue::Undefined(bytes) if bytes.len() > 16 => {
+            MetadataValue::Binary { len: bytes.len() }
+        }
+        IfdValue::Byte(v) | IfdValue::Undefined(v) => integers(v),
+        IfdValue::Short(v) => integers(v),
+        IfdValue::Long(v) => integers(v),
+        IfdValue::SByte(v) => integers(v),
+        IfdValue::SShort(v) => integers(v),
+        IfdValue::SLong(v) => integers(v),
+        IfdValue::Float(v) => one_or_list(v, |x| MetadataValue::Text(x.to_string())),
+        IfdValue::Double(v) => one_or_list(v, |x| MetadataValue::Text(x.to_string())),
+        IfdValue::Rational(v) => rationals(v),
+        IfdValue::SRational(v) => rationals(v),
+    }
+}
+
+/// Converts a degrees/minutes/seconds triple into decimal degrees.
+fn dms_to_degrees(dms: &[(u32, u32)]) -> Option<f64> {
+    let part = |i: usize| {
+        let (n, d) = *dms.get(i)?;
+        (d != 0).then(|| n as f64 / d as f64)
+    };
+    Some(part(0)? + part(1).unwrap_or(0.0) / 60.0 + part(2).unwrap_or(0.0) / 3600.0)
+}
+
+/// Converts an EXIF `YYYY:MM:DD HH:MM:SS` timestamp into ISO 8601.
+/// Returns `None` for anything that doesn't look like one.
+fn iso_datetime(text: &str, offset: Option<&str>) -> Option<String> {
+    let text = text.trim();
+    let bytes = text.as_bytes();
+    let well_formed = bytes.len() == 19
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 | 13 | 16 => *b == b':',
+            10 => *b == b' ',
+            _ => b.is_ascii_digit(),
+        });
+    if !well_formed {
+        return None;
+    }
+    Some(format!(
+        "{}-{}-{}T{}{}",
+        &text[0..4],
+        &text[5..7],
+        &text[8..10],
+        &text[11..19],
+        offset.unwrap_or("")
+    ))
+}
+
+/// Decodes an EXIF `UserComment`, which starts with an 8-byte character code.
+fn user_comment(bytes: &[u8]) -> String {
+    let (code, text) = bytes.split_at(bytes.len().min(8));
+    let text = match code {
+        b"UNICODE\0" => {
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).into_owned(),
+    };
+    text.trim_end_matches(['\0', ' ']).to_string()
+}
+
+/// The names EXIF gives to the eight orientation values.
+fn orientation_name(value: u16) -> Option<&'static str> {
+    Some(match value {
+        1 => "Horizontal (normal)",
+        2 => "Mirror horizontal",
+        3 => "Rotate 180",
+        4 => "Mirror vertical",
+        5 => "Mirror horizontal and rotate 270 CW",
+        6 => "Rotate 90 CW",
+        7 => "Mirror horizontal and rotate 90 CW",
+        8 => "Rotate 270 CW",
+        _ => return None,
+    })
+}
+
+/// Returns true if the bytes are printable ASCII, ignoring trailing NULs.
+fn is_printable(bytes: &[u8]) -> bool {
+    let trimmed = bytes
+        .iter()
+        .rposition(|&b| b != 0)
+        .map_or(&[][..], |end| &bytes[..=end]);
+    !trimmed.is_empty() && trimmed.iter().all(|b| (0x20..0x7F).contains(b))
+}
+
+/// Returns true for tags whose values are offsets to other structures.
+fn is_structural(tag: u16) -> bool {
+    matches!(
+        tag,
+        TAG_EXIF_IFD_POINTER | TAG_GPS_IFD_POINTER | TAG_INTEROP_IFD_POINTER
+    )
+}
+
+/// Reads the single LONG/SHORT value of the entry with `tag`, if present.
+fn pointer_value(entries: &[IfdEntry], tag: u16, order: ByteOrder) -> Option<usize> {
+    let entry = entries.iter().find(|e| e.tag == tag)?;
+    match entry.field_type {
+        3 => order.read_u16(&entry.data, 0).map(usize::from),
+        _ => order.read_u32(&entry.data, 0).map(|v| v as usize),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Selector;
+
+    /// Builds a big-endian TIFF with Make in IFD0 and a GPS IFD holding GPSLatitudeRef.
+    fn tiff_with_gps() -> Vec<u8> {
+        let mut t = b"MM\x00\x2A\x00\x00\x00\x08".to_vec();
+        // IFD0 at 8: two entries -> ends at 8 + 2 + 24 + 4 = 38
+        t.extend_from_slice(&[0x00, 0x02]);
+        t.extend_from_slice(&[0x01, 0x0F, 0x00, 0x02, 0x00, 0x00, 0x00, 0x04]);
+        t.extend_from_slice(b"ACME");
+        t.extend_from_slice(&[0x88, 0x25, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]);
+        t.extend_from_slice(&[0x00, 0x00, 0x00, 0x26]);
+        t.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        // GPS IFD at 38
+        t.extend_from_slice(&[0x00, 0x01]);
+        t.extend_from_slice(&[0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02]);
+        t.extend_from_slice(b"N\0\0\0");
+        t.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        t
+    }
+
+    fn ascii(tag: u16, text: &str) -> IfdEntry {
+        let mut data = text.as_bytes().to_vec();
+        data.push(0);
+        IfdEntry {
+            tag,
+            field_type: 2,
+            count: data.len() as u32,
+            data,
+        }
+    }
+
+    fn rationals(tag: u16, values: &[(u32, u32)]) -> IfdEntry {
+        let data = values
+            .iter()
+            .flat_map(|&(n, d)| [n.to_be_bytes(), d.to_be_bytes()].concat())
+            .collect();
+        IfdEntry {
+            tag,
+            field_type: 5,
+            count: values.len() as u32,
+            data,
+        }
+    }
+
+    fn exif_with(ifds: Vec<(IfdKind, Vec<IfdEntry>)>) -> ExifData {
+        ExifData {
+            order: ByteOrder::Big,
+            ifds: ifds
+                .into_iter()
+                .map(|(kind, entries)| Ifd { kind, entries })
+                .collect(),
+            thumbnail: None,
+        }
+    }
+
+    #[test]
+    fn parse_follows_gps_pointer() {
+        let exif = ExifData::parse(&tiff_with_gps()).unwrap();
+        let entries = exif.metadata_entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].category, MetadataCategory::Ifd0);
+        assert_eq!(entries[0].key, "Make");
+        assert_eq!(entries[1].category, MetadataCategory::Gps);
+        assert_eq!(entries[1].key, "GPSLatitudeRef");
+        assert_eq!(entries[1].tag_id, Some(0x0001));
+    }
+
+    #[test]
+    fn out_of_range_thumbnails_are_ignored() {
+        let mut t = b"MM\x00\x2A\x00\x00\x00\x08".to_vec();
+        // IFD0 at 8, with Make, pointing at IFD1 at 26.
+        t.extend_from_slice(&[0x00, 0x01]);
+        t.extend_from_slice(&[0x01, 0x0F, 0x00, 0x02, 0x00, 0x00, 0x00, 0x04]);
+        t.extend_from_slice(b"ACME");
+        t.extend_from_slice(&[0x00, 0x00, 0x00, 0x1A]);
+        // IFD1, whose thumbnail offset and length overflow when added.
+        t.extend_from_slice(&[0x00, 0x02]);
+        t.extend_from_slice(&[0x02, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]);
+        t.extend_from_slice(&[0xFF; 4]);
+        t.extend_from_slice(&[0x02, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]);
+        t.extend_from_slice(&[0xFF; 4]);
+        t.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+
+        let exif = ExifData::parse(&t).unwrap();
+        assert!(exif.thumbnail.is_none());
+        assert_eq!(exif.metadata_entries()[0].key, "Make");
+    }
+
+    #[test]
+    fn gps_coordinates_are_signed_decimal_degrees() {
+        let exif = exif_with(vec![(
+            IfdKind::Gps,
+            vec![
+                ascii(0x0001, "S"),
+                rationals(0x0002, &[(33, 1), (51, 1), (2412, 100)]),
+                ascii(0x0003, "E"),
+                rationals(0x0004, &[(151, 1), (12, 1), (3000, 100)]),
+            ],
+        )]);
+        let entries = exif.metadata_entries();
+        assert_eq!(entries[1].key, "GPSLatitude");
+        assert!(matches!(entries[1].value, MetadataValue::GpsCoordinate(_)));
+        assert!((entries[1].value.as_f64().unwrap() + 33.8567).abs() < 1e-9);
+        assert_eq!(entries[1].display_value, "-33.856700");
+        assert_eq!(entries[3].display_value, "151.208333");
+    }
+
+    #[test]
+    fn datetimes_are_iso_8601_with_offsets() {
+        let exif = exif_with(vec![(
+            IfdKind::Exif,
+            vec![
+                ascii(0x9003, "2024:05:01 14:30:00"),
+                ascii(0x9011, "+02:00"),
+                rationals(0x829A, &[(1, 250)]),
+            ],
+        )]);
+        let entries = exif.metadata_entries();
+        assert_eq!(entries[0].key, "DateTimeOriginal");
+        assert_eq!(
+            entries[0].value,
+            MetadataValue::DateTime("2024-05-01T14:30:00+02:00".into())
+        );
+        assert_eq!(entries[2].key, "ExposureTime");
+        assert_eq!(
+            entries[2].value,
+            MetadataValue::Rational {
+                numerator: 1,
+                denominator: 250
+            }
+        );
+        assert_eq!(entries[2].display_value, "0.004");
+    }
+
+    #[test]
+    fn round_trip_preserves_entries() {
+        let exif = ExifData::parse(&tiff_with_gps()).unwrap();
+        let reparsed = ExifData::parse(&exif.to_bytes()).unwrap();
+        assert_eq!(exif, reparsed);
+    }
+
+    #[test]
+    fn apply_policy_drops_gps_ifd() {
+        let mut exif = ExifData::parse(&tiff_with_gps()).unwrap();
+        let policy = ScrubPolicy::keep_all().remove(Selector::Category(MetadataCategory::Gps));
+        let (kept, removed) = exif.apply_policy(&policy);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(removed.len(), 1);
+
+        let reparsed = ExifData::parse(&exif.to_bytes()).unwrap();
+        assert_eq!(reparsed.ifds.len(), 1);
+        assert_eq!(reparsed.ifds[0].kind, IfdKind::Ifd0);
+        assert_eq!(
+            reparsed.ifds[0].entries[0].value(reparsed.order),
+            IfdValue::Ascii("ACME".into())
+        );
+    }
+}
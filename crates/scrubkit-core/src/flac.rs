@@ -0,0 +1,443 @@
+// File: crates/scrubkit-core/src/flac.rs
+
+//! FLAC support.
+//!
+//! After the `fLaC` marker, a FLAC file is a chain of metadata blocks, then
+//! the audio frames. STREAMINFO, SEEKTABLE and PADDING blocks describe the
+//! audio and are always kept. VORBIS_COMMENT blocks hold the tags and are
+//! filtered field by field. PICTURE, APPLICATION and CUESHEET blocks are
+//! kept or removed whole, except that a kept picture has its image scrubbed
+//! with the same policy. Some taggers also put an ID3v2 tag in front of the
+//! marker; it is filtered frame by frame, as in MP3 files.
+
+use crate::id3::{self, Id3v2, picture_type};
+use crate::vorbis::VorbisComment;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+    embedded_metadata, scrub_embedded,
+};
+
+const MARKER: &[u8] = b"fLaC";
+
+const STREAMINFO: u8 = 0;
+const PADDING: u8 = 1;
+const APPLICATION: u8 = 2;
+const SEEKTABLE: u8 = 3;
+const VORBIS_COMMENT: u8 = 4;
+const CUESHEET: u8 = 5;
+const PICTURE: u8 = 6;
+
+/// Set in the header of the last metadata block.
+const LAST_BLOCK: u8 = 0x80;
+
+/// Returns the length of the ID3v2 tags in front of the data, if any.
+fn id3v2_len(bytes: &[u8]) -> usize {
+    let mut start = 0;
+    while let Some(len) = id3::tag_len(&bytes[start..]).filter(|len| start + len <= bytes.len()) {
+        start += len;
+    }
+    start
+}
+
+/// Returns true for files with the `fLaC` marker, possibly after ID3v2 tags.
+pub fn is_flac(bytes: &[u8]) -> bool {
+    bytes[id3v2_len(bytes)..].starts_with(MARKER)
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> Option<usize> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// A PICTURE block: its type, its MIME type, and where the image starts.
+/// Ogg files carry the same structure, base64-encoded, in a Vorbis comment.
+pub(crate) struct Picture {
+    kind: u32,
+    mime: String,
+    image_start: usize,
+}
+
+impl Picture {
+    pub(crate) fn parse(data: &[u8]) -> Option<Self> {
+        let kind = read_u32_be(data, 0)? as u32;
+        let mime_len = read_u32_be(data, 4)?;
+        let mime = String::from_utf8_lossy(data.get(8..8 + mime_len)?).into_owned();
+        let description_len = read_u32_be(data, 8 + mime_len)?;
+        // Width, height, color depth and palette size, then the image length.
+        let length_at = 12 + mime_len + description_len + 16;
+        let image_len = read_u32_be(data, length_at)?;
+        let image_start = length_at + 4;
+        data.get(image_start..image_start + image_len)?;
+        Some(Self {
+            kind,
+            mime,
+            image_start,
+        })
+    }
+
+    pub(crate) fn image<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.image_start..]
+    }
+
+    /// Names the picture type, MIME type and image size, for display.
+    pub(crate) fn describe(&self, data: &[u8]) -> String {
+        let len = data.len() - self.image_start;
+        format!("{}, {} ({} bytes)", picture_type(self.kind), self.mime, len)
+    }
+
+    fn entry(&self, data: &[u8]) -> MetadataEntry {
+        let len = data.len() - self.image_start;
+        MetadataEntry::new(
+            MetadataCategory::Flac,
+            "Picture",
+            MetadataValue::Binary { len },
+        )
+        .with_display_value(self.describe(data))
+    }
+
+    /// Rebuilds the block around a new image.
+    fn with_image(&self, data: &[u8], image: &[u8]) -> Vec<u8> {
+        let mut out = data[..self.image_start - 4].to_vec();
+        out.extend_from_slice(&(image.len() as u32).to_be_bytes());
+        out.extend_from_slice(image);
+        out
+    }
+}
+
+/// A metadata block, borrowed from the file.
+#[derive(Debug, Clone, Copy)]
+struct Block<'a> {
+    kind: u8,
+    data: &'a [u8],
+}
+
+impl Block<'_> {
+    fn is_metadata(&self) -> bool {
+        !matches!(self.kind, STREAMINFO | PADDING | SEEKTABLE)
+    }
+
+    /// Describes the metadata in this block. Vorbis comments are handled
+    /// separately, since they are filtered field by field.
+    fn metadata_entries(&self) -> Vec<MetadataEntry> {
+        let binary = |category, key: String| {
+            let len = self.data.len();
+            MetadataEntry::new(category, key, MetadataValue::Binary { len })
+        };
+        match self.kind {
+            PICTURE => match Picture::parse(self.data) {
+                Some(picture) => {
+                    let mut entries = vec![picture.entry(self.data)];
+                    entries.extend(embedded_metadata(picture.image(self.data)));
+                    entries
+                }
+                None => vec![binary(MetadataCategory::Flac, "Picture".into())],
+            },
+            APPLICATION => {
+                let id = String::from_utf8_lossy(self.data.get(..4).unwrap_or_default());
+                vec![binary(
+                    MetadataCategory::Vendor,
+                    format!("Application {}", id.trim_end_matches('\0')),
+                )]
+            }
+            CUESHEET => vec![binary(MetadataCategory::Flac, "CueSheet".into())],
+            kind => vec![binary(MetadataCategory::Flac, format!("Block {}", kind))],
+        }
+    }
+}
+
+/// A FLAC file split into its parts.
+struct Stream<'a> {
+    id3v2: Vec<&'a [u8]>,
+    blocks: Vec<Block<'a>>,
+    audio: &'a [u8],
+}
+
+fn stream(bytes: &[u8]) -> Result<Stream<'_>, ScrubError> {
+    let mut id3v2 = Vec::new();
+    let mut pos = 0;
+    while let Some(len) = id3::tag_len(&bytes[pos..]).filter(|len| pos + len <= bytes.len()) {
+        id3v2.push(&bytes[pos..pos + len]);
+        pos += len;
+    }
+    if !bytes[pos..].starts_with(MARKER) {
+        return Err(ScrubError::ParsingError("Not a valid FLAC file".into()));
+    }
+    pos += MARKER.len();
+
+    let mut blocks = Vec::new();
+    loop {
+        let corrupt =
+            || ScrubError::ParsingError(format!("Corrupt FLAC metadata block at offset {}", pos));
+        let header = bytes.get(pos..pos + 4).ok_or_else(corrupt)?;
+        let len = header[1..]
+            .iter()
+            .fold(0, |acc, &b| (acc << 8) | b as usize);
+        let data = bytes.get(pos + 4..pos + 4 + len).ok_or_else(corrupt)?;
+        blocks.push(Block {
+            kind: header[0] & !LAST_BLOCK,
+            data,
+        });
+        pos += 4 + len;
+        if header[0] & LAST_BLOCK != 0 {
+            break;
+        }
+    }
+    if blocks.first().is_none_or(|b| b.kind != STREAMINFO) {
+        return Err(ScrubError::ParsingError(
+            "FLAC file does not start with STREAMINFO".into(),
+        ));
+    }
+
+    Ok(Stream {
+        id3v2,
+        blocks,
+        audio: &bytes[pos..],
+    })
+}
+
+fn write_blocks(out: &mut Vec<u8>, blocks: &[(u8, Vec<u8>)]) {
+    out.extend_from_slice(MARKER);
+    for (i, (kind, data)) in blocks.iter().enumerate() {
+        let last = if i + 1 == blocks.len() { LAST_BLOCK } else { 0 };
+        out.push(kind | last);
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+        out.extend_from_slice(data);
+    }
+}
+
+/// A Scrubber implementation for FLAC files.
+#[derive(Debug, Clone)]
+pub struct FlacScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for FlacScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        let stream = stream(&file_bytes)?;
+        for tag in stream.id3v2 {
+            Id3v2::parse(tag)?;
+        }
+        for block in stream.blocks.iter().filter(|b| b.kind == VORBIS_COMMENT) {
+            VorbisComment::parse(block.data)?;
+        }
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let stream = stream(&self.file_bytes)?;
+        let mut metadata = Vec::new();
+        for tag in stream.id3v2 {
+            metadata.extend(Id3v2::parse(tag)?.metadata_entries());
+        }
+        for block in stream.blocks.iter().filter(|b| b.is_metadata()) {
+            if block.kind == VORBIS_COMMENT {
+                metadata.extend(VorbisComment::parse(block.data)?.metadata_entries());
+            } else {
+                metadata.extend(block.metadata_entries());
+            }
+        }
+        Ok(metadata)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let stream = stream(&self.file_bytes)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut cleaned_bytes = Vec::new();
+
+        for tag in stream.id3v2 {
+            let mut id3 = Id3v2::parse(tag)?;
+            let (kept, removed) = id3.apply_policy(policy);
+            if removed.is_empty() {
+                cleaned_bytes.extend_from_slice(tag);
+            } else if !id3.is_empty() {
+                cleaned_bytes.extend(id3.to_bytes());
+            }
+            metadata_kept.extend(kept);
+            metadata_removed.extend(removed);
+        }
+
+        let mut blocks = Vec::new();
+        for block in stream.blocks {
+            if !block.is_metadata() {
+                blocks.push((block.kind, block.data.to_vec()));
+                continue;
+            }
+            if block.kind == VORBIS_COMMENT {
+                let mut comment = VorbisComment::parse(block.data)?;
+                let (kept, removed) = comment.apply_policy(policy);
+                if removed.is_empty() {
+                    blocks.push((block.kind, block.data.to_vec()));
+                } else if !comment.is_empty() {
+                    blocks.push((block.kind, comment.to_bytes()));
+                }
+                metadata_kept.extend(kept);
+                metadata_removed.extend(removed);
+                continue;
+            }
+
+            let mut entries = block.metadata_entries();
+            if !policy.keeps(&entries[0]) {
+                metadata_removed.extend(entries);
+                continue;
+            }
+            // A kept picture has its image scrubbed instead.
+            let picture = Picture::parse(block.data).filter(|_| block.kind == PICTURE);
+            match picture.and_then(|p| Some((scrub_embedded(p.image(block.data), policy)?, p))) {
+                Some((result, picture)) => {
+                    metadata_kept.push(entries.remove(0));
+                    metadata_kept.extend(result.metadata_kept);
+                    let data = if result.metadata_removed.is_empty() {
+                        block.data.to_vec()
+                    } else {
+                        picture.with_image(block.data, &result.cleaned_file_bytes)
+                    };
+                    metadata_removed.extend(result.metadata_removed);
+                    blocks.push((block.kind, data));
+                }
+                None => {
+                    metadata_kept.extend(entries);
+                    blocks.push((block.kind, block.data.to_vec()));
+                }
+            }
+        }
+        write_blocks(&mut cleaned_bytes, &blocks);
+        cleaned_bytes.extend_from_slice(stream.audio);
+
+        if metadata_removed.is_empty() {
+            cleaned_bytes = self.file_bytes.clone();
+        }
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A FLAC frame header followed by silence.
+    const AUDIO: &[u8] = b"\xFF\xF8\x69\x08\0\0\0\0";
+    /// A JPEG whose only metadata is a comment.
+    const COVER: &[u8] = b"\xFF\xD8\xFF\xFE\x00\x06Jane\xFF\xDA\x00\x02\x00\xFF\xD9";
+
+    fn comments() -> Vec<u8> {
+        let mut data = b"\x0D\0\0\0libFLAC 1.4.3\x02\0\0\0".to_vec();
+        for comment in ["TITLE=Song", "ARTIST=Jane Doe"] {
+            data.extend((comment.len() as u32).to_le_bytes());
+            data.extend(comment.bytes());
+        }
+        data
+    }
+
+    fn picture(image: &[u8]) -> Vec<u8> {
+        let mut data = b"\0\0\0\x03\0\0\0\x0Aimage/jpeg\0\0\0\0".to_vec();
+        data.extend([0; 16]);
+        data.extend((image.len() as u32).to_be_bytes());
+        data.extend_from_slice(image);
+        data
+    }
+
+    fn flac(blocks: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_blocks(&mut bytes, blocks);
+        bytes.extend_from_slice(AUDIO);
+        bytes
+    }
+
+    fn tagged() -> Vec<u8> {
+        flac(&[
+            (STREAMINFO, vec![0x12; 34]),
+            (VORBIS_COMMENT, comments()),
+            (PICTURE, picture(COVER)),
+            (APPLICATION, b"riffdata".to_vec()),
+            (PADDING, vec![0; 8]),
+        ])
+    }
+
+    #[test]
+    fn view_metadata_lists_comments_and_pictures() {
+        let entries = FlacScrubber::new(tagged())
+            .unwrap()
+            .view_metadata()
+            .unwrap();
+        let listed: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.display_value.as_str()))
+            .collect();
+
+        assert_eq!(
+            listed,
+            [
+                ("Vendor", "libFLAC 1.4.3"),
+                ("TITLE", "Song"),
+                ("ARTIST", "Jane Doe"),
+                ("Picture", "Front cover, image/jpeg (17 bytes)"),
+                ("Comment", "Jane"),
+                ("Application riff", "(8 bytes of binary data)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn scrub_keeps_stream_info_and_audio() {
+        let result = FlacScrubber::new(tagged()).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 6);
+        assert_eq!(
+            result.cleaned_file_bytes,
+            flac(&[(STREAMINFO, vec![0x12; 34]), (PADDING, vec![0; 8])])
+        );
+    }
+
+    #[test]
+    fn scrub_with_policy_scrubs_kept_pictures() {
+        let policy =
+            ScrubPolicy::remove_all().keep(crate::Selector::Category(MetadataCategory::Flac));
+        let result = FlacScrubber::new(tagged())
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        assert_eq!(
+            result.cleaned_file_bytes,
+            flac(&[
+                (STREAMINFO, vec![0x12; 34]),
+                (PICTURE, picture(b"\xFF\xD8\xFF\xDA\x00\x02\x00\xFF\xD9")),
+                (PADDING, vec![0; 8]),
+            ])
+        );
+    }
+
+    #[test]
+    fn id3_tags_without_readable_frames_are_removed() {
+        // The frame size runs past the end of the tag.
+        let tag = b"ID3\x03\x00\x00\x00\x00\x00\x13TIT2\x00\x00\x01\x00\x00\x00\x00Jane Doe";
+        let plain = flac(&[(STREAMINFO, vec![0x12; 34])]);
+        let result = FlacScrubber::new([tag.as_slice(), &plain].concat())
+            .unwrap()
+            .scrub()
+            .unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "ID3v2");
+        assert_eq!(result.cleaned_file_bytes, plain);
+    }
+
+    #[test]
+    fn pictures_that_are_not_still_images_are_not_followed() {
+        let nested = flac(&[(STREAMINFO, vec![0x12; 34]), (PICTURE, picture(&tagged()))]);
+        let scrubber = FlacScrubber::new(nested).unwrap();
+        let keys: Vec<String> = scrubber
+            .view_metadata()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        assert_eq!(keys, ["Picture"]);
+
+        let result = scrubber.scrub_with(&ScrubPolicy::keep_all()).unwrap();
+        assert_eq!(result.metadata_kept.len(), 1);
+    }
+}
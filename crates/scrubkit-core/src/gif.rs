@@ -0,0 +1,406 @@
+// File: crates/scrubkit-core/src/gif.rs
+
+//! GIF support.
+//!
+//! After the header, the logical screen descriptor and an optional global
+//! color table, a GIF is a stream of blocks: images, and extensions made of
+//! length-prefixed sub-blocks. Comment, plain text and most application
+//! extensions are metadata. Graphic control extensions (frame timing and
+//! transparency) and the NETSCAPE2.0 looping extension are kept so that
+//! animations still play the same way.
+
+use crate::iptc::{parse_iptc, parse_resources};
+use crate::xmp::parse_xmp;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+
+const EXTENSION: u8 = 0x21;
+const IMAGE: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+
+const PLAIN_TEXT_LABEL: u8 = 0x01;
+const COMMENT_LABEL: u8 = 0xFE;
+const APPLICATION_LABEL: u8 = 0xFF;
+
+/// Application extensions that control playback rather than describe the file.
+const PLAYBACK_APPLICATIONS: &[&[u8; 11]] = &[b"NETSCAPE2.0", b"ANIMEXTS1.0"];
+
+/// A block in the GIF stream, borrowed from the file.
+#[derive(Debug, Clone, Copy)]
+struct Block<'a> {
+    /// The extension label, or `None` for image blocks.
+    label: Option<u8>,
+    /// The whole block, including the introducer and the block terminator.
+    bytes: &'a [u8],
+}
+
+impl Block<'_> {
+    /// For application extensions, the identifier and authentication code.
+    fn application(&self) -> Option<&[u8]> {
+        match (self.label, self.bytes.get(2)) {
+            (Some(APPLICATION_LABEL), Some(11)) => self.bytes.get(3..14),
+            _ => None,
+        }
+    }
+
+    fn is_metadata(&self) -> bool {
+        match self.label {
+            Some(PLAIN_TEXT_LABEL | COMMENT_LABEL) => true,
+            Some(APPLICATION_LABEL) => !self
+                .application()
+                .is_some_and(|app| PLAYBACK_APPLICATIONS.iter().any(|p| p.as_slice() == app)),
+            _ => false,
+        }
+    }
+
+    /// The extension data: its sub-blocks joined together, after the
+    /// application identifier if there is one.
+    fn data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut pos = 2;
+        while let Some(&len) = self.bytes.get(pos)
+            && len != 0
+        {
+            data.extend_from_slice(
+                self.bytes
+                    .get(pos + 1..pos + 1 + len as usize)
+                    .unwrap_or_default(),
+            );
+            pos += 1 + len as usize;
+        }
+        match self.application() {
+            Some(_) => data.split_off(11.min(data.len())),
+            None => data,
+        }
+    }
+
+    /// Describes the metadata in this block.
+    fn metadata_entries(&self) -> Vec<MetadataEntry> {
+        let data = self.data();
+        let (category, key) = match (self.label, self.application()) {
+            (Some(COMMENT_LABEL), _) => {
+                let text = String::from_utf8_lossy(&data)
+                    .trim_end_matches('\0')
+                    .to_string();
+                return vec![MetadataEntry::new(
+                    MetadataCategory::Comment,
+                    "Comment",
+                    MetadataValue::Text(text),
+                )];
+            }
+            // Plain text is drawn on the canvas, after a 12-byte text grid header.
+            (Some(PLAIN_TEXT_LABEL), _) => {
+                let text = String::from_utf8_lossy(data.get(12..).unwrap_or_default());
+                return vec![MetadataEntry::new(
+                    MetadataCategory::Comment,
+                    "PlainText",
+                    MetadataValue::Text(text.trim_end_matches('\0').to_string()),
+                )];
+            }
+            (_, Some(b"XMP DataXMP")) => return parse_xmp(self.xmp_packet()),
+            (_, Some(b"MGKIPTC0000")) => return parse_iptc(&data),
+            (_, Some(b"MGK8BIM0000")) => return parse_resources(&data),
+            (_, Some(b"ICCRGBG1012")) => (MetadataCategory::Icc, "ICCProfile".to_string()),
+            (_, Some(app)) => (
+                MetadataCategory::Vendor,
+                format!(
+                    "Application {}",
+                    String::from_utf8_lossy(app).trim_matches(|c: char| c == '\0' || c == ' ')
+                ),
+            ),
+            _ => (MetadataCategory::Vendor, "Extension".to_string()),
+        };
+        vec![MetadataEntry::new(
+            category,
+            key,
+            MetadataValue::Binary { len: data.len() },
+        )]
+    }
+
+    /// XMP is stored as raw bytes rather than sub-blocks, followed by a
+    /// "magic trailer" that makes the raw bytes parse as sub-blocks. The
+    /// packet ends with its closing `xpacket` instruction or `x:xmpmeta` tag.
+    fn xmp_packet(&self) -> &[u8] {
+        let raw = self.bytes.get(14..).unwrap_or_default();
+        let find = |needle: &[u8]| raw.windows(needle.len()).rposition(|w| w == needle);
+        let end = find(b"<?xpacket end")
+            .and_then(|start| {
+                raw[start..]
+                    .iter()
+                    .position(|&b| b == b'>')
+                    .map(|p| start + p + 1)
+            })
+            .or_else(|| find(b"</x:xmpmeta>").map(|start| start + 12))
+            .unwrap_or(raw.len());
+        &raw[..end]
+    }
+}
+
+/// Returns the end of the sub-blocks starting at `pos`, after their terminator.
+fn sub_blocks_end(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *bytes.get(pos)? as usize;
+        pos += 1 + len;
+        if len == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+/// The size of a color table, if the packed field says there is one.
+fn color_table_size(packed: u8) -> usize {
+    if packed & 0x80 != 0 {
+        3 << ((packed & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// A GIF split into its parts.
+struct Stream<'a> {
+    /// The signature, logical screen descriptor and global color table.
+    header: &'a [u8],
+    blocks: Vec<Block<'a>>,
+    /// Anything after the trailer byte.
+    trailer: &'a [u8],
+}
+
+/// Splits a GIF into its header, its blocks, and any data after the trailer.
+fn stream(bytes: &[u8]) -> Result<Stream<'_>, ScrubError> {
+    if bytes.len() < 13 || !(bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a")) {
+        return Err(ScrubError::ParsingError("Not a valid GIF file".into()));
+    }
+    let header_end = 13 + color_table_size(bytes[10]);
+    let corrupt =
+        |offset| ScrubError::ParsingError(format!("Corrupt GIF block at offset {}", offset));
+    if header_end > bytes.len() {
+        return Err(corrupt(13));
+    }
+
+    let mut blocks = Vec::new();
+    let mut pos = header_end;
+    loop {
+        let start = pos;
+        let (label, end) = match bytes.get(pos) {
+            Some(&EXTENSION) => {
+                let label = *bytes.get(pos + 1).ok_or_else(|| corrupt(start))?;
+                (Some(label), sub_blocks_end(bytes, pos + 2))
+            }
+            Some(&IMAGE) => {
+                let packed = *bytes.get(pos + 9).ok_or_else(|| corrupt(start))?;
+                // Descriptor, local color table, LZW minimum code size, then the data.
+                let data_start = pos + 10 + color_table_size(packed) + 1;
+                (None, sub_blocks_end(bytes, data_start))
+            }
+            // Some encoders leave out the trailer.
+            Some(&TRAILER) | None => {
+                return Ok(Stream {
+                    header: &bytes[..header_end],
+                    blocks,
+                    trailer: bytes.get(pos + 1..).unwrap_or_default(),
+                });
+            }
+            Some(_) => return Err(corrupt(start)),
+        };
+        let end = end
+            .filter(|e| *e <= bytes.len())
+            .ok_or_else(|| corrupt(start))?;
+        blocks.push(Block {
+            label,
+            bytes: &bytes[start..end],
+        });
+        pos = end;
+    }
+}
+
+fn trailer_entry(trailer: &[u8]) -> MetadataEntry {
+    MetadataEntry::new(
+        MetadataCategory::Vendor,
+        "Trailer",
+        MetadataValue::Binary { len: trailer.len() },
+    )
+}
+
+/// A Scrubber implementation for GIF files.
+#[derive(Debug, Clone)]
+pub struct GifScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for GifScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        stream(&file_bytes)?;
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let Stream {
+            blocks, trailer, ..
+        } = stream(&self.file_bytes)?;
+        let mut metadata: Vec<MetadataEntry> = blocks
+            .iter()
+            .filter(|block| block.is_metadata())
+            .flat_map(|block| block.metadata_entries())
+            .collect();
+        if !trailer.is_empty() {
+            metadata.push(trailer_entry(trailer));
+        }
+        Ok(metadata)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let Stream {
+            header,
+            blocks,
+            trailer,
+        } = stream(&self.file_bytes)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut cleaned_bytes = header.to_vec();
+
+        for block in blocks {
+            if !block.is_metadata() {
+                cleaned_bytes.extend_from_slice(block.bytes);
+                continue;
+            }
+            // A block is copied as-is, so it's only kept if every entry in it is.
+            let entries = block.metadata_entries();
+            if entries.iter().all(|e| policy.keeps(e)) {
+                cleaned_bytes.extend_from_slice(block.bytes);
+                metadata_kept.extend(entries);
+            } else {
+                metadata_removed.extend(entries);
+            }
+        }
+        cleaned_bytes.push(TRAILER);
+        if !trailer.is_empty() {
+            let entry = trailer_entry(trailer);
+            if policy.keeps(&entry) {
+                cleaned_bytes.extend_from_slice(trailer);
+                metadata_kept.push(entry);
+            } else {
+                metadata_removed.push(entry);
+            }
+        }
+
+        if metadata_removed.is_empty() {
+            cleaned_bytes = self.file_bytes.clone();
+        }
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1x1 canvas with a two-color global color table.
+    const SCREEN: &[u8] = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\x00\x00\x00\xFF\xFF\xFF";
+    const LOOP: &[u8] = b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00";
+    const GRAPHIC_CONTROL: &[u8] = b"\x21\xF9\x04\x00\x0A\x00\x00\x00";
+    const FRAME: &[u8] = b"\x2C\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00";
+
+    fn extension(label: u8, sub_blocks: &[&[u8]]) -> Vec<u8> {
+        let mut block = vec![EXTENSION, label];
+        for sub_block in sub_blocks {
+            block.push(sub_block.len() as u8);
+            block.extend_from_slice(sub_block);
+        }
+        block.push(0);
+        block
+    }
+
+    /// An XMP application extension, with the packet written raw and the magic trailer.
+    fn xmp() -> Vec<u8> {
+        xmp_with(br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/" dc:creator="Jane"/></rdf:RDF></x:xmpmeta>"#)
+    }
+
+    fn xmp_with(packet: &[u8]) -> Vec<u8> {
+        let mut block = b"\x21\xFF\x0BXMP DataXMP".to_vec();
+        block.extend_from_slice(packet);
+        block.push(1);
+        block.extend((0..=255u8).rev());
+        block.push(0);
+        block
+    }
+
+    fn gif(blocks: &[&[u8]]) -> Vec<u8> {
+        [SCREEN, &blocks.concat(), b"\x3B"].concat()
+    }
+
+    #[test]
+    fn view_metadata_lists_comments_and_applications() {
+        let file = gif(&[
+            LOOP,
+            &extension(COMMENT_LABEL, &[b"Made by ", b"Jane"]),
+            &xmp(),
+            &extension(APPLICATION_LABEL, &[b"ACMEAPP1.0\0", b"data"]),
+            GRAPHIC_CONTROL,
+            FRAME,
+        ]);
+        let entries = GifScrubber::new(file).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(keys, ["Comment", "dc:creator", "Application ACMEAPP1.0"]);
+        assert_eq!(entries[0].display_value, "Made by Jane");
+    }
+
+    #[test]
+    fn scrub_keeps_looping_and_frame_timing() {
+        let file = [
+            gif(&[
+                LOOP,
+                &extension(COMMENT_LABEL, &[b"hello"]),
+                &xmp(),
+                GRAPHIC_CONTROL,
+                FRAME,
+                &extension(PLAIN_TEXT_LABEL, &[&[0; 12], b"caption"]),
+                GRAPHIC_CONTROL,
+                FRAME,
+            ]),
+            b"appended".to_vec(),
+        ]
+        .concat();
+        let result = GifScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 4);
+        assert_eq!(
+            result.cleaned_file_bytes,
+            gif(&[LOOP, GRAPHIC_CONTROL, FRAME, GRAPHIC_CONTROL, FRAME])
+        );
+    }
+
+    #[test]
+    fn scrub_with_policy_keeps_comments() {
+        let file = gif(&[&extension(COMMENT_LABEL, &[b"hello"]), &xmp(), FRAME]);
+        let policy =
+            ScrubPolicy::remove_all().keep(crate::Selector::Category(MetadataCategory::Comment));
+        let result = GifScrubber::new(file).unwrap().scrub_with(&policy).unwrap();
+
+        assert_eq!(
+            result.cleaned_file_bytes,
+            gif(&[&extension(COMMENT_LABEL, &[b"hello"]), FRAME])
+        );
+    }
+
+    #[test]
+    fn truncated_color_table_is_an_error() {
+        assert!(GifScrubber::new(SCREEN[..13].to_vec()).is_err());
+    }
+
+    #[test]
+    fn empty_xmp_packets_are_removed() {
+        let file = gif(&[
+            &xmp_with(br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#),
+            FRAME,
+        ]);
+        let result = GifScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "XMP");
+        assert_eq!(result.cleaned_file_bytes, gif(&[FRAME]));
+    }
+}
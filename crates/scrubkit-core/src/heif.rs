@@ -0,0 +1,773 @@
+// File: crates/scrubkit-core/src/heif.rs
+
+//! HEIF support, covering HEIC and AVIF.
+//!
+//! HEIF stores everything as items described in the top-level `meta` box:
+//! `iinf` says what each item is, and `iloc` says where its bytes are, usually
+//! as absolute offsets into `mdat`. EXIF is an item of type `Exif`, and XMP is
+//! a `mime` item with the `application/rdf+xml` content type. Scrubbing cuts
+//! those items' bytes out and rewrites `iinf`, `iloc` and `iref` to match.
+
+use crate::exif::ExifData;
+use crate::isobmff::{
+    BmffBox, Edits, exif_tiff, full_box_children, read_boxes, read_uint, write_box, write_uint,
+};
+use crate::xmp::parse_xmp;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+use std::ops::Range;
+
+/// Brands that mark a file as HEIF-based.
+const HEIF_BRANDS: &[&[u8; 4]] = &[
+    b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"hevm", b"hevs", b"mif1", b"msf1",
+    b"mif2", b"miaf", b"avif", b"avis", b"avio",
+];
+
+/// Returns true if the file starts with an `ftyp` box naming a HEIF brand.
+pub(crate) fn is_heif(bytes: &[u8]) -> bool {
+    let Some(size) = read_uint(bytes, 0, 4) else {
+        return false;
+    };
+    if bytes.get(4..8) != Some(b"ftyp") || size < 16 {
+        return false;
+    }
+    // The major brand, a minor version, then the compatible brands.
+    let brands = bytes
+        .get(8..(size as usize).min(bytes.len()))
+        .unwrap_or_default();
+    brands
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(i, _)| *i != 1)
+        .any(|(_, brand)| HEIF_BRANDS.iter().any(|b| b.as_slice() == brand))
+}
+
+/// What kind of metadata an item holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemKind {
+    Exif,
+    Xmp,
+}
+
+/// An `infe` entry for a metadata item.
+#[derive(Debug, Clone)]
+struct ItemInfo {
+    id: u32,
+    kind: ItemKind,
+    /// The `infe` box, so `iinf` can be rewritten without it.
+    infe: BmffBox,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Extent {
+    index: u64,
+    offset: u64,
+    length: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ItemLocation {
+    id: u32,
+    construction_method: u8,
+    data_reference_index: u16,
+    base_offset: u64,
+    extents: Vec<Extent>,
+}
+
+impl ItemLocation {
+    /// The position of each extent in the data it points into, either the
+    /// file or the `idat` box, which is `len` bytes long.
+    fn file_ranges(&self, len: usize) -> Result<Vec<Range<usize>>, ScrubError> {
+        self.extents
+            .iter()
+            .map(|e| {
+                let start = self.base_offset.checked_add(e.offset);
+                let end = start.and_then(|start| start.checked_add(e.length));
+                match (start, end) {
+                    (Some(start), Some(end)) if end <= len as u64 => {
+                        Ok(start as usize..end as usize)
+                    }
+                    _ => Err(ScrubError::ParsingError(format!(
+                        "Item {} extends past the end of its data",
+                        self.id
+                    ))),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A parsed `iloc` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Iloc {
+    version: u8,
+    flags: [u8; 3],
+    offset_size: usize,
+    length_size: usize,
+    base_offset_size: usize,
+    index_size: usize,
+    items: Vec<ItemLocation>,
+}
+
+impl Iloc {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let version = *data.first()?;
+        let flags = [*data.get(1)?, *data.get(2)?, *data.get(3)?];
+        let sizes = read_uint(data, 4, 2)? as u16;
+        let offset_size = (sizes >> 12) as usize;
+        let length_size = ((sizes >> 8) & 0xF) as usize;
+        let base_offset_size = ((sizes >> 4) & 0xF) as usize;
+        let index_size = if version > 0 {
+            (sizes & 0xF) as usize
+        } else {
+            0
+        };
+
+        let id_size = if version < 2 { 2 } else { 4 };
+        let mut pos = 6;
+        let item_count = read_uint(data, pos, id_size)?;
+        pos += id_size;
+
+        let mut items = Vec::new();
+        for _ in 0..item_count {
+            let id = read_uint(data, pos, id_size)? as u32;
+            pos += id_size;
+            let mut construction_method = 0;
+            if version > 0 {
+                construction_method = (read_uint(data, pos, 2)? & 0xF) as u8;
+                pos += 2;
+            }
+            let data_reference_index = read_uint(data, pos, 2)? as u16;
+            let base_offset = read_uint(data, pos + 2, base_offset_size)?;
+            pos += 2 + base_offset_size;
+            let extent_count = read_uint(data, pos, 2)?;
+            pos += 2;
+
+            let mut extents = Vec::new();
+            for _ in 0..extent_count {
+                let index = read_uint(data, pos, index_size)?;
+                let offset = read_uint(data, pos + index_size, offset_size)?;
+                let length = read_uint(data, pos + index_size + offset_size, length_size)?;
+                pos += index_size + offset_size + length_size;
+                extents.push(Extent {
+                    index,
+                    offset,
+                    length,
+                });
+            }
+            items.push(ItemLocation {
+                id,
+                construction_method,
+                data_reference_index,
+                base_offset,
+                extents,
+            });
+        }
+
+        Some(Self {
+            version,
+            flags,
+            offset_size,
+            length_size,
+            base_offset_size,
+            index_size,
+            items,
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, ScrubError> {
+        let mut data = vec![self.version];
+        data.extend_from_slice(&self.flags);
+        let sizes = (self.offset_size << 12)
+            | (self.length_size << 8)
+            | (self.base_offset_size << 4)
+            | self.index_size;
+        data.extend_from_slice(&(sizes as u16).to_be_bytes());
+
+        let id_size = if self.version < 2 { 2 } else { 4 };
+        write_uint(&mut data, self.items.len() as u64, id_size)?;
+        for item in &self.items {
+            write_uint(&mut data, item.id as u64, id_size)?;
+            if self.version > 0 {
+                data.extend_from_slice(&(item.construction_method as u16).to_be_bytes());
+            }
+            data.extend_from_slice(&item.data_reference_index.to_be_bytes());
+            write_uint(&mut data, item.base_offset, self.base_offset_size)?;
+            write_uint(&mut data, item.extents.len() as u64, 2)?;
+            for extent in &item.extents {
+                write_uint(&mut data, extent.index, self.index_size)?;
+                write_uint(&mut data, extent.offset, self.offset_size)?;
+                write_uint(&mut data, extent.length, self.length_size)?;
+            }
+        }
+        Ok(write_box(b"iloc", &data))
+    }
+}
+
+/// `iinf` version 0 has a 16-bit entry count, later versions a 32-bit one.
+fn iinf_count_size(data: &[u8]) -> usize {
+    if data.first() == Some(&0) { 2 } else { 4 }
+}
+
+/// Reads the `infe` boxes in `iinf`.
+fn iinf_entries(bytes: &[u8], iinf: &BmffBox) -> Result<Vec<BmffBox>, ScrubError> {
+    let count_size = iinf_count_size(iinf.data(bytes));
+    read_boxes(bytes, iinf.data_start() + 4 + count_size, iinf.end)
+}
+
+/// Reads the metadata items listed in `iinf`.
+fn metadata_items(bytes: &[u8], iinf: &BmffBox) -> Result<Vec<ItemInfo>, ScrubError> {
+    let entries = iinf_entries(bytes, iinf)?;
+
+    let mut items = Vec::new();
+    for infe in entries.into_iter().filter(|b| b.is(b"infe")) {
+        let data = infe.data(bytes);
+        // Versions 0 and 1 predate item types and can't hold EXIF or XMP items.
+        let id_size = match data.first() {
+            Some(2) => 2,
+            Some(3) => 4,
+            _ => continue,
+        };
+        let Some(id) = read_uint(data, 4, id_size) else {
+            continue;
+        };
+        let type_start = 4 + id_size + 2;
+        let Some(item_type) = data.get(type_start..type_start + 4) else {
+            continue;
+        };
+        // The item name and content type are NUL-terminated strings.
+        let mut strings = data[type_start + 4..].split(|b| *b == 0);
+        strings.next();
+        let content_type = strings.next().unwrap_or_default();
+
+        let kind = match item_type {
+            b"Exif" => ItemKind::Exif,
+            b"mime" if content_type.starts_with(b"application/rdf+xml") => ItemKind::Xmp,
+            _ => continue,
+        };
+        items.push(ItemInfo {
+            id: id as u32,
+            kind,
+            infe,
+        });
+    }
+    Ok(items)
+}
+
+/// The parts of the `meta` box the scrubber works with.
+struct Meta {
+    meta: BmffBox,
+    children: Vec<BmffBox>,
+    iloc: Iloc,
+    items: Vec<ItemInfo>,
+}
+
+/// A Scrubber implementation for HEIF-based images: HEIC, HEIF and AVIF.
+#[derive(Debug, Clone)]
+pub struct HeifScrubber {
+    file_bytes: Vec<u8>,
+}
+
+// Private helper functions for HeifScrubber
+impl HeifScrubber {
+    fn top_level_boxes(&self) -> Result<Vec<BmffBox>, ScrubError> {
+        read_boxes(&self.file_bytes, 0, self.file_bytes.len())
+    }
+
+    fn meta(&self, boxes: &[BmffBox]) -> Result<Option<Meta>, ScrubError> {
+        let bytes = &self.file_bytes;
+        let Some(meta) = boxes.iter().find(|b| b.is(b"meta")).copied() else {
+            return Ok(None);
+        };
+        let children = full_box_children(bytes, &meta)?;
+        let (Some(iinf), Some(iloc)) = (
+            children.iter().find(|b| b.is(b"iinf")),
+            children.iter().find(|b| b.is(b"iloc")),
+        ) else {
+            return Ok(None);
+        };
+        let iloc = Iloc::parse(iloc.data(bytes))
+            .ok_or_else(|| ScrubError::ParsingError("Corrupt iloc box".into()))?;
+        let items = metadata_items(bytes, iinf)?;
+        Ok(Some(Meta {
+            meta,
+            children,
+            iloc,
+            items,
+        }))
+    }
+
+    /// Reads an item's bytes, from the file or from the `idat` box.
+    fn item_data(&self, meta: &Meta, id: u32) -> Option<Vec<u8>> {
+        let bytes = &self.file_bytes;
+        let location = meta.iloc.items.iter().find(|l| l.id == id)?;
+        let source = match location.construction_method {
+            0 => bytes.as_slice(),
+            1 => meta.children.iter().find(|b| b.is(b"idat"))?.data(bytes),
+            _ => return None,
+        };
+        let mut data = Vec::new();
+        for range in location.file_ranges(source.len()).ok()? {
+            data.extend_from_slice(&source[range]);
+        }
+        Some(data)
+    }
+
+    fn item_entries(&self, meta: &Meta, item: &ItemInfo) -> Vec<MetadataEntry> {
+        let data = self.item_data(meta, item.id).unwrap_or_default();
+        match item.kind {
+            ItemKind::Exif => match exif_tiff(&data).map(|(_, tiff)| ExifData::parse(tiff)) {
+                Some(Ok(exif)) => exif.metadata_entries(),
+                _ => vec![MetadataEntry::new(
+                    MetadataCategory::Exif,
+                    "Exif",
+                    MetadataValue::Binary { len: data.len() },
+                )],
+            },
+            ItemKind::Xmp => parse_xmp(&data),
+        }
+    }
+}
+
+impl Scrubber for HeifScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_heif(&file_bytes) {
+            return Err(ScrubError::ParsingError("Not a valid HEIF file".into()));
+        }
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let boxes = self.top_level_boxes()?;
+        let Some(meta) = self.meta(&boxes)? else {
+            return Ok(Vec::new());
+        };
+        Ok(meta
+            .items
+            .iter()
+            .flat_map(|item| self.item_entries(&meta, item))
+            .collect())
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let bytes = &self.file_bytes;
+        let boxes = self.top_level_boxes()?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let unchanged = |metadata_kept, metadata_removed| ScrubResult {
+            cleaned_file_bytes: bytes.clone(),
+            metadata_removed,
+            metadata_kept,
+        };
+        let Some(meta) = self.meta(&boxes)? else {
+            return Ok(unchanged(metadata_kept, metadata_removed));
+        };
+
+        // Decide what happens to each item: dropped, or replaced by a filtered copy.
+        let mut removed_items = Vec::new();
+        let mut replaced_items = Vec::new();
+        for item in &meta.items {
+            let data = self.item_data(&meta, item.id).unwrap_or_default();
+            if item.kind == ItemKind::Exif
+                && let Some((prefix, tiff)) = exif_tiff(&data)
+                && let Ok(mut exif) = ExifData::parse(tiff)
+            {
+                let (kept, removed) = exif.apply_policy(policy);
+                if removed.is_empty() {
+                    // Nothing to do.
+                } else if exif.is_empty() {
+                    removed_items.push(item.id);
+                } else {
+                    replaced_items.push((item.id, [prefix, &exif.to_bytes()].concat()));
+                }
+                metadata_kept.extend(kept);
+                metadata_removed.extend(removed);
+                continue;
+            }
+            let entries = self.item_entries(&meta, item);
+            if entries.iter().all(|e| policy.keeps(e)) {
+                metadata_kept.extend(entries);
+            } else {
+                removed_items.push(item.id);
+                metadata_removed.extend(entries);
+            }
+        }
+        if removed_items.is_empty() && replaced_items.is_empty() {
+            return Ok(unchanged(metadata_kept, metadata_removed));
+        }
+
+        let mut iloc = meta.iloc.clone();
+        let mut idat = meta
+            .children
+            .iter()
+            .find(|b| b.is(b"idat"))
+            .map(|b| b.data(bytes).to_vec());
+        let mut edits = Edits::default();
+        let mut removed_ranges = Vec::new();
+
+        // Cut removed items out of the file. Items in `idat` are blanked in place.
+        for location in iloc.items.iter_mut() {
+            let replacement = replaced_items.iter().find(|(id, _)| *id == location.id);
+            if !removed_items.contains(&location.id) && replacement.is_none() {
+                continue;
+            }
+            match location.construction_method {
+                0 => {
+                    let mut ranges = location.file_ranges(bytes.len())?.into_iter();
+                    if let Some((_, data)) = replacement {
+                        let first = ranges.next().ok_or_else(|| {
+                            ScrubError::ParsingError("EXIF item has no extents".into())
+                        })?;
+                        edits.replace(first, data.clone())?;
+                    }
+                    removed_ranges.extend(ranges);
+                }
+                1 => {
+                    let idat = idat.as_mut().ok_or_else(|| {
+                        ScrubError::ParsingError("Item refers to a missing idat box".into())
+                    })?;
+                    let ranges = location.file_ranges(idat.len())?;
+                    let mut data = replacement.map(|(_, d)| d.clone()).unwrap_or_default();
+                    let total = ranges.iter().map(|r| r.len()).sum::<usize>();
+                    if data.len() > total {
+                        return Err(ScrubError::ParsingError(
+                            "Filtered EXIF does not fit in its idat item".into(),
+                        ));
+                    }
+                    let length = data.len();
+                    data.resize(total, 0);
+                    let mut data = data.as_slice();
+                    for range in &ranges {
+                        let target = idat.get_mut(range.clone()).ok_or_else(|| {
+                            ScrubError::ParsingError("Item extends past its idat box".into())
+                        })?;
+                        let (chunk, rest) = data.split_at(range.len());
+                        target.copy_from_slice(chunk);
+                        data = rest;
+                    }
+                    location.extents = vec![Extent {
+                        index: 0,
+                        offset: location.extents.first().map_or(0, |e| e.offset),
+                        length: length as u64,
+                    }];
+                }
+                _ => {
+                    return Err(ScrubError::ParsingError(
+                        "Unsupported item construction method".into(),
+                    ));
+                }
+            }
+        }
+        // Items may share or overlap extents, so merge them before cutting.
+        removed_ranges.sort_by_key(|r: &Range<usize>| r.start);
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for range in removed_ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        for range in merged {
+            edits.remove(range)?;
+        }
+        iloc.items.retain(|l| !removed_items.contains(&l.id));
+
+        // Rebuild the meta box once with the old offsets to learn its size,
+        // then again with the offsets mapped through every edit.
+        let rebuild = |iloc: &Iloc| -> Result<Vec<u8>, ScrubError> {
+            let mut data = bytes[meta.meta.data_start()..meta.meta.data_start() + 4].to_vec();
+            for child in &meta.children {
+                match &child.box_type {
+                    b"iloc" => data.extend(iloc.to_bytes()?),
+                    b"iinf" => {
+                        data.extend(rewrite_iinf(bytes, child, &meta.items, &removed_items)?)
+                    }
+                    b"iref" => data.extend(rewrite_iref(child.data(bytes), &removed_items)?),
+                    b"idat" => data.extend(write_box(b"idat", idat.as_deref().unwrap_or_default())),
+                    _ => data.extend_from_slice(&bytes[child.range()]),
+                }
+            }
+            Ok(write_box(b"meta", &data))
+        };
+        edits.replace(meta.meta.range(), rebuild(&iloc)?)?;
+
+        let original_iloc = iloc.clone();
+        for (location, original) in iloc.items.iter_mut().zip(&original_iloc.items) {
+            if location.construction_method != 0 || location.data_reference_index != 0 {
+                continue;
+            }
+            let replacement = replaced_items.iter().find(|(id, _)| *id == location.id);
+            let ranges = original.file_ranges(bytes.len())?;
+            let new_starts: Vec<usize> = match replacement {
+                Some((_, data)) => {
+                    location.extents.truncate(1);
+                    location.extents[0].length = data.len() as u64;
+                    vec![edits.map(ranges[0].start)]
+                }
+                None => ranges.iter().map(|r| edits.map(r.start)).collect(),
+            };
+            // Keep the base offset where it was relative to the data, and make
+            // each extent relative to it.
+            if original.base_offset != 0 || iloc.offset_size == 0 {
+                location.base_offset = match iloc.offset_size {
+                    0 => new_starts[0] as u64,
+                    _ => edits.map(original.base_offset as usize) as u64,
+                };
+            }
+            for (extent, start) in location.extents.iter_mut().zip(&new_starts) {
+                extent.offset = *start as u64 - location.base_offset;
+            }
+        }
+        let meta_start = meta.meta.start;
+        edits.update(meta_start, rebuild(&iloc)?);
+
+        Ok(ScrubResult {
+            cleaned_file_bytes: edits.apply_with_sizes(bytes, &boxes)?,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+/// Rewrites `iinf` without the removed items.
+fn rewrite_iinf(
+    bytes: &[u8],
+    iinf: &BmffBox,
+    items: &[ItemInfo],
+    removed: &[u32],
+) -> Result<Vec<u8>, ScrubError> {
+    let count_size = iinf_count_size(iinf.data(bytes));
+    let entries = iinf_entries(bytes, iinf)?;
+    let dropped: Vec<BmffBox> = items
+        .iter()
+        .filter(|item| removed.contains(&item.id))
+        .map(|item| item.infe)
+        .collect();
+
+    let kept: Vec<&BmffBox> = entries.iter().filter(|b| !dropped.contains(b)).collect();
+    let mut data = iinf.data(bytes).get(..4).unwrap_or_default().to_vec();
+    write_uint(&mut data, kept.len() as u64, count_size)?;
+    for entry in kept {
+        data.extend_from_slice(&bytes[entry.range()]);
+    }
+    Ok(write_box(b"iinf", &data))
+}
+
+/// Rewrites `iref` without references from or to the removed items.
+fn rewrite_iref(data: &[u8], removed: &[u32]) -> Result<Vec<u8>, ScrubError> {
+    let id_size = if data.first() == Some(&0) { 2 } else { 4 };
+    let mut out = data[..4.min(data.len())].to_vec();
+    for reference in read_boxes(data, 4, data.len())? {
+        let body = reference.data(data);
+        let (Some(from), Some(count)) = (read_uint(body, 0, id_size), read_uint(body, id_size, 2))
+        else {
+            continue;
+        };
+        if removed.contains(&(from as u32)) {
+            continue;
+        }
+        let to: Vec<u64> = (0..count as usize)
+            .filter_map(|i| read_uint(body, id_size + 2 + i * id_size, id_size))
+            .filter(|id| !removed.contains(&(*id as u32)))
+            .collect();
+        if to.is_empty() {
+            continue;
+        }
+        let mut entry = Vec::new();
+        write_uint(&mut entry, from, id_size)?;
+        write_uint(&mut entry, to.len() as u64, 2)?;
+        for id in to {
+            write_uint(&mut entry, id, id_size)?;
+        }
+        out.extend(write_box(&reference.box_type, &entry));
+    }
+    Ok(write_box(b"iref", &out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A big-endian TIFF with Make = "ACME" and Orientation = 1.
+    fn tiff() -> Vec<u8> {
+        [
+            b"MM\0*\0\0\0\x08\0\x02".as_slice(),
+            b"\x01\x0F\0\x02\0\0\0\x05\0\0\0\x26",
+            b"\x01\x12\0\x03\0\0\0\x01\0\x01\0\0",
+            b"\0\0\0\0ACME\0",
+        ]
+        .concat()
+    }
+
+    const XMP: &[u8] = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/" dc:creator="Jane"/></rdf:RDF></x:xmpmeta>"#;
+    const IMAGE: &[u8] = b"coded image data";
+
+    /// An item's ID, type, extra `infe` fields and payload.
+    type TestItem = (u16, &'static [u8; 4], &'static [u8], Vec<u8>);
+
+    fn infe(id: u16, item_type: &[u8; 4], extra: &[u8]) -> Vec<u8> {
+        let data = [
+            &[2, 0, 0, 0][..],
+            &id.to_be_bytes(),
+            &[0, 0],
+            item_type,
+            b"\0",
+            extra,
+        ]
+        .concat();
+        write_box(b"infe", &data)
+    }
+
+    /// Builds a HEIC file with an image item (1), an EXIF item (2) and an XMP item (3),
+    /// all stored in `mdat` in that order.
+    fn heic(items: &[TestItem]) -> Vec<u8> {
+        let ftyp = write_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        let infes: Vec<u8> = items
+            .iter()
+            .flat_map(|(id, t, extra, _)| infe(*id, t, extra))
+            .collect();
+        let iinf = write_box(
+            b"iinf",
+            &[
+                &[0, 0, 0, 0][..],
+                &(items.len() as u16).to_be_bytes(),
+                &infes,
+            ]
+            .concat(),
+        );
+        let iref = write_box(
+            b"iref",
+            &[&[0, 0, 0, 0][..], &write_box(b"cdsc", &[0, 2, 0, 1, 0, 1])].concat(),
+        );
+        let hdlr = write_box(b"hdlr", b"\0\0\0\0\0\0\0\0pict\0\0\0\0\0\0\0\0\0\0\0\0\0");
+
+        // iloc version 0 with 4-byte offsets and lengths; offsets are filled in below.
+        let iloc = |mdat_start: u32| {
+            let mut data = vec![0, 0, 0, 0, 0x44, 0x00];
+            data.extend_from_slice(&(items.len() as u16).to_be_bytes());
+            let mut offset = mdat_start;
+            for (id, _, _, payload) in items {
+                data.extend_from_slice(&id.to_be_bytes());
+                data.extend_from_slice(&[0, 0, 0, 1]);
+                data.extend_from_slice(&offset.to_be_bytes());
+                data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+                offset += payload.len() as u32;
+            }
+            write_box(b"iloc", &data)
+        };
+        let meta = |mdat_start| {
+            write_box(
+                b"meta",
+                &[&[0, 0, 0, 0][..], &hdlr, &iinf, &iref, &iloc(mdat_start)].concat(),
+            )
+        };
+        let mdat_start = (ftyp.len() + meta(0).len() + 8) as u32;
+        let payloads: Vec<u8> = items.iter().flat_map(|(.., p)| p.clone()).collect();
+        [ftyp, meta(mdat_start), write_box(b"mdat", &payloads)].concat()
+    }
+
+    fn exif_payload() -> Vec<u8> {
+        [&[0, 0, 0, 6][..], b"Exif\0\0", &tiff()].concat()
+    }
+
+    fn all_items() -> Vec<TestItem> {
+        vec![
+            (1, b"hvc1", b"", IMAGE.to_vec()),
+            (2, b"Exif", b"", exif_payload()),
+            (3, b"mime", b"application/rdf+xml\0", XMP.to_vec()),
+        ]
+    }
+
+    #[test]
+    fn view_metadata_finds_exif_and_xmp_items() {
+        let scrubber = HeifScrubber::new(heic(&all_items())).unwrap();
+        let keys: Vec<String> = scrubber
+            .view_metadata()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        assert_eq!(keys, ["Make", "Orientation", "dc:creator"]);
+    }
+
+    #[test]
+    fn scrub_removes_items_and_fixes_offsets() {
+        let scrubber = HeifScrubber::new(heic(&all_items())).unwrap();
+        let result = scrubber.scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 3);
+        let cleaned = HeifScrubber::new(result.cleaned_file_bytes).unwrap();
+        assert!(cleaned.view_metadata().unwrap().is_empty());
+        let boxes = cleaned.top_level_boxes().unwrap();
+        let meta = cleaned.meta(&boxes).unwrap().unwrap();
+        assert_eq!(meta.iloc.items.len(), 1);
+        assert_eq!(cleaned.item_data(&meta, 1).unwrap(), IMAGE);
+        assert_eq!(boxes[2].data(&cleaned.file_bytes), IMAGE);
+    }
+
+    #[test]
+    fn scrub_with_policy_rewrites_exif_item() {
+        let scrubber = HeifScrubber::new(heic(&all_items())).unwrap();
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::TagId(0x0112));
+        let result = scrubber.scrub_with(&policy).unwrap();
+
+        assert_eq!(result.metadata_kept.len(), 1);
+        let cleaned = HeifScrubber::new(result.cleaned_file_bytes).unwrap();
+        assert_eq!(cleaned.view_metadata().unwrap(), result.metadata_kept);
+        let boxes = cleaned.top_level_boxes().unwrap();
+        let meta = cleaned.meta(&boxes).unwrap().unwrap();
+        assert_eq!(cleaned.item_data(&meta, 1).unwrap(), IMAGE);
+    }
+
+    #[test]
+    fn empty_xmp_items_are_removed() {
+        let items = [
+            (1, b"hvc1", b"".as_slice(), IMAGE.to_vec()),
+            (
+                3,
+                b"mime",
+                b"application/rdf+xml\0",
+                br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#.to_vec(),
+            ),
+        ];
+        let result = HeifScrubber::new(heic(&items)).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "XMP");
+        let cleaned = HeifScrubber::new(result.cleaned_file_bytes).unwrap();
+        let boxes = cleaned.top_level_boxes().unwrap();
+        let meta = cleaned.meta(&boxes).unwrap().unwrap();
+        assert_eq!(meta.iloc.items.len(), 1);
+    }
+
+    #[test]
+    fn scrub_merges_items_that_share_extents() {
+        let mut file = heic(&all_items());
+        // Point the XMP item at the EXIF item's extent.
+        let iloc = file.windows(4).position(|w| w == b"iloc").unwrap();
+        let entry = |n: usize| iloc + 12 + n * 14 + 6;
+        let exif_extent = file[entry(1)..entry(1) + 8].to_vec();
+        file[entry(2)..entry(2) + 8].copy_from_slice(&exif_extent);
+
+        let result = HeifScrubber::new(file).unwrap().scrub().unwrap();
+        let cleaned = HeifScrubber::new(result.cleaned_file_bytes).unwrap();
+        let boxes = cleaned.top_level_boxes().unwrap();
+        let meta = cleaned.meta(&boxes).unwrap().unwrap();
+        assert_eq!(cleaned.item_data(&meta, 1).unwrap(), IMAGE);
+    }
+
+    #[test]
+    fn file_ranges_reject_overflowing_extents() {
+        let location = ItemLocation {
+            id: 1,
+            construction_method: 0,
+            data_reference_index: 0,
+            base_offset: u64::MAX,
+            extents: vec![Extent {
+                index: 0,
+                offset: 8,
+                length: 4,
+            }],
+        };
+        assert!(location.file_ranges(100).is_err());
+    }
+}
@@ -0,0 +1,668 @@
+// File: crates/scrubkit-core/src/id3.rs
+
+//! ID3v2 tags, as found at the start of MP3 files and inside WAV and AIFF
+//! chunks.
+//!
+//! A tag is a 10-byte header followed by frames, each with its own ID, size
+//! and flags. Versions 2.2, 2.3 and 2.4 differ in the width of frame IDs and
+//! in how sizes and unsynchronisation are encoded. Frames are kept as raw
+//! bytes so that the ones a policy keeps are written back exactly as they
+//! were. Cover art is the exception: the embedded image is scrubbed with the
+//! same policy, since it can carry EXIF of its own.
+
+use crate::xmp::looks_like_date;
+use crate::{MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy};
+use crate::{embedded_metadata, scrub_embedded};
+
+const HEADER_LEN: usize = 10;
+
+/// Tag header flags.
+const UNSYNCHRONISATION: u8 = 0x80;
+const EXTENDED_HEADER: u8 = 0x40;
+const FOOTER: u8 = 0x10;
+
+/// Friendly names for well-known frames, by their ID3v2.3/2.4 and ID3v2.2 IDs.
+const FRAME_NAMES: &[(&str, &str, &str)] = &[
+    ("TIT1", "TT1", "Grouping"),
+    ("TIT2", "TT2", "Title"),
+    ("TIT3", "TT3", "Subtitle"),
+    ("TPE1", "TP1", "Artist"),
+    ("TPE2", "TP2", "AlbumArtist"),
+    ("TPE3", "TP3", "Conductor"),
+    ("TALB", "TAL", "Album"),
+    ("TRCK", "TRK", "Track"),
+    ("TPOS", "TPA", "Disc"),
+    ("TCON", "TCO", "Genre"),
+    ("TCOM", "TCM", "Composer"),
+    ("TEXT", "TXT", "Lyricist"),
+    ("TOPE", "TOA", "OriginalArtist"),
+    ("TYER", "TYE", "Year"),
+    ("TDAT", "TDA", "Date"),
+    ("TIME", "TIM", "Time"),
+    ("TDRC", "", "RecordingTime"),
+    ("TDEN", "", "EncodingTime"),
+    ("TDTG", "", "TaggingTime"),
+    ("TENC", "TEN", "EncodedBy"),
+    ("TSSE", "TSS", "EncoderSettings"),
+    ("TCOP", "TCR", "Copyright"),
+    ("TPUB", "TPB", "Publisher"),
+    ("TOWN", "", "FileOwner"),
+    ("TLEN", "TLE", "Length"),
+    ("TBPM", "TBP", "BPM"),
+    ("TLAN", "TLA", "Language"),
+    ("TXXX", "TXX", "UserDefinedText"),
+    ("WXXX", "WXX", "UserDefinedURL"),
+    ("COMM", "COM", "Comment"),
+    ("USLT", "ULT", "Lyrics"),
+    ("APIC", "PIC", "Picture"),
+    ("GEOB", "GEO", "EncapsulatedObject"),
+    ("PRIV", "", "Private"),
+    ("UFID", "UFI", "UniqueFileID"),
+    ("POPM", "POP", "Popularimeter"),
+    ("PCNT", "CNT", "PlayCounter"),
+];
+
+/// Reads a 28-bit "syncsafe" integer, stored 7 bits per byte.
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |acc, &b| (acc << 7) | (b & 0x7F) as usize)
+}
+
+fn write_syncsafe(out: &mut Vec<u8>, value: usize) {
+    out.extend((0..4).rev().map(|i| ((value >> (7 * i)) & 0x7F) as u8));
+}
+
+/// Undoes unsynchronisation, which inserts a zero after every 0xFF so that
+/// players don't mistake tag data for an audio frame.
+fn resync(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut after_ff = false;
+    for &b in bytes {
+        if !(after_ff && b == 0) {
+            out.push(b);
+        }
+        after_ff = b == 0xFF;
+    }
+    out
+}
+
+/// Returns the length of the ID3v2 tag at the start of `bytes`, including its
+/// header and footer.
+pub(crate) fn tag_len(bytes: &[u8]) -> Option<usize> {
+    if !bytes.starts_with(b"ID3") || bytes.len() < HEADER_LEN {
+        return None;
+    }
+    let footer = if bytes[3] == 4 && bytes[5] & FOOTER != 0 {
+        HEADER_LEN
+    } else {
+        0
+    };
+    Some(HEADER_LEN + syncsafe(&bytes[6..10]) + footer)
+}
+
+/// Decodes a string in one of the four ID3 text encodings.
+fn decode(encoding: u8, bytes: &[u8]) -> String {
+    match encoding {
+        0 => bytes.iter().map(|&b| b as char).collect(),
+        1 | 2 => {
+            // UTF-16 strings start with a byte order mark; 2 is always big-endian.
+            let (little_endian, bytes) = match bytes {
+                [0xFF, 0xFE, rest @ ..] if encoding == 1 => (true, rest),
+                [0xFE, 0xFF, rest @ ..] if encoding == 1 => (false, rest),
+                _ => (false, bytes),
+            };
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| {
+                    if little_endian {
+                        u16::from_le_bytes([c[0], c[1]])
+                    } else {
+                        u16::from_be_bytes([c[0], c[1]])
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Splits off a null-terminated string. The terminator is two bytes wide in
+/// the UTF-16 encodings.
+fn split_terminated(encoding: u8, bytes: &[u8]) -> (&[u8], &[u8]) {
+    let end = match encoding {
+        1 | 2 => (0..bytes.len().saturating_sub(1))
+            .step_by(2)
+            .find(|&i| bytes[i] == 0 && bytes[i + 1] == 0)
+            .map(|i| (i, i + 2)),
+        _ => bytes.iter().position(|&b| b == 0).map(|i| (i, i + 1)),
+    };
+    match end {
+        Some((end, next)) => (&bytes[..end], &bytes[next..]),
+        None => (bytes, &[]),
+    }
+}
+
+fn text_value(text: String) -> MetadataValue {
+    if looks_like_date(&text) {
+        MetadataValue::DateTime(text)
+    } else {
+        MetadataValue::Text(text)
+    }
+}
+
+/// Reads the strings of a text frame. ID3v2.4 separates multiple values with
+/// null terminators.
+fn text_values(content: &[u8]) -> Option<MetadataValue> {
+    let (&encoding, mut rest) = content.split_first()?;
+    let mut values = Vec::new();
+    while !rest.is_empty() {
+        let (text, next) = split_terminated(encoding, rest);
+        values.push(decode(encoding, text));
+        rest = next;
+    }
+    values.retain(|v| !v.is_empty());
+    match values.len() {
+        0 => None,
+        1 => values.pop().map(text_value),
+        _ => Some(MetadataValue::List(
+            values.into_iter().map(text_value).collect(),
+        )),
+    }
+}
+
+/// Names the picture types shared by ID3v2 `APIC` frames and FLAC `PICTURE` blocks.
+pub(crate) fn picture_type(kind: u32) -> &'static str {
+    match kind {
+        1 | 2 => "File icon",
+        3 => "Front cover",
+        4 => "Back cover",
+        5 => "Leaflet page",
+        6 => "Media",
+        7 => "Lead artist",
+        8 => "Artist",
+        9 => "Conductor",
+        10 => "Band",
+        11 => "Composer",
+        12 => "Lyricist",
+        13 => "Recording location",
+        14 => "During recording",
+        15 => "During performance",
+        _ => "Picture",
+    }
+}
+
+/// An attached picture: its type, its MIME type (or, in ID3v2.2, its image
+/// format), and where the image starts in the frame content.
+struct Picture {
+    kind: u8,
+    mime: String,
+    image_start: usize,
+}
+
+fn picture(major: u8, content: &[u8]) -> Option<Picture> {
+    let (&encoding, rest) = content.split_first()?;
+    let (mime, rest) = match major {
+        2 => (rest.get(..3)?, rest.get(3..)?),
+        _ => split_terminated(0, rest),
+    };
+    let (&kind, rest) = rest.split_first()?;
+    let (_, image) = split_terminated(encoding, rest);
+    Some(Picture {
+        kind,
+        mime: decode(0, mime),
+        image_start: content.len() - image.len(),
+    })
+}
+
+/// A frame, kept as raw bytes.
+struct Frame {
+    id: String,
+    /// The status and format flags; ID3v2.2 frames have none.
+    flags: [u8; 2],
+    /// The frame as stored, header included.
+    raw: Vec<u8>,
+    /// The frame content with unsynchronisation undone, or `None` if it is
+    /// compressed or encrypted.
+    content: Option<Vec<u8>>,
+}
+
+impl Frame {
+    fn name(&self) -> &str {
+        FRAME_NAMES
+            .iter()
+            .find(|(id, v22, _)| *id == self.id || *v22 == self.id)
+            .map_or(&self.id, |(_, _, name)| name)
+    }
+
+    fn is_picture(&self) -> bool {
+        self.name() == "Picture"
+    }
+
+    /// Describes the frame itself. The image inside a picture frame is
+    /// described separately.
+    fn entry(&self, major: u8) -> MetadataEntry {
+        let name = self.name();
+        let Some(content) = &self.content else {
+            let len = self.raw.len();
+            return MetadataEntry::new(MetadataCategory::Id3, name, MetadataValue::Binary { len });
+        };
+        let binary = |len| MetadataValue::Binary { len };
+        let encoding = content.first().copied().unwrap_or(0);
+        let rest = content.get(1..).unwrap_or_default();
+
+        let (key, value, display) = match name {
+            // User-defined frames carry their own name.
+            "UserDefinedText" | "UserDefinedURL" => {
+                let (description, value) = split_terminated(encoding, rest);
+                let description = decode(encoding, description);
+                let value = match name {
+                    "UserDefinedText" => text_values(&[&[encoding], value].concat()),
+                    _ => Some(MetadataValue::Text(decode(0, value))),
+                };
+                let key = if description.is_empty() {
+                    name.to_string()
+                } else {
+                    description
+                };
+                (
+                    key,
+                    value.unwrap_or(MetadataValue::Text(String::new())),
+                    None,
+                )
+            }
+            "Comment" | "Lyrics" => {
+                let (_, text) = split_terminated(encoding, rest.get(3..).unwrap_or_default());
+                let value = MetadataValue::Text(decode(encoding, text));
+                (name.to_string(), value, None)
+            }
+            "Picture" => match picture(major, content) {
+                Some(p) => {
+                    let len = content.len() - p.image_start;
+                    let display = format!(
+                        "{}, {} ({} bytes)",
+                        picture_type(p.kind.into()),
+                        p.mime,
+                        len
+                    );
+                    (name.to_string(), binary(len), Some(display))
+                }
+                None => (name.to_string(), binary(content.len()), None),
+            },
+            "Private" | "UniqueFileID" => {
+                let (owner, data) = split_terminated(0, content);
+                let display = format!("{} ({} bytes)", decode(0, owner), data.len());
+                (name.to_string(), binary(data.len()), Some(display))
+            }
+            "Popularimeter" => {
+                let (email, rest) = split_terminated(0, content);
+                let email = decode(0, email);
+                let display = match rest.first() {
+                    Some(rating) => format!("{} (rating {})", email, rating),
+                    None => email.clone(),
+                };
+                (name.to_string(), MetadataValue::Text(email), Some(display))
+            }
+            _ if self.id.starts_with('T') => {
+                let value = text_values(content).unwrap_or(MetadataValue::Text(String::new()));
+                (name.to_string(), value, None)
+            }
+            _ if self.id.starts_with('W') => {
+                let value = MetadataValue::Text(decode(0, split_terminated(0, content).0));
+                (name.to_string(), value, None)
+            }
+            _ => (name.to_string(), binary(content.len()), None),
+        };
+        let entry = MetadataEntry::new(MetadataCategory::Id3, key, value);
+        match display {
+            Some(display) => entry.with_display_value(display),
+            None => entry,
+        }
+    }
+
+    /// The embedded image of a picture frame.
+    fn image(&self, major: u8) -> Option<&[u8]> {
+        let content = self.content.as_ref().filter(|_| self.is_picture())?;
+        Some(&content[picture(major, content)?.image_start..])
+    }
+}
+
+/// An ID3v2 tag.
+pub(crate) struct Id3v2 {
+    major: u8,
+    revision: u8,
+    frames: Vec<Frame>,
+    /// Whatever follows the last frame that could be read, unless it is padding.
+    rest: Vec<u8>,
+}
+
+impl Id3v2 {
+    /// Parses the tag at the start of `bytes`.
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self, ScrubError> {
+        if tag_len(bytes).is_none_or(|len| len > bytes.len()) {
+            return Err(ScrubError::ParsingError("Truncated ID3v2 tag".into()));
+        }
+        let (major, revision, flags) = (bytes[3], bytes[4], bytes[5]);
+        if !(2..=4).contains(&major) {
+            return Err(ScrubError::UnsupportedFileType(format!(
+                "ID3v2.{} tags are not supported",
+                major
+            )));
+        }
+        // ID3v2.2 used this flag for a compression scheme that was never defined.
+        if major == 2 && flags & 0x40 != 0 {
+            return Err(ScrubError::UnsupportedFileType(
+                "Compressed ID3v2.2 tags are not supported".into(),
+            ));
+        }
+
+        let body = &bytes[HEADER_LEN..HEADER_LEN + syncsafe(&bytes[6..10])];
+        // Before ID3v2.4, unsynchronisation applies to the whole tag.
+        let body = if major < 4 && flags & UNSYNCHRONISATION != 0 {
+            resync(body)
+        } else {
+            body.to_vec()
+        };
+        let mut pos = 0;
+        if flags & EXTENDED_HEADER != 0 && major > 2 {
+            // The ID3v2.3 size leaves out its own four bytes; the 2.4 one is syncsafe.
+            pos = match (major, body.get(..4)) {
+                (3, Some(size)) => {
+                    4 + u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize
+                }
+                (_, Some(size)) => syncsafe(size),
+                (_, None) => 0,
+            };
+        }
+
+        let (id_len, header_len) = if major == 2 { (3, 6) } else { (4, 10) };
+        let mut frames = Vec::new();
+        while let Some(header) = body.get(pos..pos + header_len) {
+            let id = &header[..id_len];
+            // Padding, or garbage that no frame ID looks like.
+            if !id
+                .iter()
+                .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+            {
+                break;
+            }
+            let size = match major {
+                2 => header[3..6]
+                    .iter()
+                    .fold(0, |acc, &b| (acc << 8) | b as usize),
+                3 => u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize,
+                _ => syncsafe(&header[4..8]),
+            };
+            let Some(data) = body.get(pos + header_len..pos + header_len + size) else {
+                break;
+            };
+            let flags = match major {
+                2 => [0, 0],
+                _ => [header[8], header[9]],
+            };
+            frames.push(Frame {
+                id: String::from_utf8_lossy(id).into_owned(),
+                flags,
+                raw: body[pos..pos + header_len + size].to_vec(),
+                content: frame_content(major, flags[1], data),
+            });
+            pos += header_len + size;
+        }
+        // A garbled frame, or one whose size runs past the end of the tag,
+        // may still hold readable text, so the rest is kept to be reported.
+        let rest = body.get(pos..).unwrap_or_default();
+        let rest = if rest.iter().all(|&b| b == 0) {
+            Vec::new()
+        } else {
+            rest.to_vec()
+        };
+
+        Ok(Self {
+            major,
+            revision,
+            frames,
+            rest,
+        })
+    }
+
+    /// Describes every frame, followed by the metadata of its embedded image
+    /// if it has one.
+    pub(crate) fn metadata_entries(&self) -> Vec<MetadataEntry> {
+        let mut entries = Vec::new();
+        for frame in &self.frames {
+            entries.push(frame.entry(self.major));
+            if let Some(image) = frame.image(self.major) {
+                entries.extend(embedded_metadata(image));
+            }
+        }
+        entries.extend(self.rest_entry());
+        entries
+    }
+
+    /// Describes the bytes that could not be read as frames, if there are any.
+    fn rest_entry(&self) -> Option<MetadataEntry> {
+        (!self.rest.is_empty()).then(|| {
+            MetadataEntry::new(
+                MetadataCategory::Id3,
+                "ID3v2",
+                MetadataValue::Binary {
+                    len: self.rest.len(),
+                },
+            )
+        })
+    }
+
+    /// Drops every frame the policy does not keep, and scrubs the images in
+    /// the picture frames it does. Returns the `(kept, removed)` entries.
+    pub(crate) fn apply_policy(
+        &mut self,
+        policy: &ScrubPolicy,
+    ) -> (Vec<MetadataEntry>, Vec<MetadataEntry>) {
+        let (mut kept, mut removed) = (Vec::new(), Vec::new());
+        let major = self.major;
+        self.frames.retain_mut(|frame| {
+            let entry = frame.entry(major);
+            if !policy.keeps(&entry) {
+                removed.push(entry);
+                removed.extend(
+                    frame
+                        .image(major)
+                        .map(embedded_metadata)
+                        .unwrap_or_default(),
+                );
+                return false;
+            }
+            kept.push(entry);
+            let Some(image) = frame.image(major) else {
+                return true;
+            };
+            let image_len = image.len();
+            let Some(result) = scrub_embedded(image, policy) else {
+                return true;
+            };
+            if !result.metadata_removed.is_empty()
+                && let Some(content) = &frame.content
+            {
+                let prefix = &content[..content.len() - image_len];
+                let content = [prefix, &result.cleaned_file_bytes].concat();
+                frame.raw = encode_frame(major, &frame.id, frame.flags, &content);
+                frame.content = Some(content);
+            }
+            kept.extend(result.metadata_kept);
+            removed.extend(result.metadata_removed);
+            true
+        });
+        if let Some(entry) = self.rest_entry() {
+            if policy.keeps(&entry) {
+                kept.push(entry);
+            } else {
+                removed.push(entry);
+                self.rest.clear();
+            }
+        }
+        (kept, removed)
+    }
+
+    /// Returns true if nothing is left of the tag.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.frames.is_empty() && self.rest.is_empty()
+    }
+
+    /// Serializes the tag, without padding, an extended header or a footer.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut frames: Vec<u8> = self.frames.iter().flat_map(|f| f.raw.clone()).collect();
+        frames.extend_from_slice(&self.rest);
+        let mut out = vec![b'I', b'D', b'3', self.major, self.revision, 0];
+        write_syncsafe(&mut out, frames.len());
+        out.extend(frames);
+        out
+    }
+}
+
+/// Reads a frame's content, undoing ID3v2.4 per-frame unsynchronisation and
+/// skipping its data length indicator.
+fn frame_content(major: u8, format_flags: u8, data: &[u8]) -> Option<Vec<u8>> {
+    match major {
+        2 => Some(data.to_vec()),
+        // Compression, encryption and grouping.
+        3 if format_flags & 0xE0 != 0 => None,
+        3 => Some(data.to_vec()),
+        _ if format_flags & 0x4C != 0 => None,
+        _ => {
+            let data = if format_flags & 0x01 != 0 {
+                data.get(4..)?
+            } else {
+                data
+            };
+            if format_flags & 0x02 != 0 {
+                Some(resync(data))
+            } else {
+                Some(data.to_vec())
+            }
+        }
+    }
+}
+
+/// Builds a frame holding plain content, with no format flags set.
+fn encode_frame(major: u8, id: &str, flags: [u8; 2], content: &[u8]) -> Vec<u8> {
+    let mut out = id.as_bytes().to_vec();
+    match major {
+        2 => out.extend_from_slice(&(content.len() as u32).to_be_bytes()[1..]),
+        3 => out.extend_from_slice(&(content.len() as u32).to_be_bytes()),
+        _ => write_syncsafe(&mut out, content.len()),
+    }
+    if major > 2 {
+        out.extend_from_slice(&[flags[0], 0]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(id: &str, content: &[u8]) -> Vec<u8> {
+        encode_frame(3, id, [0, 0], content)
+    }
+
+    fn tag(frames: &[&[u8]]) -> Vec<u8> {
+        let frames = frames.concat();
+        let mut out = b"ID3\x03\x00\x00".to_vec();
+        write_syncsafe(&mut out, frames.len() + 16);
+        out.extend(frames);
+        out.extend([0; 16]);
+        out
+    }
+
+    #[test]
+    fn parse_decodes_text_and_user_frames() {
+        let bytes = tag(&[
+            &frame("TIT2", b"\x01\xFF\xFEH\0i\0"),
+            &frame("TSSE", b"\x00LAME 3.100"),
+            &frame("TXXX", b"\x03Recorded at\0Studio B"),
+            &frame("COMM", b"\x00engnote\0Rough mix"),
+            &frame("PRIV", b"WM/MediaClassPrimaryID\0\x01\x02\x03\x04"),
+        ]);
+        let entries = Id3v2::parse(&bytes).unwrap().metadata_entries();
+        let listed: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.display_value.as_str()))
+            .collect();
+
+        assert_eq!(
+            listed,
+            [
+                ("Title", "Hi"),
+                ("EncoderSettings", "LAME 3.100"),
+                ("Recorded at", "Studio B"),
+                ("Comment", "Rough mix"),
+                ("Private", "WM/MediaClassPrimaryID (4 bytes)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_policy_keeps_selected_frames() {
+        let title = frame("TIT2", b"\x00Song");
+        let bytes = tag(&[&title, &frame("TENC", b"\x00Jane Doe")]);
+        let mut id3 = Id3v2::parse(&bytes).unwrap();
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("Title".into()));
+        let (kept, removed) = id3.apply_policy(&policy);
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(removed[0].key, "EncodedBy");
+        let mut expected = b"ID3\x03\x00\x00".to_vec();
+        write_syncsafe(&mut expected, title.len());
+        expected.extend(title);
+        assert_eq!(id3.to_bytes(), expected);
+    }
+
+    #[test]
+    fn parse_reads_unsynchronised_v24_frames() {
+        // A 2.4 frame with a data length indicator and an unsynchronised 0xFF.
+        let mut bytes = b"ID3\x04\x00\x00".to_vec();
+        let frame = b"TPE1\x00\x00\x00\x09\x00\x03\x00\x00\x00\x05\x00A\xFF\x00B";
+        write_syncsafe(&mut bytes, frame.len());
+        bytes.extend_from_slice(frame);
+
+        let entries = Id3v2::parse(&bytes).unwrap().metadata_entries();
+        assert_eq!(entries[0].key, "Artist");
+        assert_eq!(entries[0].display_value, "A\u{FF}B");
+    }
+
+    #[test]
+    fn unreadable_frames_are_reported_and_removed() {
+        // A frame whose size runs past the end of the tag.
+        let mut bytes = b"ID3\x04\x00\x00".to_vec();
+        let frame = b"TIT2\x00\x00\x01\x00\x00\x00\x00Jane Doe";
+        write_syncsafe(&mut bytes, frame.len() + 4);
+        bytes.extend_from_slice(frame);
+        bytes.extend([0; 4]);
+        let mut id3 = Id3v2::parse(&bytes).unwrap();
+
+        let entries = id3.metadata_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, "ID3v2");
+        assert_eq!(entries[0].value, MetadataValue::Binary { len: 23 });
+        let (kept, removed) = id3.apply_policy(&ScrubPolicy::remove_all());
+        assert_eq!((kept.len(), removed.len()), (0, 1));
+        assert!(id3.is_empty());
+    }
+
+    #[test]
+    fn pictures_are_only_read_if_they_are_still_images() {
+        // Cover art that is itself a tagged MP3 must not be followed, or
+        // nested tags would recurse without end.
+        let inner = tag(&[&frame("TIT2", b"\x00Inner")]);
+        let picture = [b"\x00image/jpeg\0\x03\0".as_slice(), &inner].concat();
+        let bytes = tag(&[&frame("APIC", &picture)]);
+        let mut id3 = Id3v2::parse(&bytes).unwrap();
+
+        let keys: Vec<String> = id3.metadata_entries().into_iter().map(|e| e.key).collect();
+        assert_eq!(keys, ["Picture"]);
+        let (kept, removed) = id3.apply_policy(&ScrubPolicy::keep_all());
+        assert_eq!((kept.len(), removed.len()), (1, 0));
+    }
+}
@@ -0,0 +1,170 @@
+// File: crates/scrubkit-core/src/iff.rs
+
+//! RIFF and IFF chunk containers, shared by WAV and AIFF.
+//!
+//! Both start with a 12-byte header: `RIFF` or `FORM`, the size of the rest
+//! of the file and a form type such as `WAVE` or `AIFF`. Chunks follow, each
+//! with a FourCC, a size and data padded to an even length. RIFF sizes are
+//! little-endian, IFF sizes big-endian.
+
+use crate::ScrubError;
+
+const HEADER_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteOrder {
+    /// RIFF, as in WAV files.
+    Little,
+    /// IFF, as in AIFF files.
+    Big,
+}
+
+impl ByteOrder {
+    pub(crate) fn read_u32(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            ByteOrder::Little => u32::from_le_bytes(bytes),
+            ByteOrder::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    fn write_u32(self, value: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+/// A chunk, borrowed from the file.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Chunk<'a> {
+    pub(crate) fourcc: [u8; 4],
+    pub(crate) data: &'a [u8],
+    /// The whole chunk, including its header and padding byte.
+    pub(crate) bytes: &'a [u8],
+}
+
+impl Chunk<'_> {
+    /// The FourCC as text, without trailing spaces.
+    pub(crate) fn name(&self) -> String {
+        String::from_utf8_lossy(&self.fourcc).trim_end().to_string()
+    }
+}
+
+/// Returns true if the file has the given container magic and one of the
+/// form types.
+pub(crate) fn has_form(bytes: &[u8], magic: &[u8; 4], forms: &[&[u8; 4]]) -> bool {
+    bytes.len() >= HEADER_LEN
+        && &bytes[0..4] == magic
+        && forms.iter().any(|form| &bytes[8..12] == *form)
+}
+
+/// Splits the chunks of a container. `format` names the file type in errors.
+pub(crate) fn chunks<'a>(
+    bytes: &'a [u8],
+    order: ByteOrder,
+    format: &str,
+) -> Result<Vec<Chunk<'a>>, ScrubError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(ScrubError::ParsingError(format!(
+            "Not a valid {} file",
+            format
+        )));
+    }
+    // Trust the container size over the file length, ignoring anything appended.
+    let end = (8 + order.read_u32(&bytes[4..8]) as usize).min(bytes.len());
+    split(bytes, HEADER_LEN, end, order, format)
+}
+
+/// Splits the sub-chunks of a `LIST` chunk, after its list type.
+pub(crate) fn list_chunks<'a>(
+    list: &'a [u8],
+    order: ByteOrder,
+    format: &str,
+) -> Result<Vec<Chunk<'a>>, ScrubError> {
+    split(list, 4.min(list.len()), list.len(), order, format)
+}
+
+fn split<'a>(
+    bytes: &'a [u8],
+    start: usize,
+    end: usize,
+    order: ByteOrder,
+    format: &str,
+) -> Result<Vec<Chunk<'a>>, ScrubError> {
+    let mut chunks = Vec::new();
+    let mut offset = start;
+    while offset + 8 <= end {
+        let header = &bytes[offset..offset + 8];
+        let size = order.read_u32(&header[4..8]) as usize;
+        let data = bytes.get(offset + 8..offset + 8 + size).ok_or_else(|| {
+            ScrubError::ParsingError(format!("Corrupt {} chunk at offset {}", format, offset))
+        })?;
+        let chunk_end = (offset + 8 + size.next_multiple_of(2)).min(end);
+        chunks.push(Chunk {
+            fourcc: [header[0], header[1], header[2], header[3]],
+            data,
+            bytes: &bytes[offset..chunk_end],
+        });
+        offset = chunk_end;
+    }
+    Ok(chunks)
+}
+
+/// Builds a chunk, padding its data to an even length.
+pub(crate) fn write_chunk(
+    fourcc: &[u8; 4],
+    data: &[u8],
+    order: ByteOrder,
+) -> Result<Vec<u8>, ScrubError> {
+    let size = u32::try_from(data.len())
+        .map_err(|_| ScrubError::ParsingError("Chunk too large".into()))?;
+    let mut bytes = fourcc.to_vec();
+    bytes.extend_from_slice(&order.write_u32(size));
+    bytes.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    Ok(bytes)
+}
+
+/// Builds a container with the header of `original` around the given
+/// chunks, with its size field updated.
+pub(crate) fn write_container(
+    original: &[u8],
+    chunks: &[Vec<u8>],
+    order: ByteOrder,
+) -> Result<Vec<u8>, ScrubError> {
+    let body_len = 4 + chunks.iter().map(Vec::len).sum::<usize>();
+    let size =
+        u32::try_from(body_len).map_err(|_| ScrubError::ParsingError("File too large".into()))?;
+    let mut bytes = original[0..4].to_vec();
+    bytes.extend_from_slice(&order.write_u32(size));
+    bytes.extend_from_slice(&original[8..12]);
+    chunks
+        .iter()
+        .for_each(|chunk| bytes.extend_from_slice(chunk));
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_round_trip_with_padding() {
+        let chunks_in = vec![
+            write_chunk(b"COMM", &[1, 2, 3], ByteOrder::Big).unwrap(),
+            write_chunk(b"NAME", b"ab", ByteOrder::Big).unwrap(),
+        ];
+        let file = write_container(b"FORM\0\0\0\0AIFF", &chunks_in, ByteOrder::Big).unwrap();
+        assert_eq!(&file[4..8], &26u32.to_be_bytes());
+
+        let chunks = chunks(&file, ByteOrder::Big, "AIFF").unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].data, [1, 2, 3]);
+        assert_eq!(chunks[0].bytes.len(), 12);
+        assert_eq!(chunks[1].name(), "NAME");
+    }
+}
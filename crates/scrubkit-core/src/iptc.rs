@@ -0,0 +1,328 @@
+// File: crates/scrubkit-core/src/iptc.rs
+
+//! Reads Photoshop image resource blocks and the IPTC-NAA records inside them.
+//!
+//! JPEG files carry these in APP13 segments. The resource block is a list of
+//! `8BIM` resources; resource `0x0404` holds IPTC IIM datasets, each one a
+//! `0x1C` marker followed by a record number, a dataset number and a length.
+
+use crate::exif::ExifData;
+use crate::xmp::parse_xmp;
+use crate::{MetadataCategory, MetadataEntry, MetadataValue};
+
+const IPTC_NAA: u16 = 0x0404;
+const EXIF_INFO: u16 = 0x0422;
+const XMP: u16 = 0x0424;
+
+/// Parses a Photoshop image resource block into metadata entries.
+///
+/// IPTC records, embedded EXIF and embedded XMP are decoded. Other resources
+/// are reported by name with their size.
+pub(crate) fn parse_resources(data: &[u8]) -> Vec<MetadataEntry> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset + 12 <= data.len() && is_resource_signature(&data[offset..offset + 4]) {
+        let id = u16::from_be_bytes([data[offset + 4], data[offset + 5]]);
+        // The name is a Pascal string padded to an even length.
+        let name_len = data[offset + 6] as usize;
+        let size_offset = offset + 6 + (1 + name_len).next_multiple_of(2);
+        let Some(size) = data
+            .get(size_offset..size_offset + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        else {
+            break;
+        };
+        let start = size_offset + 4;
+        let Some(resource) = data.get(start..start + size) else {
+            break;
+        };
+
+        match id {
+            IPTC_NAA => entries.extend(parse_iptc(resource)),
+            EXIF_INFO => match ExifData::parse(resource) {
+                Ok(exif) => entries.extend(exif.metadata_entries()),
+                Err(_) => entries.push(resource_entry(id, resource)),
+            },
+            XMP => entries.extend(parse_xmp(resource)),
+            _ => entries.push(resource_entry(id, resource)),
+        }
+        offset = start + size.next_multiple_of(2);
+    }
+    entries
+}
+
+fn is_resource_signature(signature: &[u8]) -> bool {
+    matches!(signature, b"8BIM" | b"PHUT" | b"DCSR" | b"AgHg" | b"MeSa")
+}
+
+fn resource_entry(id: u16, data: &[u8]) -> MetadataEntry {
+    let key = match resource_name(id) {
+        Some(name) => name.to_string(),
+        None => format!("Resource(0x{:04X})", id),
+    };
+    let value = match id {
+        // The URL resource is plain text.
+        0x040B => MetadataValue::Text(String::from_utf8_lossy(data).into_owned()),
+        _ => MetadataValue::Binary { len: data.len() },
+    };
+    MetadataEntry::new(MetadataCategory::Photoshop, key, value).with_tag_id(id)
+}
+
+fn resource_name(id: u16) -> Option<&'static str> {
+    Some(match id {
+        0x03E9 => "MacintoshPrintInfo",
+        0x03ED => "ResolutionInfo",
+        0x03F3 => "PrintFlags",
+        0x0406 => "JPEGQuality",
+        0x0408 => "GridGuidesInfo",
+        0x0409 => "PhotoshopThumbnailOld",
+        0x040A => "CopyrightFlag",
+        0x040B => "URL",
+        0x040C => "PhotoshopThumbnail",
+        0x040D => "GlobalAngle",
+        0x0414 => "DocumentIDSeed",
+        0x0419 => "GlobalAltitude",
+        0x041A => "Slices",
+        0x041E => "URLList",
+        0x0421 => "VersionInfo",
+        0x0425 => "IPTCDigest",
+        0x0426 => "PrintScale",
+        0x0428 => "PixelAspectRatio",
+        0x043A => "PrintInfo",
+        0x043B => "PrintStyle",
+        _ => return None,
+    })
+}
+
+/// Parses IPTC IIM datasets. Repeated datasets, such as keywords, are merged
+/// into a single list entry.
+pub(crate) fn parse_iptc(data: &[u8]) -> Vec<MetadataEntry> {
+    let mut datasets: Vec<(u8, u8, Vec<MetadataValue>)> = Vec::new();
+    let mut offset = 0;
+    while offset + 5 <= data.len() && data[offset] == 0x1C {
+        let record = data[offset + 1];
+        let dataset = data[offset + 2];
+        let mut size = u16::from_be_bytes([data[offset + 3], data[offset + 4]]) as usize;
+        let mut start = offset + 5;
+        // Extended datasets store the length of their length field instead.
+        if size & 0x8000 != 0 {
+            let len_len = size & 0x7FFF;
+            let Some(len_bytes) = data.get(start..start + len_len).filter(|b| b.len() <= 8) else {
+                break;
+            };
+            size = len_bytes.iter().fold(0, |acc, b| (acc << 8) | *b as usize);
+            start += len_len;
+        }
+        let Some(value) = data.get(start..start + size) else {
+            break;
+        };
+
+        let value = dataset_value(record, dataset, value);
+        match datasets
+            .iter_mut()
+            .find(|(r, d, _)| *r == record && *d == dataset)
+        {
+            Some((_, _, values)) => values.push(value),
+            None => datasets.push((record, dataset, vec![value])),
+        }
+        offset = start + size;
+    }
+
+    datasets
+        .into_iter()
+        .map(|(record, dataset, mut values)| {
+            let key = match dataset_name(record, dataset) {
+                Some(name) => name.to_string(),
+                None => format!("IPTC({}:{})", record, dataset),
+            };
+            let value = if values.len() == 1 {
+                values.remove(0)
+            } else {
+                MetadataValue::List(values)
+            };
+            MetadataEntry::new(MetadataCategory::Iptc, key, value)
+        })
+        .collect()
+}
+
+fn dataset_value(record: u8, dataset: u8, data: &[u8]) -> MetadataValue {
+    match (record, dataset) {
+        // Record versions are binary shorts.
+        (1 | 2, 0) if data.len() == 2 => {
+            MetadataValue::Integer(u16::from_be_bytes([data[0], data[1]]) as i64)
+        }
+        (1, 70) | (2, 30) | (2, 37) | (2, 47) | (2, 55) | (2, 62) => iptc_date(data),
+        (1, 80) | (2, 35) | (2, 38) | (2, 60) | (2, 63) => iptc_time(data),
+        (1 | 2, _) => MetadataValue::Text(
+            String::from_utf8_lossy(data)
+                .trim_end_matches('\0')
+                .to_string(),
+        ),
+        // Records 3 and up hold binary object data.
+        _ => MetadataValue::Binary { len: data.len() },
+    }
+}
+
+/// Converts an IIM `CCYYMMDD` date to ISO 8601.
+fn iptc_date(data: &[u8]) -> MetadataValue {
+    let text = String::from_utf8_lossy(data);
+    if text.len() == 8 && text.bytes().all(|b| b.is_ascii_digit()) {
+        MetadataValue::DateTime(format!("{}-{}-{}", &text[..4], &text[4..6], &text[6..]))
+    } else {
+        MetadataValue::Text(text.into_owned())
+    }
+}
+
+/// Converts an IIM `HHMMSS±HHMM` time to ISO 8601.
+fn iptc_time(data: &[u8]) -> MetadataValue {
+    let text = String::from_utf8_lossy(data);
+    // Check the raw bytes, so the slicing below stays on character boundaries.
+    let is_time = data.len() == 11
+        && data[..6].iter().all(u8::is_ascii_digit)
+        && matches!(data[6], b'+' | b'-')
+        && data[7..].iter().all(u8::is_ascii_digit);
+    if is_time {
+        MetadataValue::DateTime(format!(
+            "{}:{}:{}{}{}:{}",
+            &text[..2],
+            &text[2..4],
+            &text[4..6],
+            &text[6..7],
+            &text[7..9],
+            &text[9..]
+        ))
+    } else {
+        MetadataValue::Text(text.into_owned())
+    }
+}
+
+fn dataset_name(record: u8, dataset: u8) -> Option<&'static str> {
+    Some(match (record, dataset) {
+        (1, 0) => "EnvelopeRecordVersion",
+        (1, 5) => "Destination",
+        (1, 20) => "FileFormat",
+        (1, 30) => "ServiceIdentifier",
+        (1, 40) => "EnvelopeNumber",
+        (1, 50) => "ProductID",
+        (1, 70) => "DateSent",
+        (1, 80) => "TimeSent",
+        (1, 90) => "CodedCharacterSet",
+        (1, 100) => "UniqueObjectName",
+        (2, 0) => "ApplicationRecordVersion",
+        (2, 3) => "ObjectTypeReference",
+        (2, 4) => "ObjectAttributeReference",
+        (2, 5) => "ObjectName",
+        (2, 7) => "EditStatus",
+        (2, 10) => "Urgency",
+        (2, 12) => "SubjectReference",
+        (2, 15) => "Category",
+        (2, 20) => "SupplementalCategories",
+        (2, 22) => "FixtureIdentifier",
+        (2, 25) => "Keywords",
+        (2, 26) => "ContentLocationCode",
+        (2, 27) => "ContentLocationName",
+        (2, 30) => "ReleaseDate",
+        (2, 35) => "ReleaseTime",
+        (2, 37) => "ExpirationDate",
+        (2, 38) => "ExpirationTime",
+        (2, 40) => "SpecialInstructions",
+        (2, 45) => "ReferenceService",
+        (2, 47) => "ReferenceDate",
+        (2, 50) => "ReferenceNumber",
+        (2, 55) => "DateCreated",
+        (2, 60) => "TimeCreated",
+        (2, 62) => "DigitalCreationDate",
+        (2, 63) => "DigitalCreationTime",
+        (2, 65) => "OriginatingProgram",
+        (2, 70) => "ProgramVersion",
+        (2, 75) => "ObjectCycle",
+        (2, 80) => "By-line",
+        (2, 85) => "By-lineTitle",
+        (2, 90) => "City",
+        (2, 92) => "Sub-location",
+        (2, 95) => "Province-State",
+        (2, 100) => "Country-PrimaryLocationCode",
+        (2, 101) => "Country-PrimaryLocationName",
+        (2, 103) => "OriginalTransmissionReference",
+        (2, 105) => "Headline",
+        (2, 110) => "Credit",
+        (2, 115) => "Source",
+        (2, 116) => "CopyrightNotice",
+        (2, 118) => "Contact",
+        (2, 120) => "Caption-Abstract",
+        (2, 122) => "Writer-Editor",
+        (2, 130) => "ImageType",
+        (2, 131) => "ImageOrientation",
+        (2, 135) => "LanguageIdentifier",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RiskLevel;
+
+    fn dataset(record: u8, number: u8, value: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0x1C, record, number];
+        bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(value);
+        bytes
+    }
+
+    #[test]
+    fn iptc_datasets_are_named_and_merged() {
+        let data = [
+            dataset(2, 80, b"Jane Doe"),
+            dataset(2, 25, b"beach"),
+            dataset(2, 25, b"family"),
+            dataset(2, 55, b"20240501"),
+            dataset(2, 90, b"Lisbon"),
+        ]
+        .concat();
+        let entries = parse_iptc(&data);
+        let find = |key: &str| entries.iter().find(|e| e.key == key).unwrap();
+
+        assert_eq!(entries.len(), 4);
+        assert_eq!(find("By-line").risk, RiskLevel::High);
+        assert_eq!(find("Keywords").display_value, "beach, family");
+        assert_eq!(
+            find("DateCreated").value,
+            MetadataValue::DateTime("2024-05-01".into())
+        );
+        assert_eq!(find("City").category, MetadataCategory::Iptc);
+    }
+
+    #[test]
+    fn malformed_times_are_kept_as_text() {
+        assert_eq!(
+            iptc_time(b"143000+0200"),
+            MetadataValue::DateTime("14:30:00+02:00".into())
+        );
+        let text = "12345\u{e9}6789";
+        assert_eq!(iptc_time(text.as_bytes()), MetadataValue::Text(text.into()));
+    }
+
+    #[test]
+    fn resources_wrap_iptc_and_report_others() {
+        let iptc = dataset(2, 120, b"A caption");
+        let mut data = Vec::new();
+        for (id, payload) in [(0x0404u16, iptc.as_slice()), (0x040C, &[0u8; 5][..])] {
+            data.extend_from_slice(b"8BIM");
+            data.extend_from_slice(&id.to_be_bytes());
+            data.extend_from_slice(&[0, 0]); // empty name, padded
+            data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+            data.extend_from_slice(payload);
+            if payload.len() % 2 == 1 {
+                data.push(0);
+            }
+        }
+        let entries = parse_resources(&data);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key, "Caption-Abstract");
+        assert_eq!(entries[1].key, "PhotoshopThumbnail");
+        assert_eq!(entries[1].category, MetadataCategory::Photoshop);
+        assert_eq!(entries[1].value, MetadataValue::Binary { len: 5 });
+    }
+}
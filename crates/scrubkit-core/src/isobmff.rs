@@ -0,0 +1,304 @@
+// File: crates/scrubkit-core/src/isobmff.rs
+
+//! Shared helpers for ISO base media files (HEIF, AVIF, MP4, MOV).
+//!
+//! These files are trees of boxes, each starting with a 32-bit size and a
+//! four-letter type. Metadata usually lives in its own box or item, but the
+//! media data it sits next to is addressed by absolute file offsets, so
+//! removing anything means fixing up every offset that points past it.
+
+use crate::ScrubError;
+use std::ops::Range;
+
+/// The `uuid` of Adobe's XMP box, used by MP4 and JPEG 2000 files.
+pub(crate) const XMP_UUID: [u8; 16] = [
+    0xBE, 0x7A, 0xCF, 0xCB, 0x97, 0xA9, 0x42, 0xE8, 0x9C, 0x71, 0x99, 0x94, 0x91, 0xE3, 0xAF, 0xAC,
+];
+
+/// A box header, with positions relative to the start of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BmffBox {
+    pub(crate) box_type: [u8; 4],
+    pub(crate) start: usize,
+    pub(crate) header_len: usize,
+    pub(crate) end: usize,
+}
+
+impl BmffBox {
+    /// The box contents, after the header.
+    pub(crate) fn data<'a>(&self, bytes: &'a [u8]) -> &'a [u8] {
+        &bytes[self.data_start()..self.end]
+    }
+
+    pub(crate) fn data_start(&self) -> usize {
+        self.start + self.header_len
+    }
+
+    pub(crate) fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    pub(crate) fn is(&self, box_type: &[u8; 4]) -> bool {
+        &self.box_type == box_type
+    }
+}
+
+/// Reads the boxes in `bytes[start..end]`.
+pub(crate) fn read_boxes(
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+) -> Result<Vec<BmffBox>, ScrubError> {
+    let mut boxes = Vec::new();
+    let mut offset = start;
+    while offset + 8 <= end {
+        let corrupt = || ScrubError::ParsingError(format!("Corrupt box at offset {}", offset));
+        let size = read_uint(bytes, offset, 4).ok_or_else(corrupt)? as usize;
+        let box_type = [
+            bytes[offset + 4],
+            bytes[offset + 5],
+            bytes[offset + 6],
+            bytes[offset + 7],
+        ];
+        let (size, mut header_len) = match size {
+            // A size of 0 means the box runs to the end of its container.
+            0 => (end - offset, 8),
+            // A size of 1 means a 64-bit size follows the type.
+            1 => (
+                read_uint(bytes, offset + 8, 8).ok_or_else(corrupt)? as usize,
+                16,
+            ),
+            size => (size, 8),
+        };
+        if &box_type == b"uuid" {
+            header_len += 16;
+        }
+        let box_end = offset
+            .checked_add(size)
+            .filter(|e| *e <= end)
+            .ok_or_else(corrupt)?;
+        if size < header_len {
+            return Err(corrupt());
+        }
+        boxes.push(BmffBox {
+            box_type,
+            start: offset,
+            header_len,
+            end: box_end,
+        });
+        offset = box_end;
+    }
+    Ok(boxes)
+}
+
+/// Reads the children of a full box, which has a version and flags before them.
+pub(crate) fn full_box_children(
+    bytes: &[u8],
+    parent: &BmffBox,
+) -> Result<Vec<BmffBox>, ScrubError> {
+    read_boxes(bytes, parent.data_start() + 4, parent.end)
+}
+
+/// Reads a big-endian unsigned integer of `size` bytes (0 to 8).
+pub(crate) fn read_uint(bytes: &[u8], offset: usize, size: usize) -> Option<u64> {
+    let field = bytes.get(offset..offset.checked_add(size)?)?;
+    Some(field.iter().fold(0, |acc, b| (acc << 8) | *b as u64))
+}
+
+/// Splits an EXIF payload, as stored in HEIF items and JPEG XL boxes, into
+/// its prefix and the TIFF structure. The payload starts with the offset of
+/// the TIFF header, counted from the end of that field; it usually skips an
+/// `Exif\0\0` prefix.
+pub(crate) fn exif_tiff(payload: &[u8]) -> Option<(&[u8], &[u8])> {
+    let offset = read_uint(payload, 0, 4)? as usize;
+    let tiff_start = 4usize.checked_add(offset)?;
+    Some((payload.get(..tiff_start)?, payload.get(tiff_start..)?))
+}
+
+/// Appends a big-endian unsigned integer of `size` bytes, failing if it doesn't fit.
+pub(crate) fn write_uint(out: &mut Vec<u8>, value: u64, size: usize) -> Result<(), ScrubError> {
+    if size < 8 && value >> (size * 8) != 0 {
+        return Err(ScrubError::ParsingError(format!(
+            "Offset {} does not fit in {} bytes",
+            value, size
+        )));
+    }
+    out.extend_from_slice(&value.to_be_bytes()[8 - size..]);
+    Ok(())
+}
+
+/// Builds a box from its type and contents.
+pub(crate) fn write_box(box_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len() + 16);
+    match u32::try_from(data.len() + 8) {
+        Ok(size) => bytes.extend_from_slice(&size.to_be_bytes()),
+        Err(_) => {
+            bytes.extend_from_slice(&1u32.to_be_bytes());
+            bytes.extend_from_slice(box_type);
+            bytes.extend_from_slice(&(data.len() as u64 + 16).to_be_bytes());
+            bytes.extend_from_slice(data);
+            return bytes;
+        }
+    }
+    bytes.extend_from_slice(box_type);
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// A set of non-overlapping byte range replacements on a file.
+///
+/// Offsets into the original file can be mapped to where the same byte ends
+/// up after the edits, which is what offset tables need to be rewritten.
+#[derive(Debug, Default)]
+pub(crate) struct Edits {
+    edits: Vec<(Range<usize>, Vec<u8>)>,
+}
+
+impl Edits {
+    /// Replaces `range` with `bytes`. Fails if the range overlaps an edit
+    /// added earlier, which only a corrupt file can ask for.
+    pub(crate) fn replace(
+        &mut self,
+        range: Range<usize>,
+        bytes: Vec<u8>,
+    ) -> Result<(), ScrubError> {
+        if range.start > range.end
+            || self
+                .edits
+                .iter()
+                .any(|(r, _)| r.start < range.end && range.start < r.end)
+        {
+            return Err(ScrubError::ParsingError(format!(
+                "Overlapping edit at offset {}",
+                range.start
+            )));
+        }
+        self.edits.push((range, bytes));
+        self.edits.sort_by_key(|(range, _)| range.start);
+        Ok(())
+    }
+
+    /// Removes `range`.
+    pub(crate) fn remove(&mut self, range: Range<usize>) -> Result<(), ScrubError> {
+        self.replace(range, Vec::new())
+    }
+
+    /// Changes the replacement of an edit added earlier, which must keep its length.
+    pub(crate) fn update(&mut self, start: usize, bytes: Vec<u8>) {
+        if let Some((_, old)) = self.edits.iter_mut().find(|(r, _)| r.start == start) {
+            debug_assert_eq!(old.len(), bytes.len());
+            *old = bytes;
+        }
+    }
+
+    /// Maps an offset in the original file to the edited file. Offsets at the
+    /// start of an edited range map to the start of its replacement.
+    pub(crate) fn map(&self, offset: usize) -> usize {
+        let mut mapped = offset as isize;
+        for (range, bytes) in &self.edits {
+            if range.end <= offset {
+                mapped += bytes.len() as isize - range.len() as isize;
+            } else if range.start < offset && offset < range.end {
+                // Inside a replaced range: clamp to where the replacement starts.
+                return self.map(range.start);
+            }
+        }
+        mapped as usize
+    }
+
+    /// Applies the edits. Fails if an edit runs past the end of `bytes`.
+    pub(crate) fn apply(&self, bytes: &[u8]) -> Result<Vec<u8>, ScrubError> {
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut offset = 0;
+        for (range, replacement) in &self.edits {
+            let kept = bytes.get(offset..range.start).ok_or_else(|| {
+                ScrubError::ParsingError(format!("Edit at offset {} is out of range", range.start))
+            })?;
+            out.extend_from_slice(kept);
+            out.extend_from_slice(replacement);
+            offset = range.end;
+        }
+        out.extend_from_slice(bytes.get(offset..).ok_or_else(|| {
+            ScrubError::ParsingError(format!("Edit ending at offset {} is out of range", offset))
+        })?);
+        Ok(out)
+    }
+
+    /// Applies the edits and fixes the size field of every box in `boxes`
+    /// whose contents changed length.
+    pub(crate) fn apply_with_sizes(
+        &self,
+        bytes: &[u8],
+        boxes: &[BmffBox],
+    ) -> Result<Vec<u8>, ScrubError> {
+        let mut out = self.apply(bytes)?;
+        for b in boxes {
+            // Boxes that were replaced outright already have the right size.
+            if self.edits.iter().any(|(range, _)| *range == b.range()) {
+                continue;
+            }
+            let (start, end) = (self.map(b.start), self.map(b.end));
+            let size = (end - start) as u64;
+            if size == (b.end - b.start) as u64 || read_uint(bytes, b.start, 4) == Some(0) {
+                continue;
+            }
+            if read_uint(bytes, b.start, 4) == Some(1) {
+                out[start + 8..start + 16].copy_from_slice(&size.to_be_bytes());
+            } else {
+                out[start..start + 4].copy_from_slice(&(size as u32).to_be_bytes());
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_nested_boxes() {
+        let inner = write_box(b"free", b"abc");
+        let file = [write_box(b"ftyp", b"heic"), write_box(b"meta", &inner)].concat();
+        let boxes = read_boxes(&file, 0, file.len()).unwrap();
+
+        assert_eq!(boxes.len(), 2);
+        assert!(boxes[1].is(b"meta"));
+        let children = read_boxes(&file, boxes[1].data_start(), boxes[1].end).unwrap();
+        assert_eq!(children[0].data(&file), b"abc");
+    }
+
+    #[test]
+    fn edits_map_offsets_and_fix_box_sizes() {
+        let file = [
+            write_box(b"ftyp", b"heic"),
+            write_box(b"mdat", b"0123456789"),
+        ]
+        .concat();
+        let boxes = read_boxes(&file, 0, file.len()).unwrap();
+        let mdat = boxes[1].data_start();
+
+        let mut edits = Edits::default();
+        edits.remove(mdat + 2..mdat + 5).unwrap();
+        assert_eq!(edits.map(mdat), mdat);
+        assert_eq!(edits.map(mdat + 5), mdat + 2);
+
+        let out = edits.apply_with_sizes(&file, &boxes).unwrap();
+        assert_eq!(
+            out,
+            [write_box(b"ftyp", b"heic"), write_box(b"mdat", b"0156789")].concat()
+        );
+    }
+
+    #[test]
+    fn edits_reject_overlapping_and_out_of_range_ranges() {
+        let mut edits = Edits::default();
+        edits.remove(4..8).unwrap();
+        assert!(edits.remove(4..8).is_err());
+        assert!(edits.replace(6..10, vec![0]).is_err());
+        edits.remove(8..10).unwrap();
+
+        assert!(edits.apply(&[0; 9]).is_err());
+        assert_eq!(edits.apply(&[0; 12]).unwrap(), [0; 6]);
+    }
+}
@@ -0,0 +1,440 @@
+// File: crates/scrubkit-core/src/jp2.rs
+
+//! JPEG 2000 support.
+//!
+//! A JP2 or JPX file is a sequence of boxes: a signature box, `ftyp`, the
+//! `jp2h` header describing the image, and the codestream in a `jp2c` box.
+//! Metadata sits in boxes of its own: `xml ` boxes, `uuid` boxes (XMP, and
+//! GeoJP2 georeferencing, which is a tiny GeoTIFF), `uinf` boxes with URLs
+//! and, in JPX files, `asoc` boxes tying labels and XML (such as GML
+//! georeferencing) to the image. The codestream's main header may also have
+//! COM marker segments, which usually name the encoder.
+//!
+//! Metadata boxes are kept or removed whole, as are COM segments; the header
+//! and everything else in the codestream are copied unchanged. Bare `.j2k`
+//! codestreams are supported too, and only have COM segments to remove.
+
+use crate::isobmff::{BmffBox, XMP_UUID, read_boxes, write_box};
+use crate::tiff::{IfdValue, read_header, read_ifd};
+use crate::xmp::parse_xmp;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+use std::ops::Range;
+
+/// The `jP  ` signature box that starts a JP2 or JPX file.
+const SIGNATURE: &[u8] = &[
+    0, 0, 0, 0x0C, b'j', b'P', b' ', b' ', 0x0D, 0x0A, 0x87, 0x0A,
+];
+
+/// The start of a codestream: SOC, then the SIZ marker.
+const CODESTREAM: &[u8] = &[0xFF, 0x4F, 0xFF, 0x51];
+
+// Codestream markers.
+const COM: u16 = 0xFF64;
+const SOT: u16 = 0xFF90;
+const EOC: u16 = 0xFFD9;
+
+/// The `uuid` of GeoJP2 boxes.
+const GEOJP2_UUID: [u8; 16] = [
+    0xB1, 0x4B, 0xF8, 0xBD, 0x08, 0x3D, 0x4B, 0x43, 0xA5, 0xAE, 0x8C, 0xD7, 0xD5, 0xA6, 0xCE, 0x03,
+];
+
+const METADATA_BOXES: &[&[u8; 4]] = &[b"xml ", b"uuid", b"uinf", b"asoc"];
+
+/// The GeoTIFF tags of a GeoJP2 box, with the names we show for them.
+const GEOTIFF_TAGS: &[(u16, &str)] = &[
+    (33550, "ModelPixelScale"),
+    (33922, "ModelTiepoint"),
+    (34264, "ModelTransformation"),
+    (34735, "GeoKeyDirectory"),
+    (34736, "GeoDoubleParams"),
+    (34737, "GeoAsciiParams"),
+];
+
+/// Returns true for JP2 and JPX files, and for bare codestreams.
+pub fn is_jp2(bytes: &[u8]) -> bool {
+    bytes.starts_with(SIGNATURE) || bytes.starts_with(CODESTREAM)
+}
+
+fn binary_entry(category: MetadataCategory, key: impl Into<String>, len: usize) -> MetadataEntry {
+    MetadataEntry::new(category, key, MetadataValue::Binary { len })
+}
+
+/// Describes the georeferencing in a GeoJP2 payload.
+fn geojp2_entries(tiff: &[u8]) -> Option<Vec<MetadataEntry>> {
+    let (order, offset) = read_header(tiff).ok()?;
+    let (entries, _) = read_ifd(tiff, order, offset as usize).ok()?;
+    let list = |values: Vec<MetadataValue>| MetadataValue::List(values);
+    let entries = entries
+        .iter()
+        .filter_map(|entry| {
+            let (_, key) = GEOTIFF_TAGS.iter().find(|(tag, _)| *tag == entry.tag)?;
+            let value = match entry.value(order) {
+                IfdValue::Ascii(text) => MetadataValue::Text(text),
+                IfdValue::Short(v) => list(
+                    v.into_iter()
+                        .map(|v| MetadataValue::Integer(v.into()))
+                        .collect(),
+                ),
+                IfdValue::Double(v) => list(
+                    v.into_iter()
+                        .map(|v| MetadataValue::Text(v.to_string()))
+                        .collect(),
+                ),
+                _ => MetadataValue::Binary {
+                    len: entry.data.len(),
+                },
+            };
+            Some(MetadataEntry::new(MetadataCategory::Gps, *key, value).with_tag_id(entry.tag))
+        })
+        .collect();
+    Some(entries)
+}
+
+/// Describes the metadata in a box. Boxes we can't read are shown as opaque
+/// data, so they are still listed and removed.
+fn box_entries(bmff: &BmffBox, bytes: &[u8]) -> Vec<MetadataEntry> {
+    let data = bmff.data(bytes);
+    let (entries, category, key) = match &bmff.box_type {
+        b"xml " => {
+            // XML boxes hold XMP or other XML, such as GML georeferencing,
+            // which parse_xmp reports as a single opaque entry.
+            let mut entries = parse_xmp(data);
+            if let [entry] = entries.as_slice()
+                && matches!(entry.value, MetadataValue::Binary { .. })
+            {
+                entries.clear();
+            }
+            (entries, MetadataCategory::Jp2, "XML".to_string())
+        }
+        b"uuid" => {
+            let uuid = &bytes[bmff.data_start() - 16..bmff.data_start()];
+            if uuid == XMP_UUID {
+                (parse_xmp(data), MetadataCategory::Xmp, "XMP".to_string())
+            } else if uuid == GEOJP2_UUID {
+                let entries = geojp2_entries(data).unwrap_or_default();
+                (entries, MetadataCategory::Gps, "GeoJP2".to_string())
+            } else {
+                let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+                (
+                    Vec::new(),
+                    MetadataCategory::Vendor,
+                    format!("UUID {}", hex),
+                )
+            }
+        }
+        b"uinf" => (Vec::new(), MetadataCategory::Jp2, "UUIDInfo".to_string()),
+        // Association boxes usually start with a label saying what they hold.
+        _ => {
+            let label = read_boxes(data, 0, data.len())
+                .ok()
+                .and_then(|children| children.into_iter().find(|c| c.is(b"lbl ")))
+                .map(|label| String::from_utf8_lossy(label.data(data)).into_owned());
+            let key = match label {
+                Some(label) => format!("Association {}", label),
+                None => "Association".to_string(),
+            };
+            (Vec::new(), MetadataCategory::Jp2, key)
+        }
+    };
+    if entries.is_empty() {
+        vec![binary_entry(category, key, data.len())]
+    } else {
+        entries
+    }
+}
+
+/// A COM marker segment in the main header of a codestream.
+struct Comment {
+    range: Range<usize>,
+    entry: MetadataEntry,
+}
+
+/// Finds the COM segments in the main header of a codestream, which ends at
+/// the first tile-part.
+fn comments(codestream: &[u8]) -> Result<Vec<Comment>, ScrubError> {
+    if !codestream.starts_with(&CODESTREAM[..2]) {
+        return Err(ScrubError::ParsingError(
+            "Not a JPEG 2000 codestream".into(),
+        ));
+    }
+    let read_u16 = |at: usize| {
+        codestream
+            .get(at..at + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+    let mut comments = Vec::new();
+    let mut pos = 2;
+    loop {
+        let corrupt =
+            || ScrubError::ParsingError(format!("Corrupt codestream marker at offset {}", pos));
+        let marker = read_u16(pos).ok_or_else(corrupt)?;
+        if marker == SOT || marker == EOC {
+            return Ok(comments);
+        }
+        let len = read_u16(pos + 2).ok_or_else(corrupt)? as usize;
+        let end = pos + 2 + len;
+        let segment = codestream
+            .get(pos + 4..end)
+            .filter(|_| len >= 2)
+            .ok_or_else(corrupt)?;
+        if marker == COM {
+            // The registration value says whether the comment is Latin-1 text.
+            let entry = match segment.split_first_chunk::<2>() {
+                Some(([0, 1], text)) => MetadataEntry::new(
+                    MetadataCategory::Comment,
+                    "Comment",
+                    MetadataValue::Text(text.iter().map(|&b| char::from(b)).collect()),
+                ),
+                _ => binary_entry(MetadataCategory::Comment, "Comment", segment.len()),
+            };
+            comments.push(Comment {
+                range: pos..end,
+                entry,
+            });
+        }
+        pos = end;
+    }
+}
+
+/// Filters the COM segments of a codestream. Returns the new codestream if
+/// any were removed.
+fn scrub_codestream(
+    codestream: &[u8],
+    policy: &ScrubPolicy,
+    metadata_kept: &mut Vec<MetadataEntry>,
+    metadata_removed: &mut Vec<MetadataEntry>,
+) -> Result<Option<Vec<u8>>, ScrubError> {
+    let mut scrubbed = Vec::with_capacity(codestream.len());
+    let mut start = 0;
+    for comment in comments(codestream)? {
+        if policy.keeps(&comment.entry) {
+            metadata_kept.push(comment.entry);
+        } else {
+            metadata_removed.push(comment.entry);
+            scrubbed.extend_from_slice(&codestream[start..comment.range.start]);
+            start = comment.range.end;
+        }
+    }
+    if start == 0 {
+        return Ok(None);
+    }
+    scrubbed.extend_from_slice(&codestream[start..]);
+    Ok(Some(scrubbed))
+}
+
+/// A Scrubber implementation for JPEG 2000 files.
+#[derive(Debug, Clone)]
+pub struct Jp2Scrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Jp2Scrubber {
+    fn boxes(&self) -> Result<Vec<BmffBox>, ScrubError> {
+        read_boxes(&self.file_bytes, 0, self.file_bytes.len())
+    }
+
+    fn is_codestream(&self) -> bool {
+        self.file_bytes.starts_with(CODESTREAM)
+    }
+}
+
+impl Scrubber for Jp2Scrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_jp2(&file_bytes) {
+            return Err(ScrubError::ParsingError(
+                "Not a valid JPEG 2000 file".into(),
+            ));
+        }
+        let scrubber = Self { file_bytes };
+        if scrubber.is_codestream() {
+            comments(&scrubber.file_bytes)?;
+        } else {
+            scrubber.boxes()?;
+        }
+        Ok(scrubber)
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        if self.is_codestream() {
+            return Ok(comments(&self.file_bytes)?
+                .into_iter()
+                .map(|c| c.entry)
+                .collect());
+        }
+        let mut metadata = Vec::new();
+        for bmff in self.boxes()? {
+            if bmff.is(b"jp2c") {
+                metadata.extend(
+                    comments(bmff.data(&self.file_bytes))?
+                        .into_iter()
+                        .map(|c| c.entry),
+                );
+            } else if METADATA_BOXES.contains(&&bmff.box_type) {
+                metadata.extend(box_entries(&bmff, &self.file_bytes));
+            }
+        }
+        Ok(metadata)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+
+        let cleaned_bytes = if self.is_codestream() {
+            scrub_codestream(
+                &self.file_bytes,
+                policy,
+                &mut metadata_kept,
+                &mut metadata_removed,
+            )?
+        } else {
+            let boxes = self.boxes()?;
+            let mut cleaned_bytes = Vec::with_capacity(self.file_bytes.len());
+            for bmff in &boxes {
+                if bmff.is(b"jp2c") {
+                    let codestream = bmff.data(&self.file_bytes);
+                    match scrub_codestream(
+                        codestream,
+                        policy,
+                        &mut metadata_kept,
+                        &mut metadata_removed,
+                    )? {
+                        Some(scrubbed) => cleaned_bytes.extend(write_box(b"jp2c", &scrubbed)),
+                        None => cleaned_bytes.extend_from_slice(&self.file_bytes[bmff.range()]),
+                    }
+                    continue;
+                }
+                if METADATA_BOXES.contains(&&bmff.box_type) {
+                    let entries = box_entries(bmff, &self.file_bytes);
+                    if !entries.iter().all(|e| policy.keeps(e)) {
+                        metadata_removed.extend(entries);
+                        continue;
+                    }
+                    metadata_kept.extend(entries);
+                }
+                cleaned_bytes.extend_from_slice(&self.file_bytes[bmff.range()]);
+            }
+            // Fragment tables point at codestream fragments by file offset.
+            if !metadata_removed.is_empty() && boxes.iter().any(|b| b.is(b"ftbl")) {
+                return Err(ScrubError::UnsupportedFileType(
+                    "JPX files with fragment tables are not supported".into(),
+                ));
+            }
+            Some(cleaned_bytes)
+        };
+
+        Ok(ScrubResult {
+            cleaned_file_bytes: match cleaned_bytes {
+                Some(bytes) if !metadata_removed.is_empty() => bytes,
+                _ => self.file_bytes.clone(),
+            },
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codestream(comment: &str) -> Vec<u8> {
+        let mut com = vec![0xFF, 0x64];
+        com.extend(((comment.len() + 4) as u16).to_be_bytes());
+        com.extend([0, 1]);
+        com.extend(comment.bytes());
+        [
+            &[0xFF, 0x4F, 0xFF, 0x51, 0, 4, 0, 0][..],
+            &com,
+            &[0xFF, 0x90, 0, 2, 0xFF, 0x93, 0xAB, 0xCD, 0xFF, 0xD9],
+        ]
+        .concat()
+    }
+
+    fn uuid_box(uuid: &[u8; 16], data: &[u8]) -> Vec<u8> {
+        write_box(b"uuid", &[uuid.as_slice(), data].concat())
+    }
+
+    /// A big-endian GeoTIFF with a single tiepoint.
+    fn geotiff() -> Vec<u8> {
+        let mut tiff = b"MM\0*\0\0\0\x08\0\x01".to_vec();
+        tiff.extend([0x84, 0x82, 0, 12, 0, 0, 0, 6, 0, 0, 0, 26]);
+        tiff.extend([0; 4]);
+        for v in [0.0, 0.0, 0.0, 2.3522, 48.8566, 0.0f64] {
+            tiff.extend(v.to_be_bytes());
+        }
+        tiff
+    }
+
+    const XMP: &[u8] = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/" dc:creator="Jane"/></rdf:RDF></x:xmpmeta>"#;
+
+    fn jp2(boxes: &[Vec<u8>], comment: &str) -> Vec<u8> {
+        [
+            SIGNATURE.to_vec(),
+            write_box(b"ftyp", b"jp2 \0\0\0\0jp2 "),
+            write_box(b"jp2h", &write_box(b"ihdr", &[0; 14])),
+            boxes.concat(),
+            write_box(b"jp2c", &codestream(comment)),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn view_metadata_decodes_xmp_geojp2_and_comments() {
+        let file = jp2(
+            &[
+                uuid_box(&XMP_UUID, XMP),
+                uuid_box(&GEOJP2_UUID, &geotiff()),
+                write_box(b"xml ", b"<gml:FeatureCollection/>"),
+            ],
+            "Kakadu-v8.0",
+        );
+        let entries = Jp2Scrubber::new(file).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(keys, ["dc:creator", "ModelTiepoint", "XML", "Comment"]);
+        assert_eq!(entries[1].display_value, "0, 0, 0, 2.3522, 48.8566, 0");
+        assert_eq!(entries[3].value, MetadataValue::Text("Kakadu-v8.0".into()));
+    }
+
+    #[test]
+    fn scrub_keeps_the_header_and_codestream() {
+        let file = jp2(
+            &[uuid_box(&GEOJP2_UUID, &geotiff()), write_box(b"xml ", XMP)],
+            "Kakadu-v8.0",
+        );
+        let result = Jp2Scrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 3);
+        let expected = [
+            SIGNATURE.to_vec(),
+            write_box(b"ftyp", b"jp2 \0\0\0\0jp2 "),
+            write_box(b"jp2h", &write_box(b"ihdr", &[0; 14])),
+            write_box(
+                b"jp2c",
+                &[
+                    0xFF, 0x4F, 0xFF, 0x51, 0, 4, 0, 0, 0xFF, 0x90, 0, 2, 0xFF, 0x93, 0xAB, 0xCD,
+                    0xFF, 0xD9,
+                ],
+            ),
+        ]
+        .concat();
+        assert_eq!(result.cleaned_file_bytes, expected);
+    }
+
+    #[test]
+    fn bare_codestreams_lose_their_comments() {
+        let scrubber = Jp2Scrubber::new(codestream("Created by OpenJPEG")).unwrap();
+        let result = scrubber.scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 1);
+        assert_eq!(
+            result.cleaned_file_bytes,
+            [
+                0xFF, 0x4F, 0xFF, 0x51, 0, 4, 0, 0, 0xFF, 0x90, 0, 2, 0xFF, 0x93, 0xAB, 0xCD, 0xFF,
+                0xD9
+            ]
+        );
+    }
+}
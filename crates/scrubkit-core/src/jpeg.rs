@@ -1,280 +1,462 @@
-use crate::{MetadataEntry, ScrubError, ScrubResult, Scrubber};
+// File: crates/scrubkit-core/src/jpeg.rs
+
+//! JPEG support.
+//!
+//! A JPEG file is a sequence of marker segments. The file is walked segment by
+//! segment, and each one is classified as either needed to decode the image or
+//! as carrying metadata. Metadata segments are then kept, rewritten or dropped
+//! according to the scrub policy.
+
+use crate::exif::ExifData;
+use crate::iptc::parse_resources;
+use crate::xmp::parse_xmp;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+
+const SOI: u8 = 0xD8;
+const EOI: u8 = 0xD9;
+const SOS: u8 = 0xDA;
+const COM: u8 = 0xFE;
+const APP0: u8 = 0xE0;
+const APP15: u8 = 0xEF;
+
+const EXIF_SIGNATURE: &[u8] = b"Exif\0\0";
+const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const EXTENDED_XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+const ICC_SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+const PHOTOSHOP_SIGNATURE: &[u8] = b"Photoshop 3.0\0";
+
+/// What a segment holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SegmentKind {
+    /// Needed to decode the image: frame and scan headers, tables, entropy-coded
+    /// data, the JFIF header, ICC profiles and the Adobe APP14 color transform.
+    Structural,
+    /// An APP1 `Exif\0\0` segment.
+    Exif,
+    /// An APP1 XMP packet.
+    Xmp,
+    /// An APP1 chunk of an extended XMP packet too large for one segment.
+    ExtendedXmp,
+    /// An APP13 Photoshop resource block, usually holding IPTC records.
+    Photoshop,
+    /// A COM segment.
+    Comment,
+    /// Any other APPn segment, such as Ducky (APP12), MPF or FlashPix (APP2).
+    Vendor,
+    /// Data after the end-of-image marker.
+    Trailer,
+}
 
-/// A Scrubber implementation for JPEG files.
-#[derive(Debug, Clone)]
-pub struct JpegScrubber {
-    file_bytes: Vec<u8>,
+/// A marker segment, including its marker bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    marker: u8,
+    start: usize,
+    end: usize,
+    kind: SegmentKind,
 }
 
-// Private helper functions for JpegScrubber
-impl JpegScrubber {
-    /// Finds the EXIF data segment (APP1) in the JPEG byte stream.
-    /// Returns (start_offset, length_including_marker) of the APP1 segment.
-    fn find_exif_segment(&self) -> Option<(usize, usize)> {
-        let mut offset = 2; // Skip the initial SOI marker (0xFFD8)
-        while offset + 4 <= self.file_bytes.len() {
-            if self.file_bytes[offset] != 0xFF {
-                eprintln!(
-                    "DBG: Invalid marker start at offset {}: byte is {}",
-                    offset, self.file_bytes[offset]
-                );
-                return None;
-            }
+impl Segment {
+    /// The segment's payload, after the marker and length field.
+    /// Standalone markers have an empty payload.
+    fn payload<'a>(&self, bytes: &'a [u8]) -> &'a [u8] {
+        match self.kind {
+            SegmentKind::Trailer => &bytes[self.start..self.end],
+            _ => bytes.get(self.start + 4..self.end).unwrap_or_default(),
+        }
+    }
+}
 
-            let marker = self.file_bytes[offset + 1];
+/// Splits a JPEG file into segments, from SOI to EOI plus any trailing data.
+fn segments(bytes: &[u8]) -> Result<Vec<Segment>, ScrubError> {
+    let corrupt = |offset: usize| {
+        ScrubError::ParsingError(format!("Corrupt JPEG segment at offset {}", offset))
+    };
+
+    let mut segments = vec![Segment {
+        marker: SOI,
+        start: 0,
+        end: 2,
+        kind: SegmentKind::Structural,
+    }];
+    let mut offset = 2;
+    while offset < bytes.len() {
+        let start = offset;
+        if bytes[offset] != 0xFF {
+            return Err(corrupt(offset));
+        }
+        // Any number of 0xFF fill bytes may precede a marker.
+        while bytes.get(offset + 1) == Some(&0xFF) {
+            offset += 1;
+        }
+        let marker = *bytes.get(offset + 1).ok_or_else(|| corrupt(offset))?;
 
-            if (0xD0..=0xD7).contains(&marker) || marker == 0x01 {
-                offset += 2;
-                continue;
+        // Standalone markers have no length field.
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            offset += 2;
+            segments.push(structural(marker, start, offset));
+            continue;
+        }
+        if marker == EOI {
+            offset += 2;
+            segments.push(structural(marker, start, offset));
+            if offset < bytes.len() {
+                segments.push(Segment {
+                    marker,
+                    start: offset,
+                    end: bytes.len(),
+                    kind: SegmentKind::Trailer,
+                });
             }
+            return Ok(segments);
+        }
 
-            if marker == 0xD9 || marker == 0xDA {
-                break;
-            }
+        let length = u16::from_be_bytes([
+            *bytes.get(offset + 2).ok_or_else(|| corrupt(offset))?,
+            *bytes.get(offset + 3).ok_or_else(|| corrupt(offset))?,
+        ]) as usize;
+        let mut end = offset + 2 + length;
+        if length < 2 || end > bytes.len() {
+            return Err(corrupt(offset));
+        }
+        if marker == SOS {
+            end = entropy_data_end(bytes, end);
+        }
 
-            if offset + 4 > self.file_bytes.len() {
-                eprintln!("DBG: Not enough bytes to read length at offset {}", offset);
-                return None;
+        let mut segment = structural(marker, start, end);
+        // The payload starts after the fill bytes, marker and length field.
+        segment.kind = classify(marker, &bytes[offset + 4..offset + 2 + length]);
+        if segment.kind != SegmentKind::Structural {
+            // Metadata segments start at their marker, so the fill bytes
+            // before them survive on their own.
+            segment.start = offset;
+            if offset > start {
+                segments.push(structural(0xFF, start, offset));
             }
+        }
+        segments.push(segment);
+        offset = end;
+    }
+    // Truncated files without an EOI are common enough to accept.
+    Ok(segments)
+}
 
-            let length_bytes = [self.file_bytes[offset + 2], self.file_bytes[offset + 3]];
-            let length = u16::from_be_bytes(length_bytes) as usize;
+fn structural(marker: u8, start: usize, end: usize) -> Segment {
+    Segment {
+        marker,
+        start,
+        end,
+        kind: SegmentKind::Structural,
+    }
+}
 
-            if length < 2 || offset + 2 + length > self.file_bytes.len() {
-                eprintln!("DBG: Corrupt length field at offset {}: {}", offset, length);
-                return None;
+/// Finds the end of the entropy-coded data following a scan header.
+///
+/// Inside the data, `0xFF` is either stuffed (`FF 00`) or starts a restart
+/// marker, so the data ends at the first other marker.
+fn entropy_data_end(bytes: &[u8], mut offset: usize) -> usize {
+    while offset + 1 < bytes.len() {
+        if bytes[offset] == 0xFF {
+            match bytes[offset + 1] {
+                0x00 | 0xD0..=0xD7 => offset += 2,
+                0xFF => offset += 1,
+                _ => return offset,
             }
+        } else {
+            offset += 1;
+        }
+    }
+    bytes.len()
+}
 
-            if marker == 0xE1 && length >= 6 {
-                let exif_sig_start = offset + 4; // 2 (marker) + 2 (length bytes)
-                let exif_sig_end = exif_sig_start + 6; // 6 bytes for "Exif\0\0"
-                if exif_sig_end <= self.file_bytes.len()
-                    && self.file_bytes[exif_sig_start..exif_sig_end] == *b"Exif\0\0"
-                {
-                    // Found the EXIF APP1 segment
-                    // The `length` variable already includes the 2-byte length field.
-                    // The total number of bytes in the segment is `length`.
-                    // eprintln!("DBG: Found EXIF segment at offset {}, length {}", offset, length); // Correct debug
-                    // Return (start_offset, total_segment_length)
-                    return Some((offset, length)); // <-- FIX: Remove the erroneous + 2
+/// Classifies a segment with a length field by its marker and signature.
+fn classify(marker: u8, payload: &[u8]) -> SegmentKind {
+    match marker {
+        0xE0 if payload.starts_with(b"JFIF\0") => SegmentKind::Structural,
+        0xE1 if payload.starts_with(EXIF_SIGNATURE) => SegmentKind::Exif,
+        0xE1 if payload.starts_with(XMP_SIGNATURE) => SegmentKind::Xmp,
+        0xE1 if payload.starts_with(EXTENDED_XMP_SIGNATURE) => SegmentKind::ExtendedXmp,
+        // Color profiles change how the image is displayed, as PNG's iCCP does.
+        0xE2 if payload.starts_with(ICC_SIGNATURE) => SegmentKind::Structural,
+        0xED if payload.starts_with(PHOTOSHOP_SIGNATURE) => SegmentKind::Photoshop,
+        // Decoders need the Adobe transform flag to read CMYK and YCCK images.
+        0xEE if payload.starts_with(b"Adobe") => SegmentKind::Structural,
+        APP0..=APP15 => SegmentKind::Vendor,
+        COM => SegmentKind::Comment,
+        _ => SegmentKind::Structural,
+    }
+}
+
+/// Metadata segments that are kept or removed together, with their entries.
+///
+/// Most blocks are a single segment, but extended XMP packets are split over
+/// several and only make sense as a whole.
+#[derive(Debug)]
+struct MetadataBlock {
+    segments: Vec<usize>,
+    entries: Vec<MetadataEntry>,
+    /// The parsed EXIF data, for EXIF blocks, which are filtered entry by entry.
+    exif: Option<ExifData>,
+}
+
+/// Part of a block split over several segments: the segment's index, the
+/// part's position within the block, and its data.
+type Chunk<'a> = (usize, u32, &'a [u8]);
+
+/// A Scrubber implementation for JPEG files.
+#[derive(Debug, Clone)]
+pub struct JpegScrubber {
+    file_bytes: Vec<u8>,
+}
+
+// Private helper functions for JpegScrubber
+impl JpegScrubber {
+    /// Groups the metadata segments into blocks and reads their entries.
+    fn metadata_blocks(&self, segments: &[Segment]) -> Vec<MetadataBlock> {
+        let bytes = &self.file_bytes;
+        let mut blocks = Vec::new();
+        let mut extended_xmp: Vec<(&[u8], Vec<Chunk>)> = Vec::new();
+
+        for (index, segment) in segments.iter().enumerate() {
+            let payload = segment.payload(bytes);
+            let entries = match segment.kind {
+                SegmentKind::Structural => continue,
+                SegmentKind::Exif => {
+                    match ExifData::parse(&payload[EXIF_SIGNATURE.len()..]) {
+                        Ok(exif) => {
+                            blocks.push(MetadataBlock {
+                                segments: vec![index],
+                                entries: exif.metadata_entries(),
+                                exif: Some(exif),
+                            });
+                            continue;
+                        }
+                        // An unreadable EXIF block can only be removed as a whole.
+                        Err(_) => vec![MetadataEntry::new(
+                            MetadataCategory::Exif,
+                            "Exif",
+                            MetadataValue::Binary { len: payload.len() },
+                        )],
+                    }
                 }
-            }
+                SegmentKind::Xmp => parse_xmp(&payload[XMP_SIGNATURE.len()..]),
+                SegmentKind::ExtendedXmp => {
+                    // A 32-byte GUID, the full packet length and this chunk's offset.
+                    let chunk = &payload[EXTENDED_XMP_SIGNATURE.len()..];
+                    if chunk.len() >= 40 {
+                        let guid = &chunk[..32];
+                        let chunk_offset =
+                            u32::from_be_bytes([chunk[36], chunk[37], chunk[38], chunk[39]]);
+                        let part = (index, chunk_offset, &chunk[40..]);
+                        match extended_xmp.iter_mut().find(|(g, _)| *g == guid) {
+                            Some((_, parts)) => parts.push(part),
+                            None => extended_xmp.push((guid, vec![part])),
+                        }
+                        continue;
+                    }
+                    vec![binary_entry(MetadataCategory::Xmp, "ExtendedXMP", chunk)]
+                }
+                SegmentKind::Photoshop => {
+                    let resources = &payload[PHOTOSHOP_SIGNATURE.len()..];
+                    let entries = parse_resources(resources);
+                    if entries.is_empty() {
+                        vec![binary_entry(
+                            MetadataCategory::Photoshop,
+                            "Photoshop",
+                            resources,
+                        )]
+                    } else {
+                        entries
+                    }
+                }
+                SegmentKind::Comment => {
+                    let text = String::from_utf8_lossy(payload);
+                    vec![MetadataEntry::new(
+                        MetadataCategory::Comment,
+                        "Comment",
+                        MetadataValue::Text(text.trim_end_matches('\0').to_string()),
+                    )]
+                }
+                SegmentKind::Vendor => {
+                    let key = match identifier(payload) {
+                        Some(id) => format!("APP{} {}", segment.marker - APP0, id),
+                        None => format!("APP{}", segment.marker - APP0),
+                    };
+                    vec![binary_entry(MetadataCategory::Vendor, key, payload)]
+                }
+                SegmentKind::Trailer => {
+                    vec![binary_entry(MetadataCategory::Vendor, "Trailer", payload)]
+                }
+            };
+            blocks.push(MetadataBlock {
+                segments: vec![index],
+                entries,
+                exif: None,
+            });
+        }
 
-            offset += 2 + length;
+        for (_, mut parts) in extended_xmp {
+            parts.sort_by_key(|(_, chunk_offset, _)| *chunk_offset);
+            let packet: Vec<u8> = parts
+                .iter()
+                .flat_map(|(_, _, data)| *data)
+                .copied()
+                .collect();
+            blocks.push(MetadataBlock {
+                segments: parts.iter().map(|(index, _, _)| *index).collect(),
+                entries: parse_xmp(&packet),
+                exif: None,
+            });
         }
-        eprintln!("DBG: EXIF APP1 segment not found");
-        None
+        blocks
     }
 }
 
+/// Reads the NUL-terminated ASCII identifier most APPn segments start with.
+fn identifier(payload: &[u8]) -> Option<String> {
+    let end = payload.iter().take(32).position(|b| *b == 0)?;
+    let id = &payload[..end];
+    (!id.is_empty() && id.iter().all(|b| b.is_ascii_graphic() || *b == b' '))
+        .then(|| String::from_utf8_lossy(id).trim().to_string())
+}
+
+fn binary_entry(category: MetadataCategory, key: impl Into<String>, data: &[u8]) -> MetadataEntry {
+    MetadataEntry::new(category, key, MetadataValue::Binary { len: data.len() })
+}
+
 impl Scrubber for JpegScrubber {
     fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
         // Basic JPEG check
         if file_bytes.len() < 2 || file_bytes[0..2] != [0xFF, 0xD8] {
             return Err(ScrubError::ParsingError("Not a valid JPEG file".into()));
         }
-        eprintln!(
-            "DBG (JpegScrubber::new): Received file_bytes with length {}",
-            file_bytes.len()
-        ); // Add this line
         Ok(Self { file_bytes })
     }
 
     fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
-        use nom_exif::{ExifIter, MediaParser, MediaSource};
-        use std::io::Cursor; // Remove ParsedExifEntry from here
-
-        let media_source = MediaSource::seekable(Cursor::new(&self.file_bytes)).map_err(|e| {
-            ScrubError::ParsingError(format!("Failed to create MediaSource: {:?}", e))
-        })?;
-
-        let mut parser = MediaParser::new();
-
-        let exif_iter_result = parser.parse(media_source);
+        let segments = segments(&self.file_bytes)?;
+        Ok(self
+            .metadata_blocks(&segments)
+            .into_iter()
+            .flat_map(|block| block.entries)
+            .collect())
+    }
 
-        let exif_iter: ExifIter = match exif_iter_result {
-            Ok(iter) => iter,
-            Err(_parse_error) => {
-                return Ok(Vec::new());
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let segments = segments(&self.file_bytes)?;
+        // `None` keeps a segment, `Some(None)` drops it and `Some(Some(bytes))` replaces it.
+        let mut replacements: Vec<Option<Option<Vec<u8>>>> = vec![None; segments.len()];
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+
+        for block in self.metadata_blocks(&segments) {
+            if let Some(mut exif) = block.exif {
+                let (kept, removed) = exif.apply_policy(policy);
+                if !removed.is_empty() {
+                    let segment = (!exif.is_empty())
+                        .then(|| exif_segment(&exif.to_bytes()))
+                        .transpose()?;
+                    replacements[block.segments[0]] = Some(segment);
+                }
+                metadata_kept.extend(kept);
+                metadata_removed.extend(removed);
+                continue;
             }
-        };
-
-        let mut metadata_entries = Vec::new();
-
-        // Standard for loop syntax
-        for entry in exif_iter {
-            // --- Access fields from the ParsedExifEntry correctly ---
-
-            // --- Tag Name ---
-            // Placeholder due to previous type inference issues with `entry.tag()`.
-            let tag_name = "<Tag Name Unavailable>".to_string();
-
-            // --- IFD Category ---
-            // We are back to the original problem of type inference for method returns.
-            // Let's try to force the type of the result by explicitly typing the variable
-            // and seeing if that helps the compiler connect the dots.
-            // We assume `ifd_index()` returns a `usize`.
-            let ifd_num_result = entry.ifd_index();
-            let ifd_num: usize = ifd_num_result; // Explicitly type the result variable
-
-            let category = match ifd_num {
-                0 => "IFD0".to_string(),
-                1 => "IFD1".to_string(),
-                2 => "EXIF".to_string(),
-                3 => "GPS".to_string(),
-                4 => "Interop".to_string(),
-                _ => format!("IFD_{}", ifd_num),
-            };
 
-            // --- Value ---
-            // Similarly, try to explicitly type the result of `entry.value()`.
-            // We know it returns `Option<&EntryValue>`.
-            let opt_value_ref_result = entry.get_value();
-            // Note: Typing `Option<&EntryValue>` requires `EntryValue` to be in scope.
-            // If `EntryValue` is not directly importable from `nom_exif`, this will be tricky.
-            // Let's assume it is for now, or that we can use `_` for the inner type.
-            // let opt_value_ref: Option<&nom_exif::EntryValue> = opt_value_ref_result;
-            // Using `_` for the referenced type might work if it's unambiguous.
-            let opt_value_ref: Option<_> = opt_value_ref_result; // Let the compiler infer &T
-
-            let value_string = match opt_value_ref {
-                Some(value_ref) => {
-                    // Format the EntryValue. We still need to know how to get a clean string.
-                    // If EntryValue has a Display impl or a method, use it.
-                    // For now, stick to Debug as it's always there.
-                    // If EntryValue's Debug output is "Text(\"str\")", this is what we get.
-                    format!("{:?}", value_ref)
+            // Other blocks can't be edited, so they're only kept if every entry is.
+            if block.entries.iter().all(|e| policy.keeps(e)) {
+                metadata_kept.extend(block.entries);
+            } else {
+                for index in block.segments {
+                    replacements[index] = Some(None);
                 }
-                None => "<No Value>".to_string(),
-            };
+                metadata_removed.extend(block.entries);
+            }
+        }
 
-            metadata_entries.push(MetadataEntry {
-                key: tag_name,
-                value: value_string,
-                category,
+        if metadata_removed.is_empty() {
+            return Ok(ScrubResult {
+                cleaned_file_bytes: self.file_bytes.clone(),
+                metadata_removed,
+                metadata_kept,
             });
         }
-        Ok(metadata_entries)
-    }
-
-    fn scrub(&self) -> Result<ScrubResult, ScrubError> {
-        let metadata_removed = self.view_metadata()?; // This should work now
-
-        if let Some((start_offset, segment_length)) = self.find_exif_segment() {
-            eprintln!(
-                "DBG (scrub): Preparing to remove segment. Start: {}, Length: {}",
-                start_offset, segment_length
-            );
-
-            // Sanity check lengths
-            let original_len = self.file_bytes.len();
-            let part1_len = start_offset;
-            let part2_start = start_offset + segment_length;
-            let part2_len = original_len - part2_start;
-            let calculated_cleaned_len = part1_len + part2_len;
-
-            eprintln!(
-                "DBG (scrub): Original len: {}, Part1 len: {}, Part2 start: {}, Part2 len: {}, Calculated cleaned len: {}",
-                original_len, part1_len, part2_start, part2_len, calculated_cleaned_len
-            );
-
-            if part2_start > original_len {
-                eprintln!(
-                    "DBG (scrub): ERROR - part2_start ({}) is beyond file length ({})",
-                    part2_start, original_len
-                );
-                // Handle error or return original?
-            }
 
-            let mut cleaned_bytes = Vec::with_capacity(calculated_cleaned_len); // Use calculated length
-            eprintln!("DBG (scrub): Copying Part 1: indices [0..{})", start_offset);
-            cleaned_bytes.extend_from_slice(&self.file_bytes[..start_offset]);
-
-            eprintln!(
-                "DBG (scrub): Copying Part 2: indices [{}..{})",
-                part2_start, original_len
-            );
-            cleaned_bytes.extend_from_slice(&self.file_bytes[part2_start..]);
-
-            eprintln!(
-                "DBG (scrub): Final cleaned_bytes length: {}",
-                cleaned_bytes.len()
-            );
-
-            // Optional: Print first and last few bytes of result for debugging
-            if !cleaned_bytes.is_empty() {
-                let first_len = std::cmp::min(10, cleaned_bytes.len());
-                let last_start = std::cmp::max(cleaned_bytes.len(), 10) - 10;
-                eprintln!(
-                    "DBG (scrub): First {} bytes: {:?}",
-                    first_len,
-                    &cleaned_bytes[0..first_len]
-                );
-                eprintln!(
-                    "DBG (scrub): Last 10 bytes: {:?}",
-                    &cleaned_bytes[last_start..]
-                );
+        let mut cleaned_bytes = Vec::with_capacity(self.file_bytes.len());
+        for (segment, replacement) in segments.iter().zip(replacements) {
+            match replacement {
+                None => {
+                    cleaned_bytes.extend_from_slice(&self.file_bytes[segment.start..segment.end])
+                }
+                Some(Some(bytes)) => cleaned_bytes.extend_from_slice(&bytes),
+                Some(None) => {}
             }
-
-            Ok(ScrubResult {
-                cleaned_file_bytes: cleaned_bytes,
-                metadata_removed,
-            })
-        } else {
-            eprintln!("DBG (scrub): No EXIF segment found");
-            Ok(ScrubResult {
-                cleaned_file_bytes: self.file_bytes.clone(),
-                metadata_removed: vec![],
-            })
         }
+
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
     }
 }
 
-// --- Tests remain the same ---
-// (Keeping the test code from the previous response as the logic for Scrubber impl is the focus)
-// Note: I'll make one small adjustment to the test assertion based on the likely output format.
+/// Wraps a TIFF structure in an APP1 `Exif\0\0` segment.
+fn exif_segment(tiff: &[u8]) -> Result<Vec<u8>, ScrubError> {
+    let length = u16::try_from(2 + 6 + tiff.len())
+        .map_err(|_| ScrubError::ParsingError("EXIF data too large for an APP1 segment".into()))?;
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&length.to_be_bytes());
+    segment.extend_from_slice(b"Exif\0\0");
+    segment.extend_from_slice(tiff);
+    Ok(segment)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     // A 1x1 pixel JPEG with EXIF data. Camera Model: "Test Camera"
     // A 1x1 pixel JPEG with EXIF data. Contains Make: "Test Camera", Model: "Test Model"
-    // Total length: 174 bytes.
-    // APP1 Segment: Indices 2-75 (Length 74 bytes)
+    // Total length: 212 bytes.
+    // APP1 Segment: Indices 2-72 (Length 71 bytes)
 
     const TEST_JPEG_WITH_EXIF: &[u8] = &[
-        0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x4A, 0x45, 0x78, 0x69, 0x66, 0x00, 0x00, 0x4D, 0x4D, 0x00,
-        0x2A, 0x00, 0x00, 0x00, 0x08, 0x00, 0x02, 0x01, 0x0F, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0D,
-        0x00, 0x00, 0x00, 0x1A, 0x01, 0x10, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0C, 0x00, 0x00, 0x00,
-        0x28, 0x00, 0x00, 0x00, 0x00, 0x54, 0x65, 0x73, 0x74, 0x20, 0x43, 0x61, 0x6D, 0x65, 0x72,
+        0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x45, 0x45, 0x78, 0x69, 0x66, 0x00, 0x00, 0x4D, 0x4D, 0x00,
+        0x2A, 0x00, 0x00, 0x00, 0x08, 0x00, 0x02, 0x01, 0x0F, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0C,
+        0x00, 0x00, 0x00, 0x26, 0x01, 0x10, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0B, 0x00, 0x00, 0x00,
+        0x32, 0x00, 0x00, 0x00, 0x00, 0x54, 0x65, 0x73, 0x74, 0x20, 0x43, 0x61, 0x6D, 0x65, 0x72,
         0x61, 0x00, 0x54, 0x65, 0x73, 0x74, 0x20, 0x4D, 0x6F, 0x64, 0x65, 0x6C, 0x00, 0xFF, 0xDB,
         0x00, 0x43, 0x00, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
         0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
         0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
         0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
-        0x01, 0x01, 0x01, 0x01, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x01, 0x00, 0x01, 0x03, 0x01,
-        0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01, 0xFF, 0xC4, 0x00, 0x1F, 0x00, 0x00, 0x01,
-        0x05, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0xFF, 0xDA, 0x00, 0x0C,
-        0x03, 0x01, 0x00, 0x02, 0x11, 0x03, 0x11, 0x00, 0x3F, 0x00, 0xF7, 0xC8, 0xFF, 0xD9,
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x01, 0x00,
+        0x01, 0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01, 0xFF, 0xC4, 0x00, 0x1F,
+        0x00, 0x00, 0x01, 0x05, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0xFF,
+        0xDA, 0x00, 0x0C, 0x03, 0x01, 0x00, 0x02, 0x11, 0x03, 0x11, 0x00, 0x3F, 0x00, 0xF7, 0xC8,
+        0xFF, 0xD9,
     ];
 
     // The expected result after scrubbing the above JPEG.
-    // It should be the original JPEG with the 74-byte APP1 segment (indices 2-75) removed.
+    // It should be the original JPEG with the 71-byte APP1 segment (indices 2-72) removed.
     // Part 1: Indices [0..2]   -> [0xFF, 0xD8] (2 bytes: SOI)
-    // Part 2: Indices [76..174] -> 98 bytes of data starting with 0xFF, 0xDB
-    // Total expected length: 2 + 98 = 100 bytes.
+    // Part 2: Indices [73..212] -> 139 bytes of data starting with 0xFF, 0xDB
+    // Total expected length: 2 + 139 = 141 bytes.
 
     const TEST_JPEG_WITHOUT_EXIF: &[u8] = &[
-        0xFF, 0xD8, 0x43, 0x00, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+        0xFF, 0xD8, 0xFF, 0xDB, 0x00, 0x43, 0x00, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
         0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
         0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
         0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
-        0x01, 0x01, 0x01, 0x01, 0x01, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x01, 0x00, 0x01, 0x03,
-        0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01, 0xFF, 0xC4, 0x00, 0x1F, 0x00, 0x00,
-        0x01, 0x05, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0xFF, 0xDA, 0x00,
-        0x0C, 0x03, 0x01, 0x00, 0x02, 0x11, 0x03, 0x11, 0x00, 0x3F, 0x00, 0xF7, 0xC8, 0xFF, 0xD9,
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0xFF, 0xC0, 0x00, 0x11,
+        0x08, 0x00, 0x01, 0x00, 0x01, 0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01,
+        0xFF, 0xC4, 0x00, 0x1F, 0x00, 0x00, 0x01, 0x05, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        0x09, 0x0A, 0x0B, 0xFF, 0xDA, 0x00, 0x0C, 0x03, 0x01, 0x00, 0x02, 0x11, 0x03, 0x11, 0x00,
+        0x3F, 0x00, 0xF7, 0xC8, 0xFF, 0xD9,
     ];
 
     #[test]
@@ -288,10 +470,15 @@ mod tests {
     fn view_metadata_finds_exif_data() {
         let scrubber = JpegScrubber::new(TEST_JPEG_WITH_EXIF.to_vec()).unwrap();
         let metadata = scrubber.view_metadata().unwrap();
-        println!("Found meta {:?}", metadata); // Debug print
         assert!(!metadata.is_empty(), "No metadata was found");
 
-        let model_entry_found = metadata.iter().any(|m| m.value.contains("st Camera"));
+        let make = metadata.iter().find(|m| m.key == "Make").unwrap();
+        assert_eq!(make.category, crate::MetadataCategory::Ifd0);
+        assert_eq!(make.tag_id, Some(0x010F));
+
+        let model_entry_found = metadata
+            .iter()
+            .any(|m| m.display_value.contains("st Camera"));
         assert!(
             model_entry_found,
             "Camera model metadata entry (containing 'st Camera') not found. Metadata list: {:?}",
@@ -301,17 +488,11 @@ mod tests {
 
     #[test]
     fn scrub_removes_exif_segment_and_reports_it() {
-        // Optional: Print length for debugging (can be removed later)
-        eprintln!(
-            "DBG (Test): TEST_JPEG_WITH_EXIF length: {}",
-            TEST_JPEG_WITH_EXIF.len()
-        );
-
         // Assertion to ensure the test constant is the expected size
-        // (This was failing before because it expected 174, now it expects 209)
+        // (This was failing before because it expected 174, now it expects 212)
         assert_eq!(
             TEST_JPEG_WITH_EXIF.len(),
-            209,
+            212,
             "Test constant length has changed!"
         );
 
@@ -343,13 +524,15 @@ mod tests {
             !result.metadata_removed.is_empty(),
             "Metadata removed should not be empty"
         );
-        // Optional: Check if reported metadata matches expected (if view_metadata is fully functional)
-        // assert_eq!(result.metadata_removed, expected_metadata_removed);
+        assert_eq!(result.metadata_removed, expected_metadata_removed);
 
         // 3. Verify EXIF segment is gone from the scrubbed bytes
         let new_scrubber = JpegScrubber::new(result.cleaned_file_bytes.clone()).unwrap();
         assert!(
-            new_scrubber.find_exif_segment().is_none(),
+            segments(&new_scrubber.file_bytes)
+                .unwrap()
+                .iter()
+                .all(|s| s.kind != SegmentKind::Exif),
             "EXIF segment should be removed from the scrubbed file"
         );
 
@@ -364,7 +547,6 @@ mod tests {
     fn view_metadata_on_jpeg_without_exif_returns_empty() {
         let scrubber = JpegScrubber::new(TEST_JPEG_WITHOUT_EXIF.to_vec()).unwrap();
         let metadata = scrubber.view_metadata().unwrap();
-        println!("Metadata for clean JPEG: {:?}", metadata); // Debug print
         assert!(
             metadata.is_empty(),
             "Metadata should be empty for a clean JPEG. Found: {:?}",
@@ -390,106 +572,140 @@ mod tests {
     }
 
     #[test]
-    fn _calculate_correct_without_exif_for_209_byte_input() {
-        // Directly use the confirmed TEST_JPEG_WITH_EXIF constant
-        // We know it's 209 bytes and starts with 0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x4A
-        println!(
-            "DBG: Using TEST_JPEG_WITH_EXIF with length {}",
-            TEST_JPEG_WITH_EXIF.len()
-        );
+    fn scrub_with_policy_keeps_selected_tags() {
+        let scrubber = JpegScrubber::new(TEST_JPEG_WITH_EXIF.to_vec()).unwrap();
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::TagId(0x0110));
+        let result = scrubber.scrub_with(&policy).unwrap();
 
-        // --- Core Calculation Logic ---
-        // Assuming the APP1 segment structure is standard:
-        // Marker (0xFFE1): 2 bytes at indices 2-3
-        // Length (Big-endian): 2 bytes at indices 4-5. Value is 0x004A = 74 bytes.
-        //  Segment data: indices 6 to (2 + 2 + 74 - 1) = 6 to 75 (70 bytes of payload + "Exif\0\0")
-        // Total segment size to remove: 2 (marker) + 2 (length) + 70 (payload) = 74 bytes.
-        // Start index to remove: 2
-        // End index of segment: 2 + 74 - 1 = 75
-        // Start index of data after segment: 76
-
-        let start_remove_index = 2;
-        let segment_length = 74; // As determined by find_exif_segment logic
-        let end_remove_index = start_remove_index + segment_length - 1; // 75
-        let start_keep_after_index = end_remove_index + 1; // 76
-
-        println!(
-            "DBG: Calculating removal from index {} for {} bytes (indices {} to {})",
-            start_remove_index, segment_length, start_remove_index, end_remove_index
-        );
+        assert_eq!(result.metadata_removed.len(), 1);
+        assert_eq!(result.metadata_removed[0].tag_id, Some(0x010F));
+        assert_eq!(result.metadata_kept.len(), 1);
+        assert_eq!(result.metadata_kept[0].tag_id, Some(0x0110));
 
-        // Verify bounds
-        assert!(
-            start_remove_index + segment_length <= TEST_JPEG_WITH_EXIF.len(),
-            "Segment exceeds file bounds"
-        );
-        assert!(
-            start_keep_after_index <= TEST_JPEG_WITH_EXIF.len(),
-            "Data after segment exceeds file bounds"
-        );
+        let new_scrubber = JpegScrubber::new(result.cleaned_file_bytes).unwrap();
+        let metadata = new_scrubber.view_metadata().unwrap();
+        assert_eq!(metadata, result.metadata_kept);
+    }
 
-        let part1_bytes = &TEST_JPEG_WITH_EXIF[..start_remove_index]; // Indices 0 to 1 ([0xFF, 0xD8])
-        let part2_bytes = &TEST_JPEG_WITH_EXIF[start_keep_after_index..]; // Indices 76 to 208
+    /// Builds a marker segment with a correct length field.
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0xFF, marker];
+        bytes.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
 
-        println!(
-            "DBG: Part 1 length: {}, Part 2 length: {}",
-            part1_bytes.len(),
-            part2_bytes.len()
-        );
+    /// Inserts segments right after the SOI marker of the clean fixture.
+    fn jpeg_with(segments: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = TEST_JPEG_WITHOUT_EXIF[..2].to_vec();
+        bytes.extend(segments.concat());
+        bytes.extend_from_slice(&TEST_JPEG_WITHOUT_EXIF[2..]);
+        bytes
+    }
 
-        let mut correct_without_exif_bytes: Vec<u8> =
-            Vec::with_capacity(part1_bytes.len() + part2_bytes.len());
-        correct_without_exif_bytes.extend_from_slice(part1_bytes);
-        correct_without_exif_bytes.extend_from_slice(part2_bytes);
+    fn jfif() -> Vec<u8> {
+        segment(0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0")
+    }
 
-        // --- Output the Result ---
-        println!(
-            "\n--- CORRECT TEST_JPEG_WITHOUT_EXIF ({} bytes) ---",
-            correct_without_exif_bytes.len()
-        );
-        println!("Replace the current TEST_JPEG_WITHOUT_EXIF constant with this array:");
-        print!("const TEST_JPEG_WITHOUT_EXIF: &[u8] = &[");
-        for (i, &byte) in correct_without_exif_bytes.iter().enumerate() {
-            if i % 16 == 0 {
-                print!("\n   ");
-            }
-            print!(" 0x{:02X},", byte);
-        }
-        println!("\n];");
-        println!("--- END OF CORRECT ARRAY ---");
+    fn xmp() -> Vec<u8> {
+        let packet = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:CreatorTool="Editor"/></rdf:RDF></x:xmpmeta>"#;
+        segment(0xE1, &[XMP_SIGNATURE, packet.as_slice()].concat())
+    }
+
+    fn iptc() -> Vec<u8> {
+        let mut payload = PHOTOSHOP_SIGNATURE.to_vec();
+        payload.extend_from_slice(b"8BIM\x04\x04\0\0\0\0\0\x0D");
+        payload.extend_from_slice(b"\x1C\x02\x50\0\x08Jane Doe\0");
+        segment(0xED, &payload)
+    }
 
+    #[test]
+    fn scrub_removes_every_metadata_segment() {
+        let adobe = segment(0xEE, b"Adobe\0\x64\0\0\0\0\x01");
+        let icc = segment(0xE2, b"ICC_PROFILE\0\x01\x01profile");
+        let file = jpeg_with(&[
+            jfif(),
+            xmp(),
+            iptc(),
+            icc.clone(),
+            segment(0xEC, b"Ducky\0\x01\0\x04\0\0\0\x50"),
+            segment(0xFE, b"shot on my phone"),
+            adobe.clone(),
+        ]);
+        let scrubber = JpegScrubber::new(file).unwrap();
+
+        let keys: Vec<String> = scrubber
+            .view_metadata()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
         assert_eq!(
-            correct_without_exif_bytes.len(),
-            135,
-            "Expected 135 bytes for the scrubbed file"
-        );
-        println!(
-            "\nSUCCESS: Calculation completed. Copy the array above to update TEST_JPEG_WITHOUT_EXIF."
+            keys,
+            ["xmp:CreatorTool", "By-line", "APP12 Ducky", "Comment"]
         );
 
-        // Optional: Uncomment the line below to force a failure and ensure output is always seen,
-        // but it's not needed if the test runs and prints correctly.
-        // assert!(false, "Forced failure to ensure output is displayed. Calculation was successful.");
+        let result = scrubber.scrub().unwrap();
+        assert_eq!(result.metadata_removed.len(), 4);
+        // The JFIF header, color profile and Adobe color transform are needed
+        // to show the image as it was meant to look.
+        assert_eq!(result.cleaned_file_bytes, jpeg_with(&[jfif(), icc, adobe]));
     }
 
     #[test]
-    fn _debug_test_jpeg_length() {
-        // This simple test just prints the length of the constant
-        // to confirm which one the tests are seeing.
-        println!(
-            "--- DEBUG: TEST_JPEG_WITH_EXIF length is {} ---",
-            TEST_JPEG_WITH_EXIF.len()
-        );
+    fn segments_are_only_kept_if_every_entry_is() {
+        let file = jpeg_with(&[xmp(), iptc()]);
+        let policy =
+            ScrubPolicy::remove_all().keep(crate::Selector::Category(crate::MetadataCategory::Xmp));
+        let result = JpegScrubber::new(file)
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        assert_eq!(result.metadata_kept.len(), 1);
+        assert_eq!(result.metadata_removed[0].key, "By-line");
+        assert_eq!(result.cleaned_file_bytes, jpeg_with(&[xmp()]));
+    }
+
+    #[test]
+    fn empty_xmp_packets_are_reported_and_removed() {
+        let packet = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#;
+        let file = jpeg_with(&[segment(0xE1, &[XMP_SIGNATURE, packet].concat())]);
+        let result = JpegScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 1);
+        assert_eq!(result.metadata_removed[0].key, "XMP");
+        assert_eq!(result.cleaned_file_bytes, TEST_JPEG_WITHOUT_EXIF);
+    }
+
+    #[test]
+    fn unreadable_exif_is_reported_when_dropped() {
+        let file = jpeg_with(&[segment(0xE1, b"Exif\0\0not a tiff")]);
+        let result = JpegScrubber::new(file).unwrap().scrub().unwrap();
 
-        // Print first 10 bytes to further confirm
-        let print_len = std::cmp::min(10, TEST_JPEG_WITH_EXIF.len());
-        println!(
-            "--- DEBUG: First {} bytes: {:?}",
-            print_len,
-            &TEST_JPEG_WITH_EXIF[..print_len]
+        assert_eq!(result.metadata_removed.len(), 1);
+        assert_eq!(result.metadata_removed[0].key, "Exif");
+        assert_eq!(
+            result.metadata_removed[0].value,
+            crate::MetadataValue::Binary { len: 16 }
         );
+        assert_eq!(result.cleaned_file_bytes, TEST_JPEG_WITHOUT_EXIF);
+    }
+
+    #[test]
+    fn walker_handles_restart_markers_and_trailers() {
+        let mut file = TEST_JPEG_WITHOUT_EXIF.to_vec();
+        // Put a restart marker and a stuffed byte into the scan, then append a trailer.
+        let eoi = file.len() - 2;
+        file.splice(eoi..eoi, [0xFF, 0xD0, 0xFF, 0x00, 0x12]);
+        file.extend_from_slice(b"trailing vendor data");
+        let scrubber = JpegScrubber::new(file.clone()).unwrap();
 
-        // Force a failure to ensure output is shown
-        // assert!(false, "Forced failure to show output");
+        let metadata = scrubber.view_metadata().unwrap();
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata[0].key, "Trailer");
+
+        let result = scrubber.scrub().unwrap();
+        assert_eq!(result.cleaned_file_bytes, file[..file.len() - 20]);
     }
 }
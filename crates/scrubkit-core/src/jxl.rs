@@ -0,0 +1,296 @@
+// File: crates/scrubkit-core/src/jxl.rs
+
+//! JPEG XL support.
+//!
+//! A JPEG XL file is either a bare codestream, which starts with `FF 0A` and
+//! has no room for metadata, or an ISO BMFF-style container. The container
+//! holds the codestream in a `jxlc` box or split across `jxlp` boxes, next to
+//! optional `Exif`, `xml ` (XMP) and `jumb` (JUMBF, used for C2PA content
+//! credentials) boxes. Metadata boxes may be Brotli-compressed in a `brob`
+//! box, whose first four bytes give the type of the box inside.
+//!
+//! Nothing in the container points at box offsets, so metadata boxes can be
+//! dropped without touching the rest. EXIF is filtered entry by entry; a
+//! compressed EXIF box that is filtered is written back uncompressed.
+
+use crate::exif::ExifData;
+use crate::isobmff::{BmffBox, exif_tiff, read_boxes, write_box};
+use crate::xmp::parse_xmp;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, RiskLevel, ScrubError, ScrubPolicy,
+    ScrubResult, Scrubber,
+};
+use brotli_decompressor::Decompressor;
+use std::io::Read;
+
+/// The start of a bare codestream.
+const CODESTREAM: &[u8] = &[0xFF, 0x0A];
+/// The `JXL ` signature box that starts a container.
+const SIGNATURE: &[u8] = &[
+    0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A,
+];
+
+const METADATA_BOXES: &[&[u8; 4]] = &[b"Exif", b"xml ", b"jumb"];
+
+/// The most a `brob` box may decompress to, so a small file can't expand
+/// into gigabytes.
+const MAX_DECOMPRESSED_LEN: u64 = 64 << 20;
+
+/// Returns true for bare JPEG XL codestreams and JPEG XL containers.
+pub fn is_jxl(bytes: &[u8]) -> bool {
+    bytes.starts_with(CODESTREAM) || bytes.starts_with(SIGNATURE)
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>, ScrubError> {
+    let mut decompressed = Vec::new();
+    Decompressor::new(data, 4096)
+        .take(MAX_DECOMPRESSED_LEN + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| ScrubError::ParsingError(format!("Corrupt brob box: {}", e)))?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_LEN {
+        return Err(ScrubError::ParsingError("brob box too large".into()));
+    }
+    Ok(decompressed)
+}
+
+/// A metadata box, with its contents decompressed if it was in a `brob` box.
+struct MetadataBox {
+    bmff: BmffBox,
+    box_type: [u8; 4],
+    data: Vec<u8>,
+}
+
+impl MetadataBox {
+    fn exif(&self) -> Option<(&[u8], ExifData)> {
+        let (prefix, tiff) = exif_tiff(&self.data)?;
+        Some((prefix, ExifData::parse(tiff).ok()?))
+    }
+
+    /// Describes the metadata in this box.
+    fn metadata_entries(&self) -> Vec<MetadataEntry> {
+        let len = self.data.len();
+        match &self.box_type {
+            b"Exif" => match self.exif() {
+                Some((_, exif)) => exif.metadata_entries(),
+                None => vec![MetadataEntry::new(
+                    MetadataCategory::Exif,
+                    "EXIF",
+                    MetadataValue::Binary { len },
+                )],
+            },
+            b"xml " => parse_xmp(&self.data),
+            _ => {
+                // Content credentials can name the author and list every edit.
+                let entry = MetadataEntry::new(
+                    MetadataCategory::Jumbf,
+                    "JUMBF",
+                    MetadataValue::Binary { len },
+                )
+                .with_risk(RiskLevel::High);
+                vec![match jumbf_label(&self.data) {
+                    Some(label) => entry.with_display_value(format!("{} ({} bytes)", label, len)),
+                    None => entry,
+                }]
+            }
+        }
+    }
+}
+
+/// Reads the label of a JUMBF superbox from its description box, such as
+/// `c2pa` for a C2PA manifest store.
+fn jumbf_label(data: &[u8]) -> Option<String> {
+    let description = read_boxes(data, 0, data.len()).ok()?.into_iter().next()?;
+    let fields = description.data(data);
+    // A 16-byte content type UUID, then toggles saying which fields follow.
+    const HAS_LABEL: u8 = 0x02;
+    if !description.is(b"jumd") || fields.get(16)? & HAS_LABEL == 0 {
+        return None;
+    }
+    let label = &fields[17..];
+    let end = label.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&label[..end]).into_owned())
+}
+
+/// Finds the metadata boxes of a container, decompressing `brob` boxes.
+/// A bare codestream has none.
+fn metadata_boxes(bytes: &[u8]) -> Result<Vec<MetadataBox>, ScrubError> {
+    if !bytes.starts_with(SIGNATURE) {
+        return Ok(Vec::new());
+    }
+    let mut boxes = Vec::new();
+    for bmff in read_boxes(bytes, 0, bytes.len())? {
+        let data = bmff.data(bytes);
+        let (box_type, compressed) = if bmff.is(b"brob") {
+            let box_type = data
+                .get(..4)
+                .ok_or_else(|| ScrubError::ParsingError("Truncated brob box".into()))?;
+            ([box_type[0], box_type[1], box_type[2], box_type[3]], true)
+        } else {
+            (bmff.box_type, false)
+        };
+        if !METADATA_BOXES.contains(&&box_type) {
+            continue;
+        }
+        let data = if compressed {
+            decompress(&data[4..])?
+        } else {
+            data.to_vec()
+        };
+        boxes.push(MetadataBox {
+            bmff,
+            box_type,
+            data,
+        });
+    }
+    Ok(boxes)
+}
+
+/// A Scrubber implementation for JPEG XL files.
+#[derive(Debug, Clone)]
+pub struct JxlScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for JxlScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_jxl(&file_bytes) {
+            return Err(ScrubError::ParsingError("Not a valid JPEG XL file".into()));
+        }
+        metadata_boxes(&file_bytes)?;
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        Ok(metadata_boxes(&self.file_bytes)?
+            .iter()
+            .flat_map(|b| b.metadata_entries())
+            .collect())
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        // The boxes to drop or replace, in file order.
+        let mut edits: Vec<(BmffBox, Option<Vec<u8>>)> = Vec::new();
+
+        for metadata in metadata_boxes(&self.file_bytes)? {
+            // EXIF is filtered entry by entry, like in JPEG files.
+            if &metadata.box_type == b"Exif"
+                && let Some((prefix, mut exif)) = metadata.exif()
+            {
+                let (kept, removed) = exif.apply_policy(policy);
+                if !removed.is_empty() {
+                    let replacement = (!exif.is_empty())
+                        .then(|| write_box(b"Exif", &[prefix, &exif.to_bytes()].concat()));
+                    edits.push((metadata.bmff, replacement));
+                }
+                metadata_kept.extend(kept);
+                metadata_removed.extend(removed);
+                continue;
+            }
+            let entries = metadata.metadata_entries();
+            if entries.iter().all(|e| policy.keeps(e)) {
+                metadata_kept.extend(entries);
+            } else {
+                metadata_removed.extend(entries);
+                edits.push((metadata.bmff, None));
+            }
+        }
+
+        let mut cleaned_bytes = Vec::with_capacity(self.file_bytes.len());
+        let mut start = 0;
+        for (bmff, replacement) in edits {
+            cleaned_bytes.extend_from_slice(&self.file_bytes[start..bmff.start]);
+            cleaned_bytes.extend(replacement.unwrap_or_default());
+            start = bmff.end;
+        }
+        cleaned_bytes.extend_from_slice(&self.file_bytes[start..]);
+
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A big-endian TIFF with Make = "ACME" and Orientation = 1.
+    fn tiff() -> Vec<u8> {
+        [
+            b"MM\0*\0\0\0\x08\0\x02".as_slice(),
+            b"\x01\x0F\0\x02\0\0\0\x05\0\0\0\x26",
+            b"\x01\x12\0\x03\0\0\0\x01\0\x01\0\0",
+            b"\0\0\0\0ACME\0",
+        ]
+        .concat()
+    }
+
+    /// A Brotli stream holding `data` in a single uncompressed meta-block.
+    fn brotli_stored(data: &[u8]) -> Vec<u8> {
+        // A 16-bit window, a meta-block header with a four-nibble length and
+        // the uncompressed flag, then an empty last meta-block.
+        let header = (((data.len() - 1) << 4) | (1 << 20)) as u32;
+        [&header.to_le_bytes()[..3], data, &[0x03]].concat()
+    }
+
+    fn container(boxes: &[Vec<u8>]) -> Vec<u8> {
+        [
+            SIGNATURE.to_vec(),
+            write_box(b"ftyp", b"jxl \0\0\0\0jxl "),
+            write_box(b"jxlc", &[0xFF, 0x0A, 0x01, 0x02]),
+            boxes.concat(),
+        ]
+        .concat()
+    }
+
+    fn c2pa() -> Vec<u8> {
+        let mut description = vec![0; 16];
+        description.push(0x03);
+        description.extend(b"c2pa\0");
+        write_box(b"jumb", &write_box(b"jumd", &description))
+    }
+
+    #[test]
+    fn bare_codestreams_have_no_metadata() {
+        let codestream = vec![0xFF, 0x0A, 0xFA, 0x03];
+        let scrubber = JxlScrubber::new(codestream.clone()).unwrap();
+
+        assert!(scrubber.view_metadata().unwrap().is_empty());
+        assert_eq!(scrubber.scrub().unwrap().cleaned_file_bytes, codestream);
+    }
+
+    #[test]
+    fn view_metadata_decompresses_brob_boxes() {
+        let exif = [&[0, 0, 0, 0][..], &tiff()].concat();
+        let brob = [b"Exif".as_slice(), &brotli_stored(&exif)].concat();
+        let file = container(&[write_box(b"brob", &brob), c2pa()]);
+        let entries = JxlScrubber::new(file).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(keys, ["Make", "Orientation", "JUMBF"]);
+        assert_eq!(entries[2].display_value, "c2pa (30 bytes)");
+    }
+
+    #[test]
+    fn scrub_drops_metadata_boxes_and_keeps_the_codestream() {
+        let exif = [&[0, 0, 0, 6][..], b"Exif\0\0", &tiff()].concat();
+        let brob = [b"xml ".as_slice(), &brotli_stored(b"<x/>")].concat();
+        let file = container(&[write_box(b"Exif", &exif), write_box(b"brob", &brob), c2pa()]);
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("Orientation".into()));
+        let result = JxlScrubber::new(file).unwrap().scrub_with(&policy).unwrap();
+
+        let kept_exif = ExifData::parse(&tiff()).map(|mut exif| {
+            exif.apply_policy(&policy);
+            exif.to_bytes()
+        });
+        let expected_exif = [&[0, 0, 0, 6][..], b"Exif\0\0", &kept_exif.unwrap()].concat();
+        assert_eq!(result.metadata_removed.len(), 3);
+        assert_eq!(
+            result.cleaned_file_bytes,
+            container(&[write_box(b"Exif", &expected_exif)])
+        );
+    }
+}
@@ -1,10 +1,59 @@
 // crates/scrubkit-core/src/lib.rs
 
+pub mod aiff;
+pub mod dicom;
+mod exif;
+mod exif_tags;
+pub mod flac;
+pub mod gif;
+pub mod heif;
+mod id3;
+mod iff;
+mod iptc;
+mod isobmff;
+pub mod jp2;
 pub mod jpeg;
+pub mod jxl;
+pub mod metadata;
+pub mod mp3;
+pub mod mp4;
+pub mod odf;
+pub mod ogg;
+pub mod ooxml;
+mod package;
+pub mod pdf;
 pub mod png;
+pub mod policy;
+pub mod risk;
+pub mod svg;
+pub mod tiff;
+mod vorbis;
+pub mod wav;
+pub mod webp;
+mod xmp;
+use aiff::AiffScrubber;
+use dicom::DicomScrubber;
+use flac::FlacScrubber;
+use gif::GifScrubber;
+use heif::HeifScrubber;
+use jp2::Jp2Scrubber;
 use jpeg::JpegScrubber;
+use jxl::JxlScrubber;
+pub use metadata::{MetadataCategory, MetadataValue};
+use mp3::Mp3Scrubber;
+use mp4::Mp4Scrubber;
+use odf::OdfScrubber;
+use ogg::OggScrubber;
+use ooxml::OoxmlScrubber;
+use pdf::PdfScrubber;
 use png::PngScrubber;
+pub use policy::{PolicyAction, ScrubPolicy, Selector};
+pub use risk::{MetadataSummary, RiskLevel};
+use svg::SvgScrubber;
 use thiserror::Error;
+use tiff::TiffScrubber;
+use wav::WavScrubber;
+use webp::WebpScrubber;
 
 /// A universal error type for all scrubbing operations.
 #[derive(Error, Debug)]
@@ -23,11 +72,52 @@ pub enum ScrubError {
 }
 
 /// Represents a single piece of metadata.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MetadataEntry {
     pub key: String,
-    pub value: String,
-    pub category: String, // e.g., "EXIF", "GPS", "Document Properties"
+    pub value: MetadataValue,
+    /// A human-readable rendering of `value`, for the CLI and web UI.
+    pub display_value: String,
+    pub category: MetadataCategory,
+    /// The numeric tag ID, for formats that have one (e.g. EXIF).
+    pub tag_id: Option<u16>,
+    /// How much this entry could reveal about the file's author.
+    pub risk: RiskLevel,
+}
+
+impl MetadataEntry {
+    /// Creates an entry whose display string is the value's default rendering.
+    /// The risk level is derived from the category, key and value.
+    pub fn new(category: MetadataCategory, key: impl Into<String>, value: MetadataValue) -> Self {
+        let key = key.into();
+        Self {
+            risk: risk::classify(category, &key, &value),
+            key,
+            display_value: value.to_string(),
+            value,
+            category,
+            tag_id: None,
+        }
+    }
+
+    /// Sets the numeric tag ID.
+    pub fn with_tag_id(mut self, tag_id: u16) -> Self {
+        self.tag_id = Some(tag_id);
+        self
+    }
+
+    /// Overrides the display string, e.g. to name an enumerated value.
+    pub fn with_display_value(mut self, display_value: impl Into<String>) -> Self {
+        self.display_value = display_value.into();
+        self
+    }
+
+    /// Overrides the derived risk level, for formats that know more about
+    /// an entry than its key says.
+    pub fn with_risk(mut self, risk: RiskLevel) -> Self {
+        self.risk = risk;
+        self
+    }
 }
 
 /// The result of a successful scrub operation.
@@ -37,6 +127,8 @@ pub struct ScrubResult {
     pub cleaned_file_bytes: Vec<u8>,
     /// A report of the metadata entries that were removed.
     pub metadata_removed: Vec<MetadataEntry>,
+    /// The metadata entries the policy chose to keep.
+    pub metadata_kept: Vec<MetadataEntry>,
 }
 
 /// The central trait of our library.
@@ -51,13 +143,29 @@ pub trait Scrubber {
     /// Returns all found metadata in a structured format.
     fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError>;
 
+    /// Returns the metadata grouped by privacy risk, most sensitive first.
+    fn summary(&self) -> Result<MetadataSummary, ScrubError> {
+        Ok(MetadataSummary::new(self.view_metadata()?))
+    }
+
     /// Removes all identifiable metadata.
-    fn scrub(&self) -> Result<ScrubResult, ScrubError>;
+    fn scrub(&self) -> Result<ScrubResult, ScrubError> {
+        self.scrub_with(&ScrubPolicy::default())
+    }
+
+    /// Removes the metadata the given policy doesn't keep.
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError>;
 }
 
 /// Detects the file type and returns the appropriate scrubber.
 /// This is the main entry point for consumers of the library.
 pub fn scrubber_for_file(file_bytes: Vec<u8>) -> Result<Box<dyn Scrubber>, ScrubError> {
+    // DICOM files have `DICM` after a 128-byte preamble, which may itself look like a TIFF header.
+    if dicom::is_dicom(&file_bytes) {
+        let scrubber = DicomScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
     // PNG files start with a specific 8-byte signature.
     if file_bytes.len() > 8 && file_bytes[0..8] == [137, 80, 78, 71, 13, 10, 26, 10] {
         let scrubber = PngScrubber::new(file_bytes)?;
@@ -70,7 +178,145 @@ pub fn scrubber_for_file(file_bytes: Vec<u8>) -> Result<Box<dyn Scrubber>, Scrub
         return Ok(Box::new(scrubber));
     }
 
+    // TIFF files (including DNG) start with a byte order mark and the number 42.
+    if file_bytes.len() > 8 && (file_bytes[0..4] == *b"II*\0" || file_bytes[0..4] == *b"MM\0*") {
+        let scrubber = TiffScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // GIF files start with their version signature.
+    if file_bytes.starts_with(b"GIF87a") || file_bytes.starts_with(b"GIF89a") {
+        let scrubber = GifScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // WebP files are RIFF containers with the WEBP form type.
+    if file_bytes.len() > 12 && &file_bytes[0..4] == b"RIFF" && &file_bytes[8..12] == b"WEBP" {
+        let scrubber = WebpScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // JPEG XL files are either a bare codestream or a box container with a signature box.
+    if jxl::is_jxl(&file_bytes) {
+        let scrubber = JxlScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // JPEG 2000 files start with a `jP  ` signature box, or are a bare codestream.
+    if jp2::is_jp2(&file_bytes) {
+        let scrubber = Jp2Scrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // HEIC, HEIF and AVIF files start with an `ftyp` box naming a HEIF brand.
+    if heif::is_heif(&file_bytes) {
+        let scrubber = HeifScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // MP4 and QuickTime movies are box trees too, usually starting with `ftyp`.
+    if mp4::is_mp4(&file_bytes) {
+        let scrubber = Mp4Scrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // PDF files start with a `%PDF-` header, though some tools put junk before it.
+    if pdf::is_pdf(&file_bytes) {
+        let scrubber = PdfScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // Word, Excel and PowerPoint files are ZIP packages with a content types part.
+    if ooxml::is_ooxml(&file_bytes) {
+        let scrubber = OoxmlScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // OpenDocument files are ZIP packages that start with a `mimetype` entry.
+    if odf::is_odf(&file_bytes) {
+        let scrubber = OdfScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // FLAC files start with `fLaC`, sometimes after an ID3v2 tag.
+    if flac::is_flac(&file_bytes) {
+        let scrubber = FlacScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // Ogg files start with the `OggS` capture pattern of their first page.
+    if ogg::is_ogg(&file_bytes) {
+        let scrubber = OggScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // WAV files are RIFF containers with the WAVE form type.
+    if wav::is_wav(&file_bytes) {
+        let scrubber = WavScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // AIFF files are IFF containers with the AIFF or AIFC form type.
+    if aiff::is_aiff(&file_bytes) {
+        let scrubber = AiffScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // MP3 files start with an ID3v2 tag or go straight into an audio frame.
+    if mp3::is_mp3(&file_bytes) {
+        let scrubber = Mp3Scrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
+    // SVG files are XML text, so they are recognized by their root element.
+    if svg::is_svg(&file_bytes) {
+        let scrubber = SvgScrubber::new(file_bytes)?;
+        return Ok(Box::new(scrubber));
+    }
+
     Err(ScrubError::UnsupportedFileType(
         "Could not determine file type.".to_string(),
     ))
 }
+
+/// Returns true if the file is a still image. Only these are handed on from
+/// the files they are embedded in, since cover art in a format that can
+/// itself embed pictures (an MP3 or FLAC file) could nest without end.
+fn is_still_image(file_bytes: &[u8]) -> bool {
+    !dicom::is_dicom(file_bytes)
+        && (file_bytes.starts_with(&[137, 80, 78, 71, 13, 10, 26, 10])
+            || file_bytes.starts_with(&[0xFF, 0xD8])
+            || file_bytes.starts_with(b"II*\0")
+            || file_bytes.starts_with(b"MM\0*")
+            || file_bytes.starts_with(b"GIF87a")
+            || file_bytes.starts_with(b"GIF89a")
+            || (file_bytes.len() > 12
+                && &file_bytes[0..4] == b"RIFF"
+                && &file_bytes[8..12] == b"WEBP")
+            || jxl::is_jxl(file_bytes)
+            || jp2::is_jp2(file_bytes)
+            || heif::is_heif(file_bytes))
+}
+
+/// Lists the metadata of an image embedded in another file, such as cover
+/// art. Images in formats we don't support, and anything that isn't a still
+/// image, have none.
+pub(crate) fn embedded_metadata(file_bytes: &[u8]) -> Vec<MetadataEntry> {
+    if !is_still_image(file_bytes) {
+        return Vec::new();
+    }
+    scrubber_for_file(file_bytes.to_vec())
+        .and_then(|scrubber| scrubber.view_metadata())
+        .unwrap_or_default()
+}
+
+/// Scrubs an image embedded in another file with the same policy, or returns
+/// `None` if it isn't a still image in a format we support.
+pub(crate) fn scrub_embedded(file_bytes: &[u8], policy: &ScrubPolicy) -> Option<ScrubResult> {
+    if !is_still_image(file_bytes) {
+        return None;
+    }
+    scrubber_for_file(file_bytes.to_vec())
+        .and_then(|scrubber| scrubber.scrub_with(policy))
+        .ok()
+}
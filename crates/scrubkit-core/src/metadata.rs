@@ -0,0 +1,263 @@
+// File: crates/scrubkit-core/src/metadata.rs
+
+use crate::ScrubError;
+use std::fmt;
+use std::str::FromStr;
+
+/// A typed metadata value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    Text(String),
+    Integer(i64),
+    Rational {
+        numerator: i64,
+        denominator: i64,
+    },
+    /// An ISO 8601 timestamp, e.g. `2024-05-01T14:30:00+02:00`.
+    /// Dates without a time of day are kept as `YYYY-MM-DD`.
+    DateTime(String),
+    /// A latitude or longitude in signed decimal degrees (south and west are negative).
+    GpsCoordinate(f64),
+    /// Binary data we don't decode, such as maker notes or embedded thumbnails.
+    Binary {
+        len: usize,
+    },
+    List(Vec<MetadataValue>),
+}
+
+impl MetadataValue {
+    /// Returns the value as a float, for numeric values.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            MetadataValue::Integer(v) => Some(v as f64),
+            MetadataValue::Rational {
+                numerator,
+                denominator,
+            } if denominator != 0 => Some(numerator as f64 / denominator as f64),
+            MetadataValue::GpsCoordinate(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MetadataValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataValue::Text(text) | MetadataValue::DateTime(text) => f.write_str(text),
+            MetadataValue::Integer(v) => write!(f, "{}", v),
+            MetadataValue::Rational {
+                numerator,
+                denominator,
+            } => {
+                if *denominator == 0 {
+                    return f.write_str("undefined");
+                }
+                // At most four fractional digits, without trailing zeros.
+                let formatted = format!("{:.4}", *numerator as f64 / *denominator as f64);
+                f.write_str(formatted.trim_end_matches('0').trim_end_matches('.'))
+            }
+            MetadataValue::GpsCoordinate(degrees) => write!(f, "{:.6}", degrees),
+            MetadataValue::Binary { len } => write!(f, "({} bytes of binary data)", len),
+            MetadataValue::List(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Where a metadata entry was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MetadataCategory {
+    /// The main EXIF IFD: camera, software and copyright information.
+    Ifd0,
+    /// The EXIF IFD describing the embedded thumbnail.
+    Ifd1,
+    /// The EXIF sub-IFD holding capture settings and timestamps.
+    Exif,
+    /// Location data: the EXIF GPS sub-IFD, or the place a video was recorded.
+    Gps,
+    /// The EXIF interoperability sub-IFD.
+    Interop,
+    /// PNG text and timestamp chunks: tEXt, zTXt, iTXt and tIME.
+    PngText,
+    /// XMP packets, including JPEG extended XMP.
+    Xmp,
+    /// IPTC-NAA records, such as the photographer's byline or the city.
+    Iptc,
+    /// Photoshop image resources other than IPTC.
+    Photoshop,
+    /// Embedded ICC color profiles.
+    Icc,
+    /// Free-form comments, such as JPEG COM segments.
+    Comment,
+    /// Vendor-specific blocks, such as JPEG APPn segments written by cameras or editors.
+    Vendor,
+    /// QuickTime and MP4 user data, metadata items and movie timestamps.
+    QuickTime,
+    /// The PDF document information dictionary and the trailer's document ID.
+    PdfInfo,
+    /// Earlier revisions of a PDF, kept by incremental updates.
+    PdfHistory,
+    /// Office document properties, such as the OOXML docProps parts and ODF's `meta.xml`.
+    OfficeProperties,
+    /// Author names, editing session IDs and cached files inside office documents.
+    OfficeContent,
+    /// ID3v1 and ID3v2 tags: titles, artists, comments, encoder settings and cover art.
+    Id3,
+    /// APEv2 tags, found at the end of MP3 files.
+    Ape,
+    /// Vorbis comments, the tags of FLAC, Ogg Vorbis and Opus files.
+    VorbisComment,
+    /// FLAC metadata blocks other than Vorbis comments, such as embedded pictures.
+    Flac,
+    /// WAV `LIST` chunks of type `INFO`: titles, artists, engineers and creation dates.
+    RiffInfo,
+    /// Broadcast WAV `bext` and `iXML` chunks: originators, recording times and project notes.
+    BroadcastWave,
+    /// AIFF text chunks: names, authors, annotations, copyright and comments.
+    AiffText,
+    /// SVG `<metadata>` blocks and editor attributes, such as Inkscape's export file names.
+    Svg,
+    /// JUMBF boxes, such as C2PA content credentials recording who made and edited a file.
+    Jumbf,
+    /// JPEG 2000 XML, UUID info and association boxes, other than XMP and GeoJP2.
+    Jp2,
+    /// DICOM attributes covered by the PS3.15 confidentiality profile: patient, physician and institution details, dates and UIDs.
+    Dicom,
+}
+
+impl MetadataCategory {
+    /// Every category, in the order they are usually listed.
+    pub const ALL: &'static [MetadataCategory] = &[
+        MetadataCategory::Ifd0,
+        MetadataCategory::Ifd1,
+        MetadataCategory::Exif,
+        MetadataCategory::Gps,
+        MetadataCategory::Interop,
+        MetadataCategory::PngText,
+        MetadataCategory::Xmp,
+        MetadataCategory::Iptc,
+        MetadataCategory::Photoshop,
+        MetadataCategory::Icc,
+        MetadataCategory::Comment,
+        MetadataCategory::Vendor,
+        MetadataCategory::QuickTime,
+        MetadataCategory::PdfInfo,
+        MetadataCategory::PdfHistory,
+        MetadataCategory::OfficeProperties,
+        MetadataCategory::OfficeContent,
+        MetadataCategory::Id3,
+        MetadataCategory::Ape,
+        MetadataCategory::VorbisComment,
+        MetadataCategory::Flac,
+        MetadataCategory::RiffInfo,
+        MetadataCategory::BroadcastWave,
+        MetadataCategory::AiffText,
+        MetadataCategory::Svg,
+        MetadataCategory::Jumbf,
+        MetadataCategory::Jp2,
+        MetadataCategory::Dicom,
+    ];
+
+    /// The short name shown to users.
+    pub fn name(self) -> &'static str {
+        match self {
+            MetadataCategory::Ifd0 => "IFD0",
+            MetadataCategory::Ifd1 => "IFD1",
+            MetadataCategory::Exif => "EXIF",
+            MetadataCategory::Gps => "GPS",
+            MetadataCategory::Interop => "Interop",
+            MetadataCategory::PngText => "PNG Text",
+            MetadataCategory::Xmp => "XMP",
+            MetadataCategory::Iptc => "IPTC",
+            MetadataCategory::Photoshop => "Photoshop",
+            MetadataCategory::Icc => "ICC",
+            MetadataCategory::Comment => "Comment",
+            MetadataCategory::Vendor => "Vendor",
+            MetadataCategory::QuickTime => "QuickTime",
+            MetadataCategory::PdfInfo => "PDF Info",
+            MetadataCategory::PdfHistory => "PDF History",
+            MetadataCategory::OfficeProperties => "Office",
+            MetadataCategory::OfficeContent => "Office Content",
+            MetadataCategory::Id3 => "ID3",
+            MetadataCategory::Ape => "APE",
+            MetadataCategory::VorbisComment => "Vorbis Comment",
+            MetadataCategory::Flac => "FLAC",
+            MetadataCategory::RiffInfo => "RIFF INFO",
+            MetadataCategory::BroadcastWave => "Broadcast WAV",
+            MetadataCategory::AiffText => "AIFF Text",
+            MetadataCategory::Svg => "SVG",
+            MetadataCategory::Jumbf => "JUMBF",
+            MetadataCategory::Jp2 => "JPEG 2000",
+            MetadataCategory::Dicom => "DICOM",
+        }
+    }
+}
+
+impl fmt::Display for MetadataCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for MetadataCategory {
+    type Err = ScrubError;
+
+    /// Parses a category from its name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MetadataCategory::ALL
+            .iter()
+            .copied()
+            .find(|c| c.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ScrubError::ParsingError(format!("Unknown metadata category: {}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_display_for_people() {
+        let rational = MetadataValue::Rational {
+            numerator: 1,
+            denominator: 250,
+        };
+        assert_eq!(rational.to_string(), "0.004");
+        assert_eq!(
+            MetadataValue::GpsCoordinate(-33.8567).to_string(),
+            "-33.856700"
+        );
+        assert_eq!(
+            MetadataValue::Binary { len: 12 }.to_string(),
+            "(12 bytes of binary data)"
+        );
+
+        let list = MetadataValue::List(vec![MetadataValue::Integer(8), MetadataValue::Integer(8)]);
+        assert_eq!(list.to_string(), "8, 8");
+    }
+
+    #[test]
+    fn categories_parse_from_their_names() {
+        assert_eq!(
+            "gps".parse::<MetadataCategory>().unwrap(),
+            MetadataCategory::Gps
+        );
+        assert_eq!(
+            "PNG Text".parse::<MetadataCategory>().unwrap(),
+            MetadataCategory::PngText
+        );
+        assert_eq!(
+            "xmp".parse::<MetadataCategory>().unwrap(),
+            MetadataCategory::Xmp
+        );
+        assert!("nonsense".parse::<MetadataCategory>().is_err());
+    }
+}
@@ -0,0 +1,389 @@
+// File: crates/scrubkit-core/src/mp3.rs
+
+//! MP3 support.
+//!
+//! An MP3 file is a stream of audio frames, usually with an ID3v2 tag in
+//! front of it. Older taggers append an ID3v1 tag (a fixed 128-byte block
+//! starting with `TAG`) and an APEv2 tag before it. ID3v2 frames are removed
+//! one at a time; the ID3v1 and APEv2 tags are kept or removed whole. The
+//! audio frames in between are copied unchanged.
+
+use crate::id3::{self, Id3v2};
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+
+const ID3V1_LEN: usize = 128;
+const APE_FOOTER_LEN: usize = 32;
+const APE_HAS_HEADER: u32 = 0x8000_0000;
+
+/// Returns true for files that start with an ID3v2 tag or an MPEG audio frame.
+pub fn is_mp3(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"ID3")
+        || matches!(bytes, [0xFF, b1, b2, ..] if b1 & 0xE0 == 0xE0 && b1 & 0x06 != 0 && b2 & 0xF0 != 0xF0)
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// An MP3 split into its tags and its audio.
+struct Layout<'a> {
+    id3v2: Vec<&'a [u8]>,
+    audio: &'a [u8],
+    ape: Option<&'a [u8]>,
+    id3v1: Option<&'a [u8]>,
+}
+
+fn layout(bytes: &[u8]) -> Result<Layout<'_>, ScrubError> {
+    if !is_mp3(bytes) {
+        return Err(ScrubError::ParsingError("Not a valid MP3 file".into()));
+    }
+    // Some files have more than one ID3v2 tag in front.
+    let mut id3v2 = Vec::new();
+    let mut start = 0;
+    while let Some(len) = id3::tag_len(&bytes[start..]) {
+        let tag = bytes
+            .get(start..start + len)
+            .ok_or_else(|| ScrubError::ParsingError("Truncated ID3v2 tag".into()))?;
+        id3v2.push(tag);
+        start += len;
+    }
+
+    let mut end = bytes.len();
+    let id3v1 = (end - start >= ID3V1_LEN && bytes[end - ID3V1_LEN..].starts_with(b"TAG"))
+        .then(|| &bytes[end - ID3V1_LEN..]);
+    end -= id3v1.map_or(0, <[u8]>::len);
+    let ape = ape_len(&bytes[start..end]).map(|len| &bytes[end - len..end]);
+    end -= ape.map_or(0, <[u8]>::len);
+
+    Ok(Layout {
+        id3v2,
+        audio: &bytes[start..end],
+        ape,
+        id3v1,
+    })
+}
+
+/// Returns the length of the APEv2 tag at the end of `bytes`, found by its footer.
+fn ape_len(bytes: &[u8]) -> Option<usize> {
+    let footer = bytes.get(bytes.len().checked_sub(APE_FOOTER_LEN)?..)?;
+    if !footer.starts_with(b"APETAGEX") {
+        return None;
+    }
+    // The size covers the items and the footer, but not the optional header.
+    let size = read_u32_le(footer, 12)? as usize;
+    let header = match read_u32_le(footer, 20)? & APE_HAS_HEADER {
+        0 => 0,
+        _ => APE_FOOTER_LEN,
+    };
+    Some(size + header).filter(|len| *len >= APE_FOOTER_LEN && *len <= bytes.len())
+}
+
+/// Describes the items of an APEv2 tag.
+fn ape_entries(tag: &[u8]) -> Vec<MetadataEntry> {
+    let footer = &tag[tag.len() - APE_FOOTER_LEN..];
+    let count = read_u32_le(footer, 16).unwrap_or(0);
+    let mut pos = tag.len() - read_u32_le(footer, 12).unwrap_or(0) as usize;
+    let items_end = tag.len() - APE_FOOTER_LEN;
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let (Some(len), Some(flags)) = (read_u32_le(tag, pos), read_u32_le(tag, pos + 4)) else {
+            break;
+        };
+        let Some(key_len) = tag[(pos + 8).min(items_end)..items_end]
+            .iter()
+            .position(|&b| b == 0)
+        else {
+            break;
+        };
+        let key = String::from_utf8_lossy(&tag[pos + 8..pos + 8 + key_len]).into_owned();
+        let value_start = pos + 8 + key_len + 1;
+        let Some(value) = tag.get(value_start..value_start + len as usize) else {
+            break;
+        };
+        pos = value_start + len as usize;
+
+        // Bits 1-2 give the item type: UTF-8 text, binary, or a link.
+        let entry = match (flags >> 1) & 0x03 {
+            1 => {
+                // Cover art items start with the image's file name.
+                let name_len = value.iter().position(|&b| b == 0).unwrap_or(0);
+                let name = String::from_utf8_lossy(&value[..name_len]);
+                let len = value.len() - (name_len + 1).min(value.len());
+                let entry =
+                    MetadataEntry::new(MetadataCategory::Ape, key, MetadataValue::Binary { len });
+                if name.is_empty() {
+                    entry
+                } else {
+                    entry.with_display_value(format!("{} ({} bytes)", name, len))
+                }
+            }
+            _ => {
+                let text = String::from_utf8_lossy(value);
+                // Multiple values are separated by null bytes.
+                let mut values: Vec<MetadataValue> = text
+                    .split('\0')
+                    .map(|v| MetadataValue::Text(v.to_string()))
+                    .collect();
+                let value = match values.len() {
+                    1 => values.remove(0),
+                    _ => MetadataValue::List(values),
+                };
+                MetadataEntry::new(MetadataCategory::Ape, key, value)
+            }
+        };
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Describes the fields of an ID3v1 tag. ID3v1.1 puts the track number in
+/// the last byte of the comment.
+fn id3v1_entries(tag: &[u8]) -> Vec<MetadataEntry> {
+    let text = |range: std::ops::Range<usize>| {
+        let text: String = tag[range].iter().map(|&b| b as char).collect();
+        text.trim_end_matches(['\0', ' ']).to_string()
+    };
+    let has_track = tag[125] == 0 && tag[126] != 0;
+    let comment_end = if has_track { 125 } else { 127 };
+
+    let mut entries: Vec<MetadataEntry> = [
+        ("Title", text(3..33)),
+        ("Artist", text(33..63)),
+        ("Album", text(63..93)),
+        ("Year", text(93..97)),
+        ("Comment", text(97..comment_end)),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| MetadataEntry::new(MetadataCategory::Id3, key, MetadataValue::Text(value)))
+    .collect();
+    if has_track {
+        entries.push(MetadataEntry::new(
+            MetadataCategory::Id3,
+            "Track",
+            MetadataValue::Integer(tag[126].into()),
+        ));
+    }
+    // 255 means no genre.
+    if tag[127] != 255 {
+        entries.push(MetadataEntry::new(
+            MetadataCategory::Id3,
+            "Genre",
+            MetadataValue::Integer(tag[127].into()),
+        ));
+    }
+    entries
+}
+
+/// A Scrubber implementation for MP3 files.
+#[derive(Debug, Clone)]
+pub struct Mp3Scrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for Mp3Scrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        for tag in layout(&file_bytes)?.id3v2 {
+            Id3v2::parse(tag)?;
+        }
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let layout = layout(&self.file_bytes)?;
+        let mut metadata = Vec::new();
+        for tag in layout.id3v2 {
+            metadata.extend(Id3v2::parse(tag)?.metadata_entries());
+        }
+        metadata.extend(layout.ape.map(ape_entries).unwrap_or_default());
+        metadata.extend(layout.id3v1.map(id3v1_entries).unwrap_or_default());
+        Ok(metadata)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let layout = layout(&self.file_bytes)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut cleaned_bytes = Vec::new();
+
+        for tag in layout.id3v2 {
+            let mut id3 = Id3v2::parse(tag)?;
+            let (kept, removed) = id3.apply_policy(policy);
+            if removed.is_empty() {
+                cleaned_bytes.extend_from_slice(tag);
+            } else if !id3.is_empty() {
+                cleaned_bytes.extend(id3.to_bytes());
+            }
+            metadata_kept.extend(kept);
+            metadata_removed.extend(removed);
+        }
+        cleaned_bytes.extend_from_slice(layout.audio);
+
+        let trailing = [
+            layout.ape.map(|tag| (tag, ape_entries(tag))),
+            layout.id3v1.map(|tag| (tag, id3v1_entries(tag))),
+        ];
+        for (tag, entries) in trailing.into_iter().flatten() {
+            // These tags are copied as-is, so they're only kept if every entry is.
+            if entries.iter().all(|e| policy.keeps(e)) {
+                cleaned_bytes.extend_from_slice(tag);
+                metadata_kept.extend(entries);
+            } else {
+                metadata_removed.extend(entries);
+            }
+        }
+
+        if metadata_removed.is_empty() {
+            cleaned_bytes = self.file_bytes.clone();
+        }
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An MPEG-1 Layer III frame header followed by silence.
+    const AUDIO: &[u8] = b"\xFF\xFB\x90\x64\0\0\0\0\0\0\0\0";
+    /// A JPEG whose only metadata is a comment.
+    const COVER: &[u8] = b"\xFF\xD8\xFF\xFE\x00\x06Jane\xFF\xDA\x00\x02\x00\xFF\xD9";
+
+    fn frame(id: &str, content: &[u8]) -> Vec<u8> {
+        [
+            id.as_bytes(),
+            &(content.len() as u32).to_be_bytes(),
+            b"\0\0",
+            content,
+        ]
+        .concat()
+    }
+
+    fn id3v2(frames: &[&[u8]]) -> Vec<u8> {
+        let frames = frames.concat();
+        let size = frames.len() as u32;
+        let syncsafe = [size >> 21, size >> 14, size >> 7, size].map(|b| (b & 0x7F) as u8);
+        [b"ID3\x03\x00\x00".as_slice(), &syncsafe, &frames].concat()
+    }
+
+    fn ape() -> Vec<u8> {
+        let item = b"\x08\0\0\0\0\0\0\0Artist\0Jane Doe".to_vec();
+        let mut footer = b"APETAGEX\xD0\x07\0\0".to_vec();
+        footer.extend(((item.len() + APE_FOOTER_LEN) as u32).to_le_bytes());
+        footer.extend(1u32.to_le_bytes());
+        footer.extend([0; 12]);
+        [item, footer].concat()
+    }
+
+    fn id3v1() -> Vec<u8> {
+        let mut tag = b"TAG".to_vec();
+        for (field, len) in [
+            ("Song", 30),
+            ("Jane Doe", 30),
+            ("", 30),
+            ("2024", 4),
+            ("", 28),
+        ] {
+            tag.extend(field.bytes());
+            tag.resize(tag.len() + len - field.len(), 0);
+        }
+        tag.extend([0, 3, 255]);
+        tag
+    }
+
+    fn mp3() -> Vec<u8> {
+        let tag = id3v2(&[
+            &frame("TIT2", b"\x00Song"),
+            &frame("TSSE", b"\x00LAME 3.100"),
+            &frame(
+                "APIC",
+                &[b"\x00image/jpeg\0\x03\0".as_slice(), COVER].concat(),
+            ),
+        ]);
+        [tag, AUDIO.to_vec(), ape(), id3v1()].concat()
+    }
+
+    #[test]
+    fn view_metadata_lists_every_tag() {
+        let entries = Mp3Scrubber::new(mp3()).unwrap().view_metadata().unwrap();
+        let listed: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.display_value.as_str()))
+            .collect();
+
+        assert_eq!(
+            listed,
+            [
+                ("Title", "Song"),
+                ("EncoderSettings", "LAME 3.100"),
+                ("Picture", "Front cover, image/jpeg (17 bytes)"),
+                ("Comment", "Jane"),
+                ("Artist", "Jane Doe"),
+                ("Title", "Song"),
+                ("Artist", "Jane Doe"),
+                ("Year", "2024"),
+                ("Track", "3"),
+            ]
+        );
+        assert_eq!(entries[4].category, MetadataCategory::Ape);
+    }
+
+    #[test]
+    fn scrub_leaves_only_the_audio() {
+        let result = Mp3Scrubber::new(mp3()).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 9);
+        assert_eq!(result.cleaned_file_bytes, AUDIO);
+    }
+
+    #[test]
+    fn tags_without_readable_frames_are_removed() {
+        // The frame size runs past the end of the tag.
+        let tag = id3v2(&[b"TIT2\x00\x00\x01\x00\x00\x00\x00Jane Doe"]);
+        let result = Mp3Scrubber::new([tag, AUDIO.to_vec()].concat())
+            .unwrap()
+            .scrub()
+            .unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "ID3v2");
+        assert_eq!(result.cleaned_file_bytes, AUDIO);
+    }
+
+    #[test]
+    fn scrub_with_policy_scrubs_kept_cover_art() {
+        let policy =
+            ScrubPolicy::remove_all().keep(crate::Selector::Category(MetadataCategory::Id3));
+        let result = Mp3Scrubber::new(mp3())
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        let keys: Vec<&str> = result
+            .metadata_removed
+            .iter()
+            .map(|e| e.key.as_str())
+            .collect();
+        assert_eq!(keys, ["Comment", "Artist"]);
+        let entries = Mp3Scrubber::new(result.cleaned_file_bytes.clone())
+            .unwrap()
+            .view_metadata()
+            .unwrap();
+        assert_eq!(entries.len(), 7);
+        assert_eq!(
+            entries[2].display_value,
+            "Front cover, image/jpeg (9 bytes)"
+        );
+        assert!(
+            result
+                .cleaned_file_bytes
+                .ends_with(&[AUDIO, &id3v1()].concat())
+        );
+    }
+}
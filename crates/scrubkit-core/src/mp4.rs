@@ -0,0 +1,672 @@
+// File: crates/scrubkit-core/src/mp4.rs
+
+//! MP4 and QuickTime (MOV) support.
+//!
+//! Video metadata lives in a handful of places inside `moov`: `udta` atoms
+//! such as `©xyz` (the recording location) and `©mod`, `meta` boxes whose
+//! `ilst` items are named by a `keys` table (Apple's
+//! `com.apple.quicktime.location.ISO6709` and friends), `uuid` boxes written
+//! by vendors, and the creation and modification times in the `mvhd`, `tkhd`
+//! and `mdhd` headers.
+//!
+//! Metadata atoms are cut out and timestamps are zeroed in place. The media
+//! samples are never touched: `stco`/`co64` chunk offsets that point past a
+//! removed atom are shifted to match. Fragmented files, whose fragments use
+//! absolute offsets of their own, get removed atoms replaced by `free` boxes
+//! of the same size instead.
+
+use crate::isobmff::{
+    BmffBox, Edits, XMP_UUID, full_box_children, read_boxes, read_uint, write_box,
+};
+use crate::xmp::{looks_like_date, parse_xmp};
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+use std::ops::Range;
+
+/// Boxes that only hold other boxes, on the way to the ones we care about.
+const CONTAINERS: &[&[u8; 4]] = &[b"moov", b"trak", b"mdia", b"minf", b"stbl"];
+
+/// Box types a QuickTime or MP4 file can start with.
+const FIRST_BOXES: &[&[u8; 4]] = &[b"ftyp", b"moov", b"mdat", b"wide", b"free", b"skip"];
+
+/// Seconds between 1904-01-01, the QuickTime epoch, and 1970-01-01.
+const QUICKTIME_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// Returns true if the file starts with a box a movie can start with.
+pub(crate) fn is_mp4(bytes: &[u8]) -> bool {
+    bytes
+        .get(4..8)
+        .is_some_and(|t| FIRST_BOXES.iter().any(|b| b.as_slice() == t))
+}
+
+/// Renders a four-character code. QuickTime uses Latin-1, so `0xA9` is `©`.
+fn fourcc(box_type: &[u8; 4]) -> String {
+    box_type.iter().map(|&b| b as char).collect()
+}
+
+/// Friendly names for well-known `©` atoms and `ilst` items.
+fn atom_name(box_type: &[u8; 4]) -> Option<&'static str> {
+    Some(match box_type {
+        b"\xA9xyz" => "Location",
+        b"\xA9mak" => "Make",
+        b"\xA9mod" => "Model",
+        b"\xA9swr" => "Software",
+        b"\xA9too" => "Encoder",
+        b"\xA9day" => "CreationDate",
+        b"\xA9nam" => "Title",
+        b"\xA9ART" => "Artist",
+        b"\xA9aut" => "Author",
+        b"\xA9cmt" => "Comment",
+        b"\xA9des" => "Description",
+        b"\xA9inf" => "Information",
+        b"\xA9cpy" => "Copyright",
+        b"\xA9alb" => "Album",
+        b"\xA9wrt" => "Writer",
+        b"\xA9dir" => "Director",
+        b"\xA9prd" => "Producer",
+        b"\xA9hst" => "HostComputer",
+        b"\xA9key" => "Keywords",
+        b"cprt" => "Copyright",
+        b"auth" => "Author",
+        b"titl" => "Title",
+        b"dscp" => "Description",
+        b"perf" => "Performer",
+        b"albm" => "Album",
+        b"loci" => "Location",
+        _ => return None,
+    })
+}
+
+/// Location atoms and keys go under GPS, everything else under QuickTime.
+fn category_for(key: &str) -> MetadataCategory {
+    if key == "Location" || key.to_ascii_lowercase().contains("location") {
+        MetadataCategory::Gps
+    } else {
+        MetadataCategory::QuickTime
+    }
+}
+
+fn text_entry(key: String, text: String) -> MetadataEntry {
+    let value = if looks_like_date(&text) {
+        MetadataValue::DateTime(text)
+    } else {
+        MetadataValue::Text(text)
+    };
+    MetadataEntry::new(category_for(&key), key, value)
+}
+
+fn binary_entry(category: MetadataCategory, key: String, len: usize) -> MetadataEntry {
+    MetadataEntry::new(category, key, MetadataValue::Binary { len })
+}
+
+/// Converts seconds since 1904 into an ISO 8601 UTC timestamp.
+pub(crate) fn quicktime_time(seconds: u64) -> String {
+    let unix = seconds as i64 - QUICKTIME_EPOCH_OFFSET;
+    let (days, secs) = (unix.div_euclid(86_400), unix.rem_euclid(86_400));
+    // Civil date from days since 1970-01-01, after Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Decodes the value of an iTunes-style `data` box.
+fn data_value(data: &[u8]) -> MetadataValue {
+    let well_known_type = read_uint(data, 0, 4).unwrap_or(0) & 0x00FF_FFFF;
+    let payload = data.get(8..).unwrap_or_default();
+    let int = || match payload.len() {
+        1 | 2 | 4 | 8 => read_uint(payload, 0, payload.len()),
+        _ => None,
+    };
+    match well_known_type {
+        1 => MetadataValue::Text(String::from_utf8_lossy(payload).into_owned()),
+        2 => {
+            let units: Vec<u16> = payload
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            MetadataValue::Text(String::from_utf16_lossy(&units))
+        }
+        // Signed integers, sign-extended from their actual width.
+        21 => match int() {
+            Some(v) => {
+                let shift = 64 - payload.len() * 8;
+                MetadataValue::Integer(((v << shift) as i64) >> shift)
+            }
+            None => MetadataValue::Binary { len: payload.len() },
+        },
+        22 => match int() {
+            Some(v) => MetadataValue::Integer(v as i64),
+            None => MetadataValue::Binary { len: payload.len() },
+        },
+        23 if payload.len() == 4 => {
+            let bits = read_uint(payload, 0, 4).unwrap_or(0) as u32;
+            MetadataValue::Text(f32::from_bits(bits).to_string())
+        }
+        24 if payload.len() == 8 => {
+            let bits = read_uint(payload, 0, 8).unwrap_or(0);
+            MetadataValue::Text(f64::from_bits(bits).to_string())
+        }
+        _ => MetadataValue::Binary { len: payload.len() },
+    }
+}
+
+/// Reads the values of an `ilst` item, or of a `©` atom written the same way.
+fn item_entries(bytes: &[u8], item: &BmffBox, key: String) -> Vec<MetadataEntry> {
+    let children = read_boxes(bytes, item.data_start(), item.end).unwrap_or_default();
+    // Freeform `----` items carry their own name.
+    let key = children
+        .iter()
+        .find(|b| b.is(b"name"))
+        .and_then(|b| b.data(bytes).get(4..))
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .unwrap_or(key);
+
+    let entries: Vec<MetadataEntry> = children
+        .iter()
+        .filter(|b| b.is(b"data"))
+        .map(|b| match data_value(b.data(bytes)) {
+            MetadataValue::Text(text) => text_entry(key.clone(), text),
+            value => MetadataEntry::new(category_for(&key), key.clone(), value),
+        })
+        .collect();
+    if entries.is_empty() {
+        vec![binary_entry(
+            category_for(&key),
+            key,
+            item.end - item.data_start(),
+        )]
+    } else {
+        entries
+    }
+}
+
+/// Reads a QuickTime `udta` text atom: a list of strings, each with a
+/// 16-bit length and a language code.
+fn quicktime_text(data: &[u8]) -> Option<Vec<String>> {
+    let mut strings = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let len = read_uint(data, pos, 2)? as usize;
+        let text = data.get(pos + 4..pos + 4 + len)?;
+        strings.push(
+            String::from_utf8_lossy(text)
+                .trim_end_matches('\0')
+                .to_string(),
+        );
+        pos += 4 + len;
+    }
+    Some(strings)
+}
+
+/// Describes a child of `udta` other than `meta`.
+fn user_data_entries(bytes: &[u8], atom: &BmffBox) -> Vec<MetadataEntry> {
+    let data = atom.data(bytes);
+    let key = atom_name(&atom.box_type)
+        .map(str::to_string)
+        .unwrap_or_else(|| fourcc(&atom.box_type));
+
+    // Some writers store `©` atoms like `ilst` items, with `data` children.
+    if data.get(4..8) == Some(b"data") {
+        return item_entries(bytes, atom, key);
+    }
+    match &atom.box_type {
+        b"XMP_" => return parse_xmp(data),
+        [0xA9, ..] => {
+            if let Some(strings) = quicktime_text(data) {
+                return strings
+                    .into_iter()
+                    .map(|text| text_entry(key.clone(), text))
+                    .collect();
+            }
+        }
+        // 3GPP asset atoms: a full box header, a language code, then UTF-8.
+        b"cprt" | b"auth" | b"titl" | b"dscp" | b"perf" | b"albm" => {
+            if let Some(text) = data.get(6..) {
+                let text = String::from_utf8_lossy(text);
+                return vec![text_entry(key, text.trim_end_matches('\0').to_string())];
+            }
+        }
+        _ => {}
+    }
+    let category = match atom_name(&atom.box_type) {
+        Some(_) => category_for(&key),
+        None => MetadataCategory::Vendor,
+    };
+    vec![binary_entry(category, key, data.len())]
+}
+
+/// Describes a `uuid` box.
+fn uuid_entries(bytes: &[u8], uuid: &BmffBox) -> Vec<MetadataEntry> {
+    let user_type = &bytes[uuid.data_start() - 16..uuid.data_start()];
+    if user_type == XMP_UUID {
+        return parse_xmp(uuid.data(bytes));
+    }
+    let hex: String = user_type.iter().map(|b| format!("{:02x}", b)).collect();
+    vec![binary_entry(
+        MetadataCategory::Vendor,
+        format!("uuid {}", hex),
+        uuid.end - uuid.data_start(),
+    )]
+}
+
+/// What happens to a unit of metadata when the policy drops it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Removal {
+    /// Cut the whole box out.
+    Remove,
+    /// Overwrite the bytes with zeros, for fields inside structural boxes.
+    Zero,
+}
+
+/// A piece of metadata that is kept or dropped as a whole.
+#[derive(Debug)]
+struct Unit {
+    range: Range<usize>,
+    removal: Removal,
+    entries: Vec<MetadataEntry>,
+}
+
+/// Everything found while walking the box tree.
+#[derive(Debug, Default)]
+struct Walk {
+    units: Vec<Unit>,
+    /// The `stco` and `co64` boxes.
+    chunk_offsets: Vec<BmffBox>,
+    /// Boxes whose size must be fixed if their contents change.
+    containers: Vec<BmffBox>,
+}
+
+impl Walk {
+    fn unit(&mut self, range: Range<usize>, removal: Removal, entries: Vec<MetadataEntry>) {
+        self.units.push(Unit {
+            range,
+            removal,
+            entries,
+        });
+    }
+
+    fn boxes(&mut self, bytes: &[u8], boxes: &[BmffBox]) -> Result<(), ScrubError> {
+        for b in boxes {
+            match &b.box_type {
+                t if CONTAINERS.contains(&t) => {
+                    self.containers.push(*b);
+                    self.boxes(bytes, &read_boxes(bytes, b.data_start(), b.end)?)?;
+                }
+                b"udta" => {
+                    self.containers.push(*b);
+                    for atom in read_boxes(bytes, b.data_start(), b.end)? {
+                        if atom.is(b"meta") {
+                            self.meta(bytes, &atom)?;
+                        } else {
+                            self.unit(
+                                atom.range(),
+                                Removal::Remove,
+                                user_data_entries(bytes, &atom),
+                            );
+                        }
+                    }
+                }
+                b"meta" => self.meta(bytes, b)?,
+                b"uuid" => self.unit(b.range(), Removal::Remove, uuid_entries(bytes, b)),
+                b"mvhd" => self.timestamps(bytes, b, "Movie"),
+                b"tkhd" => self.timestamps(bytes, b, "Track"),
+                b"mdhd" => self.timestamps(bytes, b, "Media"),
+                b"stco" | b"co64" => self.chunk_offsets.push(*b),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a `meta` box. QuickTime's is a plain box, while MP4's is a full
+    /// box with a version and flags, so look for where `hdlr` starts.
+    fn meta(&mut self, bytes: &[u8], meta: &BmffBox) -> Result<(), ScrubError> {
+        self.containers.push(*meta);
+        let children = if meta.data(bytes).get(4..8) == Some(b"hdlr") {
+            read_boxes(bytes, meta.data_start(), meta.end)?
+        } else {
+            full_box_children(bytes, meta)?
+        };
+
+        let keys = children
+            .iter()
+            .find(|b| b.is(b"keys"))
+            .map(|keys| read_keys(keys.data(bytes)))
+            .unwrap_or_default();
+        for ilst in children.iter().filter(|b| b.is(b"ilst")) {
+            self.containers.push(*ilst);
+            for item in read_boxes(bytes, ilst.data_start(), ilst.end)? {
+                // Items are either named by a four-character code or by a
+                // one-based index into `keys`.
+                let index = u32::from_be_bytes(item.box_type) as usize;
+                let key = match keys.get(index.wrapping_sub(1)) {
+                    Some(key) => key.clone(),
+                    None => atom_name(&item.box_type)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| fourcc(&item.box_type)),
+                };
+                self.unit(
+                    item.range(),
+                    Removal::Remove,
+                    item_entries(bytes, &item, key),
+                );
+            }
+        }
+        for xml in children.iter().filter(|b| b.is(b"xml ")) {
+            let entries = parse_xmp(xml.data(bytes).get(4..).unwrap_or_default());
+            self.unit(xml.range(), Removal::Remove, entries);
+        }
+        Ok(())
+    }
+
+    /// Reports the creation and modification times of a movie, track or
+    /// media header, which are zeroed rather than removed.
+    fn timestamps(&mut self, bytes: &[u8], header: &BmffBox, prefix: &str) {
+        let size = if header.data(bytes).first() == Some(&1) {
+            8
+        } else {
+            4
+        };
+        for (i, name) in ["CreationTime", "ModificationTime"].into_iter().enumerate() {
+            let start = header.data_start() + 4 + i * size;
+            match read_uint(bytes, start, size) {
+                Some(0) | None => continue,
+                Some(seconds) => {
+                    let entry = MetadataEntry::new(
+                        MetadataCategory::QuickTime,
+                        format!("{}{}", prefix, name),
+                        MetadataValue::DateTime(quicktime_time(seconds)),
+                    );
+                    self.unit(start..start + size, Removal::Zero, vec![entry]);
+                }
+            }
+        }
+    }
+}
+
+/// Reads the key names of a `keys` box.
+fn read_keys(data: &[u8]) -> Vec<String> {
+    let mut keys = Vec::new();
+    let count = read_uint(data, 4, 4).unwrap_or(0);
+    let mut pos = 8;
+    for _ in 0..count {
+        let Some(size) = read_uint(data, pos, 4).map(|s| s as usize) else {
+            break;
+        };
+        let Some(name) = data.get(pos + 8..pos + size.max(8)) else {
+            break;
+        };
+        keys.push(String::from_utf8_lossy(name).into_owned());
+        pos += size.max(8);
+    }
+    keys
+}
+
+/// Rewrites a chunk offset table so each offset points where its chunk ends up.
+fn remap_chunk_offsets(bytes: &[u8], table: &BmffBox, edits: &Edits) -> Option<Vec<u8>> {
+    let data = table.data(bytes);
+    let size = if table.is(b"co64") { 8 } else { 4 };
+    let count = read_uint(data, 4, 4)? as usize;
+    let mut out = data.get(..8)?.to_vec();
+    for i in 0..count {
+        let offset = read_uint(data, 8 + i * size, size)? as usize;
+        let mapped = edits.map(offset) as u64;
+        out.extend_from_slice(&mapped.to_be_bytes()[8 - size..]);
+    }
+    Some(out)
+}
+
+/// A Scrubber implementation for MP4 and QuickTime movies.
+#[derive(Debug, Clone)]
+pub struct Mp4Scrubber {
+    file_bytes: Vec<u8>,
+}
+
+// Private helper functions for Mp4Scrubber
+impl Mp4Scrubber {
+    fn walk(&self) -> Result<(Vec<BmffBox>, Walk), ScrubError> {
+        let boxes = read_boxes(&self.file_bytes, 0, self.file_bytes.len())?;
+        let mut walk = Walk::default();
+        walk.boxes(&self.file_bytes, &boxes)?;
+        Ok((boxes, walk))
+    }
+}
+
+impl Scrubber for Mp4Scrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_mp4(&file_bytes) {
+            return Err(ScrubError::ParsingError(
+                "Not a valid MP4 or QuickTime file".into(),
+            ));
+        }
+        let scrubber = Self { file_bytes };
+        scrubber.walk()?;
+        Ok(scrubber)
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let (_, walk) = self.walk()?;
+        Ok(walk.units.into_iter().flat_map(|u| u.entries).collect())
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let bytes = &self.file_bytes;
+        let (boxes, walk) = self.walk()?;
+        let fragmented = boxes.iter().any(|b| b.is(b"moof"));
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut edits = Edits::default();
+
+        for unit in walk.units {
+            if unit.entries.iter().all(|e| policy.keeps(e)) {
+                metadata_kept.extend(unit.entries);
+                continue;
+            }
+            metadata_removed.extend(unit.entries);
+            let replacement = match unit.removal {
+                Removal::Zero => vec![0; unit.range.len()],
+                Removal::Remove if fragmented => write_box(b"free", &vec![0; unit.range.len() - 8]),
+                Removal::Remove => Vec::new(),
+            };
+            edits.replace(unit.range, replacement)?;
+        }
+
+        if metadata_removed.is_empty() {
+            return Ok(ScrubResult {
+                cleaned_file_bytes: bytes.clone(),
+                metadata_removed,
+                metadata_kept,
+            });
+        }
+
+        // Chunk offset tables keep their size, so rewriting them doesn't move anything.
+        for table in &walk.chunk_offsets {
+            let remapped = remap_chunk_offsets(bytes, table, &edits).ok_or_else(|| {
+                ScrubError::ParsingError(format!("Corrupt chunk offset table at {}", table.start))
+            })?;
+            if remapped != table.data(bytes) {
+                edits.replace(table.data_start()..table.end, remapped)?;
+            }
+        }
+
+        let mut resized = boxes;
+        resized.extend(walk.containers);
+        Ok(ScrubResult {
+            cleaned_file_bytes: edits.apply_with_sizes(bytes, &resized)?,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEDIA: &[u8] = b"sample data";
+
+    /// A version 0 movie, track or media header with the given creation time.
+    fn header(box_type: &[u8; 4], created: u32) -> Vec<u8> {
+        let mut data = vec![0; 4];
+        data.extend_from_slice(&created.to_be_bytes());
+        data.extend_from_slice(&created.to_be_bytes());
+        data.extend_from_slice(&[0; 20]);
+        write_box(box_type, &data)
+    }
+
+    fn text_atom(box_type: &[u8; 4], text: &str) -> Vec<u8> {
+        let mut data = (text.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&[0x15, 0xC7]);
+        data.extend_from_slice(text.as_bytes());
+        write_box(box_type, &data)
+    }
+
+    /// A QuickTime `meta` box with a single `keys`/`ilst` entry.
+    fn apple_meta(key: &str, value: &str) -> Vec<u8> {
+        let hdlr = write_box(b"hdlr", &[&[0; 8][..], b"mdta", &[0; 13]].concat());
+        let key_entry = write_box(b"mdta", key.as_bytes());
+        let keys = write_box(
+            b"keys",
+            &[&[0, 0, 0, 0, 0, 0, 0, 1][..], &key_entry].concat(),
+        );
+        let data = write_box(
+            b"data",
+            &[&[0, 0, 0, 1, 0, 0, 0, 0][..], value.as_bytes()].concat(),
+        );
+        let ilst = write_box(b"ilst", &write_box(&[0, 0, 0, 1], &data));
+        write_box(b"meta", &[hdlr, keys, ilst].concat())
+    }
+
+    /// Builds a movie with its `moov` before `mdat`, as phones write them.
+    fn movie(udta: &[Vec<u8>]) -> Vec<u8> {
+        let ftyp = write_box(b"ftyp", b"qt  \0\0\0\0qt  ");
+        let moov = |chunk_offset: u32| {
+            let stco = write_box(
+                b"stco",
+                &[&[0, 0, 0, 0, 0, 0, 0, 1][..], &chunk_offset.to_be_bytes()].concat(),
+            );
+            let stbl = write_box(b"stbl", &stco);
+            let mdia = write_box(
+                b"mdia",
+                &[header(b"mdhd", 3_800_000_000), write_box(b"minf", &stbl)].concat(),
+            );
+            let trak = write_box(b"trak", &[header(b"tkhd", 3_800_000_000), mdia].concat());
+            write_box(
+                b"moov",
+                &[
+                    header(b"mvhd", 3_800_000_000),
+                    trak,
+                    write_box(b"udta", &udta.concat()),
+                    apple_meta("com.apple.quicktime.location.ISO6709", "+48.8577+002.2950/"),
+                ]
+                .concat(),
+            )
+        };
+        let chunk_offset = (ftyp.len() + moov(0).len() + 8) as u32;
+        [ftyp, moov(chunk_offset), write_box(b"mdat", MEDIA)].concat()
+    }
+
+    fn chunk_offset(file: &[u8]) -> usize {
+        let scrubber = Mp4Scrubber::new(file.to_vec()).unwrap();
+        let (_, walk) = scrubber.walk().unwrap();
+        read_uint(walk.chunk_offsets[0].data(file), 8, 4).unwrap() as usize
+    }
+
+    #[test]
+    fn view_metadata_reads_atoms_keys_and_timestamps() {
+        let file = movie(&[
+            text_atom(b"\xA9xyz", "+48.8577+002.2950/"),
+            text_atom(b"\xA9mod", "Phone 15"),
+        ]);
+        let entries = Mp4Scrubber::new(file).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(
+            keys,
+            [
+                "MovieCreationTime",
+                "MovieModificationTime",
+                "TrackCreationTime",
+                "TrackModificationTime",
+                "MediaCreationTime",
+                "MediaModificationTime",
+                "Location",
+                "Model",
+                "com.apple.quicktime.location.ISO6709",
+            ]
+        );
+        assert_eq!(entries[0].display_value, "2024-05-31T11:33:20Z");
+        assert_eq!(entries[6].category, MetadataCategory::Gps);
+        assert_eq!(entries[8].category, MetadataCategory::Gps);
+        assert_eq!(entries[7].display_value, "Phone 15");
+    }
+
+    #[test]
+    fn scrub_removes_atoms_and_fixes_chunk_offsets() {
+        let file = movie(&[
+            text_atom(b"\xA9xyz", "+48.8577+002.2950/"),
+            text_atom(b"\xA9mod", "Phone 15"),
+        ]);
+        let result = Mp4Scrubber::new(file).unwrap().scrub().unwrap();
+        assert_eq!(result.metadata_removed.len(), 9);
+
+        let cleaned = result.cleaned_file_bytes;
+        let offset = chunk_offset(&cleaned);
+        assert_eq!(&cleaned[offset..offset + MEDIA.len()], MEDIA);
+        let scrubber = Mp4Scrubber::new(cleaned.clone()).unwrap();
+        assert!(scrubber.view_metadata().unwrap().is_empty());
+        // The emptied udta, meta and ilst boxes are still there, with the right sizes.
+        let boxes = read_boxes(&cleaned, 0, cleaned.len()).unwrap();
+        assert_eq!(boxes.last().unwrap().end, cleaned.len());
+    }
+
+    #[test]
+    fn empty_xmp_packets_are_removed() {
+        let packet = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#;
+        let mut file = movie(&[write_box(b"XMP_", packet)]);
+        file.extend(write_box(b"uuid", &[&XMP_UUID[..], packet].concat()));
+        let scrubber = Mp4Scrubber::new(file).unwrap();
+        let xmp_entries =
+            |entries: Vec<MetadataEntry>| entries.iter().filter(|e| e.key == "XMP").count();
+
+        assert_eq!(xmp_entries(scrubber.view_metadata().unwrap()), 2);
+        let result = scrubber.scrub().unwrap();
+        assert_eq!(xmp_entries(result.metadata_removed), 2);
+        let cleaned = Mp4Scrubber::new(result.cleaned_file_bytes).unwrap();
+        assert!(cleaned.view_metadata().unwrap().is_empty());
+    }
+
+    #[test]
+    fn scrub_with_policy_keeps_selected_atoms() {
+        let file = movie(&[
+            text_atom(b"\xA9xyz", "+48.8577+002.2950/"),
+            text_atom(b"\xA9mod", "Phone 15"),
+        ]);
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("Model".into()));
+        let result = Mp4Scrubber::new(file).unwrap().scrub_with(&policy).unwrap();
+
+        let cleaned = result.cleaned_file_bytes;
+        let offset = chunk_offset(&cleaned);
+        assert_eq!(&cleaned[offset..offset + MEDIA.len()], MEDIA);
+        assert_eq!(
+            Mp4Scrubber::new(cleaned).unwrap().view_metadata().unwrap(),
+            result.metadata_kept
+        );
+        assert_eq!(result.metadata_kept.len(), 1);
+    }
+}
@@ -0,0 +1,288 @@
+// File: crates/scrubkit-core/src/odf.rs
+
+//! OpenDocument support: LibreOffice and OpenOffice text documents,
+//! spreadsheets and presentations.
+//!
+//! An ODF file is a ZIP package whose first entry, `mimetype`, names the
+//! document type. Its properties live in `meta.xml`: the initial and last
+//! author, creation and save dates, editing cycles and total editing time,
+//! the generator string, and user-defined fields. The package can also carry
+//! `Thumbnails/thumbnail.png`, a preview of the first page.
+//!
+//! Removed properties are cut out of `meta.xml`, which is otherwise left as it
+//! was. A removed thumbnail is dropped from the package and its manifest.
+
+use crate::package::{self, Edit, MIMETYPE, Part, Property, apply_edits, qualified_name, value_of};
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+use roxmltree::{Document, Node};
+
+const MANIFEST: &str = "META-INF/manifest.xml";
+const META: &str = "meta.xml";
+const THUMBNAIL: &str = "Thumbnails/thumbnail.png";
+
+const META_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:meta:1.0";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+const MANIFEST_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:manifest:1.0";
+
+/// Returns true for ZIP packages with an OpenDocument `mimetype` and manifest.
+pub fn is_odf(bytes: &[u8]) -> bool {
+    package::has_part(bytes, MIMETYPE) && package::has_part(bytes, MANIFEST)
+}
+
+/// Reads the properties in `meta.xml`. Empty properties, and the document
+/// statistics (page and word counts), are skipped.
+fn properties(xml: &str) -> Result<Vec<Property>, ScrubError> {
+    let doc = Document::parse(xml)
+        .map_err(|e| ScrubError::ParsingError(format!("Invalid {}: {}", META, e)))?;
+    let Some(meta) = doc
+        .root_element()
+        .children()
+        .find(|n| n.has_tag_name("meta"))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut properties = Vec::new();
+    for node in meta.children().filter(Node::is_element) {
+        // User-defined fields are `<meta:user-defined meta:name="...">` elements.
+        let key = if node.has_tag_name((META_NS, "user-defined")) {
+            node.attribute((META_NS, "name"))
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            qualified_name(node)
+        };
+        // The template and auto-reload elements point elsewhere instead of having text.
+        let value = value_of(node).or_else(|| {
+            node.attribute((XLINK_NS, "href"))
+                .filter(|href| !href.is_empty())
+                .map(|href| MetadataValue::Text(href.to_string()))
+        });
+        if let Some(value) = value {
+            properties.push(Property {
+                entry: MetadataEntry::new(MetadataCategory::OfficeProperties, key, value),
+                range: node.range(),
+            });
+        }
+    }
+    Ok(properties)
+}
+
+/// Returns true for the parts that are read. Only the XML is edited, so
+/// pictures and other media are left compressed.
+fn is_read(name: &str) -> bool {
+    name.ends_with(".xml")
+}
+
+fn thumbnail_entry(part: &Part) -> MetadataEntry {
+    MetadataEntry::new(
+        MetadataCategory::OfficeContent,
+        "Thumbnail",
+        MetadataValue::Binary { len: part.size },
+    )
+}
+
+/// Edits that remove the manifest's entry for a dropped part.
+fn unlist(xml: &str, name: &str) -> Vec<Edit> {
+    let Ok(doc) = Document::parse(xml) else {
+        return Vec::new();
+    };
+    doc.descendants()
+        .filter(|n| n.has_tag_name((MANIFEST_NS, "file-entry")))
+        .filter(|n| n.attribute((MANIFEST_NS, "full-path")) == Some(name))
+        .map(|n| Edit::remove(n.range()))
+        .collect()
+}
+
+/// A Scrubber implementation for OpenDocument files.
+#[derive(Debug, Clone)]
+pub struct OdfScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for OdfScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_odf(&file_bytes) {
+            return Err(ScrubError::ParsingError(
+                "Not a valid OpenDocument file".into(),
+            ));
+        }
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let parts = package::read_parts(&self.file_bytes, is_read)?;
+        let mut metadata = Vec::new();
+        if let Some(part) = parts.iter().find(|p| p.name == META) {
+            metadata.extend(properties(&part.text())?.into_iter().map(|p| p.entry));
+        }
+        metadata.extend(
+            parts
+                .iter()
+                .filter(|p| p.name == THUMBNAIL)
+                .map(thumbnail_entry),
+        );
+        metadata.extend(package::timestamp_entry(&parts));
+        Ok(metadata)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let mut parts = package::read_parts(&self.file_bytes, is_read)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+
+        if let Some(part) = parts.iter_mut().find(|p| p.name == META) {
+            let xml = part.text();
+            let mut edits = Vec::new();
+            for property in properties(&xml)? {
+                if policy.keeps(&property.entry) {
+                    metadata_kept.push(property.entry);
+                } else {
+                    metadata_removed.push(property.entry);
+                    edits.push(Edit::remove(property.range));
+                }
+            }
+            if !edits.is_empty() {
+                part.data = apply_edits(&xml, edits).into_bytes();
+            }
+        }
+
+        if let Some(i) = parts.iter().position(|p| p.name == THUMBNAIL) {
+            let entry = thumbnail_entry(&parts[i]);
+            if policy.keeps(&entry) {
+                metadata_kept.push(entry);
+            } else {
+                metadata_removed.push(entry);
+                parts.remove(i);
+                if let Some(manifest) = parts.iter_mut().find(|p| p.name == MANIFEST) {
+                    let xml = manifest.text();
+                    manifest.data = apply_edits(&xml, unlist(&xml, THUMBNAIL)).into_bytes();
+                }
+            }
+        }
+
+        if let Some(entry) = package::timestamp_entry(&parts) {
+            if policy.keeps(&entry) {
+                metadata_kept.push(entry);
+            } else {
+                metadata_removed.push(entry);
+                parts.iter_mut().for_each(|p| p.modified = None);
+            }
+        }
+
+        let cleaned_bytes = if metadata_removed.is_empty() {
+            self.file_bytes.clone()
+        } else {
+            package::write_parts(&self.file_bytes, &parts)?
+        };
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, DateTime, ZipWriter};
+
+    const META_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:meta="urn:oasis:names:tc:opendocument:xmlns:meta:1.0" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:xlink="http://www.w3.org/1999/xlink" office:version="1.3"><office:meta><meta:initial-creator>Jane Doe</meta:initial-creator><meta:creation-date>2024-05-01T14:30:00.123456789</meta:creation-date><dc:creator>John Roe</dc:creator><meta:editing-cycles>7</meta:editing-cycles><meta:editing-duration>PT1H2M3S</meta:editing-duration><meta:generator>LibreOffice/7.6.4.1$Linux_X86_64</meta:generator><meta:template xlink:type="simple" xlink:href="../Templates/Letter.ott" xlink:title="Letter"/><meta:document-statistic meta:page-count="1" meta:word-count="2"/><meta:user-defined meta:name="Client" meta:value-type="string">Globex</meta:user-defined></office:meta></office:document-meta>"#;
+    const MANIFEST_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.3"><manifest:file-entry manifest:full-path="/" manifest:media-type="application/vnd.oasis.opendocument.text"/><manifest:file-entry manifest:full-path="Thumbnails/thumbnail.png" manifest:media-type="image/png"/><manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/><manifest:file-entry manifest:full-path="meta.xml" manifest:media-type="text/xml"/></manifest:manifest>"#;
+
+    fn odt() -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default()
+            .last_modified_time(DateTime::from_date_and_time(2024, 5, 2, 9, 15, 0).unwrap());
+        let stored = options.compression_method(CompressionMethod::Stored);
+        writer.start_file(MIMETYPE, stored).unwrap();
+        writer
+            .write_all(b"application/vnd.oasis.opendocument.text")
+            .unwrap();
+        for (name, data) in [
+            ("content.xml", "<office:document-content/>".as_bytes()),
+            (META, META_XML.as_bytes()),
+            (THUMBNAIL, b"\x89PNG\r\n\x1a\n"),
+            (MANIFEST, MANIFEST_XML.as_bytes()),
+        ] {
+            writer.start_file(name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn view_metadata_lists_meta_properties() {
+        let entries = OdfScrubber::new(odt()).unwrap().view_metadata().unwrap();
+        let listed: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.display_value.as_str()))
+            .collect();
+
+        assert_eq!(
+            listed,
+            [
+                ("meta:initial-creator", "Jane Doe"),
+                ("meta:creation-date", "2024-05-01T14:30:00.123456789"),
+                ("dc:creator", "John Roe"),
+                ("meta:editing-cycles", "7"),
+                ("meta:editing-duration", "PT1H2M3S"),
+                ("meta:generator", "LibreOffice/7.6.4.1$Linux_X86_64"),
+                ("meta:template", "../Templates/Letter.ott"),
+                ("Client", "Globex"),
+                ("Thumbnail", "(8 bytes of binary data)"),
+                ("PackageModified", "2024-05-02T09:15:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn scrub_strips_meta_and_keeps_mimetype_first() {
+        let result = OdfScrubber::new(odt()).unwrap().scrub().unwrap();
+        let parts = package::read_parts(&result.cleaned_file_bytes, is_read).unwrap();
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+
+        assert_eq!(result.metadata_removed.len(), 10);
+        assert_eq!(names, [MIMETYPE, "content.xml", META, MANIFEST]);
+        // Readers identify the file from the uncompressed media type at offset 38.
+        assert_eq!(
+            &result.cleaned_file_bytes[30..38 + 39],
+            b"mimetypeapplication/vnd.oasis.opendocument.text"
+        );
+        assert!(parts[2].text().ends_with(
+            r#"<office:meta><meta:document-statistic meta:page-count="1" meta:word-count="2"/></office:meta></office:document-meta>"#
+        ));
+        assert!(!parts[3].text().contains("Thumbnails"));
+        assert!(
+            OdfScrubber::new(result.cleaned_file_bytes)
+                .unwrap()
+                .view_metadata()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn scrub_with_policy_keeps_selected_properties() {
+        let policy = ScrubPolicy::remove_all()
+            .keep(crate::Selector::Key("meta:generator".into()))
+            .keep(crate::Selector::Category(MetadataCategory::OfficeContent));
+        let result = OdfScrubber::new(odt())
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        let entries = OdfScrubber::new(result.cleaned_file_bytes)
+            .unwrap()
+            .view_metadata()
+            .unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["meta:generator", "Thumbnail"]);
+    }
+}
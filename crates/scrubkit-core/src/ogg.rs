@@ -0,0 +1,470 @@
+// File: crates/scrubkit-core/src/ogg.rs
+
+//! Ogg Vorbis and Opus support.
+//!
+//! An Ogg file is a sequence of pages, each carrying segments of the packets
+//! of one logical stream. The second packet of a Vorbis or Opus stream is
+//! its comment header: a Vorbis comment block behind an `\x03vorbis` or
+//! `OpusTags` signature. Both codecs start the comment header on a fresh
+//! page and the audio on another, so the header pages can be rebuilt on
+//! their own.
+//!
+//! A scrubbed comment header is repaginated, and the later pages of its
+//! stream are renumbered if the page count changed. Every rewritten page
+//! gets a new checksum; the audio packets inside them are copied unchanged.
+
+use crate::vorbis::VorbisComment;
+use crate::{MetadataEntry, ScrubError, ScrubPolicy, ScrubResult, Scrubber};
+
+const CAPTURE_PATTERN: &[u8] = b"OggS";
+const PAGE_HEADER_LEN: usize = 27;
+
+/// Page header flags.
+const CONTINUED: u8 = 0x01;
+const BEGINNING_OF_STREAM: u8 = 0x02;
+const END_OF_STREAM: u8 = 0x04;
+
+/// The granule position of a page on which no packet ends.
+const NO_GRANULE: u64 = u64::MAX;
+
+/// Returns true for files that start with an Ogg page.
+pub fn is_ogg(bytes: &[u8]) -> bool {
+    bytes.starts_with(CAPTURE_PATTERN)
+}
+
+/// The lookup table for the Ogg checksum: CRC-32 with the polynomial
+/// 0x04C11DB7, unreflected, with no initial or final XOR.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |crc, &b| {
+        (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ b) as usize]
+    })
+}
+
+/// A page, borrowed from the file.
+#[derive(Debug, Clone, Copy)]
+struct Page<'a> {
+    flags: u8,
+    granule: u64,
+    serial: u32,
+    sequence: u32,
+    /// The segment table: the length of each segment in the page.
+    lacing: &'a [u8],
+    data: &'a [u8],
+    /// The whole page, as stored.
+    bytes: &'a [u8],
+}
+
+fn read_pages(bytes: &[u8]) -> Result<Vec<Page<'_>>, ScrubError> {
+    let mut pages = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let corrupt = || ScrubError::ParsingError(format!("Corrupt Ogg page at offset {}", pos));
+        let header = bytes
+            .get(pos..pos + PAGE_HEADER_LEN)
+            .filter(|h| h.starts_with(CAPTURE_PATTERN))
+            .ok_or_else(corrupt)?;
+        let segments = header[26] as usize;
+        let lacing = bytes
+            .get(pos + PAGE_HEADER_LEN..pos + PAGE_HEADER_LEN + segments)
+            .ok_or_else(corrupt)?;
+        let data_start = pos + PAGE_HEADER_LEN + segments;
+        let data_len: usize = lacing.iter().map(|&l| l as usize).sum();
+        let data = bytes
+            .get(data_start..data_start + data_len)
+            .ok_or_else(corrupt)?;
+        let read_u32 = |at: usize| {
+            u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+        };
+        pages.push(Page {
+            flags: header[5],
+            granule: u64::from_le_bytes(header[6..14].try_into().unwrap_or_default()),
+            serial: read_u32(14),
+            sequence: read_u32(18),
+            lacing,
+            data,
+            bytes: &bytes[pos..data_start + data_len],
+        });
+        pos = data_start + data_len;
+    }
+    Ok(pages)
+}
+
+/// Writes a page with a freshly computed checksum.
+fn write_page(out: &mut Vec<u8>, page: &Page, sequence: u32) {
+    let start = out.len();
+    out.extend_from_slice(CAPTURE_PATTERN);
+    out.extend_from_slice(&[0, page.flags]);
+    out.extend_from_slice(&page.granule.to_le_bytes());
+    out.extend_from_slice(&page.serial.to_le_bytes());
+    out.extend_from_slice(&sequence.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.push(page.lacing.len() as u8);
+    out.extend_from_slice(page.lacing);
+    out.extend_from_slice(page.data);
+    let crc = checksum(&out[start..]);
+    out[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Lays packets out on as few pages as possible, starting with a fresh page.
+/// Returns the pages, written from `first_sequence` on.
+fn paginate(packets: &[Vec<u8>], serial: u32, first_sequence: u32, flags: u8) -> Vec<Vec<u8>> {
+    // Each packet is a run of 255-byte segments ending in a shorter one,
+    // which may be empty.
+    let segments: Vec<&[u8]> = packets
+        .iter()
+        .flat_map(|packet| {
+            let mut segments: Vec<&[u8]> = packet.chunks(255).collect();
+            if packet.len() % 255 == 0 {
+                segments.push(&[]);
+            }
+            segments
+        })
+        .collect();
+
+    let mut pages = Vec::new();
+    let mut continued = false;
+    for (i, chunk) in segments.chunks(255).enumerate() {
+        let lacing: Vec<u8> = chunk.iter().map(|s| s.len() as u8).collect();
+        let ends_packet = lacing.iter().any(|&l| l < 255);
+        let mut page_flags = flags & !CONTINUED;
+        if continued {
+            page_flags |= CONTINUED;
+        }
+        let page = Page {
+            flags: page_flags,
+            granule: if ends_packet { 0 } else { NO_GRANULE },
+            serial,
+            sequence: 0,
+            lacing: &lacing,
+            data: &chunk.concat(),
+            bytes: &[],
+        };
+        let mut out = Vec::new();
+        write_page(&mut out, &page, first_sequence + i as u32);
+        pages.push(out);
+        continued = lacing.last() == Some(&255);
+    }
+    // Only the last page keeps the end-of-stream flag.
+    let last = pages.len() - 1;
+    for page in &mut pages[..last] {
+        if page[5] & END_OF_STREAM != 0 {
+            page[5] &= !END_OF_STREAM;
+            page[22..26].fill(0);
+            let crc = checksum(page);
+            page[22..26].copy_from_slice(&crc.to_le_bytes());
+        }
+    }
+    pages
+}
+
+/// The comment header of a Vorbis or Opus stream.
+struct CommentHeader {
+    serial: u32,
+    signature: &'static [u8],
+    /// The indices of the pages the header packets span.
+    pages: Vec<usize>,
+    /// The comment packet, followed by any packets that share its pages
+    /// (the Vorbis setup header).
+    packets: Vec<Vec<u8>>,
+}
+
+impl CommentHeader {
+    fn comment(&self) -> Result<(VorbisComment, &[u8]), ScrubError> {
+        VorbisComment::parse_prefix(&self.packets[0][self.signature.len()..])
+    }
+}
+
+/// Finds the comment header of every Vorbis and Opus stream. Other streams,
+/// such as video or a skeleton, are left alone.
+fn comment_headers(pages: &[Page]) -> Result<Vec<CommentHeader>, ScrubError> {
+    let mut headers = Vec::new();
+    for first in pages.iter().filter(|p| p.flags & BEGINNING_OF_STREAM != 0) {
+        // Vorbis has a setup header after the comments; Opus has nothing.
+        let (signature, packet_count): (&'static [u8], usize) =
+            if first.data.starts_with(b"OpusHead") {
+                (b"OpusTags", 1)
+            } else if first.data.starts_with(b"\x01vorbis") {
+                (b"\x03vorbis", 2)
+            } else {
+                continue;
+            };
+
+        let mut header = CommentHeader {
+            serial: first.serial,
+            signature,
+            pages: Vec::new(),
+            packets: Vec::new(),
+        };
+        let mut packet = Vec::new();
+        let stream_pages = pages
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.serial == first.serial)
+            .skip(1);
+        for (index, page) in stream_pages {
+            if header.pages.is_empty() && page.flags & CONTINUED != 0 {
+                break;
+            }
+            header.pages.push(index);
+            let mut offset = 0;
+            for (i, &len) in page.lacing.iter().enumerate() {
+                packet.extend_from_slice(&page.data[offset..offset + len as usize]);
+                offset += len as usize;
+                if len < 255 {
+                    header.packets.push(std::mem::take(&mut packet));
+                    if header.packets.len() == packet_count && i + 1 < page.lacing.len() {
+                        return Err(ScrubError::UnsupportedFileType(
+                            "Ogg comment headers that share a page with audio are not supported"
+                                .into(),
+                        ));
+                    }
+                }
+            }
+            if header.packets.len() >= packet_count {
+                break;
+            }
+        }
+        if header.packets.len() == packet_count && header.packets[0].starts_with(signature) {
+            headers.push(header);
+        }
+    }
+    Ok(headers)
+}
+
+/// A Scrubber implementation for Ogg Vorbis and Opus files.
+#[derive(Debug, Clone)]
+pub struct OggScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for OggScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_ogg(&file_bytes) {
+            return Err(ScrubError::ParsingError("Not a valid Ogg file".into()));
+        }
+        for header in comment_headers(&read_pages(&file_bytes)?)? {
+            header.comment()?;
+        }
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let mut metadata = Vec::new();
+        for header in comment_headers(&read_pages(&self.file_bytes)?)? {
+            metadata.extend(header.comment()?.0.metadata_entries());
+        }
+        Ok(metadata)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let pages = read_pages(&self.file_bytes)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        // New pages to write in place of a page, and how far to shift the
+        // sequence numbers of each stream's later pages.
+        let mut replaced: Vec<Option<Vec<Vec<u8>>>> = vec![None; pages.len()];
+        let mut shifts: Vec<(u32, usize, i64)> = Vec::new();
+
+        for header in comment_headers(&pages)? {
+            let (mut comment, rest) = header.comment()?;
+            let (kept, removed) = comment.apply_policy(policy);
+            metadata_kept.extend(kept);
+            if removed.is_empty() {
+                continue;
+            }
+            metadata_removed.extend(removed);
+
+            // Opus padding may be dropped unless its first bit says to keep it.
+            let rest = match rest.first() {
+                Some(b) if header.signature == b"OpusTags" && b & 1 == 0 => &[],
+                _ => rest,
+            };
+            let mut packets = header.packets.clone();
+            packets[0] = [header.signature, &comment.to_bytes(), rest].concat();
+
+            let first = &pages[header.pages[0]];
+            let last = &pages[header.pages[header.pages.len() - 1]];
+            let flags = first.flags | (last.flags & END_OF_STREAM);
+            let new_pages = paginate(&packets, header.serial, first.sequence, flags);
+            let shift = new_pages.len() as i64 - header.pages.len() as i64;
+            for &index in &header.pages {
+                replaced[index] = Some(Vec::new());
+            }
+            replaced[header.pages[0]] = Some(new_pages);
+            shifts.push((header.serial, header.pages[header.pages.len() - 1], shift));
+        }
+
+        let mut cleaned_bytes = Vec::with_capacity(self.file_bytes.len());
+        for (index, page) in pages.iter().enumerate() {
+            if let Some(new_pages) = &replaced[index] {
+                new_pages
+                    .iter()
+                    .for_each(|p| cleaned_bytes.extend_from_slice(p));
+                continue;
+            }
+            let shift = shifts
+                .iter()
+                .find(|(serial, after, _)| *serial == page.serial && index > *after)
+                .map_or(0, |(_, _, shift)| *shift);
+            if shift == 0 {
+                cleaned_bytes.extend_from_slice(page.bytes);
+            } else {
+                write_page(
+                    &mut cleaned_bytes,
+                    page,
+                    (page.sequence as i64 + shift) as u32,
+                );
+            }
+        }
+
+        if metadata_removed.is_empty() {
+            cleaned_bytes = self.file_bytes.clone();
+        }
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(flags: u8, granule: u64, sequence: u32, packets: &[&[u8]]) -> Vec<u8> {
+        let packets: Vec<Vec<u8>> = packets.iter().map(|p| p.to_vec()).collect();
+        let mut bytes = paginate(&packets, 0x1234, sequence, flags).concat();
+        // `paginate` only writes header granules.
+        bytes[6..14].copy_from_slice(&granule.to_le_bytes());
+        bytes[22..26].fill(0);
+        let crc = checksum(&bytes);
+        bytes[22..26].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    fn opus_tags(vendor: &str, comments: &[&str]) -> Vec<u8> {
+        let mut packet = b"OpusTags".to_vec();
+        packet.extend((vendor.len() as u32).to_le_bytes());
+        packet.extend(vendor.bytes());
+        packet.extend((comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            packet.extend((comment.len() as u32).to_le_bytes());
+            packet.extend(comment.bytes());
+        }
+        packet
+    }
+
+    fn opus(tags: &[u8]) -> Vec<u8> {
+        let tags_pages = paginate(&[tags.to_vec()], 0x1234, 1, 0).concat();
+        let audio = 1 + read_pages(&tags_pages).unwrap().len() as u32;
+        [
+            page(
+                BEGINNING_OF_STREAM,
+                0,
+                0,
+                &[b"OpusHead\x01\x01\x38\x01\x80\xBB\0\0\0\0\0"],
+            ),
+            tags_pages,
+            page(0, 960, audio, &[b"\xFC\xFF\xFE", b"\xFC\xFF\xFE"]),
+            page(END_OF_STREAM, 1920, audio + 1, &[b"\xFC\xFF\xFE"]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn checksum_matches_the_ogg_crc() {
+        // CRC-32/POSIX of the check string is 0x765E7680, before its final XOR.
+        assert_eq!(checksum(b"123456789"), !0x765E_7680);
+    }
+
+    #[test]
+    fn view_metadata_lists_opus_tags() {
+        let file = opus(&opus_tags(
+            "libopus 1.4",
+            &["ARTIST=Jane Doe", "ENCODER=opusenc"],
+        ));
+        let entries = OggScrubber::new(file).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(keys, ["Vendor", "ARTIST", "ENCODER"]);
+    }
+
+    #[test]
+    fn scrub_repaginates_and_renumbers_pages() {
+        // A comment long enough to need a second page.
+        let long = format!("DESCRIPTION={}", "x".repeat(70_000));
+        let file = opus(&opus_tags("libopus 1.4", &["ARTIST=Jane Doe", &long]));
+        assert_eq!(read_pages(&file).unwrap().len(), 5);
+
+        let result = OggScrubber::new(file.clone()).unwrap().scrub().unwrap();
+        assert_eq!(result.metadata_removed.len(), 3);
+        let pages = read_pages(&result.cleaned_file_bytes).unwrap();
+        let sequences: Vec<u32> = pages.iter().map(|p| p.sequence).collect();
+        assert_eq!(sequences, [0, 1, 2, 3]);
+        assert_eq!(pages[1].data, opus_tags("", &[]));
+        for page in &pages {
+            let mut bytes = page.bytes.to_vec();
+            bytes[22..26].fill(0);
+            assert_eq!(checksum(&bytes).to_le_bytes(), page.bytes[22..26]);
+        }
+        // The audio packets and their granule positions are unchanged.
+        let original = read_pages(&file).unwrap();
+        assert_eq!(pages[2].data, original[3].data);
+        assert_eq!(pages[3].granule, 1920);
+        assert_eq!(pages[3].flags, END_OF_STREAM);
+    }
+
+    #[test]
+    fn scrub_keeps_the_vorbis_setup_header() {
+        let mut comment = b"\x03vorbis".to_vec();
+        comment.extend(&opus_tags("Xiph.Org libVorbis I 20200704", &["TITLE=Song"])[8..]);
+        comment.push(1);
+        let setup = b"\x05vorbis\x00\x01\x02\x03".as_slice();
+        let file = [
+            page(
+                BEGINNING_OF_STREAM,
+                0,
+                0,
+                &[b"\x01vorbis\0\0\0\0\x01\x44\xAC\0\0"],
+            ),
+            page(0, 0, 1, &[&comment, setup]),
+            page(END_OF_STREAM, 128, 2, &[b"\x00\x01"]),
+        ]
+        .concat();
+
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("TITLE".into()));
+        let result = OggScrubber::new(file).unwrap().scrub_with(&policy).unwrap();
+        let pages = read_pages(&result.cleaned_file_bytes).unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "Vendor");
+        assert_eq!(
+            pages[1].data,
+            [
+                b"\x03vorbis\0\0\0\0\x01\0\0\0\x0A\0\0\0TITLE=Song\x01".as_slice(),
+                setup
+            ]
+            .concat()
+        );
+        assert_eq!(pages[2].data, b"\x00\x01");
+    }
+}
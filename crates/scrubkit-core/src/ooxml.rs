@@ -0,0 +1,713 @@
+// File: crates/scrubkit-core/src/ooxml.rs
+
+//! Office Open XML support: Word, Excel and PowerPoint files.
+//!
+//! An OOXML file is a ZIP package of XML parts. Its document properties live
+//! in three of them: `docProps/core.xml` (author, dates and revision),
+//! `docProps/app.xml` (application, company, manager and template) and
+//! `docProps/custom.xml` (user-defined properties).
+//!
+//! The rest of the package names people too. Tracked changes and comments
+//! carry their authors' names, initials and account IDs, and Word tags
+//! paragraphs and runs with rsids, IDs of the editing sessions that touched
+//! them. Deep scrubbing, which is off unless asked for with
+//! [`OoxmlScrubber::deep`], anonymizes the attributions, strips rsids, and
+//! drops printer settings and the cached thumbnail.
+//!
+//! Removed properties and attributions are cut out of (or replaced in) their
+//! part, which is otherwise left as it was, and the package is rewritten with
+//! its entry timestamps normalized.
+
+use crate::package::{self, Edit, Part, Property, apply_edits, qualified_name, value_of};
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+use roxmltree::{Document, Node};
+use std::collections::HashSet;
+
+const CONTENT_TYPES: &str = "[Content_Types].xml";
+const CUSTOM_PROPERTIES: &str = "docProps/custom.xml";
+const PROPERTY_PARTS: &[&str] = &["docProps/core.xml", "docProps/app.xml", CUSTOM_PROPERTIES];
+
+const WORD_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const RELATIONSHIPS_NS: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// Returns true for the parts that are read: the XML and relationship parts,
+/// and printer settings, which name the printer. Media is left compressed.
+fn is_read(name: &str) -> bool {
+    name.ends_with(".xml") || name.ends_with(".rels") || name.contains("/printerSettings/")
+}
+
+/// Returns true for ZIP packages with an OOXML content types part.
+pub fn is_ooxml(bytes: &[u8]) -> bool {
+    package::has_part(bytes, CONTENT_TYPES)
+}
+
+/// Reads the properties in a docProps part. Empty properties are skipped,
+/// since they say nothing.
+fn properties(part: &Part, xml: &str) -> Result<Vec<Property>, ScrubError> {
+    let doc = Document::parse(xml)
+        .map_err(|e| ScrubError::ParsingError(format!("Invalid {}: {}", part.name, e)))?;
+    let mut properties = Vec::new();
+    for node in doc.root_element().children().filter(Node::is_element) {
+        // Custom properties are `<property name="...">` elements holding a typed value.
+        let key = if part.name == CUSTOM_PROPERTIES {
+            node.attribute("name").unwrap_or_default().to_string()
+        } else {
+            qualified_name(node)
+        };
+        if let Some(value) = value_of(node) {
+            properties.push(Property {
+                entry: MetadataEntry::new(MetadataCategory::OfficeProperties, key, value),
+                range: node.range(),
+            });
+        }
+    }
+    Ok(properties)
+}
+
+/// An author attribution or editing session ID found in a content part.
+struct Attribution {
+    /// The index of the part it was found in.
+    part: usize,
+    key: &'static str,
+    value: String,
+    edit: Edit,
+}
+
+const RSIDS: &str = "RSIDs";
+
+/// Finds the author names, initials and user IDs in a part, and Word's rsids.
+/// Names in tracked changes, comments and the people lists of all three
+/// applications are replaced with a placeholder; rsids are removed.
+fn find_attributions(part: usize, xml: &str, found: &mut Vec<Attribution>) {
+    // Parts we can't read are copied unchanged.
+    let Ok(doc) = Document::parse(xml) else {
+        return;
+    };
+    let mut push = |key, value: &str, edit| {
+        found.push(Attribution {
+            part,
+            key,
+            value: value.to_string(),
+            edit,
+        })
+    };
+
+    for node in doc.descendants().filter(Node::is_element) {
+        let tag = node.tag_name();
+        let is_word = tag.namespace() == Some(WORD_NS);
+        // Settings list every rsid the document has had.
+        if is_word && tag.name() == "rsids" {
+            push(RSIDS, "", Edit::remove(node.range()));
+            continue;
+        }
+        for attribute in node.attributes() {
+            let (key, placeholder) = match attribute.name() {
+                "author" if matches!(tag.name(), "comment" | "person") => {
+                    ("CommentAuthor", "Author")
+                }
+                "author" => ("RevisionAuthor", "Author"),
+                // PowerPoint comment authors, and Excel's people list.
+                "name" | "displayName"
+                    if matches!(tag.name(), "cmAuthor" | "author" | "person") =>
+                {
+                    ("CommentAuthor", "Author")
+                }
+                "initials" => ("AuthorInitials", "A"),
+                "userId" => ("AuthorUserID", ""),
+                name if name.starts_with("rsid") && attribute.namespace() == Some(WORD_NS) => {
+                    push(
+                        RSIDS,
+                        attribute.value(),
+                        Edit::remove_attribute(xml, &attribute),
+                    );
+                    continue;
+                }
+                _ => continue,
+            };
+            // Already anonymous.
+            if attribute.value() == placeholder {
+                continue;
+            }
+            let edit = Edit {
+                range: attribute.range_value(),
+                replacement: placeholder,
+            };
+            push(key, attribute.value(), edit);
+        }
+        // Legacy Excel comments list their authors as text.
+        if tag.name() == "author"
+            && node.attributes().next().is_none()
+            && let Some(text) = node.first_child().filter(|c| c.is_text())
+            && text.text() != Some("Author")
+        {
+            let edit = Edit {
+                range: text.range(),
+                replacement: "Author",
+            };
+            push("CommentAuthor", text.text().unwrap_or_default(), edit);
+        }
+    }
+}
+
+/// Groups attributions into entries: one for each distinct name, initials or
+/// user ID, and one for all rsids. Each entry comes with the attributions it
+/// covers.
+fn attribution_entries(found: &[Attribution]) -> Vec<(MetadataEntry, Vec<usize>)> {
+    let mut groups: Vec<(&'static str, &str, Vec<usize>)> = Vec::new();
+    for (i, attribution) in found.iter().enumerate() {
+        let value = match attribution.key {
+            RSIDS => "",
+            _ => attribution.value.as_str(),
+        };
+        match groups
+            .iter_mut()
+            .find(|(key, v, _)| *key == attribution.key && *v == value)
+        {
+            Some((_, _, members)) => members.push(i),
+            None => groups.push((attribution.key, value, vec![i])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, value, members)| {
+            let entry = if key == RSIDS {
+                let ids: HashSet<&str> = members
+                    .iter()
+                    .map(|&i| found[i].value.as_str())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                MetadataEntry::new(
+                    MetadataCategory::OfficeContent,
+                    key,
+                    MetadataValue::Integer(ids.len() as i64),
+                )
+                .with_display_value(format!("{} editing session IDs", ids.len()))
+            } else {
+                let entry = MetadataEntry::new(
+                    MetadataCategory::OfficeContent,
+                    key,
+                    MetadataValue::Text(value.to_string()),
+                );
+                match members.len() {
+                    1 => entry,
+                    n => entry.with_display_value(format!("{} ({} places)", value, n)),
+                }
+            };
+            (entry, members)
+        })
+        .collect()
+}
+
+/// Reads the printer name from the start of a Windows DEVMODE structure.
+fn printer_name(data: &[u8]) -> Option<String> {
+    let units: Vec<u16> = data
+        .get(..64)?
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&u| u != 0)
+        .collect();
+    let name = String::from_utf16(&units).ok()?;
+    (!name.trim().is_empty() && !name.chars().any(char::is_control)).then_some(name)
+}
+
+/// Describes parts that deep scrubbing drops: printer settings, which name
+/// the printer, and the cached thumbnail of the first page.
+fn droppable_entry(part: &Part) -> Option<MetadataEntry> {
+    let (key, value) = if part.name.contains("/printerSettings/") {
+        let value = match printer_name(&part.data) {
+            Some(name) => MetadataValue::Text(name),
+            None => MetadataValue::Binary { len: part.size },
+        };
+        ("PrinterSettings", value)
+    } else if part.name.starts_with("docProps/thumbnail.") {
+        ("Thumbnail", MetadataValue::Binary { len: part.size })
+    } else {
+        return None;
+    };
+    Some(MetadataEntry::new(
+        MetadataCategory::OfficeContent,
+        key,
+        value,
+    ))
+}
+
+/// For a relationships part, the part it describes: `word/_rels/document.xml.rels`
+/// describes `word/document.xml`, and `_rels/.rels` the package itself.
+fn rels_source(name: &str) -> Option<String> {
+    let (dir, file) = name.rsplit_once("_rels/")?;
+    Some(format!("{}{}", dir, file.strip_suffix(".rels")?))
+}
+
+/// Resolves a relationship target against the directory of its source part.
+fn resolve_target(source: &str, target: &str) -> String {
+    let base = match target.strip_prefix('/') {
+        Some(_) => "",
+        None => source.rsplit_once('/').map_or("", |(dir, _)| dir),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in base.split('/').chain(target.split('/')) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Edits that unlink dropped parts from the package: their content type
+/// overrides, the relationships pointing at them, and the `r:id` attributes
+/// that refer to those relationships.
+fn unlink(parts: &[Part], dropped: &HashSet<&str>, edits: &mut [Vec<Edit>]) {
+    let mut references: Vec<(String, String)> = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let source = rels_source(&part.name);
+        if part.name != CONTENT_TYPES && source.is_none() {
+            continue;
+        }
+        let xml = part.text();
+        let Ok(doc) = Document::parse(&xml) else {
+            continue;
+        };
+        for node in doc.descendants().filter(Node::is_element) {
+            let target = match (node.tag_name().name(), &source) {
+                ("Override", None) => node
+                    .attribute("PartName")
+                    .map(|name| name.trim_start_matches('/').to_string()),
+                ("Relationship", Some(source))
+                    if node.attribute("TargetMode") != Some("External") =>
+                {
+                    node.attribute("Target").map(|t| resolve_target(source, t))
+                }
+                _ => None,
+            };
+            if target.is_some_and(|t| dropped.contains(t.as_str())) {
+                edits[i].push(Edit::remove(node.range()));
+                if let (Some(source), Some(id)) = (&source, node.attribute("Id")) {
+                    references.push((source.clone(), id.to_string()));
+                }
+            }
+        }
+    }
+
+    for (i, part) in parts.iter().enumerate() {
+        if !references.iter().any(|(source, _)| *source == part.name) {
+            continue;
+        }
+        let xml = part.text();
+        let Ok(doc) = Document::parse(&xml) else {
+            continue;
+        };
+        for node in doc.descendants().filter(Node::is_element) {
+            for attribute in node.attributes() {
+                if attribute.namespace() == Some(RELATIONSHIPS_NS)
+                    && references
+                        .iter()
+                        .any(|(source, id)| *source == part.name && id == attribute.value())
+                {
+                    edits[i].push(Edit::remove_attribute(&xml, &attribute));
+                }
+            }
+        }
+    }
+}
+
+/// Everything deep scrubbing looks at in a package.
+#[derive(Default)]
+struct DeepScan {
+    attributions: Vec<Attribution>,
+    /// Attribution entries, with the attributions each one covers.
+    entries: Vec<(MetadataEntry, Vec<usize>)>,
+    /// Parts to drop, with their entries.
+    droppable: Vec<(usize, MetadataEntry)>,
+}
+
+fn deep_scan(parts: &[Part]) -> DeepScan {
+    let mut attributions = Vec::new();
+    let mut droppable = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        if let Some(entry) = droppable_entry(part) {
+            droppable.push((i, entry));
+        } else if part.name.ends_with(".xml") && !PROPERTY_PARTS.contains(&part.name.as_str()) {
+            find_attributions(i, &part.text(), &mut attributions);
+        }
+    }
+    DeepScan {
+        entries: attribution_entries(&attributions),
+        attributions,
+        droppable,
+    }
+}
+
+/// A Scrubber implementation for OOXML documents.
+#[derive(Debug, Clone)]
+pub struct OoxmlScrubber {
+    file_bytes: Vec<u8>,
+    deep: bool,
+}
+
+impl OoxmlScrubber {
+    /// Turns on deep scrubbing: tracked-change and comment authors are
+    /// listed and anonymized, rsids stripped, and printer settings and the
+    /// cached thumbnail dropped, all under the same policy as the document
+    /// properties.
+    ///
+    /// It is off by default because, unlike the properties, these live in
+    /// the document's content, and changing them changes what reviewers see.
+    pub fn deep(mut self) -> Self {
+        self.deep = true;
+        self
+    }
+
+    fn deep_scan(&self, parts: &[Part]) -> DeepScan {
+        if self.deep {
+            deep_scan(parts)
+        } else {
+            DeepScan::default()
+        }
+    }
+}
+
+impl Scrubber for OoxmlScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_ooxml(&file_bytes) {
+            return Err(ScrubError::ParsingError(
+                "Not a valid Office Open XML file".into(),
+            ));
+        }
+        Ok(Self {
+            file_bytes,
+            deep: false,
+        })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let parts = package::read_parts(&self.file_bytes, is_read)?;
+        let mut metadata = Vec::new();
+        for part in parts
+            .iter()
+            .filter(|p| PROPERTY_PARTS.contains(&p.name.as_str()))
+        {
+            metadata.extend(properties(part, &part.text())?.into_iter().map(|p| p.entry));
+        }
+        let scan = self.deep_scan(&parts);
+        metadata.extend(scan.entries.into_iter().map(|(entry, _)| entry));
+        metadata.extend(scan.droppable.into_iter().map(|(_, entry)| entry));
+        metadata.extend(package::timestamp_entry(&parts));
+        Ok(metadata)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let mut parts = package::read_parts(&self.file_bytes, is_read)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut edits = vec![Vec::new(); parts.len()];
+
+        for (i, part) in parts.iter().enumerate() {
+            if !PROPERTY_PARTS.contains(&part.name.as_str()) {
+                continue;
+            }
+            for property in properties(part, &part.text())? {
+                if policy.keeps(&property.entry) {
+                    metadata_kept.push(property.entry);
+                } else {
+                    metadata_removed.push(property.entry);
+                    edits[i].push(Edit::remove(property.range));
+                }
+            }
+        }
+
+        let scan = self.deep_scan(&parts);
+        for (entry, members) in scan.entries {
+            if policy.keeps(&entry) {
+                metadata_kept.push(entry);
+            } else {
+                metadata_removed.push(entry);
+                for attribution in members.into_iter().map(|i| &scan.attributions[i]) {
+                    edits[attribution.part].push(attribution.edit.clone());
+                }
+            }
+        }
+        let mut dropped = HashSet::new();
+        for (i, entry) in scan.droppable {
+            if policy.keeps(&entry) {
+                metadata_kept.push(entry);
+            } else {
+                metadata_removed.push(entry);
+                dropped.insert(parts[i].name.clone());
+            }
+        }
+        if !dropped.is_empty() {
+            unlink(
+                &parts,
+                &dropped.iter().map(String::as_str).collect(),
+                &mut edits,
+            );
+        }
+
+        for (part, edits) in parts.iter_mut().zip(edits) {
+            if !edits.is_empty() {
+                part.data = apply_edits(&part.text(), edits).into_bytes();
+            }
+        }
+        parts.retain(|p| !dropped.contains(&p.name));
+
+        if let Some(entry) = package::timestamp_entry(&parts) {
+            if policy.keeps(&entry) {
+                metadata_kept.push(entry);
+            } else {
+                metadata_removed.push(entry);
+                parts.iter_mut().for_each(|p| p.modified = None);
+            }
+        }
+
+        let cleaned_bytes = if metadata_removed.is_empty() {
+            self.file_bytes.clone()
+        } else {
+            package::write_parts(&self.file_bytes, &parts)?
+        };
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::{DateTime, ZipWriter};
+
+    const CORE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"><dc:title>Budget</dc:title><dc:creator>Jane Doe</dc:creator><cp:keywords></cp:keywords><cp:lastModifiedBy>John Roe</cp:lastModifiedBy><cp:revision>7</cp:revision><dcterms:created xsi:type="dcterms:W3CDTF">2024-05-01T14:30:00Z</dcterms:created></cp:coreProperties>"#;
+    const APP: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes"><Template>Normal.dotm</Template><Company>Acme</Company><TitlesOfParts><vt:vector size="2" baseType="lpstr"><vt:lpstr>Sheet1</vt:lpstr><vt:lpstr>Sheet2</vt:lpstr></vt:vector></TitlesOfParts><AppVersion>16.0000</AppVersion></Properties>"#;
+    const CUSTOM: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes"><property fmtid="{D5CDD505-2E9C-101B-9397-08002B2CF9AE}" pid="2" name="Client"><vt:lpwstr>Globex</vt:lpwstr></property></Properties>"#;
+
+    fn package(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default()
+            .last_modified_time(DateTime::from_date_and_time(2024, 5, 2, 9, 15, 0).unwrap());
+        for (name, data) in files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn docx() -> Vec<u8> {
+        package(&[
+            (CONTENT_TYPES, b"<Types/>"),
+            ("word/document.xml", b"<w:document/>"),
+            ("docProps/core.xml", CORE.as_bytes()),
+            ("docProps/app.xml", APP.as_bytes()),
+            (CUSTOM_PROPERTIES, CUSTOM.as_bytes()),
+        ])
+    }
+
+    /// A Word document with a tracked insertion, a comment and a thumbnail.
+    fn reviewed_docx() -> Vec<u8> {
+        package(&[
+            (CONTENT_TYPES, b"<Types/>"),
+            ("_rels/.rels", br#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/thumbnail" Target="docProps/thumbnail.jpeg"/></Relationships>"#),
+            ("word/document.xml", br#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body><w:p w:rsidR="00A1" w:rsidRDefault="00B2"><w:ins w:id="1" w:author="Jane Doe" w:date="2024-05-01T14:30:00Z"><w:r><w:t>Hello</w:t></w:r></w:ins></w:p></w:body></w:document>"#),
+            ("word/comments.xml", br#"<w:comments xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:comment w:id="0" w:author="Jane Doe" w:initials="JD"><w:p w:rsidR="00A1"><w:r><w:t>Check</w:t></w:r></w:p></w:comment></w:comments>"#),
+            ("word/settings.xml", br#"<w:settings xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:zoom w:percent="100"/><w:rsids><w:rsidRoot w:val="00A1"/><w:rsid w:val="00A1"/><w:rsid w:val="00B2"/></w:rsids></w:settings>"#),
+            ("docProps/thumbnail.jpeg", b"\xFF\xD8\xFF\xD9"),
+        ])
+    }
+
+    /// An Excel workbook with a legacy comment and printer settings.
+    fn printed_xlsx() -> Vec<u8> {
+        let mut devmode: Vec<u8> = "HP LaserJet"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        devmode.resize(220, 0);
+        package(&[
+            (CONTENT_TYPES, br#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/printerSettings/printerSettings1.bin" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.printerSettings"/></Types>"#),
+            ("xl/worksheets/sheet1.xml", br#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><pageSetup orientation="portrait" r:id="rId1"/></worksheet>"#),
+            ("xl/worksheets/_rels/sheet1.xml.rels", br#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/printerSettings" Target="../printerSettings/printerSettings1.bin"/></Relationships>"#),
+            ("xl/comments1.xml", br#"<comments xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><authors><author>Jane Doe</author></authors></comments>"#),
+            ("xl/printerSettings/printerSettings1.bin", &devmode),
+        ])
+    }
+
+    fn part(bytes: &[u8], name: &str) -> String {
+        package::read_parts(bytes, is_read)
+            .unwrap()
+            .into_iter()
+            .find(|p| p.name == name)
+            .unwrap()
+            .text()
+    }
+
+    #[test]
+    fn view_metadata_lists_document_properties() {
+        let entries = OoxmlScrubber::new(docx()).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(
+            keys,
+            [
+                "dc:title",
+                "dc:creator",
+                "cp:lastModifiedBy",
+                "cp:revision",
+                "dcterms:created",
+                "Template",
+                "Company",
+                "TitlesOfParts",
+                "AppVersion",
+                "Client",
+                "PackageModified",
+            ]
+        );
+        assert_eq!(entries[6].display_value, "Acme");
+        assert_eq!(entries[7].display_value, "Sheet1, Sheet2");
+        assert_eq!(entries[10].display_value, "2024-05-02T09:15:00");
+    }
+
+    #[test]
+    fn scrub_clears_properties_and_timestamps() {
+        let result = OoxmlScrubber::new(docx()).unwrap().scrub().unwrap();
+        let cleaned = &result.cleaned_file_bytes;
+
+        assert_eq!(result.metadata_removed.len(), 11);
+        assert!(!part(cleaned, "docProps/core.xml").contains("Jane"));
+        assert!(
+            part(cleaned, "docProps/core.xml")
+                .ends_with("<cp:keywords></cp:keywords></cp:coreProperties>")
+        );
+        assert_eq!(part(cleaned, "word/document.xml"), "<w:document/>");
+        assert!(
+            OoxmlScrubber::new(cleaned.clone())
+                .unwrap()
+                .view_metadata()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn view_metadata_lists_authors_and_rsids() {
+        let entries = OoxmlScrubber::new(reviewed_docx())
+            .unwrap()
+            .deep()
+            .view_metadata()
+            .unwrap();
+        let listed: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e.key.as_str(), e.display_value.as_str()))
+            .collect();
+
+        assert_eq!(
+            listed,
+            [
+                ("RSIDs", "2 editing session IDs"),
+                ("RevisionAuthor", "Jane Doe"),
+                ("CommentAuthor", "Jane Doe"),
+                ("AuthorInitials", "JD"),
+                ("Thumbnail", "(4 bytes of binary data)"),
+                ("PackageModified", "2024-05-02T09:15:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn scrub_anonymizes_authors_and_strips_rsids() {
+        let result = OoxmlScrubber::new(reviewed_docx())
+            .unwrap()
+            .deep()
+            .scrub()
+            .unwrap();
+        let cleaned = &result.cleaned_file_bytes;
+
+        assert_eq!(
+            part(cleaned, "word/document.xml"),
+            r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body><w:p><w:ins w:id="1" w:author="Author" w:date="2024-05-01T14:30:00Z"><w:r><w:t>Hello</w:t></w:r></w:ins></w:p></w:body></w:document>"#
+        );
+        assert_eq!(
+            part(cleaned, "word/settings.xml"),
+            r#"<w:settings xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:zoom w:percent="100"/></w:settings>"#
+        );
+        assert!(part(cleaned, "word/comments.xml").contains(r#"w:author="Author" w:initials="A""#));
+        assert!(!part(cleaned, "_rels/.rels").contains("thumbnail"));
+        assert!(
+            package::read_parts(cleaned, is_read)
+                .unwrap()
+                .iter()
+                .all(|p| p.name != "docProps/thumbnail.jpeg")
+        );
+        assert!(
+            OoxmlScrubber::new(cleaned.clone())
+                .unwrap()
+                .deep()
+                .view_metadata()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn scrub_leaves_content_alone_unless_deep() {
+        let file = reviewed_docx();
+        let scrubber = OoxmlScrubber::new(file.clone()).unwrap();
+        let keys: Vec<String> = scrubber
+            .view_metadata()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        assert_eq!(keys, ["PackageModified"]);
+
+        let cleaned = scrubber.scrub().unwrap().cleaned_file_bytes;
+        for name in ["word/document.xml", "word/comments.xml", "_rels/.rels"] {
+            assert_eq!(part(&cleaned, name), part(&file, name));
+        }
+    }
+
+    #[test]
+    fn scrub_unlinks_printer_settings() {
+        let scrubber = OoxmlScrubber::new(printed_xlsx()).unwrap().deep();
+        let entries = scrubber.view_metadata().unwrap();
+        assert_eq!(entries[0].key, "CommentAuthor");
+        assert_eq!(entries[1].display_value, "HP LaserJet");
+
+        let cleaned = scrubber.scrub().unwrap().cleaned_file_bytes;
+        assert_eq!(
+            part(&cleaned, "xl/worksheets/sheet1.xml"),
+            r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><pageSetup orientation="portrait"/></worksheet>"#
+        );
+        assert!(!part(&cleaned, "xl/worksheets/_rels/sheet1.xml.rels").contains("Relationship "));
+        assert!(!part(&cleaned, CONTENT_TYPES).contains("printerSettings"));
+        assert!(part(&cleaned, "xl/comments1.xml").contains("<author>Author</author>"));
+    }
+
+    #[test]
+    fn scrub_with_policy_keeps_selected_properties() {
+        let policy = ScrubPolicy::remove_all()
+            .keep(crate::Selector::Key("dc:title".into()))
+            .keep(crate::Selector::Key("PackageModified".into()));
+        let result = OoxmlScrubber::new(docx())
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        let entries = OoxmlScrubber::new(result.cleaned_file_bytes)
+            .unwrap()
+            .view_metadata()
+            .unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["dc:title", "PackageModified"]);
+    }
+}
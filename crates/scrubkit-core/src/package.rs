@@ -0,0 +1,299 @@
+// File: crates/scrubkit-core/src/package.rs
+
+//! Reads and writes the ZIP packages that office documents are stored in,
+//! and edits the XML parts inside them.
+//!
+//! Parts are read in archive order and written back with the same names,
+//! order and compression. Only the parts a format asks for, its XML, are
+//! inflated; the rest, such as embedded media, are copied over compressed.
+//! Nothing else about the original entries is copied: extra fields (which can
+//! hold extended timestamps and owner IDs) are dropped, and each entry gets
+//! either its original modification time or the ZIP epoch,
+//! 1980-01-01 00:00:00.
+
+use crate::xmp::looks_like_date;
+use crate::{MetadataCategory, MetadataEntry, MetadataValue, ScrubError};
+use roxmltree::{Attribute, Node};
+use std::io::{Cursor, Read, Write};
+use std::ops::Range;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+/// The most a single part may inflate to, so a small file can't expand into
+/// gigabytes.
+const MAX_PART_LEN: u64 = 64 << 20;
+
+/// The most all the inflated parts of a package may add up to.
+const MAX_INFLATED_LEN: u64 = 256 << 20;
+
+/// A file or directory inside a ZIP package.
+#[derive(Debug, Clone)]
+pub(crate) struct Part {
+    pub(crate) name: String,
+    /// The contents, for parts that were inflated. Empty for the rest.
+    pub(crate) data: Vec<u8>,
+    /// The uncompressed size given in the archive.
+    pub(crate) size: usize,
+    /// The entry's index in the original archive, for parts that weren't
+    /// inflated and are copied over as they are.
+    copied_from: Option<usize>,
+    is_dir: bool,
+    compression: CompressionMethod,
+    /// When the entry was last modified, or `None` for the ZIP epoch.
+    pub(crate) modified: Option<DateTime>,
+}
+
+impl Part {
+    /// The part's contents as text, without a byte order mark.
+    pub(crate) fn text(&self) -> String {
+        String::from_utf8_lossy(&self.data)
+            .trim_start_matches('\u{FEFF}')
+            .to_string()
+    }
+}
+
+/// The part naming an OpenDocument package's media type.
+pub(crate) const MIMETYPE: &str = "mimetype";
+
+fn zip_error(e: ZipError) -> ScrubError {
+    ScrubError::ParsingError(format!("Invalid ZIP package: {}", e))
+}
+
+/// Returns true if the file is a ZIP package containing the named part.
+pub(crate) fn has_part(bytes: &[u8], name: &str) -> bool {
+    bytes.starts_with(b"PK\x03\x04")
+        && ZipArchive::new(Cursor::new(bytes))
+            .is_ok_and(|archive| archive.index_for_name(name).is_some())
+}
+
+/// Reads every part of a package, inflating those whose names `inflate`
+/// accepts.
+pub(crate) fn read_parts(
+    bytes: &[u8],
+    inflate: impl Fn(&str) -> bool,
+) -> Result<Vec<Part>, ScrubError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(zip_error)?;
+    let mut inflated_len = 0;
+    (0..archive.len())
+        .map(|i| {
+            let file = archive.by_index_raw(i).map_err(zip_error)?;
+            if file.encrypted() {
+                return Err(ScrubError::UnsupportedFileType(
+                    "Encrypted ZIP packages are not supported".into(),
+                ));
+            }
+            let mut part = Part {
+                name: file.name().to_string(),
+                data: Vec::new(),
+                size: usize::try_from(file.size()).unwrap_or(usize::MAX),
+                copied_from: Some(i),
+                is_dir: file.is_dir(),
+                compression: file.compression(),
+                modified: file.last_modified().filter(|t| *t != DateTime::default()),
+            };
+            drop(file);
+            if part.is_dir || !(part.name == MIMETYPE || inflate(&part.name)) {
+                return Ok(part);
+            }
+
+            let file = archive.by_index(i).map_err(zip_error)?;
+            file.take(MAX_PART_LEN + 1).read_to_end(&mut part.data)?;
+            inflated_len += part.data.len() as u64;
+            if part.data.len() as u64 > MAX_PART_LEN || inflated_len > MAX_INFLATED_LEN {
+                return Err(ScrubError::ParsingError(format!(
+                    "ZIP package part too large: {}",
+                    part.name
+                )));
+            }
+            part.copied_from = None;
+            Ok(part)
+        })
+        .collect()
+}
+
+/// Writes the parts out as a new package. Parts that weren't inflated are
+/// copied from `bytes`, the package they were read from.
+///
+/// A `mimetype` part is always written first and uncompressed, as OpenDocument
+/// requires, so readers can identify the package from its first bytes.
+pub(crate) fn write_parts(bytes: &[u8], parts: &[Part]) -> Result<Vec<u8>, ScrubError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(zip_error)?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let (mimetype, rest): (Vec<&Part>, Vec<&Part>) = parts.iter().partition(|p| p.name == MIMETYPE);
+    for part in mimetype.into_iter().chain(rest) {
+        // Office files only use these two; anything else is recompressed.
+        let compression = match part.compression {
+            _ if part.name == MIMETYPE => CompressionMethod::Stored,
+            CompressionMethod::Stored => CompressionMethod::Stored,
+            _ => CompressionMethod::Deflated,
+        };
+        let options = SimpleFileOptions::default()
+            .compression_method(compression)
+            .last_modified_time(part.modified.unwrap_or_default());
+        if part.is_dir {
+            writer
+                .add_directory(part.name.as_str(), options)
+                .map_err(zip_error)?;
+        } else if let Some(index) = part.copied_from {
+            let file = archive.by_index_raw(index).map_err(zip_error)?;
+            writer
+                .raw_copy_file_touch(file, part.modified.unwrap_or_default(), Some(0o644))
+                .map_err(zip_error)?;
+        } else {
+            writer
+                .start_file(part.name.as_str(), options)
+                .map_err(zip_error)?;
+            writer.write_all(&part.data)?;
+        }
+    }
+    Ok(writer.finish().map_err(zip_error)?.into_inner())
+}
+
+/// Reports the latest entry timestamp, which tells when the file was last
+/// saved. ZIP times are local times without a zone.
+pub(crate) fn timestamp_entry(parts: &[Part]) -> Option<MetadataEntry> {
+    let latest = parts
+        .iter()
+        .filter_map(|p| p.modified)
+        .max_by_key(|t| (t.datepart(), t.timepart()))?;
+    Some(MetadataEntry::new(
+        MetadataCategory::OfficeProperties,
+        "PackageModified",
+        MetadataValue::DateTime(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            latest.year(),
+            latest.month(),
+            latest.day(),
+            latest.hour(),
+            latest.minute(),
+            latest.second()
+        )),
+    ))
+}
+
+/// A replacement for a byte range of a part's text.
+#[derive(Debug, Clone)]
+pub(crate) struct Edit {
+    pub(crate) range: Range<usize>,
+    pub(crate) replacement: &'static str,
+}
+
+impl Edit {
+    pub(crate) fn remove(range: Range<usize>) -> Self {
+        Self {
+            range,
+            replacement: "",
+        }
+    }
+
+    /// Removes an attribute, along with the space before it.
+    pub(crate) fn remove_attribute(xml: &str, attribute: &Attribute) -> Self {
+        let range = attribute.range();
+        if xml[..range.start].ends_with(char::is_whitespace) {
+            Self::remove(range.start - 1..range.end)
+        } else {
+            Self::remove(range)
+        }
+    }
+}
+
+/// Applies edits to a part's text. Edits that overlap an earlier one are skipped.
+pub(crate) fn apply_edits(text: &str, mut edits: Vec<Edit>) -> String {
+    edits.sort_by_key(|e| e.range.start);
+    let mut edited = String::with_capacity(text.len());
+    let mut start = 0;
+    for edit in edits {
+        if edit.range.start < start {
+            continue;
+        }
+        edited.push_str(&text[start..edit.range.start]);
+        edited.push_str(edit.replacement);
+        start = edit.range.end;
+    }
+    edited.push_str(&text[start..]);
+    edited
+}
+
+/// A property element, and where it sits in its part.
+pub(crate) struct Property {
+    pub(crate) entry: MetadataEntry,
+    pub(crate) range: Range<usize>,
+}
+
+/// Builds a `prefix:name` key, or just the name for unprefixed elements.
+pub(crate) fn qualified_name(node: Node) -> String {
+    let tag = node.tag_name();
+    match tag.namespace().and_then(|ns| node.lookup_prefix(ns)) {
+        Some(prefix) if !prefix.is_empty() => format!("{}:{}", prefix, tag.name()),
+        _ => tag.name().to_string(),
+    }
+}
+
+fn text_value(text: &str) -> MetadataValue {
+    let text = text.trim();
+    if looks_like_date(text) {
+        MetadataValue::DateTime(text.to_string())
+    } else {
+        MetadataValue::Text(text.to_string())
+    }
+}
+
+/// Reads a property element's value: its text, or a list of the texts of its
+/// items for vectors such as the sheet names in OOXML's `app.xml`.
+pub(crate) fn value_of(node: Node) -> Option<MetadataValue> {
+    let leaves: Vec<MetadataValue> = node
+        .descendants()
+        .filter(|n| n.is_element() && !n.children().any(|c| c.is_element()))
+        .filter_map(|n| n.text())
+        .filter(|text| !text.trim().is_empty())
+        .map(text_value)
+        .collect();
+    match <[MetadataValue; 1]>::try_from(leaves) {
+        Ok([single]) => Some(single),
+        Err(leaves) if leaves.is_empty() => None,
+        Err(leaves) => Some(MetadataValue::List(leaves)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_round_trip_without_timestamps() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let modified = DateTime::from_date_and_time(2024, 5, 1, 14, 30, 0).unwrap();
+        let stored = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .last_modified_time(modified);
+        writer.start_file("mimetype", stored).unwrap();
+        writer.write_all(b"application/test").unwrap();
+        writer
+            .start_file("content.xml", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"<doc/>").unwrap();
+        writer
+            .start_file("media/image.png", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(&[7; 1000]).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let mut parts = read_parts(&bytes, |name| name.ends_with(".xml")).unwrap();
+        assert!(parts[2].data.is_empty());
+        assert_eq!(parts[2].size, 1000);
+        assert_eq!(
+            timestamp_entry(&parts).unwrap().display_value,
+            "2024-05-01T14:30:00"
+        );
+
+        parts.iter_mut().for_each(|p| p.modified = None);
+        let rewritten = read_parts(&write_parts(&bytes, &parts).unwrap(), |_| true).unwrap();
+        let names: Vec<&str> = rewritten.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["mimetype", "content.xml", "media/image.png"]);
+        assert_eq!(rewritten[0].compression, CompressionMethod::Stored);
+        assert_eq!(rewritten[1].data, b"<doc/>");
+        assert_eq!(rewritten[2].data, [7; 1000]);
+        assert!(timestamp_entry(&rewritten).is_none());
+    }
+}
@@ -0,0 +1,598 @@
+// File: crates/scrubkit-core/src/pdf.rs
+
+//! PDF support.
+//!
+//! A PDF keeps its document metadata in two places: the Info dictionary named
+//! by the trailer, and an XMP stream hanging off the document catalog. The
+//! trailer also carries a document ID, which editors copy from file to file
+//! and which can tie a shared PDF back to the original.
+//!
+//! Editors often save changes as incremental updates, appending new objects
+//! and a new cross-reference section after the old `%%EOF`. Earlier revisions,
+//! with whatever text and metadata they held, stay in the file. They are
+//! reported as history, and cleaned files are written out from scratch as a
+//! single revision without unreferenced objects. A removed document ID is
+//! replaced with a hash of the cleaned file.
+
+use crate::xmp::parse_xmp;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, RiskLevel, ScrubError, ScrubPolicy,
+    ScrubResult, Scrubber,
+};
+use lopdf::xref::XrefType;
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat, decode_text_string};
+use md5::{Digest, Md5};
+use std::collections::HashSet;
+
+/// Trailer keys needed to read the document, plus the metadata ones.
+const TRAILER_KEYS: &[&[u8]] = &[b"Root", b"Info", b"ID"];
+
+/// Returns true if the file has a PDF header. Readers accept it anywhere in
+/// the first kilobyte.
+pub fn is_pdf(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(1024)]
+        .windows(5)
+        .any(|w| w == b"%PDF-")
+}
+
+fn load(bytes: &[u8]) -> Result<Document, ScrubError> {
+    let doc = Document::load_mem(bytes)
+        .map_err(|e| ScrubError::ParsingError(format!("Invalid PDF file: {}", e)))?;
+    // Strings in encrypted files can't be read or rewritten without the key.
+    if doc.is_encrypted() {
+        return Err(ScrubError::UnsupportedFileType(
+            "Encrypted PDF files are not supported".into(),
+        ));
+    }
+    Ok(doc)
+}
+
+/// The Info dictionary, with its object ID if it is an indirect object.
+fn info(doc: &Document) -> Option<(Option<ObjectId>, &Dictionary)> {
+    let (id, object) = doc.dereference(doc.trailer.get(b"Info").ok()?).ok()?;
+    Some((id, object.as_dict().ok()?))
+}
+
+/// The catalog's XMP stream: its object ID and its decoded packet.
+fn xmp_stream(doc: &Document) -> Option<(ObjectId, Vec<u8>)> {
+    let id = doc
+        .catalog()
+        .ok()?
+        .get(b"Metadata")
+        .ok()?
+        .as_reference()
+        .ok()?;
+    let stream = doc.get_object(id).ok()?.as_stream().ok()?;
+    // Packets behind filters we can't decode are still reported, as raw bytes.
+    let packet = stream
+        .get_plain_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    Some((id, packet))
+}
+
+/// Converts a PDF date, `D:YYYYMMDDHHmmSSOHH'mm'`, to ISO 8601. Everything
+/// after the year is optional.
+fn parse_date(text: &str) -> Option<String> {
+    let text = text.strip_prefix("D:").unwrap_or(text);
+    let digits_end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (digits, zone) = text.split_at(digits_end);
+    if digits.len() < 4 || digits.len() > 14 || digits.len() % 2 != 0 {
+        return None;
+    }
+    let part =
+        |start: usize, default: &'static str| digits.get(start..start + 2).unwrap_or(default);
+    let date = format!("{}-{}-{}", &digits[..4], part(4, "01"), part(6, "01"));
+    if digits.len() <= 8 {
+        return Some(date);
+    }
+
+    let zone = match zone.chars().next() {
+        Some('Z') => "Z".to_string(),
+        Some(sign @ ('+' | '-')) => {
+            let offset: String = zone[1..].chars().filter(char::is_ascii_digit).collect();
+            match offset.get(..2) {
+                Some(hours) => format!("{}{}:{}", sign, hours, offset.get(2..4).unwrap_or("00")),
+                None => String::new(),
+            }
+        }
+        _ => String::new(),
+    };
+    Some(format!(
+        "{}T{}:{}:{}{}",
+        date,
+        &digits[8..10],
+        part(10, "00"),
+        part(12, "00"),
+        zone
+    ))
+}
+
+/// Describes one Info dictionary entry, following a reference if the value is
+/// stored in its own object.
+fn info_entry(doc: &Document, key: &[u8], value: &Object) -> MetadataEntry {
+    let key = String::from_utf8_lossy(key);
+    let value = match doc.dereference(value).map(|(_, v)| v).unwrap_or(value) {
+        value @ Object::String(..) => {
+            let text = decode_text_string(value).unwrap_or_default();
+            let text = text.trim_start_matches('\u{FEFF}');
+            match parse_date(text) {
+                Some(date) if text.starts_with("D:") || key.ends_with("Date") => {
+                    MetadataValue::DateTime(date)
+                }
+                _ => MetadataValue::Text(text.to_string()),
+            }
+        }
+        Object::Name(name) => MetadataValue::Text(String::from_utf8_lossy(name).into_owned()),
+        Object::Integer(v) => MetadataValue::Integer(*v),
+        other => MetadataValue::Text(format!("{:?}", other)),
+    };
+    MetadataEntry::new(MetadataCategory::PdfInfo, key, value)
+}
+
+/// The trailer's document ID, shown as the hex of its first (permanent) half.
+fn document_id_entry(doc: &Document) -> Option<MetadataEntry> {
+    let id = doc.trailer.get(b"ID").ok()?.as_array().ok()?;
+    let permanent = id.first()?.as_str().ok()?;
+    let hex: String = permanent.iter().map(|b| format!("{:02X}", b)).collect();
+    Some(MetadataEntry::new(
+        MetadataCategory::PdfInfo,
+        "DocumentID",
+        MetadataValue::Text(hex),
+    ))
+}
+
+/// What one revision of the file wrote, up to and including its `%%EOF`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Revision {
+    /// Objects that first appear in this revision.
+    added: usize,
+    /// Objects that replace a version from an earlier revision.
+    replaced: usize,
+}
+
+fn find_all<'a>(bytes: &'a [u8], needle: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    bytes
+        .windows(needle.len())
+        .enumerate()
+        .filter(move |(_, w)| *w == needle)
+        .map(|(i, _)| i)
+}
+
+/// Reads the object number from an `N G obj` header ending just before `obj`.
+fn object_number(before: &[u8]) -> Option<u32> {
+    if !before.last()?.is_ascii_whitespace() {
+        return None;
+    }
+    let mut tokens = before
+        .rsplit(|b| b.is_ascii_whitespace())
+        .filter(|t| !t.is_empty());
+    let generation = tokens.next()?;
+    let number = tokens.next()?;
+    if !generation.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(number).ok()?.parse().ok()
+}
+
+/// Splits the file at its `%%EOF` markers and counts the objects each
+/// revision wrote. Objects packed into object streams are only counted as
+/// their container.
+fn revisions(bytes: &[u8]) -> Vec<Revision> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    for marker in find_all(bytes, b"%%EOF") {
+        sections.push(start..marker + 5);
+        start = marker + 5;
+    }
+    // A linearized file ends its first-page section with a `%%EOF` of its
+    // own, but that section is part of the original revision.
+    if sections.len() > 1
+        && find_all(&bytes[sections[0].clone()], b"/Linearized")
+            .next()
+            .is_some()
+    {
+        let first_page = sections.remove(0);
+        sections[0].start = first_page.start;
+    }
+
+    let mut seen = HashSet::new();
+    sections
+        .into_iter()
+        .map(|section| {
+            let section = &bytes[section];
+            let mut revision = Revision {
+                added: 0,
+                replaced: 0,
+            };
+            for pos in find_all(section, b"obj") {
+                // Skip `endobj` and words that merely start with "obj".
+                if section.get(pos + 3).is_some_and(u8::is_ascii_alphanumeric) {
+                    continue;
+                }
+                if let Some(number) = object_number(&section[..pos]) {
+                    if seen.insert(number) {
+                        revision.added += 1;
+                    } else {
+                        revision.replaced += 1;
+                    }
+                }
+            }
+            revision
+        })
+        .collect()
+}
+
+/// Describes each revision, if the file has been updated at least once.
+fn history_entries(bytes: &[u8]) -> Vec<MetadataEntry> {
+    let revisions = revisions(bytes);
+    if revisions.len() < 2 {
+        return Vec::new();
+    }
+    revisions
+        .iter()
+        .enumerate()
+        .map(|(i, revision)| {
+            // Earlier revisions can still hold text and metadata deleted since.
+            MetadataEntry::new(
+                MetadataCategory::PdfHistory,
+                format!("Revision {} of {}", i + 1, revisions.len()),
+                MetadataValue::Text(format!(
+                    "objects: {} new, {} replaced",
+                    revision.added, revision.replaced
+                )),
+            )
+            .with_risk(RiskLevel::High)
+        })
+        .collect()
+}
+
+fn save(doc: &mut Document) -> Result<Vec<u8>, ScrubError> {
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A Scrubber implementation for PDF files.
+#[derive(Debug, Clone)]
+pub struct PdfScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for PdfScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        load(&file_bytes)?;
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let doc = load(&self.file_bytes)?;
+        let mut metadata = Vec::new();
+        if let Some((_, info)) = info(&doc) {
+            metadata.extend(info.iter().map(|(k, v)| info_entry(&doc, k, v)));
+        }
+        if let Some((_, packet)) = xmp_stream(&doc) {
+            metadata.extend(parse_xmp(&packet));
+        }
+        metadata.extend(document_id_entry(&doc));
+        metadata.extend(history_entries(&self.file_bytes));
+        Ok(metadata)
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let mut doc = load(&self.file_bytes)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+
+        // Info entries are filtered one by one.
+        if let Some((info_id, info)) = info(&doc) {
+            let mut cleaned_info = Dictionary::new();
+            for (key, value) in info.iter() {
+                let entry = info_entry(&doc, key, value);
+                if policy.keeps(&entry) {
+                    cleaned_info.set(key.clone(), value.clone());
+                    metadata_kept.push(entry);
+                } else {
+                    metadata_removed.push(entry);
+                }
+            }
+            // Objects no longer referenced are dropped by `prune_objects` below.
+            if cleaned_info.is_empty() {
+                doc.trailer.remove(b"Info");
+            } else if let Some(id) = info_id {
+                doc.objects.insert(id, Object::Dictionary(cleaned_info));
+            } else {
+                doc.trailer.set("Info", cleaned_info);
+            }
+        }
+
+        // The XMP stream is kept as-is, so it's only kept if every entry in it is.
+        if let Some((id, packet)) = xmp_stream(&doc) {
+            let entries = parse_xmp(&packet);
+            if entries.iter().all(|e| policy.keeps(e)) {
+                metadata_kept.extend(entries);
+            } else {
+                metadata_removed.extend(entries);
+                doc.objects.remove(&id);
+                doc.catalog_mut()
+                    .map_err(|e| ScrubError::ParsingError(e.to_string()))?
+                    .remove(b"Metadata");
+            }
+        }
+
+        let mut new_id = false;
+        if let Some(entry) = document_id_entry(&doc) {
+            if policy.keeps(&entry) {
+                metadata_kept.push(entry);
+            } else {
+                metadata_removed.push(entry);
+                doc.trailer.remove(b"ID");
+                new_id = true;
+            }
+        }
+
+        // A rewritten file has a single revision, so the history can only be
+        // kept if nothing else is removed.
+        let history = history_entries(&self.file_bytes);
+        if metadata_removed.is_empty() && history.iter().all(|e| policy.keeps(e)) {
+            metadata_kept.extend(history);
+            return Ok(ScrubResult {
+                cleaned_file_bytes: self.file_bytes.clone(),
+                metadata_removed,
+                metadata_kept,
+            });
+        }
+
+        metadata_removed.extend(history);
+
+        // Write a fresh file: one revision, one classic cross-reference table,
+        // and a trailer without leftovers from earlier revisions.
+        doc.trailer
+            .as_hashmap_mut()
+            .retain(|key, _| TRAILER_KEYS.contains(&key.as_slice()));
+        doc.reference_table.cross_reference_type = XrefType::CrossReferenceTable;
+        // Drop objects nothing refers to any more, including object and
+        // cross-reference streams, whose contents were unpacked on load.
+        doc.prune_objects();
+        doc.max_id = doc.objects.keys().map(|(id, _)| *id).max().unwrap_or(0);
+        let mut cleaned_bytes = save(&mut doc)?;
+
+        // The new ID is derived from the cleaned file alone, so it can't link
+        // back to the original, and cleaning the same content twice gives the
+        // same bytes.
+        if new_id {
+            let hash = Object::String(
+                Md5::digest(&cleaned_bytes).to_vec(),
+                StringFormat::Hexadecimal,
+            );
+            doc.trailer.set("ID", vec![hash.clone(), hash]);
+            cleaned_bytes = save(&mut doc)?;
+        }
+
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{IncrementalDocument, Stream, dictionary};
+
+    const XMP: &[u8] = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/" dc:creator="Jane"/></rdf:RDF></x:xmpmeta>"#;
+
+    /// A one-page PDF with an Info dictionary, an XMP stream and a document ID.
+    fn pdf() -> Vec<u8> {
+        pdf_with_xmp(XMP)
+    }
+
+    fn pdf_with_xmp(packet: &[u8]) -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        doc.reference_table.cross_reference_type = XrefType::CrossReferenceTable;
+        let pages_id = doc.new_object_id();
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let metadata_id = doc.add_object(Stream::new(
+            dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+            packet.to_vec(),
+        ));
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Metadata" => metadata_id,
+        });
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::string_literal("Quarterly report"),
+            "Author" => Object::String(b"\xFE\xFF\x00J\x00a\x00n\x00e".to_vec(), StringFormat::Hexadecimal),
+            "CreationDate" => Object::string_literal("D:20240501143000+02'00'"),
+        });
+        let id = Object::String(b"0123456789abcdef".to_vec(), StringFormat::Hexadecimal);
+        doc.trailer.set("Root", catalog_id);
+        doc.trailer.set("Info", info_id);
+        doc.trailer.set("ID", vec![id.clone(), id]);
+        save(&mut doc).unwrap()
+    }
+
+    /// Appends an incremental update that retitles the document and adds an
+    /// object nothing refers to.
+    fn updated(file: Vec<u8>) -> Vec<u8> {
+        let previous = Document::load_mem(&file).unwrap();
+        let info_id = previous
+            .trailer
+            .get(b"Info")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let mut update = IncrementalDocument::create_from(file, previous);
+        update.opt_clone_object_to_new_document(info_id).unwrap();
+        update
+            .new_document
+            .get_dictionary_mut(info_id)
+            .unwrap()
+            .set("Title", Object::string_literal("Final report"));
+        update
+            .new_document
+            .add_object(Object::string_literal("draft notes"));
+        let mut bytes = Vec::new();
+        update.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn view_metadata_reads_info_xmp_and_document_id() {
+        let entries = PdfScrubber::new(pdf()).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(
+            keys,
+            [
+                "Title",
+                "Author",
+                "CreationDate",
+                "dc:creator",
+                "DocumentID"
+            ]
+        );
+        assert_eq!(entries[1].display_value, "Jane");
+        assert_eq!(
+            entries[2].value,
+            MetadataValue::DateTime("2024-05-01T14:30:00+02:00".into())
+        );
+        assert_eq!(entries[4].display_value, "30313233343536373839616263646566");
+    }
+
+    #[test]
+    fn scrub_removes_metadata_and_regenerates_the_document_id() {
+        let original = pdf();
+        let result = PdfScrubber::new(original.clone()).unwrap().scrub().unwrap();
+        assert_eq!(result.metadata_removed.len(), 5);
+
+        let doc = Document::load_mem(&result.cleaned_file_bytes).unwrap();
+        assert!(!doc.trailer.has(b"Info"));
+        assert!(!doc.catalog().unwrap().has(b"Metadata"));
+        assert!(!result.cleaned_file_bytes.windows(4).any(|w| w == b"Jane"));
+        assert_eq!(doc.get_pages().len(), 1);
+
+        let id = doc.trailer.get(b"ID").unwrap().as_array().unwrap();
+        assert_ne!(id[0].as_str().unwrap(), b"0123456789abcdef");
+
+        // Cleaning the cleaned file again changes nothing.
+        let again = PdfScrubber::new(result.cleaned_file_bytes.clone())
+            .unwrap()
+            .scrub()
+            .unwrap();
+        assert_eq!(again.cleaned_file_bytes, result.cleaned_file_bytes);
+    }
+
+    #[test]
+    fn shared_info_values_are_not_deleted() {
+        let mut doc = Document::load_mem(&pdf()).unwrap();
+        let title_id = doc.add_object(Object::string_literal("Quarterly report"));
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        doc.get_dictionary_mut(page_id)
+            .unwrap()
+            .set("PieceInfo", title_id);
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        doc.get_dictionary_mut(info_id)
+            .unwrap()
+            .set("Title", title_id);
+
+        let result = PdfScrubber::new(save(&mut doc).unwrap())
+            .unwrap()
+            .scrub()
+            .unwrap();
+        let cleaned = Document::load_mem(&result.cleaned_file_bytes).unwrap();
+
+        assert!(cleaned.trailer.get(b"Info").is_err());
+        let page_id = *cleaned.get_pages().get(&1).unwrap();
+        let piece = cleaned
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"PieceInfo")
+            .unwrap();
+        assert!(cleaned.dereference(piece).is_ok());
+    }
+
+    #[test]
+    fn empty_xmp_streams_are_removed() {
+        let file = pdf_with_xmp(br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#);
+        let result = PdfScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert!(result.metadata_removed.iter().any(|e| e.key == "XMP"));
+        let doc = Document::load_mem(&result.cleaned_file_bytes).unwrap();
+        assert!(!doc.catalog().unwrap().has(b"Metadata"));
+    }
+
+    #[test]
+    fn view_metadata_reports_each_revision() {
+        let entries = PdfScrubber::new(updated(pdf()))
+            .unwrap()
+            .view_metadata()
+            .unwrap();
+        let history: Vec<(&str, &str)> = entries
+            .iter()
+            .filter(|e| e.category == MetadataCategory::PdfHistory)
+            .map(|e| (e.key.as_str(), e.display_value.as_str()))
+            .collect();
+
+        assert_eq!(entries[0].display_value, "Final report");
+        assert_eq!(
+            history,
+            [
+                ("Revision 1 of 2", "objects: 5 new, 0 replaced"),
+                ("Revision 2 of 2", "objects: 1 new, 1 replaced"),
+            ]
+        );
+    }
+
+    #[test]
+    fn scrub_flattens_the_history() {
+        let policy =
+            ScrubPolicy::keep_all().remove(crate::Selector::Category(MetadataCategory::PdfHistory));
+        let result = PdfScrubber::new(updated(pdf()))
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+        let cleaned = &result.cleaned_file_bytes;
+        let contains = |text: &[u8]| cleaned.windows(text.len()).any(|w| w == text);
+
+        assert_eq!(result.metadata_removed.len(), 2);
+        assert!(contains(b"Final report"));
+        assert!(!contains(b"Quarterly report"));
+        assert!(!contains(b"draft notes"));
+        assert_eq!(find_all(cleaned, b"%%EOF").count(), 1);
+    }
+
+    #[test]
+    fn scrub_with_policy_keeps_selected_info_entries() {
+        let policy = ScrubPolicy::remove_all()
+            .keep(crate::Selector::Key("Title".into()))
+            .keep(crate::Selector::Key("DocumentID".into()));
+        let result = PdfScrubber::new(pdf())
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        let entries = PdfScrubber::new(result.cleaned_file_bytes)
+            .unwrap()
+            .view_metadata()
+            .unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["Title", "DocumentID"]);
+        assert_eq!(entries[1].display_value, "30313233343536373839616263646566");
+    }
+}
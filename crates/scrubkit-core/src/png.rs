@@ -1,7 +1,245 @@
 // File: crates/scrubkit-core/src/png.rs
 
-use crate::{MetadataEntry, ScrubError, ScrubResult, Scrubber};
-use std::io::Cursor;
+//! PNG support.
+//!
+//! A PNG file is a signature followed by chunks, each with a length, a
+//! four-letter type, data and a CRC. Scrubbing copies the chunks needed to
+//! render the image byte for byte and drops the metadata chunks, so pixel
+//! data is never re-encoded.
+
+use crate::exif::ExifData;
+use crate::xmp::parse_xmp;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+use flate2::read::ZlibDecoder;
+use std::io::{Cursor, Read};
+
+const PNG_SIGNATURE: &[u8] = &[137, 80, 78, 71, 13, 10, 26, 10];
+
+/// The iTXt keyword XMP packets are stored under.
+const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// An upper bound for decompressed text, so a tiny chunk can't expand into gigabytes.
+const MAX_TEXT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Ancillary chunks that change how the image is displayed, or animate it.
+/// Critical chunks such as PLTE and IDAT are always kept.
+const RENDERING_CHUNKS: &[&[u8; 4]] = &[
+    b"tRNS", b"gAMA", b"cHRM", b"sRGB", b"iCCP", b"sBIT", b"bKGD", b"hIST", b"pHYs", b"sPLT",
+    b"cICP", b"mDCV", b"mDCv", b"cLLI", b"cLLi", b"acTL", b"fcTL", b"fdAT", b"oFFs", b"pCAL",
+    b"sCAL", b"sTER",
+];
+
+/// A chunk, borrowed from the file.
+#[derive(Debug, Clone, Copy)]
+struct Chunk<'a> {
+    chunk_type: [u8; 4],
+    data: &'a [u8],
+    /// The whole chunk, from the length field to the CRC.
+    bytes: &'a [u8],
+}
+
+impl Chunk<'_> {
+    /// Returns true for chunks that carry metadata rather than image data.
+    ///
+    /// Everything ancillary that isn't needed for rendering counts, including
+    /// private chunks we don't know.
+    fn is_metadata(&self) -> bool {
+        // Critical chunks have an uppercase first letter.
+        let is_critical = self.chunk_type[0].is_ascii_uppercase();
+        !is_critical && !RENDERING_CHUNKS.contains(&&self.chunk_type)
+    }
+
+    fn name(&self) -> String {
+        String::from_utf8_lossy(&self.chunk_type).into_owned()
+    }
+
+    /// Describes the metadata in this chunk.
+    fn metadata_entries(&self) -> Vec<MetadataEntry> {
+        let entries = match &self.chunk_type {
+            b"tEXt" => {
+                let (keyword, text) = split_keyword(self.data);
+                Some(vec![text_entry(keyword, latin1(text))])
+            }
+            b"zTXt" => self.ztxt_entry().map(|entry| vec![entry]),
+            b"iTXt" => self.itxt_entries(),
+            b"eXIf" => ExifData::parse(self.data)
+                .ok()
+                .map(|exif| exif.metadata_entries()),
+            b"tIME" => self.time_entry().map(|entry| vec![entry]),
+            _ => None,
+        };
+        entries.unwrap_or_else(|| {
+            let category = match &self.chunk_type {
+                b"tEXt" | b"zTXt" | b"iTXt" | b"tIME" => MetadataCategory::PngText,
+                b"eXIf" => MetadataCategory::Exif,
+                _ => MetadataCategory::Vendor,
+            };
+            vec![self.binary_entry(category)]
+        })
+    }
+
+    /// Reads a zTXt chunk: a keyword, a compression method and zlib-compressed Latin-1 text.
+    fn ztxt_entry(&self) -> Option<MetadataEntry> {
+        let (keyword, rest) = split_keyword(self.data);
+        let (&method, compressed) = rest.split_first()?;
+        let text = (method == 0).then(|| inflate(compressed)).flatten()?;
+        Some(text_entry(keyword, latin1(&text)))
+    }
+
+    /// Reads an iTXt chunk: a keyword, compression flags, a language tag, a
+    /// translated keyword and UTF-8 text. XMP packets are parsed into their properties.
+    fn itxt_entries(&self) -> Option<Vec<MetadataEntry>> {
+        let (keyword, rest) = split_keyword(self.data);
+        let [flag, method, rest @ ..] = rest else {
+            return None;
+        };
+        let (language, rest) = split_keyword(rest);
+        let (translated, rest) = split_at_nul(rest);
+        let text = match (flag, method) {
+            (0, _) => rest.to_vec(),
+            (1, 0) => inflate(rest)?,
+            _ => return None,
+        };
+
+        if keyword == XMP_KEYWORD {
+            return Some(parse_xmp(&text));
+        }
+        let translated = String::from_utf8_lossy(translated);
+        let mut entry = text_entry(keyword, String::from_utf8_lossy(&text).into_owned());
+        let details: Vec<String> = [
+            ("language", language.as_str()),
+            ("translated keyword", &translated),
+        ]
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(label, value)| format!("{}: {}", label, value))
+        .collect();
+        if !details.is_empty() {
+            entry.display_value = format!("{} ({})", entry.display_value, details.join(", "));
+        }
+        Some(vec![entry])
+    }
+
+    /// Reads a tIME chunk, the image's last modification time in UTC.
+    fn time_entry(&self) -> Option<MetadataEntry> {
+        let [y1, y2, month, day, hour, minute, second] = *self.data else {
+            return None;
+        };
+        let timestamp = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            u16::from_be_bytes([y1, y2]),
+            month,
+            day,
+            hour,
+            minute,
+            second
+        );
+        Some(MetadataEntry::new(
+            MetadataCategory::PngText,
+            "ModificationTime",
+            MetadataValue::DateTime(timestamp),
+        ))
+    }
+
+    fn binary_entry(&self, category: MetadataCategory) -> MetadataEntry {
+        MetadataEntry::new(
+            category,
+            self.name(),
+            MetadataValue::Binary {
+                len: self.data.len(),
+            },
+        )
+    }
+}
+
+/// Splits the chunks of a PNG file. Data after IEND is returned separately.
+fn chunks(bytes: &[u8]) -> Result<(Vec<Chunk<'_>>, &[u8]), ScrubError> {
+    if !bytes.starts_with(PNG_SIGNATURE) {
+        return Err(ScrubError::ParsingError("Missing PNG signature".into()));
+    }
+    let mut chunks = Vec::new();
+    let mut offset = PNG_SIGNATURE.len();
+    while offset < bytes.len() {
+        let corrupt =
+            || ScrubError::ParsingError(format!("Corrupt PNG chunk at offset {}", offset));
+        let header = bytes.get(offset..offset + 8).ok_or_else(corrupt)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type = [header[4], header[5], header[6], header[7]];
+        let end = length
+            .checked_add(12)
+            .and_then(|len| offset.checked_add(len))
+            .ok_or_else(corrupt)?;
+        let chunk_bytes = bytes.get(offset..end).ok_or_else(corrupt)?;
+        chunks.push(Chunk {
+            chunk_type,
+            data: &chunk_bytes[8..8 + length],
+            bytes: chunk_bytes,
+        });
+        offset = end;
+        if &chunk_type == b"IEND" {
+            break;
+        }
+    }
+    Ok((chunks, &bytes[offset..]))
+}
+
+/// Splits a text chunk at the NUL byte ending its keyword.
+fn split_keyword(data: &[u8]) -> (String, &[u8]) {
+    let (keyword, rest) = split_at_nul(data);
+    (latin1(keyword), rest)
+}
+
+fn split_at_nul(data: &[u8]) -> (&[u8], &[u8]) {
+    match data.iter().position(|b| *b == 0) {
+        Some(end) => (&data[..end], &data[end + 1..]),
+        None => (data, &[]),
+    }
+}
+
+fn text_entry(keyword: String, text: String) -> MetadataEntry {
+    MetadataEntry::new(
+        MetadataCategory::PngText,
+        keyword,
+        MetadataValue::Text(text),
+    )
+}
+
+/// Decompresses zlib data, giving up on corrupt or oversized streams.
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut text = Vec::new();
+    ZlibDecoder::new(data)
+        .take(MAX_TEXT_SIZE)
+        .read_to_end(&mut text)
+        .ok()?;
+    Some(text)
+}
+
+/// Builds a chunk, computing its CRC.
+fn write_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Result<Vec<u8>, ScrubError> {
+    let length = u32::try_from(data.len())
+        .map_err(|_| ScrubError::ParsingError("PNG chunk too large".into()))?;
+    let mut bytes = length.to_be_bytes().to_vec();
+    bytes.extend_from_slice(chunk_type);
+    bytes.extend_from_slice(data);
+    let crc = crc32fast::hash(&bytes[4..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+    Ok(bytes)
+}
+
+/// tEXt and zTXt chunks are Latin-1, whose code points match Unicode's.
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+fn trailer_entry(trailer: &[u8]) -> MetadataEntry {
+    MetadataEntry::new(
+        MetadataCategory::Vendor,
+        "Trailer",
+        MetadataValue::Binary { len: trailer.len() },
+    )
+}
 
 /// A Scrubber implementation for PNG files.
 #[derive(Debug, Clone)]
@@ -22,70 +260,65 @@ impl Scrubber for PngScrubber {
     }
 
     fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
-        let decoder = png::Decoder::new(Cursor::new(&self.file_bytes));
-        let reader = decoder
-            .read_info()
-            .map_err(|e| ScrubError::ParsingError(e.to_string()))?;
-        let mut metadata = Vec::new();
-
-        // Correctly iterate over the decoded text chunks.
-        for text_chunk in &reader.info().uncompressed_latin1_text {
-            metadata.push(MetadataEntry {
-                category: "tEXt/zTXt/iTXt".to_string(),
-                key: text_chunk.keyword.clone(),
-                value: text_chunk.text.clone(),
-            });
+        let (chunks, trailer) = chunks(&self.file_bytes)?;
+        let mut metadata: Vec<MetadataEntry> = chunks
+            .iter()
+            .filter(|chunk| chunk.is_metadata())
+            .flat_map(|chunk| chunk.metadata_entries())
+            .collect();
+        if !trailer.is_empty() {
+            metadata.push(trailer_entry(trailer));
         }
-
         Ok(metadata)
     }
 
-    fn scrub(&self) -> Result<ScrubResult, ScrubError> {
-        let metadata_removed = self.view_metadata()?;
-        if metadata_removed.is_empty() {
-            return Ok(ScrubResult {
-                cleaned_file_bytes: self.file_bytes.clone(),
-                metadata_removed: vec![],
-            });
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let (chunks, trailer) = chunks(&self.file_bytes)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut cleaned_bytes = PNG_SIGNATURE.to_vec();
+
+        for chunk in chunks {
+            if !chunk.is_metadata() {
+                cleaned_bytes.extend_from_slice(chunk.bytes);
+                continue;
+            }
+            // EXIF is filtered entry by entry, like in JPEG files.
+            if &chunk.chunk_type == b"eXIf"
+                && let Ok(mut exif) = ExifData::parse(chunk.data)
+            {
+                let (kept, removed) = exif.apply_policy(policy);
+                if removed.is_empty() {
+                    cleaned_bytes.extend_from_slice(chunk.bytes);
+                } else if !exif.is_empty() {
+                    cleaned_bytes.extend(write_chunk(b"eXIf", &exif.to_bytes())?);
+                }
+                metadata_kept.extend(kept);
+                metadata_removed.extend(removed);
+                continue;
+            }
+            // A chunk is copied as-is, so it's only kept if every entry in it is.
+            let entries = chunk.metadata_entries();
+            if entries.iter().all(|e| policy.keeps(e)) {
+                cleaned_bytes.extend_from_slice(chunk.bytes);
+                metadata_kept.extend(entries);
+            } else {
+                metadata_removed.extend(entries);
+            }
+        }
+        if !trailer.is_empty() {
+            let entry = trailer_entry(trailer);
+            if policy.keeps(&entry) {
+                cleaned_bytes.extend_from_slice(trailer);
+                metadata_kept.push(entry);
+            } else {
+                metadata_removed.push(entry);
+            }
         }
 
-        // To scrub, we must re-encode the image while skipping the metadata chunks.
-        let decoder = png::Decoder::new(Cursor::new(&self.file_bytes));
-        let mut reader = decoder
-            .read_info()
-            .map_err(|e| ScrubError::ParsingError(e.to_string()))?;
-
-        // Read the image data itself.
-        let mut img_data = vec![0; reader.output_buffer_size()];
-        let info = reader
-            .next_frame(&mut img_data)
-            .map_err(|e| ScrubError::ParsingError(e.to_string()))?;
-
-        // Create a new PNG in memory
-        let mut cleaned_bytes = Vec::new();
-        {
-            // Create a new scope for the encoder and writer to ensure they are dropped
-            // and release their borrow on `cleaned_bytes` before we return it.
-            let mut encoder =
-                png::Encoder::new(Cursor::new(&mut cleaned_bytes), info.width, info.height);
-            encoder.set_color(info.color_type);
-            encoder.set_depth(info.bit_depth);
-
-            // Crucially, we do *not* write any of the textual metadata chunks to the new encoder.
-
-            let mut writer = encoder
-                .write_header()
-                .map_err(|e| ScrubError::ParsingError(e.to_string()))?;
-
-            writer
-                .write_image_data(&img_data)
-                .map_err(|e| ScrubError::ParsingError(e.to_string()))?;
-        } // encoder and writer are dropped here
-
-        // The `cleaned_bytes` vec now holds the scrubbed PNG.
         Ok(ScrubResult {
             cleaned_file_bytes: cleaned_bytes,
             metadata_removed,
+            metadata_kept,
         })
     }
 }
@@ -98,10 +331,10 @@ mod tests {
     // Keyword: "Author", Text: "ScrubKit Tester"
     const TEST_PNG_WITH_METADATA: &[u8] = &[
         137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 1, 0, 0, 0, 1, 8, 6,
-        0, 0, 0, 31, 21, 196, 137, 0, 0, 0, 28, 116, 69, 88, 116, 65, 117, 116, 104, 111, 114, 0,
-        83, 99, 114, 117, 98, 75, 105, 116, 32, 84, 101, 115, 116, 101, 114, 215, 122, 61, 248, 0,
-        0, 0, 12, 73, 68, 65, 84, 8, 215, 99, 96, 96, 96, 248, 207, 192, 4, 0, 1, 10, 0, 255, 170,
-        222, 158, 221, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
+        0, 0, 0, 31, 21, 196, 137, 0, 0, 0, 22, 116, 69, 88, 116, 65, 117, 116, 104, 111, 114, 0,
+        83, 99, 114, 117, 98, 75, 105, 116, 32, 84, 101, 115, 116, 101, 114, 12, 145, 122, 29, 0,
+        0, 0, 13, 73, 68, 65, 84, 120, 156, 99, 248, 207, 192, 240, 31, 0, 5, 0, 1, 255, 137, 153,
+        61, 29, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
     ];
 
     #[test]
@@ -110,7 +343,10 @@ mod tests {
         let metadata = scrubber.view_metadata().unwrap();
         assert!(!metadata.is_empty());
         assert_eq!(metadata[0].key, "Author");
-        assert_eq!(metadata[0].value, "ScrubKit Tester");
+        assert_eq!(
+            metadata[0].value,
+            MetadataValue::Text("ScrubKit Tester".into())
+        );
     }
 
     #[test]
@@ -128,4 +364,154 @@ mod tests {
             "Scrubbed file should have no metadata"
         );
     }
+
+    #[test]
+    fn scrub_with_keeps_text_chunks_the_policy_keeps() {
+        let scrubber = PngScrubber::new(TEST_PNG_WITH_METADATA.to_vec()).unwrap();
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("Author".into()));
+        let result = scrubber.scrub_with(&policy).unwrap();
+
+        assert!(result.metadata_removed.is_empty());
+        assert_eq!(result.metadata_kept.len(), 1);
+        assert_eq!(result.cleaned_file_bytes, TEST_PNG_WITH_METADATA);
+    }
+
+    fn chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        write_chunk(chunk_type, data).unwrap()
+    }
+
+    /// A 1x1 indexed image with a transparent palette entry.
+    fn indexed_png(metadata: &[Vec<u8>]) -> Vec<u8> {
+        let ihdr = chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 3, 0, 0, 0]);
+        let plte = chunk(b"PLTE", &[255, 0, 0]);
+        let trns = chunk(b"tRNS", &[128]);
+        let gama = chunk(b"gAMA", &45455u32.to_be_bytes());
+        let idat = chunk(b"IDAT", &[120, 156, 99, 96, 0, 0, 0, 2, 0, 1]);
+        let iend = chunk(b"IEND", &[]);
+        [
+            PNG_SIGNATURE.to_vec(),
+            ihdr,
+            plte,
+            trns,
+            gama,
+            metadata.concat(),
+            idat,
+            iend,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn scrub_copies_image_chunks_byte_for_byte() {
+        let file = indexed_png(&[
+            chunk(b"tIME", &[7, 232, 5, 1, 14, 30, 0]),
+            chunk(b"tEXt", b"Author\0Jane"),
+            chunk(b"prVt", b"private vendor data"),
+        ]);
+        let scrubber = PngScrubber::new(file).unwrap();
+        let result = scrubber.scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 3);
+        assert_eq!(result.cleaned_file_bytes, indexed_png(&[]));
+
+        // The palette and transparency survive, so the image still decodes the same.
+        let decoder = png::Decoder::new(Cursor::new(&result.cleaned_file_bytes));
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().palette.as_deref(), Some(&[255, 0, 0][..]));
+        assert_eq!(reader.info().trns.as_deref(), Some(&[128][..]));
+    }
+
+    #[test]
+    fn data_after_iend_is_removed() {
+        let mut file = indexed_png(&[]);
+        file.extend_from_slice(b"appended");
+        let result = PngScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "Trailer");
+        assert_eq!(result.cleaned_file_bytes, indexed_png(&[]));
+    }
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        use flate2::{Compression, write::ZlibEncoder};
+        use std::io::Write;
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn view_metadata_reads_every_text_chunk() {
+        let ztxt = [b"Comment\0\0".as_slice(), &compress(b"shot at home")].concat();
+        let itxt = b"Author\0\0\0de\0Autor\0J\xC3\xBCrgen";
+        let file = indexed_png(&[
+            chunk(b"zTXt", &ztxt),
+            chunk(b"iTXt", itxt),
+            chunk(b"tIME", &[7, 232, 5, 1, 14, 30, 0]),
+        ]);
+        let metadata = PngScrubber::new(file).unwrap().view_metadata().unwrap();
+
+        assert_eq!(metadata.len(), 3);
+        assert_eq!(
+            metadata[0].value,
+            MetadataValue::Text("shot at home".into())
+        );
+        assert_eq!(metadata[1].key, "Author");
+        assert_eq!(
+            metadata[1].display_value,
+            "J\u{fc}rgen (language: de, translated keyword: Autor)"
+        );
+        assert_eq!(
+            metadata[2].value,
+            MetadataValue::DateTime("2024-05-01T14:30:00Z".into())
+        );
+    }
+
+    #[test]
+    fn exif_chunks_are_parsed_and_filtered_by_entry() {
+        // Little-endian TIFF with Make = "ACME" and Orientation = 1.
+        let tiff = [
+            b"II*\0\x08\0\0\0\x02\0".as_slice(),
+            b"\x0F\x01\x02\0\x05\0\0\0\x26\0\0\0",
+            b"\x12\x01\x03\0\x01\0\0\0\x01\0\0\0",
+            b"\0\0\0\0ACME\0",
+        ]
+        .concat();
+        let file = indexed_png(&[chunk(b"eXIf", &tiff)]);
+        let scrubber = PngScrubber::new(file).unwrap();
+
+        let keys: Vec<String> = scrubber
+            .view_metadata()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+        assert_eq!(keys, ["Make", "Orientation"]);
+
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("Orientation".into()));
+        let result = scrubber.scrub_with(&policy).unwrap();
+        assert_eq!(result.metadata_removed[0].key, "Make");
+
+        let scrubbed = PngScrubber::new(result.cleaned_file_bytes).unwrap();
+        assert_eq!(scrubbed.view_metadata().unwrap(), result.metadata_kept);
+    }
+
+    #[test]
+    fn empty_xmp_packets_are_removed() {
+        let itxt = [
+            b"XML:com.adobe.xmp\0\0\0\0\0".as_slice(),
+            br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#,
+        ]
+        .concat();
+        let file = indexed_png(&[chunk(b"iTXt", &itxt)]);
+        let result = PngScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "XMP");
+        assert_eq!(result.cleaned_file_bytes, indexed_png(&[]));
+    }
+
+    #[test]
+    fn oversized_chunk_lengths_are_an_error() {
+        let file = [PNG_SIGNATURE, b"\xFF\xFF\xFF\xFFtEXt"].concat();
+        assert!(matches!(chunks(&file), Err(ScrubError::ParsingError(_))));
+    }
 }
@@ -0,0 +1,144 @@
+// File: crates/scrubkit-core/src/policy.rs
+
+use crate::{MetadataCategory, MetadataEntry};
+
+/// What should happen to a metadata entry during a scrub.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyAction {
+    Keep,
+    Remove,
+}
+
+/// Selects the metadata entries a policy rule applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// Matches every entry in a category.
+    Category(MetadataCategory),
+    /// Matches entries by their key, e.g. "Orientation".
+    Key(String),
+    /// Matches entries by their numeric tag ID, e.g. `0x0112` for Orientation.
+    TagId(u16),
+}
+
+impl Selector {
+    /// Returns true if this selector matches the given entry.
+    pub fn matches(&self, entry: &MetadataEntry) -> bool {
+        match self {
+            Selector::Category(category) => entry.category == *category,
+            Selector::Key(key) => entry.key.eq_ignore_ascii_case(key),
+            Selector::TagId(id) => entry.tag_id == Some(*id),
+        }
+    }
+}
+
+/// Decides which metadata entries are kept and which are removed during a scrub.
+///
+/// Rules are evaluated in the order they were added and the last matching rule
+/// wins. Entries that no rule matches fall back to the default action.
+///
+/// ```
+/// use scrubkit_core::{ScrubPolicy, Selector};
+///
+/// // Strip everything except the orientation and copyright tags.
+/// let policy = ScrubPolicy::remove_all()
+///     .keep(Selector::TagId(0x0112))
+///     .keep(Selector::TagId(0x8298));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScrubPolicy {
+    default_action: PolicyAction,
+    rules: Vec<(Selector, PolicyAction)>,
+}
+
+impl Default for ScrubPolicy {
+    fn default() -> Self {
+        Self::remove_all()
+    }
+}
+
+impl ScrubPolicy {
+    /// A policy that removes every entry unless a rule says otherwise.
+    pub fn remove_all() -> Self {
+        Self {
+            default_action: PolicyAction::Remove,
+            rules: Vec::new(),
+        }
+    }
+
+    /// A policy that keeps every entry unless a rule says otherwise.
+    pub fn keep_all() -> Self {
+        Self {
+            default_action: PolicyAction::Keep,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Adds a rule keeping the entries matched by `selector`.
+    pub fn keep(mut self, selector: Selector) -> Self {
+        self.rules.push((selector, PolicyAction::Keep));
+        self
+    }
+
+    /// Adds a rule removing the entries matched by `selector`.
+    pub fn remove(mut self, selector: Selector) -> Self {
+        self.rules.push((selector, PolicyAction::Remove));
+        self
+    }
+
+    /// Returns the action this policy takes for the given entry.
+    pub fn action_for(&self, entry: &MetadataEntry) -> PolicyAction {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(selector, _)| selector.matches(entry))
+            .map(|(_, action)| *action)
+            .unwrap_or(self.default_action)
+    }
+
+    /// Returns true if the given entry should survive the scrub.
+    pub fn keeps(&self, entry: &MetadataEntry) -> bool {
+        self.action_for(entry) == PolicyAction::Keep
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MetadataValue;
+
+    fn entry(category: MetadataCategory, key: &str, tag_id: Option<u16>) -> MetadataEntry {
+        MetadataEntry {
+            tag_id,
+            ..MetadataEntry::new(category, key, MetadataValue::Text(String::new()))
+        }
+    }
+
+    #[test]
+    fn default_policy_removes_everything() {
+        let policy = ScrubPolicy::default();
+        assert!(!policy.keeps(&entry(MetadataCategory::Ifd0, "Make", Some(0x010F))));
+        assert!(!policy.keeps(&entry(MetadataCategory::PngText, "Author", None)));
+    }
+
+    #[test]
+    fn later_rules_override_earlier_ones() {
+        let policy = ScrubPolicy::keep_all()
+            .remove(Selector::Category(MetadataCategory::Gps))
+            .keep(Selector::TagId(0x0000));
+
+        assert!(policy.keeps(&entry(MetadataCategory::Ifd0, "Orientation", Some(0x0112))));
+        assert!(!policy.keeps(&entry(MetadataCategory::Gps, "GPSLatitude", Some(0x0002))));
+        assert!(policy.keeps(&entry(MetadataCategory::Gps, "GPSVersionID", Some(0x0000))));
+    }
+
+    #[test]
+    fn key_matching_ignores_case() {
+        let policy = ScrubPolicy::remove_all()
+            .keep(Selector::Key("copyright".into()))
+            .keep(Selector::Category(MetadataCategory::Exif));
+
+        assert!(policy.keeps(&entry(MetadataCategory::Ifd0, "Copyright", Some(0x8298))));
+        assert!(policy.keeps(&entry(MetadataCategory::Exif, "ColorSpace", Some(0xA001))));
+        assert!(!policy.keeps(&entry(MetadataCategory::Ifd0, "Artist", Some(0x013B))));
+    }
+}
@@ -0,0 +1,336 @@
+// File: crates/scrubkit-core/src/risk.rs
+
+use crate::{MetadataCategory, MetadataEntry, MetadataValue};
+use std::fmt;
+
+/// How much a metadata entry can reveal about the person who made the file.
+///
+/// Levels are ordered, so `RiskLevel::Critical > RiskLevel::Low`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RiskLevel {
+    /// Technical details such as resolution or color space.
+    Low,
+    /// Details that narrow things down, such as the camera model or software.
+    Medium,
+    /// Names and timestamps that point at a person or a moment.
+    High,
+    /// Locations and unique device identifiers.
+    Critical,
+}
+
+impl RiskLevel {
+    /// Every level, most sensitive first.
+    pub const ALL: [RiskLevel; 4] = [
+        RiskLevel::Critical,
+        RiskLevel::High,
+        RiskLevel::Medium,
+        RiskLevel::Low,
+    ];
+}
+
+impl fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RiskLevel::Low => "Low",
+            RiskLevel::Medium => "Medium",
+            RiskLevel::High => "High",
+            RiskLevel::Critical => "Critical",
+        })
+    }
+}
+
+// Key fragments are matched against whole words of the entry key, or runs of
+// adjacent words, ignoring case and hyphens: "datetime" matches
+// `DateTimeOriginal` and "city" matches `photoshop:City`, but not `Opacity`.
+const CRITICAL_KEYS: &[&str] = &["serial", "latitude", "longitude"];
+const HIGH_KEYS: &[&str] = &[
+    "artist",
+    "author",
+    "owner",
+    "creator",
+    "copyright",
+    "comment",
+    "hostcomputer",
+    "uniqueid",
+    "datetime",
+    "offsettime",
+    "subsectime",
+    "documentid",
+    "instanceid",
+    "by-line",
+    "city",
+    "country",
+    "sublocation",
+    "sub-location",
+    "province",
+    "contact",
+    "writer",
+    "filename",
+    "lastmodifiedby",
+    "manager",
+    "company",
+    "rsid",
+    "rsids",
+    "printed-by",
+    "encodedby",
+    "composer",
+    "uniquefileid",
+    "popularimeter",
+    "originator",
+    "engineer",
+    "technician",
+    "commissioned",
+    "annotation",
+    "project",
+    "timereference",
+    "umid",
+    "docname",
+    "docbase",
+    "absref",
+];
+const MEDIUM_KEYS: &[&str] = &[
+    "make",
+    "model",
+    "lens",
+    "software",
+    "producer",
+    "template",
+    "application",
+    "appversion",
+    "printer",
+    "generator",
+    "encoder",
+    "private",
+    "vendor",
+    "codinghistory",
+    "makernote",
+    "description",
+    "title",
+    "titles",
+    "subtitle",
+    "subject",
+    "keywords",
+    "caption",
+    "headline",
+];
+
+/// Classifies an entry by its category, key and value.
+pub fn classify(category: MetadataCategory, key: &str, value: &MetadataValue) -> RiskLevel {
+    let words = words(key);
+    let key_has = |fragments: &[&str]| fragments.iter().any(|f| has_run(&words, f));
+
+    if category == MetadataCategory::Gps {
+        // The version tag is the only GPS field that says nothing about where you were.
+        return if key.eq_ignore_ascii_case("GPSVersionID") {
+            RiskLevel::Low
+        } else {
+            RiskLevel::Critical
+        };
+    }
+    if matches!(value, MetadataValue::GpsCoordinate(_)) || key_has(CRITICAL_KEYS) {
+        return RiskLevel::Critical;
+    }
+    if category == MetadataCategory::Icc {
+        // Color profiles are shared by every device of a model.
+        return RiskLevel::Low;
+    }
+    if matches!(value, MetadataValue::DateTime(_)) || key_has(HIGH_KEYS) {
+        return RiskLevel::High;
+    }
+    // Vendor blocks are opaque, so assume they identify the device.
+    if key_has(MEDIUM_KEYS) || category == MetadataCategory::Vendor {
+        return RiskLevel::Medium;
+    }
+    RiskLevel::Low
+}
+
+/// Splits a key into lowercase words at separators and case changes, so
+/// `xmpMM:InstanceID` becomes `xmp`, `mm`, `instance` and `id`.
+fn words(key: &str) -> Vec<String> {
+    let chars: Vec<char> = key.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            continue;
+        }
+        // A new word starts after a lowercase letter or digit, or at the
+        // last capital of an acronym, as in `GPSLatitude`.
+        let starts_word = c.is_uppercase()
+            && i > 0
+            && (chars[i - 1].is_lowercase()
+                || chars[i - 1].is_ascii_digit()
+                || (chars[i - 1].is_uppercase()
+                    && chars.get(i + 1).is_some_and(|next| next.is_lowercase())));
+        if starts_word && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+    words.extend((!word.is_empty()).then_some(word));
+    words
+}
+
+/// Returns true if adjacent words spell out the fragment.
+fn has_run(words: &[String], fragment: &str) -> bool {
+    let fragment = fragment.replace('-', "");
+    (0..words.len()).any(|start| {
+        let mut run = String::new();
+        for word in &words[start..] {
+            run.push_str(word);
+            if run.len() >= fragment.len() {
+                return run == fragment;
+            }
+        }
+        false
+    })
+}
+
+/// A file-level overview of the metadata found in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataSummary {
+    /// Every entry, most sensitive first.
+    pub entries: Vec<MetadataEntry>,
+}
+
+impl MetadataSummary {
+    /// Builds a summary, sorting the entries by risk while keeping their
+    /// original order within each level.
+    pub fn new(mut entries: Vec<MetadataEntry>) -> Self {
+        sort_by_risk(&mut entries);
+        Self { entries }
+    }
+
+    /// The number of entries at the given level.
+    pub fn count(&self, risk: RiskLevel) -> usize {
+        self.entries.iter().filter(|e| e.risk == risk).count()
+    }
+
+    /// The most sensitive level found, or `None` if the file has no metadata.
+    pub fn highest_risk(&self) -> Option<RiskLevel> {
+        self.entries.first().map(|e| e.risk)
+    }
+
+    /// Returns true if no metadata was found.
+    pub fn is_clean(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Display for MetadataSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} entries", self.entries.len())?;
+        for risk in RiskLevel::ALL {
+            let count = self.count(risk);
+            if count > 0 {
+                write!(f, ", {} {}", count, risk.to_string().to_lowercase())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sorts entries so the most sensitive come first. The sort is stable.
+pub fn sort_by_risk(entries: &mut [MetadataEntry]) {
+    entries.sort_by_key(|e| std::cmp::Reverse(e.risk));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> MetadataValue {
+        MetadataValue::Text(value.to_string())
+    }
+
+    #[test]
+    fn gps_and_serial_numbers_are_critical() {
+        let coordinate = MetadataValue::GpsCoordinate(51.5);
+        assert_eq!(
+            classify(MetadataCategory::Gps, "GPSLatitude", &coordinate),
+            RiskLevel::Critical
+        );
+        assert_eq!(
+            classify(MetadataCategory::Exif, "BodySerialNumber", &text("123")),
+            RiskLevel::Critical
+        );
+        assert_eq!(
+            classify(MetadataCategory::Gps, "GPSVersionID", &text("2.2.0.0")),
+            RiskLevel::Low
+        );
+    }
+
+    #[test]
+    fn names_and_timestamps_are_high() {
+        assert_eq!(
+            classify(MetadataCategory::Ifd0, "Artist", &text("Jane")),
+            RiskLevel::High
+        );
+        assert_eq!(
+            classify(MetadataCategory::PngText, "Author", &text("Jane")),
+            RiskLevel::High
+        );
+        let timestamp = MetadataValue::DateTime("2024-05-01T14:30:00".into());
+        assert_eq!(
+            classify(MetadataCategory::Exif, "DateTimeOriginal", &timestamp),
+            RiskLevel::High
+        );
+    }
+
+    #[test]
+    fn technical_fields_are_low() {
+        assert_eq!(
+            classify(
+                MetadataCategory::Ifd0,
+                "XResolution",
+                &MetadataValue::Integer(72)
+            ),
+            RiskLevel::Low
+        );
+        assert_eq!(
+            classify(MetadataCategory::Ifd0, "Model", &text("X100")),
+            RiskLevel::Medium
+        );
+    }
+
+    #[test]
+    fn keys_are_matched_by_whole_words() {
+        assert_eq!(
+            classify(MetadataCategory::Xmp, "photoshop:City", &text("Oslo")),
+            RiskLevel::High
+        );
+        assert_eq!(
+            classify(MetadataCategory::Svg, "fill-opacity", &text("0.5")),
+            RiskLevel::Low
+        );
+        assert_eq!(
+            classify(MetadataCategory::Xmp, "xmpMM:InstanceID", &text("uuid")),
+            RiskLevel::High
+        );
+        assert_eq!(words("GPSLatitudeRef"), ["gps", "latitude", "ref"]);
+    }
+
+    #[test]
+    fn summary_sorts_and_counts_by_risk() {
+        let summary = MetadataSummary::new(vec![
+            MetadataEntry::new(
+                MetadataCategory::Ifd0,
+                "XResolution",
+                MetadataValue::Integer(72),
+            ),
+            MetadataEntry::new(MetadataCategory::Ifd0, "Artist", text("Jane")),
+            MetadataEntry::new(
+                MetadataCategory::Gps,
+                "GPSLatitude",
+                MetadataValue::GpsCoordinate(1.0),
+            ),
+        ]);
+
+        assert_eq!(summary.highest_risk(), Some(RiskLevel::Critical));
+        assert_eq!(summary.entries[0].key, "GPSLatitude");
+        assert_eq!(summary.entries[2].key, "XResolution");
+        assert_eq!(summary.count(RiskLevel::High), 1);
+        assert_eq!(summary.to_string(), "3 entries, 1 critical, 1 high, 1 low");
+    }
+}
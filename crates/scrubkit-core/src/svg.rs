@@ -0,0 +1,351 @@
+// File: crates/scrubkit-core/src/svg.rs
+
+//! SVG support.
+//!
+//! SVG files are XML text, so there's no magic number: a file is taken for
+//! an SVG if its root element, after any XML declaration, doctype and
+//! comments, is `svg`. Editors leave traces that don't affect rendering:
+//! `<metadata>` blocks with RDF descriptions of the document, Sodipodi and
+//! Inkscape attributes naming the files it was saved and exported to, XML
+//! comments, and Illustrator's private `i:pgf` data. These are cut out of the
+//! text, leaving everything else byte for byte.
+
+use crate::package::{Edit, apply_edits, qualified_name, value_of};
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+use roxmltree::{Document, Node, ParsingOptions};
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const SODIPODI_NS: &str = "http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd";
+const INKSCAPE_NS: &str = "http://www.inkscape.org/namespaces/inkscape";
+const ILLUSTRATOR_NS: &str = "http://ns.adobe.com/AdobeIllustrator/10.0/";
+
+const BOM: &str = "\u{FEFF}";
+
+/// How far into a file to look for the root element.
+const SNIFF_LEN: usize = 4096;
+
+/// Editor attributes that record file names, paths or the editor version.
+const TRACE_ATTRIBUTES: &[(&str, &str)] = &[
+    (SODIPODI_NS, "docname"),
+    (SODIPODI_NS, "docbase"),
+    (SODIPODI_NS, "absref"),
+    (INKSCAPE_NS, "export-filename"),
+    (INKSCAPE_NS, "version"),
+];
+
+/// Returns true if the root element of the file is `svg`.
+pub fn is_svg(bytes: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(SNIFF_LEN)]);
+    let mut rest = head.strip_prefix(BOM).unwrap_or(&head);
+    loop {
+        rest = rest.trim_start();
+        let end = if rest.starts_with("<?") {
+            rest.find("?>").map(|i| i + 2)
+        } else if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3)
+        } else if rest.starts_with("<!") {
+            // A doctype, possibly with an internal subset in brackets.
+            match (rest.find('['), rest.find('>')) {
+                (Some(open), Some(close)) if open < close => rest.find("]>").map(|i| i + 2),
+                (_, close) => close.map(|i| i + 1),
+            }
+        } else {
+            return rest.strip_prefix("<svg").is_some_and(|r| {
+                r.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            });
+        };
+        match end {
+            Some(end) => rest = &rest[end..],
+            None => return false,
+        }
+    }
+}
+
+/// Something an editor left in the file, and how to cut it out.
+struct Trace {
+    entries: Vec<MetadataEntry>,
+    edit: Edit,
+}
+
+/// Builds a `prefix:name` key for an attribute.
+fn attribute_name(node: Node, namespace: Option<&str>, name: &str) -> String {
+    match namespace.and_then(|ns| node.lookup_prefix(ns)) {
+        Some(prefix) if !prefix.is_empty() => format!("{}:{}", prefix, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Describes the properties of the RDF resources in a `<metadata>` block,
+/// such as Inkscape's `cc:Work`. Other content is described by its text.
+fn metadata_entries(metadata: Node) -> Vec<MetadataEntry> {
+    let entry = |key: String, value| MetadataEntry::new(MetadataCategory::Svg, key, value);
+    let resources: Vec<Node> = metadata
+        .descendants()
+        .filter(|n| n.tag_name().namespace() == Some(RDF_NS) && n.tag_name().name() == "RDF")
+        .flat_map(|rdf| rdf.children().filter(|n| n.is_element()))
+        .collect();
+    if resources.is_empty() {
+        return value_of(metadata)
+            .map(|value| entry(qualified_name(metadata), value))
+            .into_iter()
+            .collect();
+    }
+
+    let mut entries = Vec::new();
+    for resource in resources {
+        for attribute in resource.attributes() {
+            if attribute.namespace().is_some_and(|ns| ns != RDF_NS) {
+                let key = attribute_name(resource, attribute.namespace(), attribute.name());
+                entries.push(entry(
+                    key,
+                    MetadataValue::Text(attribute.value().to_string()),
+                ));
+            }
+        }
+        for property in resource.children().filter(|n| n.is_element()) {
+            let value = value_of(property).or_else(|| {
+                property
+                    .attribute((RDF_NS, "resource"))
+                    .map(|uri| MetadataValue::Text(uri.to_string()))
+            });
+            if let Some(value) = value {
+                entries.push(entry(qualified_name(property), value));
+            }
+        }
+    }
+    entries
+}
+
+/// Finds every trace in the document.
+fn traces(doc: &Document, xml: &str) -> Vec<Trace> {
+    let mut traces = Vec::new();
+    for node in doc.descendants() {
+        let tag = node.tag_name();
+        if node.is_comment() {
+            let text = node.text().unwrap_or_default().trim().to_string();
+            traces.push(Trace {
+                entries: vec![MetadataEntry::new(
+                    MetadataCategory::Comment,
+                    "Comment",
+                    MetadataValue::Text(text),
+                )],
+                edit: Edit::remove(node.range()),
+            });
+        } else if tag.namespace() == Some(SVG_NS) && tag.name() == "metadata" {
+            // Blocks with nothing readable are removed all the same, since
+            // their attributes may still say who made the file.
+            let mut entries = metadata_entries(node);
+            if entries.is_empty() {
+                entries.push(MetadataEntry::new(
+                    MetadataCategory::Svg,
+                    "metadata",
+                    MetadataValue::Binary {
+                        len: node.range().len(),
+                    },
+                ));
+            }
+            traces.push(Trace {
+                entries,
+                edit: Edit::remove(node.range()),
+            });
+        } else if tag.namespace() == Some(ILLUSTRATOR_NS) && tag.name() == "pgf" {
+            let len = node.text().map_or(0, str::len);
+            traces.push(Trace {
+                entries: vec![MetadataEntry::new(
+                    MetadataCategory::Vendor,
+                    qualified_name(node),
+                    MetadataValue::Binary { len },
+                )],
+                edit: Edit::remove(node.range()),
+            });
+        }
+
+        for attribute in node.attributes() {
+            let is_trace = TRACE_ATTRIBUTES
+                .iter()
+                .any(|&(ns, name)| attribute.namespace() == Some(ns) && attribute.name() == name);
+            if is_trace {
+                let key = attribute_name(node, attribute.namespace(), attribute.name());
+                traces.push(Trace {
+                    entries: vec![MetadataEntry::new(
+                        MetadataCategory::Svg,
+                        key,
+                        MetadataValue::Text(attribute.value().to_string()),
+                    )],
+                    edit: Edit::remove_attribute(xml, &attribute),
+                });
+            }
+        }
+    }
+    traces
+}
+
+/// Splits off a byte order mark, which the XML parser doesn't accept.
+fn split_bom(bytes: &[u8]) -> Result<(&str, &str), ScrubError> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| ScrubError::ParsingError("SVG file is not valid UTF-8".into()))?;
+    Ok(match text.strip_prefix(BOM) {
+        Some(xml) => (BOM, xml),
+        None => ("", text),
+    })
+}
+
+fn parse(xml: &str) -> Result<Document<'_>, ScrubError> {
+    // Illustrator declares its namespaces as doctype entities.
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Document::parse_with_options(xml, options)
+        .map_err(|e| ScrubError::ParsingError(format!("Invalid SVG: {}", e)))
+}
+
+/// A Scrubber implementation for SVG files.
+#[derive(Debug, Clone)]
+pub struct SvgScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for SvgScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        let (_, xml) = split_bom(&file_bytes)?;
+        let doc = parse(xml)?;
+        if doc.root_element().tag_name().name() != "svg" {
+            return Err(ScrubError::ParsingError("Not a valid SVG file".into()));
+        }
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let (_, xml) = split_bom(&self.file_bytes)?;
+        Ok(traces(&parse(xml)?, xml)
+            .into_iter()
+            .flat_map(|trace| trace.entries)
+            .collect())
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let (bom, xml) = split_bom(&self.file_bytes)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut edits = Vec::new();
+
+        for trace in traces(&parse(xml)?, xml) {
+            if trace.entries.iter().all(|e| policy.keeps(e)) {
+                metadata_kept.extend(trace.entries);
+            } else {
+                metadata_removed.extend(trace.entries);
+                edits.push(trace.edit);
+            }
+        }
+
+        let cleaned_bytes = if edits.is_empty() {
+            self.file_bytes.clone()
+        } else {
+            format!("{}{}", bom, apply_edits(xml, edits)).into_bytes()
+        };
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INKSCAPE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!-- Created with Inkscape (http://www.inkscape.org/) -->
+<svg width="10" height="10" version="1.1"
+   sodipodi:docname="/home/jane/drawings/logo.svg"
+   inkscape:version="1.3 (0e150ed6c4, 2023-07-21)"
+   inkscape:export-filename="/home/jane/exports/logo.png"
+   xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"
+   xmlns:sodipodi="http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd"
+   xmlns="http://www.w3.org/2000/svg"
+   xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+   xmlns:cc="http://creativecommons.org/ns#"
+   xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <metadata id="metadata1"><rdf:RDF><cc:Work rdf:about="">
+    <dc:creator><cc:Agent><dc:title>Jane Doe</dc:title></cc:Agent></dc:creator>
+    <dc:date>2024-05-01</dc:date>
+    <cc:license rdf:resource="http://creativecommons.org/licenses/by/4.0/" />
+  </cc:Work></rdf:RDF></metadata>
+  <rect width="10" height="10" fill="red" />
+</svg>
+"#;
+
+    #[test]
+    fn is_svg_looks_past_the_prolog() {
+        assert!(is_svg(INKSCAPE.as_bytes()));
+        assert!(is_svg(
+            b"\xEF\xBB\xBF<!DOCTYPE svg [<!ENTITY ns_ai \"x\">]>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"
+        ));
+        assert!(!is_svg(b"<?xml version=\"1.0\"?><html><svg/></html>"));
+        assert!(!is_svg(b"<svgfoo/>"));
+    }
+
+    #[test]
+    fn view_metadata_lists_editor_traces() {
+        let entries = SvgScrubber::new(INKSCAPE.into())
+            .unwrap()
+            .view_metadata()
+            .unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(
+            keys,
+            [
+                "Comment",
+                "sodipodi:docname",
+                "inkscape:version",
+                "inkscape:export-filename",
+                "dc:creator",
+                "dc:date",
+                "cc:license",
+            ]
+        );
+        assert_eq!(entries[4].value, MetadataValue::Text("Jane Doe".into()));
+    }
+
+    #[test]
+    fn scrub_leaves_the_drawing_untouched() {
+        let result = SvgScrubber::new(INKSCAPE.into()).unwrap().scrub().unwrap();
+        let cleaned = String::from_utf8(result.cleaned_file_bytes).unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 7);
+        assert!(!cleaned.contains("jane") && !cleaned.contains("Jane"));
+        assert!(!cleaned.contains("<!--") && !cleaned.contains("<metadata"));
+        assert!(cleaned.contains(r#"<svg width="10" height="10" version="1.1""#));
+        assert!(cleaned.contains(r#"<rect width="10" height="10" fill="red" />"#));
+        assert!(Document::parse(&cleaned).is_ok());
+    }
+
+    #[test]
+    fn scrub_removes_illustrator_private_data() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:i="http://ns.adobe.com/AdobeIllustrator/10.0/"><path d="M0 0"/><i:pgf id="adobe_illustrator_pgf">eJzsvWuPJMdxLvydv6K+Enj</i:pgf></svg>"#;
+        let result = SvgScrubber::new(svg.into()).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "i:pgf");
+        assert_eq!(
+            result.cleaned_file_bytes,
+            br#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:i="http://ns.adobe.com/AdobeIllustrator/10.0/"><path d="M0 0"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn metadata_without_text_is_still_removed() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:x="urn:x"><metadata><x:info author="Jane Doe" path="/home/jane/logo.svg"/></metadata><path d="M0 0"/></svg>"#;
+        let result = SvgScrubber::new(svg.into()).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "metadata");
+        assert_eq!(
+            result.cleaned_file_bytes,
+            br#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:x="urn:x"><path d="M0 0"/></svg>"#
+        );
+    }
+}
@@ -0,0 +1,859 @@
+// File: crates/scrubkit-core/src/tiff.rs
+
+//! TIFF support, and the IFD model it shares with EXIF.
+//!
+//! A TIFF file is a header followed by a chain of image file directories
+//! (IFDs), one per page. Each IFD is a list of tagged entries: some describe
+//! the image, some are metadata, and some point at other IFDs (SubIFDs, EXIF,
+//! GPS) or at the strips and tiles holding the pixels. DNG and most raw
+//! formats are TIFF files too.
+//!
+//! Because metadata sits in the same IFDs as the tags needed to decode the
+//! image, scrubbing can't just cut a segment out. Instead the kept entries
+//! are laid out again, followed by the image data copied byte for byte, and
+//! every offset is recomputed.
+
+use crate::exif::{
+    ExifData, Ifd, IfdKind, TAG_EXIF_IFD_POINTER, TAG_GPS_IFD_POINTER, TAG_INTEROP_IFD_POINTER,
+};
+use crate::{MetadataEntry, ScrubError, ScrubPolicy, ScrubResult, Scrubber};
+use std::ops::Range;
+
+pub(crate) const TYPE_LONG: u16 = 4;
+
+const TAG_SUB_IFDS: u16 = 0x014A;
+
+/// How deeply SubIFDs may nest, so a crafted file can't overflow the stack.
+/// Real files use one level, for previews or the raw image.
+const MAX_DEPTH: usize = 64;
+
+/// Offset and byte count tags for image data: strips, tiles and old-style JPEG.
+const DATA_TAGS: &[(u16, u16)] = &[(0x0111, 0x0117), (0x0144, 0x0145), (0x0201, 0x0202)];
+
+/// FreeOffsets and FreeByteCounts point at unused space, which isn't copied.
+const FREE_TAGS: &[u16] = &[0x0120, 0x0121];
+
+/// Tags needed to decode the image, and the ICC profile (0x8773), which
+/// changes how it is displayed, as PNG's iCCP does. These are never reported
+/// as metadata.
+const IMAGE_TAGS: &[u16] = &[
+    0x00FE, 0x00FF, 0x0100, 0x0101, 0x0102, 0x0103, 0x0106, 0x0107, 0x0108, 0x0109, 0x010A, 0x0115,
+    0x0116, 0x0117, 0x0118, 0x0119, 0x011A, 0x011B, 0x011C, 0x0122, 0x0123, 0x0124, 0x0125, 0x0128,
+    0x012D, 0x013D, 0x013E, 0x013F, 0x0140, 0x0141, 0x0142, 0x0143, 0x0145, 0x014C, 0x0152, 0x0153,
+    0x0154, 0x0155, 0x015B, 0x0200, 0x0202, 0x0203, 0x0211, 0x0212, 0x0213, 0x0214, 0x828D, 0x828E,
+    0x8773,
+];
+
+/// DNG tags from 0xC612 on describe how to render the raw data, except these.
+const DNG_METADATA_TAGS: &[u16] = &[
+    0xC615, 0xC62F, 0xC634, 0xC635, 0xC65D, 0xC68B, 0xC68C, 0xC6F3, 0xC6F4, 0xC6F8, 0xC6FE,
+];
+
+fn is_image_tag(tag: u16) -> bool {
+    let is_dng = (0xC612..=0xC7FF).contains(&tag) || (0xCD2D..=0xCD48).contains(&tag);
+    IMAGE_TAGS.contains(&tag) || (is_dng && !DNG_METADATA_TAGS.contains(&tag))
+}
+
+/// The byte order declared in the TIFF header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    pub(crate) fn read_u16(self, bytes: &[u8], offset: usize) -> Option<u16> {
+        let b: [u8; 2] = bytes.get(offset..offset + 2)?.try_into().ok()?;
+        Some(match self {
+            ByteOrder::Little => u16::from_le_bytes(b),
+            ByteOrder::Big => u16::from_be_bytes(b),
+        })
+    }
+
+    pub(crate) fn read_u32(self, bytes: &[u8], offset: usize) -> Option<u32> {
+        let b: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+        Some(match self {
+            ByteOrder::Little => u32::from_le_bytes(b),
+            ByteOrder::Big => u32::from_be_bytes(b),
+        })
+    }
+
+    pub(crate) fn u16_bytes(self, value: u16) -> [u8; 2] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub(crate) fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+/// A decoded IFD entry value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum IfdValue {
+    Byte(Vec<u8>),
+    Ascii(String),
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Rational(Vec<(u32, u32)>),
+    SByte(Vec<i8>),
+    Undefined(Vec<u8>),
+    SShort(Vec<i16>),
+    SLong(Vec<i32>),
+    SRational(Vec<(i32, i32)>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+}
+
+/// A single IFD entry with its value kept as raw bytes in the file's byte order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IfdEntry {
+    pub(crate) tag: u16,
+    pub(crate) field_type: u16,
+    pub(crate) count: u32,
+    pub(crate) data: Vec<u8>,
+}
+
+impl IfdEntry {
+    /// Decodes the raw bytes of this entry according to its field type.
+    pub(crate) fn value(&self, order: ByteOrder) -> IfdValue {
+        let d = &self.data;
+        let u16s = || (0..d.len() / 2).filter_map(|i| order.read_u16(d, i * 2));
+        let u32s = || (0..d.len() / 4).filter_map(|i| order.read_u32(d, i * 4));
+        match self.field_type {
+            1 => IfdValue::Byte(d.clone()),
+            2 => {
+                let text = d.split(|&b| b == 0).next().unwrap_or_default();
+                IfdValue::Ascii(String::from_utf8_lossy(text).into_owned())
+            }
+            3 => IfdValue::Short(u16s().collect()),
+            4 | 13 => IfdValue::Long(u32s().collect()),
+            5 => {
+                let v: Vec<u32> = u32s().collect();
+                IfdValue::Rational(v.chunks_exact(2).map(|p| (p[0], p[1])).collect())
+            }
+            6 => IfdValue::SByte(d.iter().map(|&b| b as i8).collect()),
+            8 => IfdValue::SShort(u16s().map(|v| v as i16).collect()),
+            9 => IfdValue::SLong(u32s().map(|v| v as i32).collect()),
+            10 => {
+                let v: Vec<i32> = u32s().map(|v| v as i32).collect();
+                IfdValue::SRational(v.chunks_exact(2).map(|p| (p[0], p[1])).collect())
+            }
+            11 => IfdValue::Float(u32s().map(f32::from_bits).collect()),
+            12 => {
+                let v: Vec<u32> = u32s().collect();
+                let doubles = v.chunks_exact(2).map(|p| {
+                    let (hi, lo) = match order {
+                        ByteOrder::Big => (p[0], p[1]),
+                        ByteOrder::Little => (p[1], p[0]),
+                    };
+                    f64::from_bits(((hi as u64) << 32) | lo as u64)
+                });
+                IfdValue::Double(doubles.collect())
+            }
+            _ => IfdValue::Undefined(d.clone()),
+        }
+    }
+}
+
+/// Returns the size in bytes of a single component of the given TIFF field type.
+fn type_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 | 13 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+/// Reads the IFD at `offset`, returning its entries and the offset of the next IFD.
+pub(crate) fn read_ifd(
+    tiff: &[u8],
+    order: ByteOrder,
+    offset: usize,
+) -> Result<(Vec<IfdEntry>, u32), ScrubError> {
+    let truncated = || ScrubError::ParsingError(format!("Truncated IFD at offset {}", offset));
+    let count = order.read_u16(tiff, offset).ok_or_else(truncated)? as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let pos = offset + 2 + i * 12;
+        let tag = order.read_u16(tiff, pos).ok_or_else(truncated)?;
+        let field_type = order.read_u16(tiff, pos + 2).ok_or_else(truncated)?;
+        let count = order.read_u32(tiff, pos + 4).ok_or_else(truncated)?;
+
+        // Entries of unknown types can't be sized, so they can't be copied either.
+        let Some(size) = type_size(field_type).and_then(|s| s.checked_mul(count as usize)) else {
+            continue;
+        };
+        let data_start = if size <= 4 {
+            pos + 8
+        } else {
+            order.read_u32(tiff, pos + 8).ok_or_else(truncated)? as usize
+        };
+        let Some(data) = tiff.get(data_start..data_start.saturating_add(size)) else {
+            continue;
+        };
+        entries.push(IfdEntry {
+            tag,
+            field_type,
+            count,
+            data: data.to_vec(),
+        });
+    }
+
+    let next = order.read_u32(tiff, offset + 2 + count * 12).unwrap_or(0);
+    Ok((entries, next))
+}
+
+/// The number of bytes an IFD occupies, including its out-of-line values.
+pub(crate) fn ifd_size(entries: &[IfdEntry]) -> usize {
+    let values: usize = entries
+        .iter()
+        .filter(|e| e.data.len() > 4)
+        .map(|e| e.data.len() + e.data.len() % 2)
+        .sum();
+    2 + entries.len() * 12 + 4 + values
+}
+
+/// Appends an IFD located at `offset` to `out`, with its values right after it.
+pub(crate) fn write_ifd(
+    out: &mut Vec<u8>,
+    order: ByteOrder,
+    offset: usize,
+    entries: &[IfdEntry],
+    next: u32,
+) {
+    let mut value_offset = offset + 2 + entries.len() * 12 + 4;
+    let mut values = Vec::new();
+
+    out.extend_from_slice(&order.u16_bytes(entries.len() as u16));
+    for entry in entries {
+        out.extend_from_slice(&order.u16_bytes(entry.tag));
+        out.extend_from_slice(&order.u16_bytes(entry.field_type));
+        out.extend_from_slice(&order.u32_bytes(entry.count));
+        if entry.data.len() <= 4 {
+            let mut inline = [0u8; 4];
+            inline[..entry.data.len()].copy_from_slice(&entry.data);
+            out.extend_from_slice(&inline);
+        } else {
+            out.extend_from_slice(&order.u32_bytes(value_offset as u32));
+            values.extend_from_slice(&entry.data);
+            if entry.data.len() % 2 == 1 {
+                values.push(0);
+            }
+            value_offset += entry.data.len() + entry.data.len() % 2;
+        }
+    }
+    out.extend_from_slice(&order.u32_bytes(next));
+    out.extend_from_slice(&values);
+}
+
+/// Reads the `II*\0` or `MM\0*` header, returning the byte order and the offset of IFD0.
+pub(crate) fn read_header(tiff: &[u8]) -> Result<(ByteOrder, u32), ScrubError> {
+    let order = match tiff.get(0..4) {
+        Some([b'I', b'I', 0x2A, 0x00]) => ByteOrder::Little,
+        Some([b'M', b'M', 0x00, 0x2A]) => ByteOrder::Big,
+        _ => return Err(ScrubError::ParsingError("Invalid TIFF header".into())),
+    };
+    let ifd0_offset = order
+        .read_u32(tiff, 4)
+        .ok_or_else(|| ScrubError::ParsingError("Truncated TIFF header".into()))?;
+    Ok((order, ifd0_offset))
+}
+
+/// Reads the SHORT or LONG values of the entry with `tag`.
+fn offsets_of(entries: &[IfdEntry], tag: u16, order: ByteOrder) -> Option<Vec<usize>> {
+    let entry = entries.iter().find(|e| e.tag == tag)?;
+    match entry.value(order) {
+        IfdValue::Short(values) => Some(values.into_iter().map(usize::from).collect()),
+        IfdValue::Long(values) => Some(values.into_iter().map(|v| v as usize).collect()),
+        _ => None,
+    }
+}
+
+fn long_entry(tag: u16, values: &[u32], order: ByteOrder) -> IfdEntry {
+    IfdEntry {
+        tag,
+        field_type: TYPE_LONG,
+        count: values.len() as u32,
+        data: values.iter().flat_map(|v| order.u32_bytes(*v)).collect(),
+    }
+}
+
+/// Image data referenced by an offsets tag, such as the strips of a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DataBlock {
+    offsets_tag: u16,
+    ranges: Vec<Range<usize>>,
+}
+
+/// An image IFD and the IFDs that hang off it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ImageIfd {
+    kind: IfdKind,
+    /// Every entry except the ones pointing at other IFDs or at image data.
+    entries: Vec<IfdEntry>,
+    data: Vec<DataBlock>,
+    sub_ifds: Vec<ImageIfd>,
+    exif: Vec<IfdEntry>,
+    interop: Vec<IfdEntry>,
+    gps: Vec<IfdEntry>,
+}
+
+impl ImageIfd {
+    /// The metadata of this IFD, in the EXIF model, which knows how to name
+    /// and decode it.
+    fn exif_data(&self, order: ByteOrder) -> ExifData {
+        let metadata = self
+            .entries
+            .iter()
+            .filter(|e| !is_image_tag(e.tag))
+            .cloned()
+            .collect();
+        let ifds = [
+            (self.kind, metadata),
+            (IfdKind::Exif, self.exif.clone()),
+            (IfdKind::Interop, self.interop.clone()),
+            (IfdKind::Gps, self.gps.clone()),
+        ];
+        ExifData {
+            order,
+            ifds: ifds
+                .into_iter()
+                .filter(|(_, entries)| !entries.is_empty())
+                .map(|(kind, entries)| Ifd { kind, entries })
+                .collect(),
+            thumbnail: None,
+        }
+    }
+
+    /// Replaces the metadata of this IFD with what's left in `exif`.
+    fn set_exif_data(&mut self, exif: ExifData) {
+        self.entries.retain(|e| is_image_tag(e.tag));
+        self.exif.clear();
+        self.interop.clear();
+        self.gps.clear();
+        for ifd in exif.ifds {
+            match ifd.kind {
+                IfdKind::Exif => self.exif = ifd.entries,
+                IfdKind::Interop => self.interop = ifd.entries,
+                IfdKind::Gps => self.gps = ifd.entries,
+                IfdKind::Ifd0 | IfdKind::Ifd1 => self.entries.extend(ifd.entries),
+            }
+        }
+    }
+
+    fn metadata_entries(&self, order: ByteOrder) -> Vec<MetadataEntry> {
+        let mut entries = self.exif_data(order).metadata_entries();
+        for sub_ifd in &self.sub_ifds {
+            entries.extend(sub_ifd.metadata_entries(order));
+        }
+        entries
+    }
+
+    /// Drops the metadata the policy doesn't keep, here and in every SubIFD.
+    fn apply_policy(
+        &mut self,
+        order: ByteOrder,
+        policy: &ScrubPolicy,
+    ) -> (Vec<MetadataEntry>, Vec<MetadataEntry>) {
+        let mut exif = self.exif_data(order);
+        let (mut kept, mut removed) = exif.apply_policy(policy);
+        self.set_exif_data(exif);
+        for sub_ifd in &mut self.sub_ifds {
+            let (sub_kept, sub_removed) = sub_ifd.apply_policy(order, policy);
+            kept.extend(sub_kept);
+            removed.extend(sub_removed);
+        }
+        (kept, removed)
+    }
+}
+
+/// A parsed TIFF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TiffFile {
+    order: ByteOrder,
+    pages: Vec<ImageIfd>,
+}
+
+impl TiffFile {
+    fn parse(bytes: &[u8]) -> Result<Self, ScrubError> {
+        let (order, mut offset) = read_header(bytes)?;
+        let mut reader = IfdReader {
+            bytes,
+            order,
+            visited: Vec::new(),
+            depth: 0,
+        };
+        let mut pages = Vec::new();
+        while offset != 0 {
+            let kind = if pages.is_empty() {
+                IfdKind::Ifd0
+            } else {
+                IfdKind::Ifd1
+            };
+            let (page, next) = reader.image_ifd(offset as usize, kind)?;
+            pages.push(page);
+            offset = next;
+        }
+        if pages.is_empty() {
+            return Err(ScrubError::ParsingError("TIFF file has no images".into()));
+        }
+        Ok(Self { order, pages })
+    }
+
+    /// Writes the file out again: the IFDs in order, then the image data.
+    fn to_bytes(&self, source: &[u8]) -> Result<Vec<u8>, ScrubError> {
+        let order = self.order;
+        let mut nodes = Vec::new();
+        let mut previous: Option<usize> = None;
+        for page in &self.pages {
+            let index = flatten(page, &mut nodes);
+            if let Some(previous) = previous {
+                nodes[previous].next = Some(index);
+            }
+            previous = Some(index);
+        }
+
+        // First pass: add placeholder pointers and work out where everything lands.
+        let mut ifd_offsets = Vec::with_capacity(nodes.len());
+        let mut offset = 8usize;
+        for node in &mut nodes {
+            for (tag, targets) in &node.pointers {
+                node.entries
+                    .push(long_entry(*tag, &vec![0; targets.len()], order));
+            }
+            for block in node.data {
+                node.entries.push(long_entry(
+                    block.offsets_tag,
+                    &vec![0; block.ranges.len()],
+                    order,
+                ));
+            }
+            node.entries.sort_by_key(|e| e.tag);
+            ifd_offsets.push(offset);
+            offset += ifd_size(&node.entries);
+        }
+        let mut data_offsets = Vec::with_capacity(nodes.len());
+        for node in &nodes {
+            let mut blocks = Vec::with_capacity(node.data.len());
+            for block in node.data {
+                let mut starts = Vec::with_capacity(block.ranges.len());
+                for range in &block.ranges {
+                    starts.push(offset as u32);
+                    offset += range.len();
+                }
+                blocks.push(starts);
+            }
+            data_offsets.push(blocks);
+        }
+        if u32::try_from(offset).is_err() {
+            return Err(ScrubError::ParsingError("TIFF file too large".into()));
+        }
+
+        // Second pass: fill in the offsets and write.
+        let mut out = Vec::with_capacity(offset);
+        out.extend_from_slice(&source[0..4]);
+        out.extend_from_slice(&order.u32_bytes(8));
+        for (i, node) in nodes.iter_mut().enumerate() {
+            for (tag, targets) in &node.pointers {
+                let values: Vec<u32> = targets.iter().map(|t| ifd_offsets[*t] as u32).collect();
+                replace_entry(&mut node.entries, long_entry(*tag, &values, order));
+            }
+            for (block, starts) in node.data.iter().zip(&data_offsets[i]) {
+                replace_entry(
+                    &mut node.entries,
+                    long_entry(block.offsets_tag, starts, order),
+                );
+            }
+            let next = node.next.map_or(0, |n| ifd_offsets[n] as u32);
+            write_ifd(&mut out, order, ifd_offsets[i], &node.entries, next);
+        }
+        for node in &nodes {
+            for block in node.data {
+                for range in &block.ranges {
+                    out.extend_from_slice(&source[range.clone()]);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Reads IFDs, refusing to visit the same one twice.
+struct IfdReader<'a> {
+    bytes: &'a [u8],
+    order: ByteOrder,
+    visited: Vec<usize>,
+    depth: usize,
+}
+
+impl IfdReader<'_> {
+    fn read(&mut self, offset: usize) -> Result<(Vec<IfdEntry>, u32), ScrubError> {
+        if self.visited.contains(&offset) {
+            return Err(ScrubError::ParsingError(format!(
+                "IFD at offset {} is referenced twice",
+                offset
+            )));
+        }
+        self.visited.push(offset);
+        read_ifd(self.bytes, self.order, offset)
+    }
+
+    fn image_ifd(&mut self, offset: usize, kind: IfdKind) -> Result<(ImageIfd, u32), ScrubError> {
+        let (mut entries, next) = self.read(offset)?;
+        let order = self.order;
+        let mut ifd = ImageIfd {
+            kind,
+            entries: Vec::new(),
+            data: Vec::new(),
+            sub_ifds: Vec::new(),
+            exif: Vec::new(),
+            interop: Vec::new(),
+            gps: Vec::new(),
+        };
+
+        let sub_offsets = offsets_of(&entries, TAG_SUB_IFDS, order).unwrap_or_default();
+        if !sub_offsets.is_empty() && self.depth >= MAX_DEPTH {
+            return Err(ScrubError::ParsingError("SubIFDs nested too deeply".into()));
+        }
+        self.depth += 1;
+        for sub_offset in sub_offsets {
+            ifd.sub_ifds.push(self.image_ifd(sub_offset, kind)?.0);
+        }
+        self.depth -= 1;
+        if let Some(&exif_offset) = offsets_of(&entries, TAG_EXIF_IFD_POINTER, order)
+            .as_deref()
+            .and_then(<[usize]>::first)
+        {
+            ifd.exif = self.read(exif_offset)?.0;
+            if let Some(&interop_offset) = offsets_of(&ifd.exif, TAG_INTEROP_IFD_POINTER, order)
+                .as_deref()
+                .and_then(<[usize]>::first)
+            {
+                ifd.interop = self.read(interop_offset)?.0;
+            }
+            ifd.exif.retain(|e| e.tag != TAG_INTEROP_IFD_POINTER);
+        }
+        if let Some(&gps_offset) = offsets_of(&entries, TAG_GPS_IFD_POINTER, order)
+            .as_deref()
+            .and_then(<[usize]>::first)
+        {
+            ifd.gps = self.read(gps_offset)?.0;
+        }
+
+        for &(offsets_tag, counts_tag) in DATA_TAGS {
+            let Some(offsets) = offsets_of(&entries, offsets_tag, order) else {
+                continue;
+            };
+            let counts = offsets_of(&entries, counts_tag, order).unwrap_or_default();
+            if counts.len() != offsets.len() {
+                return Err(ScrubError::ParsingError(format!(
+                    "Image data offsets in IFD at {} have no matching byte counts",
+                    offset
+                )));
+            }
+            let ranges = offsets
+                .into_iter()
+                .zip(counts)
+                .map(|(start, len)| start..start.saturating_add(len))
+                .collect::<Vec<_>>();
+            if ranges.iter().any(|r| r.end > self.bytes.len()) {
+                return Err(ScrubError::ParsingError(format!(
+                    "Image data in IFD at {} runs past the end of the file",
+                    offset
+                )));
+            }
+            ifd.data.push(DataBlock {
+                offsets_tag,
+                ranges,
+            });
+        }
+
+        entries.retain(|e| {
+            !matches!(
+                e.tag,
+                TAG_SUB_IFDS | TAG_EXIF_IFD_POINTER | TAG_GPS_IFD_POINTER
+            ) && !DATA_TAGS.iter().any(|(tag, _)| *tag == e.tag)
+                && !FREE_TAGS.contains(&e.tag)
+        });
+        ifd.entries = entries;
+        Ok((ifd, next))
+    }
+}
+
+/// An IFD about to be written.
+struct Node<'a> {
+    entries: Vec<IfdEntry>,
+    /// Tags whose values are the offsets of other nodes.
+    pointers: Vec<(u16, Vec<usize>)>,
+    data: &'a [DataBlock],
+    next: Option<usize>,
+}
+
+impl<'a> Node<'a> {
+    fn new(entries: &[IfdEntry], data: &'a [DataBlock]) -> Self {
+        Self {
+            entries: entries.to_vec(),
+            pointers: Vec::new(),
+            data,
+            next: None,
+        }
+    }
+}
+
+/// Appends an image IFD and everything it points to, returning its index.
+fn flatten<'a>(ifd: &'a ImageIfd, nodes: &mut Vec<Node<'a>>) -> usize {
+    let index = nodes.len();
+    nodes.push(Node::new(&ifd.entries, &ifd.data));
+
+    let sub_ifds: Vec<usize> = ifd.sub_ifds.iter().map(|s| flatten(s, nodes)).collect();
+    if !sub_ifds.is_empty() {
+        nodes[index].pointers.push((TAG_SUB_IFDS, sub_ifds));
+    }
+    if !ifd.exif.is_empty() || !ifd.interop.is_empty() {
+        let exif = nodes.len();
+        nodes.push(Node::new(&ifd.exif, &[]));
+        if !ifd.interop.is_empty() {
+            nodes.push(Node::new(&ifd.interop, &[]));
+            nodes[exif]
+                .pointers
+                .push((TAG_INTEROP_IFD_POINTER, vec![exif + 1]));
+        }
+        nodes[index]
+            .pointers
+            .push((TAG_EXIF_IFD_POINTER, vec![exif]));
+    }
+    if !ifd.gps.is_empty() {
+        nodes.push(Node::new(&ifd.gps, &[]));
+        let gps = nodes.len() - 1;
+        nodes[index].pointers.push((TAG_GPS_IFD_POINTER, vec![gps]));
+    }
+    index
+}
+
+fn replace_entry(entries: &mut [IfdEntry], entry: IfdEntry) {
+    if let Some(existing) = entries.iter_mut().find(|e| e.tag == entry.tag) {
+        *existing = entry;
+    }
+}
+
+/// A Scrubber implementation for TIFF files, including DNG.
+#[derive(Debug, Clone)]
+pub struct TiffScrubber {
+    file_bytes: Vec<u8>,
+    tiff: TiffFile,
+}
+
+impl Scrubber for TiffScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        let tiff = TiffFile::parse(&file_bytes)?;
+        Ok(Self { file_bytes, tiff })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        let order = self.tiff.order;
+        Ok(self
+            .tiff
+            .pages
+            .iter()
+            .flat_map(|page| page.metadata_entries(order))
+            .collect())
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let mut tiff = self.tiff.clone();
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        for page in &mut tiff.pages {
+            let (kept, removed) = page.apply_policy(tiff.order, policy);
+            metadata_kept.extend(kept);
+            metadata_removed.extend(removed);
+        }
+
+        let cleaned_file_bytes = if metadata_removed.is_empty() {
+            self.file_bytes.clone()
+        } else {
+            tiff.to_bytes(&self.file_bytes)?
+        };
+        Ok(ScrubResult {
+            cleaned_file_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MetadataCategory, Selector};
+
+    fn short(tag: u16, value: u16) -> IfdEntry {
+        IfdEntry {
+            tag,
+            field_type: 3,
+            count: 1,
+            data: ByteOrder::Little.u16_bytes(value).to_vec(),
+        }
+    }
+
+    fn long(tag: u16, value: u32) -> IfdEntry {
+        long_entry(tag, &[value], ByteOrder::Little)
+    }
+
+    fn ascii(tag: u16, text: &str) -> IfdEntry {
+        let mut data = text.as_bytes().to_vec();
+        data.push(0);
+        IfdEntry {
+            tag,
+            field_type: 2,
+            count: data.len() as u32,
+            data,
+        }
+    }
+
+    fn undefined(tag: u16, data: &[u8]) -> IfdEntry {
+        IfdEntry {
+            tag,
+            field_type: 7,
+            count: data.len() as u32,
+            data: data.to_vec(),
+        }
+    }
+
+    /// A one-row, two-pixel grayscale page stored in a single strip.
+    fn page(strip_offset: u32, extra: Vec<IfdEntry>) -> Vec<IfdEntry> {
+        let mut entries = vec![
+            short(0x0100, 2),
+            short(0x0101, 1),
+            short(0x0102, 8),
+            short(0x0103, 1),
+            short(0x0106, 1),
+            long(0x0111, strip_offset),
+            short(0x0116, 1),
+            long(0x0117, 2),
+        ];
+        entries.extend(extra);
+        entries.sort_by_key(|e| e.tag);
+        entries
+    }
+
+    /// Builds a little-endian TIFF with two pages. The first has Make, Artist,
+    /// an ICC profile, an EXIF IFD and a GPS IFD.
+    fn tiff() -> Vec<u8> {
+        let exif = vec![ascii(0x9003, "2024:05:01 14:30:00")];
+        let gps = vec![ascii(0x0001, "N")];
+        let metadata = |exif_at, gps_at| {
+            vec![
+                ascii(0x010F, "ACME"),
+                undefined(0x8773, b"profile"),
+                ascii(0x013B, "Jane Doe"),
+                long(TAG_EXIF_IFD_POINTER, exif_at),
+                long(TAG_GPS_IFD_POINTER, gps_at),
+            ]
+        };
+
+        let page0_size = ifd_size(&page(0, metadata(0, 0)));
+        let exif_at = 8 + page0_size;
+        let gps_at = exif_at + ifd_size(&exif);
+        let page1_at = gps_at + ifd_size(&gps);
+        let strips_at = page1_at + ifd_size(&page(0, Vec::new()));
+
+        let page0 = page(strips_at as u32, metadata(exif_at as u32, gps_at as u32));
+        let page1 = page(strips_at as u32 + 2, Vec::new());
+        let mut out = b"II*\0\x08\0\0\0".to_vec();
+        write_ifd(&mut out, ByteOrder::Little, 8, &page0, page1_at as u32);
+        write_ifd(&mut out, ByteOrder::Little, exif_at, &exif, 0);
+        write_ifd(&mut out, ByteOrder::Little, gps_at, &gps, 0);
+        write_ifd(&mut out, ByteOrder::Little, page1_at, &page1, 0);
+        out.extend_from_slice(&[0x10, 0x20, 0x30, 0x40]);
+        out
+    }
+
+    fn strips(scrubber: &TiffScrubber) -> Vec<&[u8]> {
+        scrubber
+            .tiff
+            .pages
+            .iter()
+            .flat_map(|page| &page.data[0].ranges)
+            .map(|range| &scrubber.file_bytes[range.clone()])
+            .collect()
+    }
+
+    #[test]
+    fn view_metadata_skips_image_tags() {
+        let scrubber = TiffScrubber::new(tiff()).unwrap();
+        let entries = scrubber.view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(
+            keys,
+            ["Make", "Artist", "DateTimeOriginal", "GPSLatitudeRef"]
+        );
+        assert_eq!(entries[3].category, MetadataCategory::Gps);
+        assert_eq!(strips(&scrubber), [&[0x10, 0x20], &[0x30, 0x40]]);
+    }
+
+    #[test]
+    fn scrub_rewrites_ifds_and_moves_image_data() {
+        let result = TiffScrubber::new(tiff()).unwrap().scrub().unwrap();
+        assert_eq!(result.metadata_removed.len(), 4);
+
+        let cleaned = TiffScrubber::new(result.cleaned_file_bytes).unwrap();
+        assert!(cleaned.view_metadata().unwrap().is_empty());
+        assert_eq!(cleaned.tiff.pages.len(), 2);
+        assert_eq!(cleaned.tiff.pages[0].entries.len(), 8);
+        let icc = cleaned.tiff.pages[0]
+            .entries
+            .iter()
+            .find(|e| e.tag == 0x8773);
+        assert_eq!(icc.map(|e| e.data.as_slice()), Some(&b"profile"[..]));
+        assert_eq!(strips(&cleaned), [&[0x10, 0x20], &[0x30, 0x40]]);
+    }
+
+    #[test]
+    fn scrub_with_policy_keeps_selected_tags() {
+        let policy = ScrubPolicy::remove_all()
+            .keep(Selector::Key("Make".into()))
+            .keep(Selector::Category(MetadataCategory::Exif));
+        let result = TiffScrubber::new(tiff())
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        let cleaned = TiffScrubber::new(result.cleaned_file_bytes).unwrap();
+        assert_eq!(cleaned.view_metadata().unwrap(), result.metadata_kept);
+        assert_eq!(result.metadata_kept.len(), 2);
+        assert!(cleaned.tiff.pages[0].gps.is_empty());
+        assert_eq!(strips(&cleaned), [&[0x10, 0x20], &[0x30, 0x40]]);
+    }
+
+    #[test]
+    fn deeply_nested_sub_ifds_are_an_error() {
+        // A chain of 1000 IFDs, each holding only a pointer to the next.
+        let mut out = b"II*\0\x08\0\0\0".to_vec();
+        let size = ifd_size(&[long(TAG_SUB_IFDS, 0)]);
+        for i in 0..1000 {
+            let at = 8 + size * i;
+            write_ifd(
+                &mut out,
+                ByteOrder::Little,
+                at,
+                &[long(TAG_SUB_IFDS, (at + size) as u32)],
+                0,
+            );
+        }
+
+        match TiffFile::parse(&out) {
+            Err(ScrubError::ParsingError(message)) => assert!(message.contains("nested")),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
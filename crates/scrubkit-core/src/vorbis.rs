@@ -0,0 +1,213 @@
+// File: crates/scrubkit-core/src/vorbis.rs
+
+//! Vorbis comments, the tags of FLAC, Ogg Vorbis and Opus files.
+//!
+//! A comment block is a vendor string naming the encoder library, followed
+//! by a list of `FIELD=value` strings. Field names are case-insensitive and
+//! may repeat. All lengths are 32-bit little-endian. Ogg files embed cover
+//! art as a base64-encoded FLAC picture block in a `METADATA_BLOCK_PICTURE`
+//! comment.
+
+use crate::flac::Picture;
+use crate::xmp::looks_like_date;
+use crate::{MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy};
+
+const PICTURE_FIELD: &str = "METADATA_BLOCK_PICTURE";
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Option<usize> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// Reads a length-prefixed string, returning it and the offset after it.
+fn read_string(bytes: &[u8], offset: usize) -> Option<(Vec<u8>, usize)> {
+    let len = read_u32_le(bytes, offset)?;
+    let text = bytes.get(offset + 4..offset + 4 + len)?;
+    Some((text.to_vec(), offset + 4 + len))
+}
+
+fn write_string(out: &mut Vec<u8>, text: &[u8]) {
+    out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    out.extend_from_slice(text);
+}
+
+/// Decodes standard base64, ignoring padding. Returns `None` on any other
+/// character.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes().filter(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// A Vorbis comment block. Strings are kept as raw bytes, so the ones a
+/// policy keeps are written back exactly as they were.
+pub(crate) struct VorbisComment {
+    vendor: Vec<u8>,
+    comments: Vec<Vec<u8>>,
+}
+
+impl VorbisComment {
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self, ScrubError> {
+        Self::parse_prefix(bytes).map(|(comment, _)| comment)
+    }
+
+    /// Parses a comment block that is followed by other data, as in Ogg
+    /// comment packets. Returns the block and the data after it.
+    pub(crate) fn parse_prefix(bytes: &[u8]) -> Result<(Self, &[u8]), ScrubError> {
+        let corrupt = || ScrubError::ParsingError("Corrupt Vorbis comment block".into());
+        let (vendor, mut pos) = read_string(bytes, 0).ok_or_else(corrupt)?;
+        let count = read_u32_le(bytes, pos).ok_or_else(corrupt)?;
+        pos += 4;
+        let mut comments = Vec::new();
+        for _ in 0..count {
+            let (comment, next) = read_string(bytes, pos).ok_or_else(corrupt)?;
+            comments.push(comment);
+            pos = next;
+        }
+        Ok((Self { vendor, comments }, &bytes[pos..]))
+    }
+
+    fn vendor_entry(&self) -> Option<MetadataEntry> {
+        (!self.vendor.is_empty()).then(|| {
+            MetadataEntry::new(
+                MetadataCategory::VorbisComment,
+                "Vendor",
+                MetadataValue::Text(String::from_utf8_lossy(&self.vendor).into_owned()),
+            )
+        })
+    }
+
+    fn comment_entry(comment: &[u8]) -> MetadataEntry {
+        let comment = String::from_utf8_lossy(comment);
+        let (field, value) = comment.split_once('=').unwrap_or((&comment, ""));
+        let field = field.to_ascii_uppercase();
+        if field == PICTURE_FIELD
+            && let Some(data) = decode_base64(value)
+            && let Some(picture) = Picture::parse(&data)
+        {
+            let len = picture.image(&data).len();
+            return MetadataEntry::new(
+                MetadataCategory::VorbisComment,
+                field,
+                MetadataValue::Binary { len },
+            )
+            .with_display_value(picture.describe(&data));
+        }
+        let value = if looks_like_date(value) {
+            MetadataValue::DateTime(value.to_string())
+        } else {
+            MetadataValue::Text(value.to_string())
+        };
+        MetadataEntry::new(MetadataCategory::VorbisComment, field, value)
+    }
+
+    /// Describes the vendor string and every comment.
+    pub(crate) fn metadata_entries(&self) -> Vec<MetadataEntry> {
+        self.vendor_entry()
+            .into_iter()
+            .chain(self.comments.iter().map(|c| Self::comment_entry(c)))
+            .collect()
+    }
+
+    /// Drops every comment the policy does not keep. A removed vendor string
+    /// is emptied, since the block can't do without one.
+    /// Returns the `(kept, removed)` entries.
+    pub(crate) fn apply_policy(
+        &mut self,
+        policy: &ScrubPolicy,
+    ) -> (Vec<MetadataEntry>, Vec<MetadataEntry>) {
+        let (mut kept, mut removed) = (Vec::new(), Vec::new());
+        if let Some(entry) = self.vendor_entry() {
+            if policy.keeps(&entry) {
+                kept.push(entry);
+            } else {
+                removed.push(entry);
+                self.vendor.clear();
+            }
+        }
+        self.comments.retain(|comment| {
+            let entry = Self::comment_entry(comment);
+            let keep = policy.keeps(&entry);
+            if keep {
+                kept.push(entry);
+            } else {
+                removed.push(entry);
+            }
+            keep
+        });
+        (kept, removed)
+    }
+
+    /// Returns true if there is neither a vendor string nor any comment left.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.vendor.is_empty() && self.comments.is_empty()
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_string(&mut out, &self.vendor);
+        out.extend_from_slice(&(self.comments.len() as u32).to_le_bytes());
+        for comment in &self.comments {
+            write_string(&mut out, comment);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(vendor: &str, comments: &[&str]) -> Vec<u8> {
+        VorbisComment {
+            vendor: vendor.into(),
+            comments: comments.iter().map(|c| c.as_bytes().to_vec()).collect(),
+        }
+        .to_bytes()
+    }
+
+    #[test]
+    fn parse_lists_vendor_and_comments() {
+        let bytes = block(
+            "Lavf60.3.100",
+            &["title=Song", "Date=2024-05-01", "ARTIST=Jane"],
+        );
+        let entries = VorbisComment::parse(&bytes).unwrap().metadata_entries();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(keys, ["Vendor", "TITLE", "DATE", "ARTIST"]);
+        assert_eq!(
+            entries[2].value,
+            MetadataValue::DateTime("2024-05-01".into())
+        );
+    }
+
+    #[test]
+    fn apply_policy_keeps_selected_fields() {
+        let mut comment =
+            VorbisComment::parse(&block("Lavf60.3.100", &["TITLE=Song", "ARTIST=Jane"])).unwrap();
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("TITLE".into()));
+        let (kept, removed) = comment.apply_policy(&policy);
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(comment.to_bytes(), block("", &["TITLE=Song"]));
+    }
+}
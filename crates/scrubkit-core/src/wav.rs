@@ -0,0 +1,412 @@
+// File: crates/scrubkit-core/src/wav.rs
+
+//! WAV support.
+//!
+//! A WAV file is a RIFF container with the `WAVE` form type. Besides the
+//! format and audio data chunks, recorders and editors add metadata chunks:
+//! `LIST` chunks of type `INFO` with text fields, Broadcast WAV `bext`
+//! chunks with the originator, origination time and coding history, `iXML`
+//! chunks with project, scene and take notes, and embedded ID3v2 tags and
+//! XMP. INFO lists and ID3 tags are filtered field by field; other chunks
+//! are kept or removed whole.
+
+use crate::id3::Id3v2;
+use crate::iff::{self, ByteOrder, Chunk};
+use crate::xmp::{looks_like_date, parse_xmp};
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+use roxmltree::Document;
+
+const ORDER: ByteOrder = ByteOrder::Little;
+
+/// Chunks that hold or describe the audio, and padding.
+const AUDIO_CHUNKS: &[&[u8; 4]] = &[
+    b"fmt ", b"data", b"fact", b"cue ", b"smpl", b"inst", b"plst", b"PEAK", b"JUNK", b"PAD ",
+    b"FLLR",
+];
+
+/// The text fields of INFO lists, with the names we show for them.
+const INFO_FIELDS: &[(&[u8; 4], &str)] = &[
+    (b"IART", "Artist"),
+    (b"ICMS", "Commissioned"),
+    (b"ICMT", "Comment"),
+    (b"ICOP", "Copyright"),
+    (b"ICRD", "CreationDate"),
+    (b"IDIT", "DigitizationDate"),
+    (b"IENG", "Engineer"),
+    (b"IGNR", "Genre"),
+    (b"IKEY", "Keywords"),
+    (b"IMED", "Medium"),
+    (b"INAM", "Title"),
+    (b"IPRD", "Product"),
+    (b"ISBJ", "Subject"),
+    (b"ISFT", "Software"),
+    (b"ISRC", "Source"),
+    (b"ISRF", "SourceForm"),
+    (b"ITCH", "Technician"),
+    (b"ITRK", "Track"),
+];
+
+/// Returns true for RIFF files with the `WAVE` form type.
+pub fn is_wav(bytes: &[u8]) -> bool {
+    iff::has_form(bytes, b"RIFF", &[b"WAVE"])
+}
+
+fn is_metadata(chunk: &Chunk) -> bool {
+    !AUDIO_CHUNKS.contains(&&chunk.fourcc)
+}
+
+fn is_info(chunk: &Chunk) -> bool {
+    &chunk.fourcc == b"LIST" && chunk.data.starts_with(b"INFO")
+}
+
+fn is_id3(chunk: &Chunk) -> bool {
+    &chunk.fourcc == b"id3 " || &chunk.fourcc == b"ID3 "
+}
+
+/// Reads a fixed-size or NUL-terminated text field.
+fn text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
+
+fn text_value(text: String) -> MetadataValue {
+    if looks_like_date(&text) {
+        MetadataValue::DateTime(text)
+    } else {
+        MetadataValue::Text(text)
+    }
+}
+
+fn info_entry(field: &Chunk) -> MetadataEntry {
+    let key = INFO_FIELDS
+        .iter()
+        .find(|(fourcc, _)| **fourcc == field.fourcc)
+        .map_or_else(|| field.name(), |(_, name)| name.to_string());
+    MetadataEntry::new(
+        MetadataCategory::RiffInfo,
+        key,
+        text_value(text(field.data)),
+    )
+}
+
+/// Describes the fields of a Broadcast WAV `bext` chunk.
+fn bext_entries(data: &[u8]) -> Vec<MetadataEntry> {
+    let field = |start: usize, end: usize| data.get(start..end).map(text).unwrap_or_default();
+    let entry = |key: &str, value| MetadataEntry::new(MetadataCategory::BroadcastWave, key, value);
+    let mut entries = Vec::new();
+
+    for (key, start, end) in [
+        ("Description", 0, 256),
+        ("Originator", 256, 288),
+        ("OriginatorReference", 288, 320),
+    ] {
+        let value = field(start, end);
+        if !value.is_empty() {
+            entries.push(entry(key, MetadataValue::Text(value)));
+        }
+    }
+    // The date and time are `yyyy-mm-dd` and `hh:mm:ss`, though any
+    // separator is allowed.
+    let date = field(320, 330);
+    if !date.is_empty() {
+        let mut stamp = date.replace(|c: char| !c.is_ascii_digit(), "-");
+        let time = field(330, 338);
+        if !time.is_empty() {
+            stamp = format!(
+                "{}T{}",
+                stamp,
+                time.replace(|c: char| !c.is_ascii_digit(), ":")
+            );
+        }
+        entries.push(entry("OriginationDate", MetadataValue::DateTime(stamp)));
+    }
+    // The first sample's position, counted in samples since midnight.
+    if let Some(bytes) = data.get(338..346) {
+        let samples = u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+        if samples != 0 {
+            entries.push(entry(
+                "TimeReference",
+                MetadataValue::Integer(samples as i64),
+            ));
+        }
+    }
+    if let Some(umid) = data.get(348..412).filter(|u| u.iter().any(|&b| b != 0)) {
+        let hex = umid.iter().map(|b| format!("{:02X}", b)).collect();
+        entries.push(entry("UMID", MetadataValue::Text(hex)));
+    }
+    let history = data.get(602..).map(text).unwrap_or_default();
+    if !history.is_empty() {
+        entries.push(entry("CodingHistory", MetadataValue::Text(history)));
+    }
+    entries
+}
+
+/// Describes the text elements of an `iXML` chunk, keyed by their path
+/// below the `BWFXML` root, e.g. `TRACK_LIST/TRACK/NAME`.
+fn ixml_entries(data: &[u8]) -> Vec<MetadataEntry> {
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    let Ok(doc) = Document::parse(text) else {
+        return Vec::new();
+    };
+    doc.root_element()
+        .descendants()
+        .filter(|n| n.is_element() && n.children().all(|c| c.is_text()))
+        .filter_map(|n| {
+            let value = n.text()?.trim();
+            if value.is_empty() {
+                return None;
+            }
+            let mut path: Vec<&str> = n
+                .ancestors()
+                .filter(|a| a.is_element() && *a != doc.root_element())
+                .map(|a| a.tag_name().name())
+                .collect();
+            path.reverse();
+            Some(MetadataEntry::new(
+                MetadataCategory::BroadcastWave,
+                path.join("/"),
+                text_value(value.to_string()),
+            ))
+        })
+        .collect()
+}
+
+/// Describes the metadata in a chunk. Chunks we can't read are shown as
+/// opaque data, so they are still listed and removed.
+fn metadata_entries(chunk: &Chunk) -> Vec<MetadataEntry> {
+    let entries = match &chunk.fourcc {
+        b"LIST" if is_info(chunk) => iff::list_chunks(chunk.data, ORDER, "WAV")
+            .map(|fields| fields.iter().map(info_entry).collect())
+            .unwrap_or_default(),
+        b"bext" => bext_entries(chunk.data),
+        b"iXML" => ixml_entries(chunk.data),
+        b"_PMX" => parse_xmp(chunk.data),
+        _ if is_id3(chunk) => Id3v2::parse(chunk.data)
+            .map(|id3| id3.metadata_entries())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    if !entries.is_empty() {
+        return entries;
+    }
+    let (category, key) = match &chunk.fourcc {
+        b"LIST" if is_info(chunk) => (MetadataCategory::RiffInfo, "INFO".to_string()),
+        b"bext" | b"iXML" => (MetadataCategory::BroadcastWave, chunk.name()),
+        _ if is_id3(chunk) => (MetadataCategory::Id3, "ID3".to_string()),
+        // Other lists, such as `adtl` cue labels, are named by their type.
+        b"LIST" => (
+            MetadataCategory::Vendor,
+            format!(
+                "LIST {}",
+                String::from_utf8_lossy(&chunk.data[..4.min(chunk.data.len())])
+            ),
+        ),
+        _ => (MetadataCategory::Vendor, chunk.name()),
+    };
+    vec![MetadataEntry::new(
+        category,
+        key,
+        MetadataValue::Binary {
+            len: chunk.data.len(),
+        },
+    )]
+}
+
+/// A Scrubber implementation for WAV files, including Broadcast WAV.
+#[derive(Debug, Clone)]
+pub struct WavScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for WavScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        if !is_wav(&file_bytes) {
+            return Err(ScrubError::ParsingError("Not a valid WAV file".into()));
+        }
+        iff::chunks(&file_bytes, ORDER, "WAV")?;
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        Ok(iff::chunks(&self.file_bytes, ORDER, "WAV")?
+            .iter()
+            .filter(|chunk| is_metadata(chunk))
+            .flat_map(metadata_entries)
+            .collect())
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let chunks = iff::chunks(&self.file_bytes, ORDER, "WAV")?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut output = Vec::new();
+
+        for chunk in &chunks {
+            if !is_metadata(chunk) {
+                output.push(chunk.bytes.to_vec());
+                continue;
+            }
+            // INFO lists are filtered field by field.
+            if is_info(chunk)
+                && let Ok(fields) = iff::list_chunks(chunk.data, ORDER, "WAV")
+                && !fields.is_empty()
+            {
+                let mut list = b"INFO".to_vec();
+                let removed_before = metadata_removed.len();
+                for field in &fields {
+                    let entry = info_entry(field);
+                    if policy.keeps(&entry) {
+                        list.extend_from_slice(field.bytes);
+                        metadata_kept.push(entry);
+                    } else {
+                        metadata_removed.push(entry);
+                    }
+                }
+                if metadata_removed.len() == removed_before {
+                    output.push(chunk.bytes.to_vec());
+                } else if list.len() > 4 {
+                    output.push(iff::write_chunk(b"LIST", &list, ORDER)?);
+                }
+                continue;
+            }
+            // ID3 tags are filtered frame by frame, as in MP3 files.
+            if is_id3(chunk)
+                && let Ok(mut id3) = Id3v2::parse(chunk.data)
+            {
+                let (kept, removed) = id3.apply_policy(policy);
+                if removed.is_empty() {
+                    output.push(chunk.bytes.to_vec());
+                } else if !id3.is_empty() {
+                    output.push(iff::write_chunk(&chunk.fourcc, &id3.to_bytes(), ORDER)?);
+                }
+                metadata_kept.extend(kept);
+                metadata_removed.extend(removed);
+                continue;
+            }
+            let entries = metadata_entries(chunk);
+            if entries.iter().all(|e| policy.keeps(e)) {
+                output.push(chunk.bytes.to_vec());
+                metadata_kept.extend(entries);
+            } else {
+                metadata_removed.extend(entries);
+            }
+        }
+
+        let cleaned_bytes = if metadata_removed.is_empty() {
+            self.file_bytes.clone()
+        } else {
+            iff::write_container(&self.file_bytes, &output, ORDER)?
+        };
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut all = vec![
+            iff::write_chunk(b"fmt ", &[1, 0, 1, 0, 0x80, 0xBB, 0, 0], ORDER).unwrap(),
+            iff::write_chunk(b"data", &[0; 6], ORDER).unwrap(),
+        ];
+        all.extend_from_slice(chunks);
+        iff::write_container(b"RIFF\0\0\0\0WAVE", &all, ORDER).unwrap()
+    }
+
+    fn info(fields: &[(&[u8; 4], &str)]) -> Vec<u8> {
+        let mut list = b"INFO".to_vec();
+        for (fourcc, value) in fields {
+            let text = [value.as_bytes(), b"\0"].concat();
+            list.extend(iff::write_chunk(fourcc, &text, ORDER).unwrap());
+        }
+        iff::write_chunk(b"LIST", &list, ORDER).unwrap()
+    }
+
+    fn bext() -> Vec<u8> {
+        let mut data = vec![0; 602];
+        data[0..9].copy_from_slice(b"Interview");
+        data[256..265].copy_from_slice(b"ZOOM Hn-6");
+        data[320..330].copy_from_slice(b"2024-05-01");
+        data[330..338].copy_from_slice(b"14.30.00");
+        data[338..346].copy_from_slice(&2_592_000_000u64.to_le_bytes());
+        data.extend_from_slice(b"A=PCM,F=48000,W=24,M=stereo,T=ZOOM Hn-6\r\n");
+        iff::write_chunk(b"bext", &data, ORDER).unwrap()
+    }
+
+    #[test]
+    fn view_metadata_reads_bext_info_and_ixml() {
+        let ixml = b"<?xml version=\"1.0\"?><BWFXML><PROJECT>Doc Shoot</PROJECT>\
+            <SPEED><NOTE>Take 3</NOTE></SPEED></BWFXML>";
+        let file = wav(&[
+            bext(),
+            iff::write_chunk(b"iXML", ixml, ORDER).unwrap(),
+            info(&[(b"IART", "Jane Doe"), (b"ICRD", "2024-05-01")]),
+        ]);
+        let entries = WavScrubber::new(file).unwrap().view_metadata().unwrap();
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+
+        assert_eq!(
+            keys,
+            [
+                "Description",
+                "Originator",
+                "OriginationDate",
+                "TimeReference",
+                "CodingHistory",
+                "PROJECT",
+                "SPEED/NOTE",
+                "Artist",
+                "CreationDate",
+            ]
+        );
+        assert_eq!(
+            entries[2].value,
+            MetadataValue::DateTime("2024-05-01T14:30:00".into())
+        );
+    }
+
+    #[test]
+    fn scrub_filters_info_fields_and_fixes_the_riff_size() {
+        let file = wav(&[bext(), info(&[(b"INAM", "Song"), (b"IENG", "Jane Doe")])]);
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::Key("Title".into()));
+        let result = WavScrubber::new(file).unwrap().scrub_with(&policy).unwrap();
+
+        assert_eq!(result.metadata_kept.len(), 1);
+        assert_eq!(result.metadata_removed.len(), 6);
+        assert_eq!(
+            result.cleaned_file_bytes,
+            wav(&[info(&[(b"INAM", "Song")])])
+        );
+    }
+
+    #[test]
+    fn scrub_keeps_audio_chunks_and_drops_unknown_ones() {
+        let file = wav(&[iff::write_chunk(b"cart", b"station", ORDER).unwrap()]);
+        let result = WavScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "cart");
+        assert_eq!(result.cleaned_file_bytes, wav(&[]));
+    }
+
+    #[test]
+    fn id3_chunks_without_readable_frames_are_removed() {
+        // The frame size runs past the end of the tag.
+        let tag = b"ID3\x03\x00\x00\x00\x00\x00\x13TIT2\x00\x00\x01\x00\x00\x00\x00Jane Doe";
+        let file = wav(&[iff::write_chunk(b"id3 ", tag, ORDER).unwrap()]);
+        let scrubber = WavScrubber::new(file).unwrap();
+
+        let entries = scrubber.view_metadata().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, "ID3v2");
+        let result = scrubber.scrub().unwrap();
+        assert_eq!(result.metadata_removed, entries);
+        assert_eq!(result.cleaned_file_bytes, wav(&[]));
+    }
+}
@@ -0,0 +1,315 @@
+// File: crates/scrubkit-core/src/webp.rs
+
+//! WebP support.
+//!
+//! A WebP file is a RIFF container: a `RIFF` header, the `WEBP` form type and
+//! a list of chunks, each with a FourCC, a little-endian size and data padded
+//! to an even length. Extended files start with a VP8X chunk whose flags say
+//! which optional chunks (ICC profile, EXIF, XMP, animation) are present.
+
+use crate::exif::ExifData;
+use crate::xmp::parse_xmp;
+use crate::{
+    MetadataCategory, MetadataEntry, MetadataValue, ScrubError, ScrubPolicy, ScrubResult, Scrubber,
+};
+
+/// Chunks that hold image data or describe how to show it. The ICC profile
+/// is kept, as in PNG files, so scrubbing doesn't change the image's colors.
+const IMAGE_CHUNKS: &[&[u8; 4]] = &[
+    b"VP8 ", b"VP8L", b"VP8X", b"ALPH", b"ANIM", b"ANMF", b"ICCP",
+];
+
+// VP8X flag bits.
+const FLAG_EXIF: u8 = 0x08;
+const FLAG_XMP: u8 = 0x04;
+
+/// A chunk, borrowed from the file.
+#[derive(Debug, Clone, Copy)]
+struct Chunk<'a> {
+    fourcc: [u8; 4],
+    data: &'a [u8],
+    /// The whole chunk, including its header and padding byte.
+    bytes: &'a [u8],
+}
+
+impl Chunk<'_> {
+    fn is_metadata(&self) -> bool {
+        !IMAGE_CHUNKS.contains(&&self.fourcc)
+    }
+
+    /// The TIFF structure in an EXIF chunk. Some encoders keep the JPEG
+    /// `Exif\0\0` prefix, which isn't part of the spec.
+    fn tiff(&self) -> &[u8] {
+        self.data.strip_prefix(b"Exif\0\0").unwrap_or(self.data)
+    }
+
+    /// Describes the metadata in this chunk.
+    fn metadata_entries(&self) -> Vec<MetadataEntry> {
+        let (category, key) = match &self.fourcc {
+            b"EXIF" => match ExifData::parse(self.tiff()) {
+                Ok(exif) => return exif.metadata_entries(),
+                Err(_) => (MetadataCategory::Exif, "EXIF".to_string()),
+            },
+            b"XMP " => return parse_xmp(self.data),
+            fourcc => (
+                MetadataCategory::Vendor,
+                String::from_utf8_lossy(fourcc).trim_end().to_string(),
+            ),
+        };
+        vec![MetadataEntry::new(
+            category,
+            key,
+            MetadataValue::Binary {
+                len: self.data.len(),
+            },
+        )]
+    }
+}
+
+/// Splits the chunks of a WebP file.
+fn chunks(bytes: &[u8]) -> Result<Vec<Chunk<'_>>, ScrubError> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WEBP" {
+        return Err(ScrubError::ParsingError("Not a valid WebP file".into()));
+    }
+    // Trust the RIFF size over the file length, ignoring anything appended.
+    let riff_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let end = riff_size.saturating_add(8).min(bytes.len());
+
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= end {
+        let header = &bytes[offset..offset + 8];
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let data = size
+            .checked_add(offset + 8)
+            .and_then(|data_end| bytes.get(offset + 8..data_end))
+            .ok_or_else(|| {
+                ScrubError::ParsingError(format!("Corrupt WebP chunk at offset {}", offset))
+            })?;
+        let chunk_end = (offset + 8 + size.next_multiple_of(2)).min(end);
+        chunks.push(Chunk {
+            fourcc: [header[0], header[1], header[2], header[3]],
+            data,
+            bytes: &bytes[offset..chunk_end],
+        });
+        offset = chunk_end;
+    }
+    Ok(chunks)
+}
+
+/// Builds a chunk, padding its data to an even length.
+fn write_chunk(fourcc: &[u8; 4], data: &[u8]) -> Result<Vec<u8>, ScrubError> {
+    let size = u32::try_from(data.len())
+        .map_err(|_| ScrubError::ParsingError("WebP chunk too large".into()))?;
+    let mut bytes = fourcc.to_vec();
+    bytes.extend_from_slice(&size.to_le_bytes());
+    bytes.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    Ok(bytes)
+}
+
+/// A Scrubber implementation for WebP files.
+#[derive(Debug, Clone)]
+pub struct WebpScrubber {
+    file_bytes: Vec<u8>,
+}
+
+impl Scrubber for WebpScrubber {
+    fn new(file_bytes: Vec<u8>) -> Result<Self, ScrubError> {
+        chunks(&file_bytes)?;
+        Ok(Self { file_bytes })
+    }
+
+    fn view_metadata(&self) -> Result<Vec<MetadataEntry>, ScrubError> {
+        Ok(chunks(&self.file_bytes)?
+            .iter()
+            .filter(|chunk| chunk.is_metadata())
+            .flat_map(|chunk| chunk.metadata_entries())
+            .collect())
+    }
+
+    fn scrub_with(&self, policy: &ScrubPolicy) -> Result<ScrubResult, ScrubError> {
+        let chunks = chunks(&self.file_bytes)?;
+        let (mut metadata_kept, mut metadata_removed) = (Vec::new(), Vec::new());
+        let mut output: Vec<([u8; 4], Vec<u8>)> = Vec::new();
+
+        for chunk in &chunks {
+            if !chunk.is_metadata() {
+                output.push((chunk.fourcc, chunk.bytes.to_vec()));
+                continue;
+            }
+            // EXIF is filtered entry by entry, like in JPEG files.
+            if &chunk.fourcc == b"EXIF"
+                && let Ok(mut exif) = ExifData::parse(chunk.tiff())
+            {
+                let (kept, removed) = exif.apply_policy(policy);
+                if removed.is_empty() {
+                    output.push((chunk.fourcc, chunk.bytes.to_vec()));
+                } else if !exif.is_empty() {
+                    output.push((chunk.fourcc, write_chunk(b"EXIF", &exif.to_bytes())?));
+                }
+                metadata_kept.extend(kept);
+                metadata_removed.extend(removed);
+                continue;
+            }
+            let entries = chunk.metadata_entries();
+            if entries.iter().all(|e| policy.keeps(e)) {
+                output.push((chunk.fourcc, chunk.bytes.to_vec()));
+                metadata_kept.extend(entries);
+            } else {
+                metadata_removed.extend(entries);
+            }
+        }
+
+        if metadata_removed.is_empty() {
+            return Ok(ScrubResult {
+                cleaned_file_bytes: self.file_bytes.clone(),
+                metadata_removed,
+                metadata_kept,
+            });
+        }
+
+        // The VP8X flags must only announce the chunks that survived.
+        let mut flags = 0;
+        for (flag, fourcc) in [(FLAG_EXIF, b"EXIF"), (FLAG_XMP, b"XMP ")] {
+            if output.iter().any(|(f, _)| f == fourcc) {
+                flags |= flag;
+            }
+        }
+        let mut body = b"WEBP".to_vec();
+        for (fourcc, mut bytes) in output {
+            if &fourcc == b"VP8X" && bytes.len() > 8 {
+                bytes[8] = (bytes[8] & !(FLAG_EXIF | FLAG_XMP)) | flags;
+            }
+            body.extend(bytes);
+        }
+
+        let riff_size = u32::try_from(body.len())
+            .map_err(|_| ScrubError::ParsingError("WebP file too large".into()))?;
+        let mut cleaned_bytes = b"RIFF".to_vec();
+        cleaned_bytes.extend_from_slice(&riff_size.to_le_bytes());
+        cleaned_bytes.extend(body);
+
+        Ok(ScrubResult {
+            cleaned_file_bytes: cleaned_bytes,
+            metadata_removed,
+            metadata_kept,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAG_ICC: u8 = 0x20;
+
+    fn chunk(fourcc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        write_chunk(fourcc, data).unwrap()
+    }
+
+    fn webp(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = [b"WEBP".to_vec(), chunks.concat()].concat();
+        [
+            b"RIFF".as_slice(),
+            &(body.len() as u32).to_le_bytes(),
+            &body,
+        ]
+        .concat()
+    }
+
+    fn vp8x(flags: u8) -> Vec<u8> {
+        // Flags, three reserved bytes, then a 1x1 canvas.
+        chunk(b"VP8X", &[flags, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+    }
+
+    /// A big-endian TIFF with Make = "ACME" and Orientation = 1.
+    fn tiff() -> Vec<u8> {
+        [
+            b"MM\0*\0\0\0\x08\0\x02".as_slice(),
+            b"\x01\x0F\0\x02\0\0\0\x05\0\0\0\x26",
+            b"\x01\x12\0\x03\0\0\0\x01\0\x01\0\0",
+            b"\0\0\0\0ACME\0",
+        ]
+        .concat()
+    }
+
+    const XMP: &[u8] = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/" dc:creator="Jane"/></rdf:RDF></x:xmpmeta>"#;
+
+    fn image() -> Vec<u8> {
+        chunk(
+            b"VP8L",
+            &[0x2F, 0, 0, 0, 0x10, 0x07, 0x10, 0x11, 0x11, 0x88],
+        )
+    }
+
+    #[test]
+    fn view_metadata_reads_exif_and_xmp() {
+        let file = webp(&[
+            vp8x(FLAG_ICC | FLAG_EXIF | FLAG_XMP),
+            chunk(b"ICCP", b"profile"),
+            image(),
+            chunk(b"EXIF", &tiff()),
+            chunk(b"XMP ", XMP),
+        ]);
+        let scrubber = WebpScrubber::new(file).unwrap();
+        let keys: Vec<String> = scrubber
+            .view_metadata()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.key)
+            .collect();
+
+        assert_eq!(keys, ["Make", "Orientation", "dc:creator"]);
+    }
+
+    #[test]
+    fn scrub_removes_chunks_and_clears_their_flags_but_keeps_icc() {
+        let file = webp(&[
+            vp8x(FLAG_ICC | FLAG_EXIF | FLAG_XMP),
+            chunk(b"ICCP", b"profile"),
+            image(),
+            chunk(b"EXIF", &tiff()),
+            chunk(b"XMP ", XMP),
+        ]);
+        let result = WebpScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed.len(), 3);
+        assert_eq!(
+            result.cleaned_file_bytes,
+            webp(&[vp8x(FLAG_ICC), chunk(b"ICCP", b"profile"), image()])
+        );
+    }
+
+    #[test]
+    fn scrub_keeps_selected_exif_entries() {
+        let file = webp(&[vp8x(FLAG_EXIF), image(), chunk(b"EXIF", &tiff())]);
+        let policy = ScrubPolicy::remove_all().keep(crate::Selector::TagId(0x0112));
+        let result = WebpScrubber::new(file)
+            .unwrap()
+            .scrub_with(&policy)
+            .unwrap();
+
+        let scrubbed = WebpScrubber::new(result.cleaned_file_bytes).unwrap();
+        assert_eq!(scrubbed.view_metadata().unwrap(), result.metadata_kept);
+        assert_eq!(result.metadata_kept[0].key, "Orientation");
+    }
+
+    #[test]
+    fn empty_xmp_chunks_are_removed() {
+        let packet = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#;
+        let file = webp(&[vp8x(FLAG_XMP), image(), chunk(b"XMP ", packet)]);
+        let result = WebpScrubber::new(file).unwrap().scrub().unwrap();
+
+        assert_eq!(result.metadata_removed[0].key, "XMP");
+        assert_eq!(result.cleaned_file_bytes, webp(&[vp8x(0), image()]));
+    }
+
+    #[test]
+    fn oversized_chunk_sizes_are_an_error() {
+        let file = b"RIFF\xFF\xFF\xFF\xFFWEBPEXIF\xFF\xFF\xFF\xFF";
+        assert!(matches!(chunks(file), Err(ScrubError::ParsingError(_))));
+    }
+}
@@ -0,0 +1,239 @@
+// File: crates/scrubkit-core/src/xmp.rs
+
+//! Reads the properties out of an XMP packet.
+//!
+//! XMP is RDF serialized as XML. Properties show up either as attributes of
+//! an `rdf:Description` or as child elements, whose values may be plain text,
+//! an `rdf:Bag`/`rdf:Seq`/`rdf:Alt` array, or a nested structure.
+
+use crate::{MetadataCategory, MetadataEntry, MetadataValue};
+use roxmltree::{Document, Node};
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Parses an XMP packet into metadata entries.
+///
+/// Packets that aren't well-formed XML, or that hold no properties, are
+/// reported as a single binary entry, so their presence is never hidden.
+pub(crate) fn parse_xmp(packet: &[u8]) -> Vec<MetadataEntry> {
+    let opaque = || {
+        vec![MetadataEntry::new(
+            MetadataCategory::Xmp,
+            "XMP",
+            MetadataValue::Binary { len: packet.len() },
+        )]
+    };
+    let text = String::from_utf8_lossy(packet);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    let Ok(doc) = Document::parse(text) else {
+        return opaque();
+    };
+
+    let mut entries = Vec::new();
+    for description in doc
+        .descendants()
+        .filter(|n| is_rdf(n, "Description") && !n.ancestors().skip(1).any(is_property))
+    {
+        read_description(description, "", &mut entries);
+    }
+    if entries.is_empty() {
+        return opaque();
+    }
+    entries
+}
+
+/// Returns true for elements that are XMP properties rather than RDF syntax.
+fn is_property(node: Node) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() != Some(RDF_NS)
+        && node.ancestors().skip(1).any(|a| is_rdf(&a, "Description"))
+}
+
+fn is_rdf(node: &Node, name: &str) -> bool {
+    node.is_element()
+        && node.tag_name().namespace() == Some(RDF_NS)
+        && node.tag_name().name() == name
+}
+
+/// Builds a `prefix:name` key for an element or attribute.
+fn qualified_name(node: Node, namespace: Option<&str>, name: &str) -> String {
+    match namespace.and_then(|ns| node.lookup_prefix(ns)) {
+        Some(prefix) => format!("{}:{}", prefix, name),
+        None => name.to_string(),
+    }
+}
+
+/// Reads the properties of an `rdf:Description` (or a struct-valued property).
+fn read_description(node: Node, parent: &str, entries: &mut Vec<MetadataEntry>) {
+    for attribute in node.attributes() {
+        let namespace = attribute.namespace();
+        if namespace == Some(RDF_NS) || namespace == Some(XML_NS) {
+            continue;
+        }
+        let key = join_key(parent, &qualified_name(node, namespace, attribute.name()));
+        entries.push(entry(
+            key,
+            MetadataValue::Text(attribute.value().to_string()),
+        ));
+    }
+    for child in node.children().filter(|c| c.is_element()) {
+        if is_rdf(&child, "Description") {
+            read_description(child, parent, entries);
+            continue;
+        }
+        let tag = child.tag_name();
+        let key = join_key(parent, &qualified_name(child, tag.namespace(), tag.name()));
+        read_property(child, &key, entries);
+    }
+}
+
+/// Reads a single property element.
+fn read_property(node: Node, key: &str, entries: &mut Vec<MetadataEntry>) {
+    if let Some(array) = node
+        .children()
+        .find(|c| is_rdf(c, "Bag") || is_rdf(c, "Seq") || is_rdf(c, "Alt"))
+    {
+        let items: Vec<MetadataValue> = array
+            .children()
+            .filter(|c| is_rdf(c, "li"))
+            .map(|li| MetadataValue::Text(text_of(li)))
+            .collect();
+        let value = match <[MetadataValue; 1]>::try_from(items) {
+            Ok([single]) => single,
+            Err(items) => MetadataValue::List(items),
+        };
+        entries.push(entry(key.to_string(), value));
+        return;
+    }
+
+    let is_struct = node.attribute((RDF_NS, "parseType")) == Some("Resource")
+        || node.children().any(|c| is_rdf(&c, "Description"))
+        || node.children().any(|c| c.is_element());
+    if is_struct {
+        read_description(node, key, entries);
+    } else {
+        entries.push(entry(key.to_string(), MetadataValue::Text(text_of(node))));
+    }
+}
+
+fn text_of(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn join_key(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// Creates an entry, recognizing GPS coordinates and timestamps in text values.
+fn entry(key: String, value: MetadataValue) -> MetadataEntry {
+    let value = match value {
+        MetadataValue::Text(text) => {
+            let is_coordinate = key.ends_with("GPSLatitude") || key.ends_with("GPSLongitude");
+            if let Some(degrees) = is_coordinate.then(|| parse_coordinate(&text)).flatten() {
+                MetadataValue::GpsCoordinate(degrees)
+            } else if looks_like_date(&text) {
+                MetadataValue::DateTime(text)
+            } else {
+                MetadataValue::Text(text)
+            }
+        }
+        other => other,
+    };
+    MetadataEntry::new(MetadataCategory::Xmp, key, value)
+}
+
+/// Parses XMP's `DDD,MM.mmk` or `DDD,MM,SSk` coordinate format.
+fn parse_coordinate(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let direction = text.chars().last()?;
+    let sign = match direction.to_ascii_uppercase() {
+        'N' | 'E' => 1.0,
+        'S' | 'W' => -1.0,
+        _ => return None,
+    };
+    let parts: Vec<f64> = text[..text.len() - 1]
+        .split(',')
+        .map(|p| p.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let degrees = match parts.as_slice() {
+        [d, m] => d + m / 60.0,
+        [d, m, s] => d + m / 60.0 + s / 3600.0,
+        _ => return None,
+    };
+    Some(sign * degrees)
+}
+
+/// Returns true for ISO 8601 dates such as `2024-05-01` or `2024-05-01T14:30:00Z`.
+pub(crate) fn looks_like_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+        && (bytes.len() == 10 || bytes[10] == b'T')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKET: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:exif="http://ns.adobe.com/exif/1.0/"
+    xmp:CreatorTool="Editor 1.0"
+    xmp:CreateDate="2024-05-01T14:30:00+02:00"
+    exif:GPSLatitude="33,51.402S">
+   <dc:creator><rdf:Seq><rdf:li>Jane Doe</rdf:li></rdf:Seq></dc:creator>
+   <dc:subject><rdf:Bag><rdf:li>beach</rdf:li><rdf:li>family</rdf:li></rdf:Bag></dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    #[test]
+    fn reads_attributes_and_arrays() {
+        let entries = parse_xmp(PACKET.as_bytes());
+        let find = |key: &str| entries.iter().find(|e| e.key == key).unwrap();
+
+        assert_eq!(find("xmp:CreatorTool").display_value, "Editor 1.0");
+        assert!(matches!(
+            find("xmp:CreateDate").value,
+            MetadataValue::DateTime(_)
+        ));
+        assert_eq!(find("exif:GPSLatitude").display_value, "-33.856700");
+        assert_eq!(
+            find("dc:creator").value,
+            MetadataValue::Text("Jane Doe".into())
+        );
+        assert_eq!(find("dc:subject").display_value, "beach, family");
+    }
+
+    #[test]
+    fn malformed_packets_are_still_reported() {
+        let entries = parse_xmp(b"<x:xmpmeta><unclosed>");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].value, MetadataValue::Binary { len: 21 });
+    }
+
+    #[test]
+    fn packets_without_properties_are_still_reported() {
+        let entries = parse_xmp(br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, "XMP");
+    }
+}
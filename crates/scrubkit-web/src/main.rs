@@ -1,5 +1,5 @@
 use dioxus::prelude::*;
-use scrubkit_core::{MetadataEntry, scrubber_for_file};
+use scrubkit_core::{MetadataEntry, MetadataSummary, RiskLevel, scrubber_for_file};
 use wasm_bindgen::JsCast;
 
 // Define an enum for our application's state
@@ -8,7 +8,7 @@ enum AppState {
     Idle,
     Loaded {
         file_name: String,
-        metadata: Vec<MetadataEntry>,
+        summary: MetadataSummary,
     },
     Scrubbed {
         file_name: String,
@@ -18,6 +18,34 @@ enum AppState {
     Error(String),
 }
 
+// Tailwind classes used to highlight an entry by its privacy risk
+fn risk_classes(risk: RiskLevel) -> (&'static str, &'static str) {
+    // (badge, row)
+    match risk {
+        RiskLevel::Critical => (
+            "bg-red-600 text-white",
+            "border-l-2 border-red-500 bg-red-900/20",
+        ),
+        RiskLevel::High => (
+            "bg-orange-500 text-gray-900",
+            "border-l-2 border-orange-400 bg-orange-900/10",
+        ),
+        RiskLevel::Medium => (
+            "bg-yellow-400 text-gray-900",
+            "border-l-2 border-yellow-400/50",
+        ),
+        RiskLevel::Low => ("bg-gray-600 text-gray-200", "border-l-2 border-gray-700"),
+    }
+}
+
+/// Names the scrubbed copy of a file, keeping its extension: `photo.webp` becomes `photo.clean.webp`.
+fn clean_file_name(file_name: &str) -> String {
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}.clean.{}", stem, extension),
+        None => format!("{}.clean", file_name),
+    }
+}
+
 // Helper function to trigger a file download in the browser
 fn download_bytes(file_name: &str, bytes: &[u8]) {
     let window = web_sys::window().unwrap();
@@ -56,11 +84,11 @@ fn app() -> Element {
         if let Some((name, bytes)) = file_bytes() {
             log::info!("File loaded: {}, size: {}", name, bytes.len());
             match scrubber_for_file(bytes) {
-                Ok(scrubber) => match scrubber.view_metadata() {
-                    Ok(metadata) => {
+                Ok(scrubber) => match scrubber.summary() {
+                    Ok(summary) => {
                         app_state.set(AppState::Loaded {
                             file_name: name,
-                            metadata,
+                            summary,
                         });
                     }
                     Err(e) => app_state.set(AppState::Error(e.to_string())),
@@ -73,10 +101,10 @@ fn app() -> Element {
     let handle_file_upload = move |evt: FormEvent| async move {
         if let Some(file_engine) = &evt.files() {
             let files = file_engine.files();
-            if let Some(file_name) = files.first() {
-                if let Some(file) = file_engine.read_file(file_name).await {
-                    file_bytes.set(Some((file_name.clone(), file)));
-                }
+            if let Some(file_name) = files.first()
+                && let Some(file) = file_engine.read_file(file_name).await
+            {
+                file_bytes.set(Some((file_name.clone(), file)));
             }
         }
     };
@@ -125,7 +153,7 @@ fn app() -> Element {
                     input {
                         r#type: "file",
                         class: "hidden",
-                        accept: ".jpg, .jpeg, .png",
+                        accept: ".jpg, .jpeg, .png, .gif, .webp, .heic, .heif, .avif, .jxl, .jp2, .jpx, .j2k, .tif, .tiff, .dng, .svg, .dcm, .mp4, .m4v, .mov, .mp3, .flac, .ogg, .oga, .opus, .wav, .aif, .aiff, .aifc, .pdf, .docx, .xlsx, .pptx, .odt, .ods, .odp",
                         oninput: handle_file_upload,
                     }
                 }
@@ -135,19 +163,31 @@ fn app() -> Element {
                     AppState::Idle => rsx! {
                         p { class: "text-center text-gray-500 animate-pulse", "Awaiting file..." }
                     },
-                    AppState::Loaded { file_name, metadata } => rsx! {
+                    AppState::Loaded { file_name, summary } => rsx! {
                         div {
                             class: "space-y-4",
                             h3 { class: "text-xl font-semibold text-green-400", ":: Metadata for ", span { class: "font-orbitron", "{file_name}" } }
-                            if metadata.is_empty() {
+                            if summary.is_clean() {
                                 p { "No metadata found." }
                             } else {
                                 div {
-                                    class: "bg-gray-900/50 p-4 rounded-md max-h-60 overflow-y-auto border border-gray-700",
-                                    for entry in metadata {
-                                        p { class: "text-sm whitespace-pre-wrap",
+                                    class: "flex flex-wrap gap-2 text-xs",
+                                    for risk in RiskLevel::ALL {
+                                        if summary.count(risk) > 0 {
+                                            span {
+                                                class: "px-2 py-1 rounded {risk_classes(risk).0}",
+                                                "{summary.count(risk)} {risk}"
+                                            }
+                                        }
+                                    }
+                                }
+                                div {
+                                    class: "bg-gray-900/50 p-4 rounded-md max-h-60 overflow-y-auto border border-gray-700 space-y-1",
+                                    for entry in summary.entries {
+                                        p { class: "text-sm whitespace-pre-wrap pl-2 {risk_classes(entry.risk).1}",
+                                            span { class: "text-xs px-1 mr-2 rounded {risk_classes(entry.risk).0}", "{entry.risk}" }
                                             span { class: "text-green-400", "{entry.key}: " }
-                                            span { class: "text-gray-300", "{entry.value}" }
+                                            span { class: "text-gray-300", "{entry.display_value}" }
                                         }
                                     }
                                 }
@@ -155,16 +195,15 @@ fn app() -> Element {
                             button {
                                 class: "w-full bg-red-600 hover:bg-red-700 text-white font-bold py-3 px-4 rounded-md transition-transform hover:scale-105",
                                 onclick: move |_| {
-                                    if let Some((name, bytes)) = file_bytes() {
-                                        if let Ok(scrubber) = scrubber_for_file(bytes) {
-                                            if let Ok(result) = scrubber.scrub() {
-                                                app_state.set(AppState::Scrubbed {
-                                                    file_name: name,
-                                                    cleaned_bytes: result.cleaned_file_bytes,
-                                                    metadata_removed: result.metadata_removed,
-                                                });
-                                            }
-                                        }
+                                    if let Some((name, bytes)) = file_bytes()
+                                        && let Ok(scrubber) = scrubber_for_file(bytes)
+                                        && let Ok(result) = scrubber.scrub()
+                                    {
+                                        app_state.set(AppState::Scrubbed {
+                                            file_name: name,
+                                            cleaned_bytes: result.cleaned_file_bytes,
+                                            metadata_removed: result.metadata_removed,
+                                        });
                                     }
                                 },
                                 "Scrub Metadata"
@@ -179,7 +218,7 @@ fn app() -> Element {
                             button {
                                 class: "w-full bg-green-600 hover:bg-green-700 text-white font-bold py-3 px-4 rounded-md transition-transform hover:scale-105",
                                 onclick: move |_| {
-                                    let scrubbed_name = format!("{}.clean.{}", file_name.strip_suffix(".jpg").unwrap_or(&file_name).strip_suffix(".jpeg").unwrap_or(&file_name).strip_suffix(".png").unwrap_or(&file_name), "jpg");
+                                    let scrubbed_name = clean_file_name(&file_name);
                                     download_bytes(&scrubbed_name, &cleaned_bytes);
                                 },
                                 "Download Anonymized File"